@@ -0,0 +1,153 @@
+use std::{
+    cmp::max,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Log files are rotated once they grow larger than this many bytes
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Maximum number of rotated log files to keep around, in addition to the current log file
+const MAX_ROTATED_LOG_FILES: usize = 5;
+
+/// Initializes logging. Logs are always written to stderr. If a log file path is given, logs are
+/// also written to that file as JSON lines, at debug level or higher verbosity so that they can be
+/// attached to bug reports.
+pub fn init(level: LevelFilter, log_file_path: Option<&Path>) -> Result<(), anyhow::Error> {
+    let stderr_logger = env_logger::Builder::new().filter_level(level).build();
+
+    let (file, file_level) = match log_file_path {
+        Some(path) => (
+            Some(Mutex::new(RotatingFile::open(path)?)),
+            max(level, LevelFilter::Debug),
+        ),
+        None => (None, LevelFilter::Off),
+    };
+
+    log::set_max_level(max(stderr_logger.filter(), file_level));
+    log::set_boxed_logger(Box::new(Logger {
+        stderr_logger,
+        file,
+        file_level,
+    }))?;
+
+    Ok(())
+}
+
+struct Logger {
+    stderr_logger: env_logger::Logger,
+    file: Option<Mutex<RotatingFile>>,
+    file_level: LevelFilter,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr_logger.enabled(metadata) || metadata.level() <= self.file_level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.stderr_logger.enabled(record.metadata()) {
+            self.stderr_logger.log(record);
+        }
+
+        if let Some(file) = &self.file {
+            if record.level() > self.file_level {
+                return;
+            }
+
+            let line = serde_json::json!({
+                "timestamp": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "file": record.file(),
+                "line": record.line(),
+                "message": record.args().to_string(),
+            });
+            // There's nowhere to report errors writing to the log file, so ignore them
+            let _ = file
+                .lock()
+                .unwrap()
+                .write_all(format!("{}\n", line).as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr_logger.flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// A log file which is rotated once it grows larger than `MAX_LOG_FILE_SIZE`. Rotated files get a
+/// numeric suffix, e.g. `alchemy.log.1` is the most recently rotated file.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        // Shift the existing rotated files up by one, dropping the oldest
+        for index in (1..MAX_ROTATED_LOG_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_LOG_FILE_SIZE {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,1917 @@
+#![feature(try_blocks)]
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{BufWriter, IsTerminal},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ahash::AHashSet;
+use anyhow::{anyhow, Context};
+use clap::{ArgGroup, Parser, Subcommand};
+use encoding_rs::{Encoding, WINDOWS_1252};
+use log::LevelFilter;
+use paths::load_user_file;
+use profile::Profile;
+
+mod logging;
+mod paths;
+mod profile;
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+#[clap(propagate_version = true)]
+struct Cli {
+    /// Makes logging more verbose. Pass once for debug log level, twice for trace log level.
+    #[clap(short, parse(from_occurrences), global = true)]
+    verbose: u8,
+    /// Also write logs to this file as JSON lines, at debug level or higher verbosity. The file is
+    /// rotated when it grows larger than 10 MiB.
+    #[clap(long, global = true)]
+    log_file: Option<String>,
+    /// Use the named profile, which stores the exported game data, caches, ingredient filters and
+    /// default options in a profile directory. Profiles are managed with the profiles subcommand.
+    #[clap(long, global = true)]
+    profile: Option<String>,
+    /// Don't color the output. Colors are also disabled when the NO_COLOR environment variable is
+    /// set or the output is not a terminal.
+    #[clap(long, global = true)]
+    no_color: bool,
+    /// Path to a TOML file with aliases for effect names, for mods which rename effects. It
+    /// contains an [aliases] table such as "Fortify Health" = ["Fortify Vitality"]. Defaults to
+    /// effect_aliases.toml in the skyrim-alchemy-rs config directory if it exists.
+    #[clap(long, global = true)]
+    effect_aliases: Option<String>,
+    /// Path to a TOML file with your tags for ingredients, e.g. "farmable" or "vendor-trash". It
+    /// contains a [tags] table such as "Skyrim.esm|03AD66" = ["farmable"], which refers to each
+    /// ingredient by its plugin and its form ID without the load order prefix, or by its editor ID
+    /// such as DeathBell = ["vendor-trash"], which also survives the plugin being renamed or
+    /// merged. An editor ID that several plugins use for different ingredients is ignored with a
+    /// warning. Defaults to ingredient_tags.toml in the skyrim-alchemy-rs config directory if it
+    /// exists. The profile's ingredient_tags.toml is merged into it.
+    #[clap(long, global = true)]
+    ingredient_tags: Option<String>,
+    /// Path to a TOML file that corrects the hostile flag of effects that a plugin mislabeled,
+    /// which turns potions into poisons or vice versa. It contains a hostile and a beneficial list
+    /// of editor IDs, such as hostile = ["AlchDamageHealth"]. Defaults to hostility_overrides.toml
+    /// in the skyrim-alchemy-rs config directory if it exists.
+    #[clap(long, global = true)]
+    hostility_overrides: Option<String>,
+    /// Path to a TOML or JSON file with ingredients that are merged into the game data, to fix a
+    /// broken record or try out a change to a mod. Each [[ingredient]] table has an editor_id and
+    /// optionally a name, value, weight and effects such as [{ effect = "AlchRestoreHealth",
+    /// magnitude = 5, duration = 0 }], which replace those of the ingredient with the editor ID.
+    /// Ingredients that don't exist yet are added. Defaults to ingredient_overlay.toml in the
+    /// skyrim-alchemy-rs config directory if it exists.
+    #[clap(long, global = true)]
+    ingredient_overlay: Option<String>,
+    /// The rules by which ingredients are combined into potions: vanilla, or caco for Complete
+    /// Alchemy & Cooking Overhaul, in which potions with mostly hostile effects are poisons.
+    /// Defaults to vanilla.
+    #[clap(long, global = true)]
+    rules: Option<alchemy_core::CraftingRules>,
+    /// When ingredients share more effects than a potion can have, keep the most valuable effects
+    /// of the potion's type (beneficial for potions, hostile for poisons) instead of the most
+    /// valuable effects overall
+    #[clap(long, global = true)]
+    prefer_type_effects: bool,
+    /// Name or editor ID of an effect to leave out of all potions, e.g. a near-worthless filler
+    /// effect that a mod adds. Potions are made as if the ingredients didn't have it, so
+    /// ingredients that only share such an effect make no potion. Names are resolved with the
+    /// effect aliases. Can be passed multiple times. Defaults to the profile's junk effects.
+    #[clap(long = "junk-effect", global = true)]
+    junk_effects: Vec<String>,
+    /// Format numbers such as gold values for this locale, e.g. en_US for 1,234 or de for 1.234.
+    /// Use plain for no thousands separators. Defaults to the locale of the environment (LC_ALL,
+    /// LC_NUMERIC or LANG).
+    #[clap(long, global = true)]
+    locale: Option<alchemy_core::NumberLocale>,
+    /// How to name potions, e.g. "{type}: {effects:2} ({value}g)". The variables are {type},
+    /// {effect} (the primary effect), {secondary}, {effects} (all effects, or the first N with
+    /// {effects:N}), {value} and {initials} (of the ingredients). Defaults to the profile's name
+    /// template, or to the game's naming scheme "{type} of {effect}".
+    #[clap(long, global = true)]
+    name_template: Option<alchemy_core::PotionNameTemplate>,
+    /// How to tell apart different ingredients with the same name in the output: plugin (e.g.
+    /// "Frost Salts [CACO]"), form-id (e.g. "Frost Salts [CACO.esp|000D62]") or none. Ingredient
+    /// filters accept the qualified names to match only one of them. Defaults to plugin.
+    #[clap(long, global = true)]
+    duplicate_names: Option<alchemy_core::DuplicateNameStyle>,
+    /// Number of threads to use when computing potions. Defaults to the number of CPU cores.
+    #[clap(long, global = true)]
+    threads: Option<usize>,
+    /// Soft memory budget in MiB for computing potions. When storing all ingredient combinations
+    /// would exceed it, they are processed while being generated instead, which is slower.
+    #[clap(long, global = true)]
+    memory_budget: Option<u64>,
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Reads ingredients and magic effects game data using your load order and exports it to a JSON
+    /// file for later usage. If the file exists, prints what changed since it was exported, e.g.
+    /// after updating your mod list.
+    ExportGameData {
+        /// Path to the game directory containing SkyrimSE.exe.
+        #[clap(long)]
+        game_path: String,
+        /// Path to the directory containing plugins.txt. Defaults to "%LocalAppData%/Skyrim Special Edition" if not specified.
+        #[clap(long)]
+        local_path: Option<String>,
+        /// Path to the JSON file that the game data will be written to. The file is gzipped if
+        /// the path ends in .gz, e.g. game_data.json.gz. Defaults to the profile's game data if a
+        /// profile is used.
+        export_path: Option<String>,
+        /// Skip plugins that cannot be parsed, such as plugins with a malformed header, instead of
+        /// failing. The skipped plugins are logged.
+        #[clap(long)]
+        skip_bad_plugins: bool,
+        /// Only write a CSV list of the ingredients' names, editor IDs, plugins and form IDs, e.g.
+        /// for building ingredient whitelist and blacklist files.
+        #[clap(long)]
+        ingredients_only: bool,
+        /// Write the JSON without indentation and line breaks, which makes the file a lot smaller.
+        #[clap(long)]
+        compact: bool,
+        /// Only read the plugins of the base game and its official DLCs, and leave out all names
+        /// and descriptions. The file can then be bundled with builds made with the vanilla-data
+        /// feature, by setting SKYRIM_ALCHEMY_VANILLA_DATA to its path, for suggest-potions
+        /// --vanilla.
+        #[clap(long, conflicts_with = "ingredients-only")]
+        vanilla: bool,
+        /// Keep this many previous exports when overwriting the file, numbered from newest to
+        /// oldest, e.g. game_data.1.json.gz. Compare them with diff-game-data.
+        #[clap(long, default_value = "0")]
+        keep_previous: usize,
+    },
+
+    /// Prints what changed between two exports of the game data: the ingredients that were added,
+    /// removed or changed and the effects whose base cost or hostility changed.
+    DiffGameData {
+        /// Path to the older game data, e.g. a previous export kept with --keep-previous.
+        old_path: String,
+        /// Path to the newer game data. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Writes an ingredient filter file with the names of all ingredients that the plugins add,
+    /// for use with --ingredients-blacklist-path or --ingredients-whitelist-path.
+    GenerateFilter {
+        /// Name of a plugin whose ingredients to include, e.g. "Hearthfires.esm". Can be passed
+        /// multiple times.
+        #[clap(long = "from-plugins", required = true)]
+        plugins: Vec<String>,
+        /// Path to the file that the filter will be written to. Printed if not specified.
+        #[clap(long)]
+        output_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Lets you pick ingredients in the terminal by searching for them, and writes their names to
+    /// an ingredient filter file for use with --ingredients-blacklist-path or
+    /// --ingredients-whitelist-path. If the file exists, its ingredients start out checked, so it
+    /// can be edited.
+    PickIngredients {
+        /// Path to the filter file to write.
+        #[clap(long)]
+        output_path: String,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    // TODO: add CLI flag for reading saves Y/N
+    // TODO: provide option to suggest potions using only ingredients that the player has
+    /// Suggests potions to mix using the ingredients and magic effects in the game data.
+    #[clap(group(ArgGroup::new("ingredients-filter").args(&["ingredients-blacklist-path", "ingredients-whitelist-path"])))]
+    SuggestPotions {
+        /// If specified, potions containing any of the ingredients in the file will not be
+        /// suggested. The file must contain one ingredient name per line.
+        #[clap(long)]
+        ingredients_blacklist_path: Option<String>,
+        /// If specified, only potions containing only the ingredients in the file will be
+        /// suggested. The file must contain one ingredient name per line.
+        #[clap(long)]
+        ingredients_whitelist_path: Option<String>,
+        /// Don't suggest potions containing ingredients with this tag. Can be passed multiple
+        /// times. See --ingredient-tags.
+        #[clap(long = "exclude-tag")]
+        exclude_tags: Vec<String>,
+        /// Don't suggest potions containing ingredients that their plugin flags as food, such as
+        /// ingredients you'd rather cook with or eat.
+        #[clap(long)]
+        exclude_food: bool,
+        /// Leave out ingredients that have this effect, e.g. "Damage Health", as if you didn't
+        /// have them. Unlike the effect filters, this applies before the potions are computed, so
+        /// no potion contains such an ingredient even if the effect cancels out. Can be passed
+        /// multiple times.
+        #[clap(long = "exclude-ingredients-with-effect")]
+        exclude_ingredients_with_effects: Vec<String>,
+        /// Only use ingredients of which you have at least this many in your inventory, read from
+        /// your latest save. Like --reserve, this limits the suggestions to your inventory.
+        #[clap(long)]
+        min_count: Option<u32>,
+        /// Keep this many of an ingredient in your inventory instead of brewing with it, e.g.
+        /// "Giant's Toe=3". Can be passed multiple times. Like --min-count, this limits the
+        /// suggestions to your inventory.
+        #[clap(long = "reserve")]
+        reserves: Vec<alchemy_core::IngredientReserve>,
+        // TODO: validate limit arg (gte 1)
+        /// Limit the number of suggestions to at most this many potions. Defaults to 20.
+        #[clap(long)]
+        limit: Option<usize>,
+        /// Skip this many of the best potions, to see the next page of suggestions, e.g. --offset
+        /// 20 for the second page with the default limit.
+        #[clap(long, default_value = "0")]
+        offset: usize,
+        /// Only suggest potions worth at least this much gold.
+        #[clap(long)]
+        min_value: Option<u32>,
+        /// Leave out potions that another potion beats on every count: one of the same type with at
+        /// least the same effects, whose ingredients cost at most as much (by their base value)
+        /// and that is worth more.
+        #[clap(long)]
+        prune_dominated: bool,
+        /// The maximum number of ingredients per potion. Some mods allow brewing potions with more
+        /// than the vanilla 3 ingredients (at most 4 are supported). Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long)]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long)]
+        strict_load_order: bool,
+        /// Only consider the ingredient effects that your character has discovered, like the
+        /// in-game crafting menu does.
+        #[clap(long)]
+        only_known_effects: bool,
+        /// What to rank potions by: "value" (gold value), "healing" (amount of health restored),
+        /// "value-density" (gold value per ingredient consumed, where ingredients you have few of
+        /// count for more) or "cheapest-ingredients" (lowest total base value of the ingredients).
+        #[clap(long, default_value = "value")]
+        potion_ranking: alchemy_core::PotionRanking,
+        /// What to rank poisons by: "value" (gold value), "strength" (magnitude and duration of
+        /// the hostile effects, for when you apply them rather than sell them), "value-density"
+        /// (gold value per ingredient consumed, where ingredients you have few of count for more)
+        /// or "cheapest-ingredients" (lowest total base value of the ingredients).
+        #[clap(long, default_value = "value")]
+        poison_ranking: alchemy_core::PoisonRanking,
+        /// Suggest training potions for leveling alchemy: the potions and poisons whose
+        /// ingredients are worth the least, most valuable first among equally cheap ones. Short
+        /// for --potion-ranking cheapest-ingredients --poison-ranking cheapest-ingredients.
+        #[clap(long, conflicts_with_all = &["potion-ranking", "poison-ranking"])]
+        cheapest_ingredients: bool,
+        /// Always compute the potions instead of reusing the potions cached by a previous run. The
+        /// cache is rebuilt automatically when the game data or options change.
+        #[clap(long)]
+        no_cache: bool,
+        /// Suggest a random sample of this many potions spread across the ranking instead of only
+        /// the best ones, for some variety.
+        #[clap(long)]
+        sample: Option<usize>,
+        /// Only suggest potions named after one of these effects (their primary effect), e.g.
+        /// "Restore Health". Can be passed multiple times.
+        #[clap(long = "primary-effect")]
+        primary_effects: Vec<String>,
+        /// Only suggest potions that restore, fortify, damage or change the regen of this
+        /// resource: "health", "magicka" or "stamina", grouped by which of these they do unless
+        /// --group-by is passed. Can be passed multiple times.
+        #[clap(long = "target")]
+        targets: Vec<alchemy_core::Target>,
+        /// Only suggest potions of this type: "potion" or "poison".
+        #[clap(long)]
+        potion_type: Option<alchemy_core::PotionType>,
+        /// Seed for --sample. The same seed gives the same sample for the same game data and
+        /// options. A random seed is used and logged if not specified.
+        #[clap(long, requires = "sample")]
+        seed: Option<u64>,
+        /// Print each potion on a single line.
+        #[clap(long, conflicts_with = "json")]
+        oneline: bool,
+        /// Print the potions as JSON.
+        #[clap(long)]
+        json: bool,
+        /// Show the in-game description of each effect next to its magnitude, duration and value,
+        /// and which ingredient the effect's magnitude and duration come from when several have
+        /// it. With --oneline, the effects are listed below each potion. JSON output always
+        /// includes these fields, with each ingredient's version of an effect in "contributions".
+        #[clap(long)]
+        show_effect_table: bool,
+        /// Group the potions under headings: "none", "primary-effect" (e.g. all Damage Health
+        /// poisons together) or "target" (e.g. all potions that restore health together). When
+        /// grouping, the offset and limit apply to each group.
+        #[clap(long, default_value = "none")]
+        group_by: alchemy_core::GroupBy,
+        /// Order the suggested potions so that each needs as few ingredients as possible besides
+        /// those of the potions before it, and list the ingredients they need. Crafting the first
+        /// few potions then takes a short shopping list.
+        #[clap(long)]
+        batch_by_ingredients: bool,
+        /// Fail instead of printing a warning when the ingredient filters contain names that don't
+        /// match any ingredient, or names that several ingredients share, which must then be
+        /// qualified as shown in the output (see --duplicate-names).
+        #[clap(long)]
+        strict_filters: bool,
+        /// Path to a TOML file with several queries to answer in one run. Each [[query]] table can
+        /// set a name, effects, primary_effects, targets, potion_type, ingredients_blacklist,
+        /// ingredients_whitelist, exclude_tags, exclude_food, limit, offset, min_value and ranking
+        /// (e.g. ranking = { potions = "healing", poisons = "strength" }). The potions are only
+        /// computed once for all queries.
+        #[clap(long, conflicts_with_all = &["ingredients-blacklist-path", "ingredients-whitelist-path"])]
+        batch: Option<String>,
+        /// Use the vanilla game data bundled with the program instead of your game data, e.g. to
+        /// plan on a machine without the game. Ingredients and effects are shown by their editor
+        /// IDs, and no save is read. Only available in builds made with the vanilla-data feature.
+        #[clap(long, conflicts_with_all = &["data-path", "saves-path", "character", "min-count", "reserves", "only-known-effects"])]
+        vanilla: bool,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Suggests the strongest potions for the fortify loop (Fortify Restoration, Fortify
+    /// Enchanting, Fortify Alchemy) that you can craft with the ingredients in your inventory.
+    FortifyLoop {
+        /// Name or editor ID of a magic effect to look for. Can be passed multiple times. Defaults to
+        /// Fortify Restoration, Fortify Enchanting and Fortify Alchemy.
+        #[clap(long = "effect")]
+        effects: Vec<String>,
+        /// The number of loop iterations to count the required ingredients for.
+        #[clap(long, default_value_t = 1u32)]
+        iterations: u32,
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Limit the number of suggestions to at most this many potions.
+        #[clap(long, default_value_t = 10usize)]
+        limit: usize,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long)]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long)]
+        strict_load_order: bool,
+        /// Also read the inventory from the SKSE co-save next to the save file, for mods which
+        /// modify your inventory at save time.
+        #[clap(long)]
+        use_cosave: bool,
+        /// Only use ingredients of which you have at least this many in your inventory.
+        #[clap(long)]
+        min_count: Option<u32>,
+        /// Keep this many of an ingredient in your inventory instead of brewing with it, e.g.
+        /// "Giant's Toe=3". Can be passed multiple times.
+        #[clap(long = "reserve")]
+        reserves: Vec<alchemy_core::IngredientReserve>,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Plans which potions to brew with the ingredients in your inventory, brewing the best potions
+    /// first and each as many times as your ingredients allow. Also shows the Alchemy XP that the
+    /// plan gives and the skill level it takes you to, starting from --alchemy-skill.
+    BrewPlan {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// What to brew first: "value" (gold value), "healing" (amount of health restored) or
+        /// "value-density" (gold value per ingredient consumed, where ingredients you have few of
+        /// count for more).
+        #[clap(long, default_value = "value")]
+        potion_ranking: alchemy_core::PotionRanking,
+        /// What to brew first: "value" (gold value), "strength" (magnitude and duration of the
+        /// hostile effects) or "value-density" (gold value per ingredient consumed, where
+        /// ingredients you have few of count for more).
+        #[clap(long, default_value = "value")]
+        poison_ranking: alchemy_core::PoisonRanking,
+        /// Keep the total weight of the ingredients used within this budget, e.g. the weight you
+        /// can carry to an alchemy lab.
+        #[clap(long)]
+        carry_budget: Option<f32>,
+        /// The weight of a crafted potion or poison, for mods that change it. Defaults to 0.5.
+        #[clap(long)]
+        potion_weight: Option<f32>,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long)]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long)]
+        strict_load_order: bool,
+        /// Also read the inventory from the SKSE co-save next to the save file, for mods which
+        /// modify your inventory at save time.
+        #[clap(long)]
+        use_cosave: bool,
+        /// Only use ingredients of which you have at least this many in your inventory.
+        #[clap(long)]
+        min_count: Option<u32>,
+        /// Keep this many of an ingredient in your inventory instead of brewing with it, e.g.
+        /// "Giant's Toe=3". Can be passed multiple times.
+        #[clap(long = "reserve")]
+        reserves: Vec<alchemy_core::IngredientReserve>,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Shows the potion that would be crafted from the given ingredients, including the magnitude,
+    /// duration and value of each effect.
+    Explain {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Show the in-game description of each effect next to its magnitude, duration and value,
+        /// and which ingredient the effect's magnitude and duration come from when several have
+        /// it.
+        #[clap(long)]
+        show_effect_table: bool,
+        /// Names or editor IDs of the ingredients to combine. Partial names are accepted as long as
+        /// they match only one ingredient.
+        #[clap(required = true)]
+        ingredients: Vec<String>,
+    },
+
+    /// Checks the values of potions you crafted in the game against the values the tool predicts,
+    /// and finds the perk bonus that gives all of them, for mod setups whose alchemy modifiers you
+    /// don't know. With a profile, the calibrated settings are stored in it.
+    Calibrate {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent, which is only used
+        /// to check the current values. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Potions you crafted and their values as shown in your inventory, e.g. "Blue Mountain
+        /// Flower, Wheat = 180". Potions with different effects give a more precise calibration.
+        /// Perks that only strengthen some effects, such as Physician, can't be calibrated, so
+        /// avoid mixing potions with and without the effects they strengthen.
+        #[clap(required = true)]
+        samples: Vec<alchemy_core::CalibrationSample>,
+    },
+
+    /// Shows what one unit of an ingredient is worth to you: the most valuable potion it makes with
+    /// ingredients from the base game, and with --inventory the most valuable potion it makes with
+    /// the ingredients in your inventory.
+    Appraise {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// Also find the most valuable potion the ingredient makes with the ingredients in your
+        /// inventory, read from your latest save.
+        #[clap(long)]
+        inventory: bool,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long, requires = "inventory")]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long, requires = "inventory")]
+        strict_load_order: bool,
+        /// Also read the inventory from the SKSE co-save next to the save file, for mods which
+        /// modify your inventory at save time.
+        #[clap(long, requires = "inventory")]
+        use_cosave: bool,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Name or editor ID of the ingredient. A partial name is accepted as long as it matches
+        /// only one ingredient.
+        ingredient: String,
+    },
+
+    /// Computes all possible potions and writes them to a JSON or CSV file, for running your own
+    /// analyses. In JSON, ingredients and magic effects are referenced by the form IDs used in the
+    /// game data.
+    ExportPotions {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// The file format: json or csv.
+        #[clap(long, default_value = "json")]
+        format: alchemy_core::ExportFormat,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Path to the file that the potions will be written to.
+        export_path: String,
+    },
+
+    /// Writes an N×N matrix of the ingredients with the gold value of the potion that each pair
+    /// of them makes, e.g. for spreadsheets or heatmaps of which ingredients go well together.
+    ExportMatrix {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The file format: csv, with empty cells for pairs that don't make a potion, or binary,
+        /// which is the bytes "SAPM", the format version and the number of ingredients N as
+        /// little-endian u32s, each name as a u16 length and UTF-8, then N×N u32 values with
+        /// 4294967295 for pairs that don't make a potion.
+        #[clap(long, default_value = "csv")]
+        format: alchemy_core::MatrixFormat,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Path to the file that the matrix will be written to.
+        export_path: String,
+    },
+
+    /// Writes the graph of the ingredients and the effects they have, e.g. to visualize the alchemy
+    /// of your mod list in Gephi. Nodes have their kind, plugin, value, base cost and hostility as
+    /// attributes, and edges the effect's magnitude and duration.
+    ExportGraph {
+        /// The file format: dot (GraphViz) or graphml (Gephi, yEd and most other graph tools).
+        #[clap(long, default_value = "dot")]
+        format: alchemy_core::GraphFormat,
+        /// Also add this many of the most valuable potions, each connected to its ingredients.
+        #[clap(long, default_value = "0")]
+        potions: usize,
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Path to the file that the graph will be written to.
+        export_path: String,
+    },
+
+    /// Prints the version and where the game data came from, including its load order hash, which
+    /// identifies the plugins it was exported from. Include this in bug reports.
+    Info {
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Lists the ingredients whose effects were removed or changed by a plugin overriding them,
+    /// e.g. by an overhaul. This explains why a combination that used to work is no longer
+    /// suggested. Re-export the game data if it was exported by an older version.
+    CheckOverrides {
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Finds the fewest ingredients whose pairs can make potions with each of the effects, e.g. to
+    /// stock an alchemy bag for the potions you usually need.
+    PlanCoverage {
+        /// Name or editor ID of a magic effect that a potion should be able to have. Can be passed
+        /// multiple times.
+        #[clap(long = "effect", required = true)]
+        effects: Vec<String>,
+        /// What to minimize: "count" (number of different ingredients) or "value" (total base gold
+        /// value of the ingredients).
+        #[clap(long, default_value = "count")]
+        cost: alchemy_core::CoverageCost,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Lists the ingredient effects whose hostile flag disagrees with their name, e.g. a "Damage"
+    /// effect that makes potions instead of poisons. Such effects can be corrected with
+    /// --hostility-overrides.
+    CheckHostility {
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Keeps running and answers queries about the potions you can craft with your latest save's
+    /// inventory over a local TCP connection or Unix socket, for game overlays and other tools.
+    /// Each request is a line such as "best 5 poison", "status", "reload" or "ping", and each
+    /// response is a line of JSON. New saves are picked up automatically.
+    Serve {
+        /// The address to listen on: a loopback address such as 127.0.0.1:7525, since anyone who
+        /// can connect can query the server, or on Unix "unix:" followed by the path of a socket
+        /// that only you can connect to, such as unix:/run/user/1000/skyrim-alchemy.sock.
+        #[clap(long, default_value = alchemy_core::DEFAULT_SERVER_ADDRESS)]
+        address: String,
+        /// How often to check for a new save, in seconds.
+        #[clap(long, default_value_t = 2u64)]
+        poll_interval: u64,
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// The maximum number of ingredients per potion. Defaults to 3.
+        #[clap(long)]
+        max_ingredients: Option<usize>,
+        /// Always compute the potions on startup instead of reusing the potions cached by a
+        /// previous run.
+        #[clap(long)]
+        no_cache: bool,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long)]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long)]
+        strict_load_order: bool,
+        /// Also read the inventory from the SKSE co-save next to the save file, for mods which
+        /// modify your inventory at save time.
+        #[clap(long)]
+        use_cosave: bool,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Parses a single plugin on its own and prints its masters, flags, strings files and
+    /// ingredient and magic effect records. Useful to find out why a mod's ingredients are missing
+    /// from the game data. The plugin's masters don't need to be present.
+    DebugPlugin {
+        /// Path to the plugin file, e.g. Data/Skyrim.esm. The strings files of a localized plugin
+        /// are looked up in the same directory.
+        path: String,
+    },
+
+    /// Lists your save files with their character, level, location and playtime, most recent
+    /// first. Only the save headers are read, so this is fast.
+    ListSaves {
+        /// Only list the saves of the character with this name.
+        #[clap(long)]
+        character: Option<String>,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+    },
+
+    /// Checks for common problems with the setup, such as a wrong game directory, a missing
+    /// plugins.txt, plugins or strings files, or a saves or cache directory that can't be used,
+    /// and explains how to fix them.
+    Doctor {
+        /// Path to the game directory containing SkyrimSE.exe. The game and load order checks are
+        /// skipped if not specified.
+        #[clap(long)]
+        game_path: Option<String>,
+        /// Path to the directory containing plugins.txt. Defaults to "%LocalAppData%/Skyrim Special Edition" if not specified.
+        #[clap(long)]
+        local_path: Option<String>,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+    },
+
+    /// Compares the ingredients in your inventory across your recent saves of one character, and
+    /// lists the ingredients you accumulate and the ones you use up, to help decide what to farm or
+    /// buy.
+    StockTrends {
+        /// Compare the saves of the character with this name instead of the character of your
+        /// latest save.
+        #[clap(long)]
+        character: Option<String>,
+        /// The number of most recent saves to compare.
+        #[clap(long, default_value_t = 10usize)]
+        saves: usize,
+        /// List at most this many accumulated and this many used up ingredients.
+        #[clap(long, default_value_t = 10usize)]
+        limit: usize,
+        /// Fail instead of printing a warning when the plugins in your saves don't match the
+        /// plugins that were active when the game data was exported.
+        #[clap(long)]
+        strict_load_order: bool,
+        /// Also read the inventories from the SKSE co-saves next to the save files, for mods which
+        /// modify your inventory at save time.
+        #[clap(long)]
+        use_cosave: bool,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data if a profile is used.
+        data_path: Option<String>,
+    },
+
+    /// Manages profiles, which each store the game data, caches, ingredient filters and default
+    /// options for one character or modlist.
+    Profiles {
+        #[clap(subcommand)]
+        command: ProfilesCommands,
+    },
+
+    /// Manages bookmarked potions, which are stored in the profile. Bookmarks refer to their
+    /// ingredients by plugin and form ID, so they keep working when the load order changes.
+    #[clap(alias = "bookmark")]
+    Bookmarks {
+        #[clap(subcommand)]
+        command: BookmarksCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfilesCommands {
+    /// Lists all profiles.
+    List,
+    /// Creates a new profile. Its default options can be edited in the settings.json file in the
+    /// profile directory, and ingredients_blacklist.txt or ingredients_whitelist.txt files can be
+    /// added there to filter suggestions.
+    Create {
+        /// Name of the profile, e.g. "vanilla" or "heavy-modlist".
+        name: String,
+    },
+    /// Deletes a profile along with its game data and caches.
+    Delete {
+        /// Name of the profile.
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BookmarksCommands {
+    /// Bookmarks the potion crafted from the given ingredients under a name.
+    Add {
+        /// Name of the bookmark, e.g. "Selling potion".
+        #[clap(long)]
+        name: String,
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data.
+        #[clap(long)]
+        data_path: Option<String>,
+        /// Names or editor IDs of the ingredients to combine. Partial names are accepted as long as
+        /// they match only one ingredient.
+        #[clap(required = true)]
+        ingredients: Vec<String>,
+    },
+    /// Lists the bookmarks as they were when they were added.
+    List,
+    /// Checks the bookmarks against the current game data: whether their ingredients are still in
+    /// it and whether a mod changed their values. With --inventory, also checks whether you have
+    /// the ingredients.
+    Check {
+        /// Your alchemy skill level. Defaults to 100.
+        #[clap(long)]
+        alchemy_skill: Option<u32>,
+        /// Your total Fortify Alchemy bonus from equipment and potions, in percent. Defaults to 0.
+        #[clap(long)]
+        fortify_alchemy: Option<f32>,
+        /// Your total alchemy bonus from perks such as Alchemist, in percent. Defaults to 0.
+        #[clap(long)]
+        perk_bonus: Option<f32>,
+        /// Also check that the ingredients are in your inventory, read from your latest save, and
+        /// show how many of each potion you can brew.
+        #[clap(long)]
+        inventory: bool,
+        /// Read the latest save of the character with this name instead of the latest save of any
+        /// character.
+        #[clap(long, requires = "inventory")]
+        character: Option<String>,
+        /// Fail instead of printing a warning when the plugins in your save don't match the plugins
+        /// that were active when the game data was exported.
+        #[clap(long, requires = "inventory")]
+        strict_load_order: bool,
+        /// Also read the inventory from the SKSE co-save next to the save file, for mods which
+        /// modify your inventory at save time.
+        #[clap(long, requires = "inventory")]
+        use_cosave: bool,
+        /// Path to the directory containing your save files. Defaults to the SLocalSavePath setting in Skyrim.ini or SkyrimCustom.ini in %UserProfile%/Documents/My Games/Skyrim Special Edition if one sets it, otherwise to the Saves directory there.
+        #[clap(long)]
+        saves_path: Option<String>,
+        /// Path to the JSON file that contains the game data. This file can be obtained through the
+        /// export-game-data subcommand. Defaults to the profile's game data.
+        #[clap(long)]
+        data_path: Option<String>,
+    },
+    /// Removes a bookmark.
+    Remove {
+        /// Name of the bookmark.
+        name: String,
+    },
+}
+
+/// Returns the given path, falling back to the path in the profile if no path was given
+fn resolve_path(
+    path: &Option<String>,
+    profile: Option<&Profile>,
+    get_profile_path: fn(&Profile) -> PathBuf,
+    arg_name: &str,
+) -> Result<PathBuf, anyhow::Error> {
+    match (path, profile) {
+        (Some(path), _) => Ok(PathBuf::from(path)),
+        (None, Some(profile)) => Ok(get_profile_path(profile)),
+        (None, None) => Err(anyhow!(
+            "The {} argument is required when not using a profile",
+            arg_name
+        )),
+    }
+}
+
+/// Reads the non-empty lines of a filter file, trimmed. Files that start with a byte order mark are
+/// decoded as UTF-8 or UTF-16 accordingly. Otherwise each line is decoded as UTF-8 if it's valid
+/// UTF-8, and as Windows-1252 (what Notepad calls ANSI) if not, so files with lines in either
+/// encoding still work.
+fn read_lines_to_hashset<P>(path: P) -> Result<AHashSet<String>, anyhow::Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let lines = match Encoding::for_bom(&data) {
+        Some((encoding, bom_length)) => encoding
+            .decode_without_bom_handling(&data[bom_length..])
+            .0
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+        None => data
+            .split(|byte| *byte == b'\n')
+            .enumerate()
+            .map(|(index, line)| match std::str::from_utf8(line) {
+                Ok(line) => line.to_string(),
+                Err(_) => {
+                    log::debug!(
+                        "Line {} of {} is not valid UTF-8, reading it as Windows-1252",
+                        index + 1,
+                        path.display()
+                    );
+                    WINDOWS_1252
+                        .decode_without_bom_handling(line)
+                        .0
+                        .into_owned()
+                }
+            })
+            .collect(),
+    };
+
+    let mut names = AHashSet::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.contains(char::is_control) {
+            Err(anyhow!(
+                "Line {} of {} contains control characters, save the file as UTF-8 and try again",
+                index + 1,
+                path.display()
+            ))?
+        }
+        names.insert(line.to_string());
+    }
+    Ok(names)
+}
+
+/// Returns the alchemy skill from the given arguments, falling back to the profile's settings and
+/// then to the defaults
+fn get_alchemy_skill(
+    skill_level: Option<u32>,
+    fortify_alchemy: Option<f32>,
+    perk_bonus: Option<f32>,
+    settings: Option<&profile::ProfileSettings>,
+) -> alchemy_core::AlchemySkill {
+    let default = alchemy_core::AlchemySkill::default();
+    alchemy_core::AlchemySkill {
+        skill_level: skill_level
+            .or_else(|| settings.and_then(|s| s.alchemy_skill))
+            .unwrap_or(default.skill_level),
+        fortify_alchemy: fortify_alchemy
+            .or_else(|| settings.and_then(|s| s.fortify_alchemy))
+            .unwrap_or(default.fortify_alchemy),
+        perk_bonus: perk_bonus
+            .or_else(|| settings.and_then(|s| s.perk_bonus))
+            .unwrap_or(default.perk_bonus),
+    }
+}
+
+/// Whether to color the output: unless `no_color` is true, the `NO_COLOR` environment variable is
+/// set or stdout is not a terminal
+fn use_color(no_color: bool) -> bool {
+    !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Returns where to find the saves: in the given saves directory or else the profile's, or in the
+/// one the game uses
+fn get_saves_location(
+    saves_path: Option<&String>,
+    settings: Option<&profile::ProfileSettings>,
+) -> alchemy_core::SavesLocation {
+    alchemy_core::SavesLocation {
+        saves_path: saves_path
+            .or_else(|| settings.and_then(|s| s.saves_path.as_ref()))
+            .map(PathBuf::from),
+        my_games_path: paths::MY_GAMES_PATH.clone(),
+        index_path: paths::DEFAULT_SAVE_INDEX_PATH.clone(),
+    }
+}
+
+fn main() -> Result<(), anyhow::Error> {
+    let cli = Cli::parse();
+
+    logging::init(
+        match cli.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        },
+        cli.log_file.as_ref().map(Path::new),
+    )?;
+
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    let profile = cli.profile.as_deref().map(Profile::open).transpose()?;
+    let profile = profile.as_ref();
+    let settings = profile.map(|profile| &profile.settings);
+    let effect_aliases = || {
+        load_user_file(
+            cli.effect_aliases.as_deref(),
+            paths::DEFAULT_EFFECT_ALIASES_PATH.as_deref(),
+            alchemy_core::EffectAliases::load,
+        )
+        .map(|effect_aliases| effect_aliases.unwrap_or_else(alchemy_core::EffectAliases::builtin))
+    };
+
+    let rules = cli.rules.clone().unwrap_or_default();
+    let import_options = alchemy_core::ImportOptions {
+        crafting_rules: alchemy_core::CraftingRules {
+            prefer_effects_of_type: rules.prefer_effects_of_type || cli.prefer_type_effects,
+            ..rules
+        },
+        ingredient_overlay: load_user_file(
+            cli.ingredient_overlay.as_deref(),
+            paths::DEFAULT_INGREDIENT_OVERLAY_PATH.as_deref(),
+            alchemy_core::IngredientOverlay::load,
+        )?,
+        hostility_overrides: load_user_file(
+            cli.hostility_overrides.as_deref(),
+            paths::DEFAULT_HOSTILITY_OVERRIDES_PATH.as_deref(),
+            alchemy_core::HostilityOverrides::load,
+        )?,
+        duplicate_name_style: cli.duplicate_names.unwrap_or_default(),
+        junk_effects: match (&cli.junk_effects[..], settings) {
+            ([], Some(settings)) => settings.junk_effects.clone(),
+            (junk_effects, _) => junk_effects.to_vec(),
+        },
+        effect_aliases: Some(effect_aliases()?),
+        potion_name_template: cli
+            .name_template
+            .clone()
+            .map(Ok)
+            .or_else(|| {
+                settings
+                    .and_then(|settings| settings.name_template.as_deref())
+                    .map(|name_template| {
+                        name_template
+                            .parse()
+                            .map_err(|err| anyhow!("Invalid name template in profile: {}", err))
+                    })
+            })
+            .transpose()?,
+        number_locale: Some(
+            cli.locale
+                .unwrap_or_else(alchemy_core::NumberLocale::from_env),
+        ),
+        memory_budget: cli.memory_budget.map(|mib| mib * 1024 * 1024),
+    };
+
+    match &cli.command {
+        Commands::ExportGameData {
+            game_path,
+            local_path,
+            export_path,
+            skip_bad_plugins,
+            ingredients_only,
+            compact,
+            vanilla,
+            keep_previous,
+        } => {
+            // The ingredient list must not replace the profile's game data
+            if *ingredients_only && export_path.is_none() {
+                Err(anyhow!("--ingredients-only requires an export path"))?
+            }
+            let diff = alchemy_core::parse_and_export_game_data(
+                game_path,
+                local_path.as_ref(),
+                resolve_path(
+                    export_path,
+                    profile,
+                    Profile::get_game_data_path,
+                    "export-path",
+                )?,
+                &alchemy_core::ExportGameDataOptions {
+                    skip_bad_plugins: *skip_bad_plugins,
+                    ingredients_only: *ingredients_only,
+                    compact: *compact,
+                    vanilla: *vanilla,
+                    keep_previous: *keep_previous,
+                },
+            )?;
+            if let Some(diff) = diff {
+                println!("Changes since the previous export:");
+                print!("{}", diff);
+            }
+        }
+        Commands::DiffGameData {
+            old_path,
+            data_path,
+        } => {
+            let diff = alchemy_core::diff_game_data(
+                old_path,
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+            )?;
+            print!("{}", diff);
+        }
+        Commands::GenerateFilter {
+            plugins,
+            output_path,
+            data_path,
+        } => {
+            let data_path =
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?;
+            match output_path {
+                Some(output_path) => {
+                    let file = fs::File::create(output_path)
+                        .with_context(|| format!("Failed to create filter file {}", output_path))?;
+                    alchemy_core::generate_plugins_filter(
+                        data_path,
+                        &import_options,
+                        plugins,
+                        BufWriter::new(file),
+                    )?;
+                }
+                None => alchemy_core::generate_plugins_filter(
+                    data_path,
+                    &import_options,
+                    plugins,
+                    std::io::stdout(),
+                )?,
+            }
+        }
+        Commands::PickIngredients {
+            output_path,
+            data_path,
+        } => {
+            let initial = match Path::new(output_path).exists() {
+                true => read_lines_to_hashset(output_path)?,
+                false => AHashSet::new(),
+            };
+            alchemy_core::pick_ingredients(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                output_path,
+                initial,
+                std::io::stdin().lock(),
+                std::io::stdout(),
+            )?;
+        }
+        Commands::SuggestPotions {
+            data_path,
+            saves_path,
+            ingredients_blacklist_path: ingredients_blacklist_file,
+            ingredients_whitelist_path: ingredients_whitelist_file,
+            exclude_tags,
+            exclude_food,
+            exclude_ingredients_with_effects,
+            min_count,
+            reserves,
+            limit,
+            offset,
+            min_value,
+            prune_dominated,
+            max_ingredients,
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            character,
+            strict_load_order,
+            only_known_effects,
+            potion_ranking,
+            poison_ranking,
+            cheapest_ingredients,
+            no_cache,
+            batch,
+            sample,
+            primary_effects,
+            targets,
+            potion_type,
+            seed,
+            oneline,
+            json,
+            show_effect_table,
+            group_by,
+            batch_by_ingredients,
+            strict_filters,
+            vanilla,
+        } => {
+            // The profile's ingredient filters are only used if no filter was passed explicitly
+            let (ingredients_blacklist_file, ingredients_whitelist_file) = match (
+                ingredients_blacklist_file,
+                ingredients_whitelist_file,
+                profile,
+            ) {
+                (None, None, Some(profile)) => (
+                    profile.get_ingredients_blacklist_path(),
+                    profile.get_ingredients_whitelist_path(),
+                ),
+                (blacklist, whitelist, _) => (
+                    blacklist.as_ref().map(PathBuf::from),
+                    whitelist.as_ref().map(PathBuf::from),
+                ),
+            };
+            let ingredients_blacklist = ingredients_blacklist_file
+                .as_ref()
+                .map(read_lines_to_hashset)
+                .transpose()?
+                .unwrap_or_default();
+            let ingredients_whitelist = ingredients_whitelist_file
+                .as_ref()
+                .map(read_lines_to_hashset)
+                .transpose()?
+                .unwrap_or_default();
+
+            let queries = match batch {
+                Some(batch) => alchemy_core::read_potion_queries(batch)?,
+                None => vec![alchemy_core::PotionQuery {
+                    ingredients_blacklist,
+                    ingredients_whitelist,
+                    exclude_tags: exclude_tags.clone(),
+                    exclude_food: *exclude_food,
+                    offset: *offset,
+                    min_value: *min_value,
+                    ranking: match cheapest_ingredients {
+                        true => alchemy_core::Ranking {
+                            potions: alchemy_core::PotionRanking::CheapestIngredients,
+                            poisons: alchemy_core::PoisonRanking::CheapestIngredients,
+                        },
+                        false => alchemy_core::Ranking {
+                            potions: *potion_ranking,
+                            poisons: *poison_ranking,
+                        },
+                    },
+                    sample: *sample,
+                    seed: *seed,
+                    primary_effects: primary_effects.clone(),
+                    targets: targets.clone(),
+                    potion_type: *potion_type,
+                    group_by: *group_by,
+                    batch_by_ingredients: *batch_by_ingredients,
+                    ..Default::default()
+                }],
+            };
+
+            // The profile's tags are merged into the user's tags
+            let ingredient_tags_paths = [
+                cli.ingredient_tags
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .or_else(|| paths::DEFAULT_INGREDIENT_TAGS_PATH.clone()),
+                profile.map(Profile::get_ingredient_tags_path),
+            ];
+
+            let options = alchemy_core::SuggestPotionsOptions {
+                queries,
+                limit: limit
+                    .or_else(|| settings.and_then(|s| s.limit))
+                    .unwrap_or(20),
+                max_ingredients: max_ingredients
+                    .or_else(|| settings.and_then(|s| s.max_ingredients))
+                    .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                alchemy_skill: get_alchemy_skill(
+                    *alchemy_skill,
+                    *fortify_alchemy,
+                    *perk_bonus,
+                    settings,
+                ),
+                character: character.clone(),
+                strict_load_order: *strict_load_order
+                    || settings.map_or(false, |s| s.strict_load_order),
+                // The settings' save options don't apply to the vanilla data
+                only_known_effects: !vanilla
+                    && (*only_known_effects || settings.map_or(false, |s| s.only_known_effects)),
+                cache_path: match (no_cache, profile) {
+                    (true, _) => None,
+                    (false, Some(profile)) => Some(profile.get_potions_cache_path()),
+                    (false, None) => paths::DEFAULT_POTIONS_CACHE_PATH.clone(),
+                },
+                formatter: alchemy_core::PotionFormatter::new(
+                    match (oneline, json) {
+                        (true, _) => alchemy_core::OutputStyle::Oneline,
+                        (_, true) => alchemy_core::OutputStyle::Json,
+                        _ => alchemy_core::OutputStyle::Block,
+                    },
+                    use_color(cli.no_color),
+                )
+                .with_effect_table(*show_effect_table),
+                effect_aliases: effect_aliases()?,
+                strict_filters: *strict_filters,
+                exclude_ingredients_with_effects: exclude_ingredients_with_effects.clone(),
+                ingredient_tags: alchemy_core::IngredientTags::load_all(
+                    ingredient_tags_paths.iter().flatten().map(PathBuf::as_path),
+                )?,
+                inventory_limits: alchemy_core::InventoryLimits {
+                    min_count: min_count.unwrap_or(0),
+                    reserves: reserves.clone(),
+                },
+                prune_dominated: *prune_dominated,
+                read_saves: !vanilla,
+            };
+            alchemy_core::suggest_potions(
+                match vanilla {
+                    true => {
+                        let path = paths::VANILLA_GAME_DATA_PATH.clone().ok_or_else(|| {
+                            anyhow!("Cannot find the cache directory to unpack the vanilla data to")
+                        })?;
+                        alchemy_core::unpack_vanilla_game_data(&path)?;
+                        path
+                    }
+                    false => {
+                        resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?
+                    }
+                },
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                &options,
+                &mut alchemy_core::WriterSink::new(&options.formatter, std::io::stdout()),
+            )?;
+        }
+        Commands::FortifyLoop {
+            effects,
+            iterations,
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            limit,
+            max_ingredients,
+            character,
+            strict_load_order,
+            use_cosave,
+            min_count,
+            reserves,
+            saves_path,
+            data_path,
+        } => {
+            let effects = if effects.is_empty() {
+                alchemy_core::DEFAULT_FORTIFY_LOOP_EFFECTS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect()
+            } else {
+                effects.clone()
+            };
+
+            alchemy_core::suggest_fortify_loop_potions(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                &alchemy_core::FortifyLoopOptions {
+                    effects,
+                    iterations: *iterations,
+                    limit: *limit,
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    character: character.clone(),
+                    strict_load_order: *strict_load_order
+                        || settings.map_or(false, |s| s.strict_load_order),
+                    use_cosave: *use_cosave,
+                    inventory_limits: alchemy_core::InventoryLimits {
+                        min_count: min_count.unwrap_or(0),
+                        reserves: reserves.clone(),
+                    },
+                    effect_aliases: effect_aliases()?,
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::BrewPlan {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            max_ingredients,
+            potion_ranking,
+            poison_ranking,
+            carry_budget,
+            potion_weight,
+            character,
+            strict_load_order,
+            use_cosave,
+            min_count,
+            reserves,
+            saves_path,
+            data_path,
+        } => {
+            alchemy_core::suggest_brew_plan(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                &alchemy_core::BrewPlanOptions {
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    ranking: alchemy_core::Ranking {
+                        potions: *potion_ranking,
+                        poisons: *poison_ranking,
+                    },
+                    carry_budget: *carry_budget,
+                    potion_weight: potion_weight
+                        .or_else(|| settings.and_then(|s| s.potion_weight))
+                        .unwrap_or(alchemy_core::DEFAULT_POTION_WEIGHT),
+                    character: character.clone(),
+                    strict_load_order: *strict_load_order
+                        || settings.map_or(false, |s| s.strict_load_order),
+                    use_cosave: *use_cosave,
+                    inventory_limits: alchemy_core::InventoryLimits {
+                        min_count: min_count.unwrap_or(0),
+                        reserves: reserves.clone(),
+                    },
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::Explain {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            data_path,
+            show_effect_table,
+            ingredients,
+        } => {
+            alchemy_core::explain_potion(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                ingredients,
+                &get_alchemy_skill(*alchemy_skill, *fortify_alchemy, *perk_bonus, settings),
+                &alchemy_core::PotionFormatter::new(
+                    alchemy_core::OutputStyle::Block,
+                    use_color(cli.no_color),
+                )
+                .with_effect_table(*show_effect_table),
+                std::io::stdout(),
+            )?;
+        }
+        Commands::Calibrate {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            data_path,
+            samples,
+        } => {
+            let calibrated_skill = alchemy_core::calibrate_alchemy_skill(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                samples,
+                &get_alchemy_skill(*alchemy_skill, *fortify_alchemy, *perk_bonus, settings),
+                std::io::stdout(),
+            )?;
+            match &cli.profile {
+                Some(name) => {
+                    let mut profile = Profile::open(name)?;
+                    profile.settings.alchemy_skill = Some(calibrated_skill.skill_level);
+                    profile.settings.fortify_alchemy = Some(calibrated_skill.fortify_alchemy);
+                    profile.settings.perk_bonus = Some(calibrated_skill.perk_bonus);
+                    profile.save_settings()?;
+                    println!("Stored the calibrated settings in profile \"{}\"", name);
+                }
+                None => println!(
+                    "Pass --perk-bonus {:.1} to use them, or calibrate with --profile to store them",
+                    calibrated_skill.perk_bonus
+                ),
+            }
+        }
+        Commands::Appraise {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            max_ingredients,
+            inventory,
+            character,
+            strict_load_order,
+            use_cosave,
+            saves_path,
+            data_path,
+            ingredient,
+        } => {
+            alchemy_core::appraise_ingredient(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                ingredient,
+                &alchemy_core::AppraiseOptions {
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    use_inventory: *inventory,
+                    character: character.clone(),
+                    strict_load_order: *strict_load_order
+                        || settings.map_or(false, |s| s.strict_load_order),
+                    use_cosave: *use_cosave,
+                    formatter: alchemy_core::PotionFormatter::new(
+                        alchemy_core::OutputStyle::Block,
+                        use_color(cli.no_color),
+                    ),
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::ExportPotions {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            max_ingredients,
+            format,
+            data_path,
+            export_path,
+        } => {
+            alchemy_core::export_potions(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                export_path,
+                &alchemy_core::ExportPotionsOptions {
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    format: *format,
+                },
+            )?;
+        }
+        Commands::ExportMatrix {
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            format,
+            data_path,
+            export_path,
+        } => {
+            alchemy_core::export_pair_matrix(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                export_path,
+                &alchemy_core::ExportMatrixOptions {
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    format: *format,
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::ExportGraph {
+            format,
+            potions,
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            max_ingredients,
+            data_path,
+            export_path,
+        } => {
+            alchemy_core::export_graph(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                export_path,
+                &alchemy_core::ExportGraphOptions {
+                    format: *format,
+                    num_potions: *potions,
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::Info { data_path } => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            alchemy_core::print_game_data_info(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                std::io::stdout(),
+            )?;
+        }
+        Commands::CheckOverrides { data_path } => {
+            alchemy_core::check_ingredient_overrides(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                std::io::stdout(),
+            )?;
+        }
+        Commands::PlanCoverage {
+            effects,
+            cost,
+            data_path,
+        } => {
+            alchemy_core::plan_effect_coverage(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &alchemy_core::CoveragePlanOptions {
+                    effects: effects.clone(),
+                    cost: *cost,
+                    effect_aliases: effect_aliases()?,
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::CheckHostility { data_path } => {
+            alchemy_core::check_effect_hostility(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                cli.hostility_overrides
+                    .as_deref()
+                    .map(Path::new)
+                    .or(paths::DEFAULT_HOSTILITY_OVERRIDES_PATH.as_deref()),
+                std::io::stdout(),
+            )?;
+        }
+        Commands::Serve {
+            address,
+            poll_interval,
+            alchemy_skill,
+            fortify_alchemy,
+            perk_bonus,
+            max_ingredients,
+            no_cache,
+            character,
+            strict_load_order,
+            use_cosave,
+            saves_path,
+            data_path,
+        } => {
+            alchemy_core::serve(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                &alchemy_core::ServeOptions {
+                    address: address.clone(),
+                    max_ingredients: max_ingredients
+                        .or_else(|| settings.and_then(|s| s.max_ingredients))
+                        .unwrap_or(alchemy_core::DEFAULT_MAX_INGREDIENTS),
+                    alchemy_skill: get_alchemy_skill(
+                        *alchemy_skill,
+                        *fortify_alchemy,
+                        *perk_bonus,
+                        settings,
+                    ),
+                    character: character.clone(),
+                    strict_load_order: *strict_load_order
+                        || settings.map_or(false, |s| s.strict_load_order),
+                    use_cosave: *use_cosave,
+                    poll_interval: Duration::from_secs(*poll_interval),
+                    cache_path: match (no_cache, profile) {
+                        (true, _) => None,
+                        (false, Some(profile)) => Some(profile.get_potions_cache_path()),
+                        (false, None) => paths::DEFAULT_POTIONS_CACHE_PATH.clone(),
+                    },
+                },
+            )?;
+        }
+        Commands::DebugPlugin { path } => {
+            print!("{}", alchemy_core::dump_plugin(path)?);
+        }
+        Commands::ListSaves {
+            character,
+            saves_path,
+        } => {
+            alchemy_core::list_saves(
+                &get_saves_location(saves_path.as_ref(), settings),
+                character.as_deref(),
+                std::io::stdout(),
+            )?;
+        }
+        Commands::StockTrends {
+            character,
+            saves,
+            limit,
+            strict_load_order,
+            use_cosave,
+            saves_path,
+            data_path,
+        } => {
+            alchemy_core::report_stock_trends(
+                resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                &import_options,
+                &get_saves_location(saves_path.as_ref(), settings),
+                &alchemy_core::StockTrendsOptions {
+                    character: character.clone(),
+                    max_saves: *saves,
+                    limit: *limit,
+                    strict_load_order: *strict_load_order
+                        || settings.map_or(false, |s| s.strict_load_order),
+                    use_cosave: *use_cosave,
+                },
+                std::io::stdout(),
+            )?;
+        }
+        Commands::Doctor {
+            game_path,
+            local_path,
+            saves_path,
+        } => {
+            let rustc_version = env!("ALCHEMY_RUSTC_VERSION");
+            let mut build_info = vec![
+                format!(
+                    "{} {} ({} build)",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION"),
+                    match cfg!(debug_assertions) {
+                        true => "debug",
+                        false => "release",
+                    }
+                ),
+                format!("Built with {}", rustc_version),
+            ];
+            if cfg!(debug_assertions) {
+                build_info.push(String::from(
+                    "Debug builds are much slower, build with cargo build --release",
+                ));
+            }
+            if !rustc_version.contains("nightly") {
+                build_info.push(String::from(
+                    "This project is meant to be built with nightly Rust, see rust-toolchain.toml",
+                ));
+            }
+
+            for line in build_info {
+                println!("{}", line);
+            }
+            println!();
+
+            let findings = alchemy_core::run_doctor(&alchemy_core::DoctorOptions {
+                game_path: game_path.as_ref().map(PathBuf::from),
+                local_path: local_path.as_ref().map(PathBuf::from),
+                saves_location: get_saves_location(saves_path.as_ref(), settings),
+                // The cache directories and the profile's directory
+                writable_dirs: [
+                    paths::DEFAULT_POTIONS_CACHE_PATH.as_deref(),
+                    paths::DEFAULT_SAVE_INDEX_PATH.as_deref(),
+                ]
+                .into_iter()
+                .flatten()
+                .filter_map(Path::parent)
+                .map(Path::to_path_buf)
+                .chain(profile.map(|profile| profile.get_path().to_path_buf()))
+                .collect(),
+            });
+            for finding in &findings {
+                println!("{}", finding);
+            }
+
+            let count = |severity: alchemy_core::FindingSeverity| {
+                findings
+                    .iter()
+                    .filter(|finding| finding.severity == severity)
+                    .count()
+            };
+            println!();
+            match (
+                count(alchemy_core::FindingSeverity::Problem),
+                count(alchemy_core::FindingSeverity::Warning),
+            ) {
+                (0, 0) => println!("No problems found"),
+                (0, num_warnings) => println!("No problems found, but {} warnings", num_warnings),
+                (num_problems, num_warnings) => Err(anyhow!(
+                    "Found {} problems and {} warnings",
+                    num_problems,
+                    num_warnings
+                ))?,
+            }
+        }
+        Commands::Profiles { command } => match command {
+            ProfilesCommands::List => {
+                for name in Profile::list()? {
+                    println!("{}", name);
+                }
+            }
+            ProfilesCommands::Create { name } => {
+                let profile = Profile::create(name)?;
+                println!(
+                    "Created profile \"{}\" in {}",
+                    name,
+                    profile.get_path().display()
+                );
+            }
+            ProfilesCommands::Delete { name } => {
+                Profile::delete(name)?;
+                println!("Deleted profile \"{}\"", name);
+            }
+        },
+        Commands::Bookmarks { command } => {
+            let bookmarks_path = profile
+                .map(Profile::get_bookmarks_path)
+                .ok_or_else(|| anyhow!("Bookmarks are stored in a profile, pass --profile"))?;
+            match command {
+                BookmarksCommands::Add {
+                    name,
+                    alchemy_skill,
+                    fortify_alchemy,
+                    perk_bonus,
+                    data_path,
+                    ingredients,
+                } => {
+                    alchemy_core::add_recipe_bookmark(
+                        resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                        &import_options,
+                        bookmarks_path,
+                        name,
+                        ingredients,
+                        &get_alchemy_skill(*alchemy_skill, *fortify_alchemy, *perk_bonus, settings),
+                        std::io::stdout(),
+                    )?;
+                }
+                BookmarksCommands::List => alchemy_core::list_recipe_bookmarks(
+                    bookmarks_path,
+                    import_options
+                        .number_locale
+                        .unwrap_or(alchemy_core::NumberLocale::PLAIN),
+                    std::io::stdout(),
+                )?,
+                BookmarksCommands::Check {
+                    alchemy_skill,
+                    fortify_alchemy,
+                    perk_bonus,
+                    inventory,
+                    character,
+                    strict_load_order,
+                    use_cosave,
+                    saves_path,
+                    data_path,
+                } => {
+                    alchemy_core::check_recipe_bookmarks(
+                        resolve_path(data_path, profile, Profile::get_game_data_path, "data-path")?,
+                        &import_options,
+                        &get_saves_location(saves_path.as_ref(), settings),
+                        bookmarks_path,
+                        &alchemy_core::CheckBookmarksOptions {
+                            alchemy_skill: get_alchemy_skill(
+                                *alchemy_skill,
+                                *fortify_alchemy,
+                                *perk_bonus,
+                                settings,
+                            ),
+                            use_inventory: *inventory,
+                            character: character.clone(),
+                            strict_load_order: *strict_load_order
+                                || settings.map_or(false, |s| s.strict_load_order),
+                            use_cosave: *use_cosave,
+                        },
+                        std::io::stdout(),
+                    )?;
+                }
+                BookmarksCommands::Remove { name } => {
+                    alchemy_core::remove_recipe_bookmark(bookmarks_path, name, std::io::stdout())?
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
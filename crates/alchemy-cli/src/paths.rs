@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    /// The default location of the user's effect aliases file
+    pub static ref DEFAULT_EFFECT_ALIASES_PATH: Option<PathBuf> = dirs::config_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("effect_aliases.toml"));
+    /// The default location of the user's hostility overrides file
+    pub static ref DEFAULT_HOSTILITY_OVERRIDES_PATH: Option<PathBuf> = dirs::config_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("hostility_overrides.toml"));
+    /// The default location of the user's ingredient overlay file
+    pub static ref DEFAULT_INGREDIENT_OVERLAY_PATH: Option<PathBuf> = dirs::config_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("ingredient_overlay.toml"));
+    /// The default location of the user's ingredient tags file
+    pub static ref DEFAULT_INGREDIENT_TAGS_PATH: Option<PathBuf> = dirs::config_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("ingredient_tags.toml"));
+    /// The default location of the potions cache
+    pub static ref DEFAULT_POTIONS_CACHE_PATH: Option<PathBuf> = dirs::cache_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("potions.cache"));
+    /// The default location of the index of save headers
+    pub static ref DEFAULT_SAVE_INDEX_PATH: Option<PathBuf> = dirs::cache_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("save_index.json"));
+    /// Where the vanilla game data bundled with the vanilla-data feature is unpacked to
+    pub static ref VANILLA_GAME_DATA_PATH: Option<PathBuf> = dirs::cache_dir()
+        .map(|path| path.join("skyrim-alchemy-rs").join("vanilla_game_data.json.gz"));
+    /// The game's directory in the user's documents, which contains its INI files and by default
+    /// its saves
+    pub static ref MY_GAMES_PATH: Option<PathBuf> = dirs::document_dir()
+        .map(|path| path.join("My Games/Skyrim Special Edition"));
+}
+
+/// Loads the file at the given path, or else the file at the default path if it exists. Returns
+/// `None` if there is neither.
+pub fn load_user_file<T>(
+    path: Option<&str>,
+    default_path: Option<&Path>,
+    load: impl FnOnce(&Path) -> Result<T, anyhow::Error>,
+) -> Result<Option<T>, anyhow::Error> {
+    match (path, default_path) {
+        (Some(path), _) => load(Path::new(path)).map(Some),
+        (None, Some(default_path)) if default_path.exists() => load(default_path).map(Some),
+        _ => Ok(None),
+    }
+}
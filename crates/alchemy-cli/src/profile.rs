@@ -0,0 +1,183 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref PROFILES_PATH: Option<PathBuf> =
+        dirs::data_dir().map(|path| path.join("skyrim-alchemy-rs").join("profiles"));
+}
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const GAME_DATA_FILE_NAME: &str = "game_data.json";
+const POTIONS_CACHE_FILE_NAME: &str = "potions.cache";
+const INGREDIENTS_BLACKLIST_FILE_NAME: &str = "ingredients_blacklist.txt";
+const INGREDIENTS_WHITELIST_FILE_NAME: &str = "ingredients_whitelist.txt";
+const INGREDIENT_TAGS_FILE_NAME: &str = "ingredient_tags.toml";
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.json";
+
+/// Default options stored in a profile. Options passed on the command line take precedence.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    pub saves_path: Option<String>,
+    /// Default limit for suggest-potions
+    pub limit: Option<usize>,
+    pub max_ingredients: Option<usize>,
+    pub strict_load_order: bool,
+    pub only_known_effects: bool,
+    pub alchemy_skill: Option<u32>,
+    pub fortify_alchemy: Option<f32>,
+    pub perk_bonus: Option<f32>,
+    /// Weight of a crafted potion, for modlists that change it
+    pub potion_weight: Option<f32>,
+    /// Default potion name template, e.g. "{type}: {effects:2} ({value}g)"
+    pub name_template: Option<String>,
+    /// Names or editor IDs of effects to leave out of all potions
+    pub junk_effects: Vec<String>,
+}
+
+/// A named profile, which keeps the exported game data, caches, ingredient filters and default
+/// options for one character or modlist together in a directory.
+pub struct Profile {
+    path: PathBuf,
+    pub settings: ProfileSettings,
+}
+
+fn get_profiles_path() -> Result<&'static Path, anyhow::Error> {
+    PROFILES_PATH
+        .as_deref()
+        .ok_or_else(|| anyhow!("Could not determine where to store profiles"))
+}
+
+fn get_profile_path(name: &str) -> Result<PathBuf, anyhow::Error> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Err(anyhow!(
+            "Invalid profile name \"{}\": only letters, digits, '-' and '_' are allowed",
+            name
+        ))?
+    }
+
+    Ok(get_profiles_path()?.join(name))
+}
+
+impl Profile {
+    /// Opens the existing profile with the given name
+    pub fn open(name: &str) -> Result<Self, anyhow::Error> {
+        let path = get_profile_path(name)?;
+        if !path.is_dir() {
+            Err(anyhow!(
+                "Profile \"{}\" does not exist, create it with `profiles create {}`",
+                name,
+                name
+            ))?
+        }
+
+        let settings_path = path.join(SETTINGS_FILE_NAME);
+        let settings = if settings_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&settings_path)?)
+                .with_context(|| format!("Failed to read {}", settings_path.display()))?
+        } else {
+            ProfileSettings::default()
+        };
+
+        Ok(Self { path, settings })
+    }
+
+    /// Creates a new profile with the given name and default settings
+    pub fn create(name: &str) -> Result<Self, anyhow::Error> {
+        let path = get_profile_path(name)?;
+        if path.exists() {
+            Err(anyhow!("Profile \"{}\" already exists", name))?
+        }
+
+        fs::create_dir_all(&path)?;
+        let profile = Self {
+            path,
+            settings: ProfileSettings::default(),
+        };
+        profile.save_settings()?;
+
+        Ok(profile)
+    }
+
+    /// Writes the profile's settings to its settings.json file
+    pub fn save_settings(&self) -> Result<(), anyhow::Error> {
+        let settings_path = self.path.join(SETTINGS_FILE_NAME);
+        fs::write(
+            &settings_path,
+            serde_json::to_string_pretty(&self.settings)?,
+        )
+        .with_context(|| format!("Failed to write {}", settings_path.display()))?;
+
+        Ok(())
+    }
+
+    /// Deletes the profile with the given name, including its game data and caches
+    pub fn delete(name: &str) -> Result<(), anyhow::Error> {
+        let path = get_profile_path(name)?;
+        if !path.is_dir() {
+            Err(anyhow!("Profile \"{}\" does not exist", name))?
+        }
+
+        fs::remove_dir_all(&path)?;
+        Ok(())
+    }
+
+    /// Returns the names of all profiles, sorted alphabetically
+    pub fn list() -> Result<Vec<String>, anyhow::Error> {
+        let profiles_path = get_profiles_path()?;
+        if !profiles_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = fs::read_dir(profiles_path)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+        names.sort();
+
+        Ok(names)
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_game_data_path(&self) -> PathBuf {
+        self.path.join(GAME_DATA_FILE_NAME)
+    }
+
+    pub fn get_potions_cache_path(&self) -> PathBuf {
+        self.path.join(POTIONS_CACHE_FILE_NAME)
+    }
+
+    /// Returns the path to the profile's ingredients blacklist, if it has one
+    pub fn get_ingredients_blacklist_path(&self) -> Option<PathBuf> {
+        Some(self.path.join(INGREDIENTS_BLACKLIST_FILE_NAME)).filter(|path| path.exists())
+    }
+
+    /// Returns the path to the profile's ingredient tags. The file may not exist.
+    pub fn get_ingredient_tags_path(&self) -> PathBuf {
+        self.path.join(INGREDIENT_TAGS_FILE_NAME)
+    }
+
+    /// Returns the path to the profile's bookmarked potions. The file may not exist.
+    pub fn get_bookmarks_path(&self) -> PathBuf {
+        self.path.join(BOOKMARKS_FILE_NAME)
+    }
+
+    /// Returns the path to the profile's ingredients whitelist, if it has one
+    pub fn get_ingredients_whitelist_path(&self) -> Option<PathBuf> {
+        Some(self.path.join(INGREDIENTS_WHITELIST_FILE_NAME)).filter(|path| path.exists())
+    }
+}
@@ -0,0 +1,40 @@
+//! Suggests the most valuable potions from some of the ingredients, like a filter file does.
+//!
+//! Run with `cargo run -p alchemy-core --features test-fixtures --example filtered_potions`.
+
+use alchemy_core::{
+    game_data::GameData, suggest_for_ingredients, AlchemySkill, GlobalFormId,
+    DEFAULT_MAX_INGREDIENTS,
+};
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut game_data = GameData::example();
+
+    // Leave out the ingredients that are expensive to buy. Ingredients can be filtered by
+    // anything, e.g. by name to match a list the user picked.
+    game_data.retain_ingredients(|ig| ig.value.map_or(true, |value| value <= 6));
+
+    let ingredient_ids = game_data
+        .get_ingredients()
+        .keys()
+        .copied()
+        .collect::<Vec<GlobalFormId>>();
+    let potions = suggest_for_ingredients(
+        &game_data,
+        &ingredient_ids,
+        &AlchemySkill::default(),
+        DEFAULT_MAX_INGREDIENTS,
+        5,
+    )?;
+    assert!(!potions.is_empty());
+    assert!(potions
+        .iter()
+        .flat_map(|potion| &potion.ingredients)
+        .all(|ig| ig.name.as_deref() != Some("Silver Thistle")));
+
+    for potion in &potions {
+        println!("{}\n", potion);
+    }
+
+    Ok(())
+}
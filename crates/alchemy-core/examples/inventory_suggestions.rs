@@ -0,0 +1,57 @@
+//! Suggests potions to brew from a player's inventory, ranked by how much gold they make per
+//! ingredient used up. The inventory would normally be read from a save, here it is made up.
+//!
+//! Run with `cargo run -p alchemy-core --features test-fixtures --example inventory_suggestions`.
+
+use std::cmp::Reverse;
+
+use alchemy_core::{
+    game_data::GameData, suggest_for_ingredients, AlchemySkill, PlayerContext, PoisonRanking,
+    PotionRanking, Ranking, DEFAULT_MAX_INGREDIENTS,
+};
+
+fn main() -> Result<(), anyhow::Error> {
+    let mut game_data = GameData::example();
+
+    let alchemy_skill = AlchemySkill::default();
+    let mut player_context = PlayerContext::new(alchemy_skill);
+    for (name, count) in [
+        ("Frost Lily", 12),
+        ("Ember Root", 1),
+        ("Marsh Cap", 7),
+        ("Salt Crystal", 20),
+    ] {
+        let ingredient_id = game_data.find_ingredient_by_name(name)?.global_form_id;
+        player_context.inventory.insert(ingredient_id, count);
+    }
+    game_data.set_player_context(player_context);
+    game_data.retain_inventory_ingredients();
+    assert_eq!(game_data.get_ingredients().len(), 4);
+
+    let ingredient_ids = game_data
+        .get_ingredients()
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    let mut potions = suggest_for_ingredients(
+        &game_data,
+        &ingredient_ids,
+        &alchemy_skill,
+        DEFAULT_MAX_INGREDIENTS,
+        usize::MAX,
+    )?;
+
+    // The sort is stable, so potions with the same score stay in order of gold value
+    let ranking = Ranking {
+        potions: PotionRanking::ValueDensity,
+        poisons: PoisonRanking::ValueDensity,
+    };
+    potions
+        .sort_by_key(|potion| Reverse(potion.get_score(&ranking, game_data.get_player_context())));
+
+    for potion in potions.iter().take(5) {
+        println!("{}\n", potion);
+    }
+
+    Ok(())
+}
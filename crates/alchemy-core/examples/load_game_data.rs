@@ -0,0 +1,47 @@
+//! Loads game data exported with `alchemy export-game-data` and lists its ingredients.
+//!
+//! Run with `cargo run -p alchemy-core --example load_game_data -- <game data path>`. Without a
+//! path, the bundled example game data is loaded.
+
+use std::path::PathBuf;
+
+use alchemy_core::{import_game_data, ImportOptions};
+use itertools::Itertools;
+
+fn main() -> Result<(), anyhow::Error> {
+    let path = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/example_game_data.json")
+        });
+
+    // Prepares the game data with the import options, e.g. the crafting rules, like the CLI does.
+    // Use `GameData::from_json_slice` to parse game data that is already in memory without them.
+    let game_data = import_game_data(&path, &ImportOptions::default())?;
+    println!(
+        "Loaded {} ingredients and {} magic effects from {}",
+        game_data.get_ingredients().len(),
+        game_data.get_magic_effects().len(),
+        path.display()
+    );
+    match game_data.get_load_order_hash() {
+        Some(load_order_hash) => println!("Load order hash: {}", load_order_hash),
+        None => println!("The game data has no load order hash"),
+    }
+
+    for ingredient in game_data
+        .get_ingredients()
+        .values()
+        .sorted_by_key(|ig| ig.name.as_deref())
+    {
+        println!(
+            "- {} ({}), {} effects",
+            ingredient.name.as_deref().unwrap_or(&ingredient.editor_id),
+            ingredient.global_form_id,
+            ingredient.effects.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Suggests the most valuable potions whose primary effect is the given effect.
+//!
+//! Run with `cargo run -p alchemy-core --features test-fixtures --example query_by_effect --
+//! "Resist Fire"`.
+
+use alchemy_core::{
+    game_data::GameData, suggest_for_ingredients, AlchemySkill, DEFAULT_MAX_INGREDIENTS,
+};
+use anyhow::anyhow;
+
+fn main() -> Result<(), anyhow::Error> {
+    let game_data = GameData::example();
+    let effect_name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Resist Fire".to_string());
+
+    let effect = game_data
+        .get_magic_effects()
+        .values()
+        .find(|mgef| mgef.name.as_deref() == Some(effect_name.as_str()))
+        .ok_or_else(|| {
+            anyhow!(
+                "no effect named {}, did you mean one of: {}",
+                effect_name,
+                game_data.find_similar_effect_names(&effect_name).join(", ")
+            )
+        })?;
+
+    // Every potion with the effect contains at least one ingredient with it
+    let ingredient_ids = game_data
+        .get_ingredients_with_effect(&effect.global_form_id)
+        .map(|ig| ig.global_form_id)
+        .collect::<Vec<_>>();
+    let potions = suggest_for_ingredients(
+        &game_data,
+        &ingredient_ids,
+        &AlchemySkill::default(),
+        DEFAULT_MAX_INGREDIENTS,
+        usize::MAX,
+    )?;
+
+    // The suggestions are sorted by gold value, so the first matches are the most valuable
+    let potions = potions
+        .iter()
+        .filter(|potion| {
+            potion.get_primary_effect().magic_effect.global_form_id == effect.global_form_id
+        })
+        .take(5)
+        .collect::<Vec<_>>();
+    assert!(!potions.is_empty());
+
+    for potion in potions {
+        println!("{}\n", potion);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+use std::{fmt::Write as _, io::Write, str::FromStr};
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData},
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId},
+    potion::{Potion, PotionType},
+};
+
+/// The file format to export the graph to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    /// GraphViz's DOT language
+    #[default]
+    Dot,
+    /// The XML format that Gephi, yEd and most other graph tools read
+    Graphml,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::Graphml),
+            other => Err(format!("unknown format {}, expected dot or graphml", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NodeKind {
+    Ingredient,
+    Effect,
+    /// A potion made from the ingredients it is connected to, which stands in for the hyperedge
+    /// between them, since neither format has hyperedges
+    Potion,
+}
+
+impl NodeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            NodeKind::Ingredient => "ingredient",
+            NodeKind::Effect => "effect",
+            NodeKind::Potion => "potion",
+        }
+    }
+}
+
+pub(crate) struct GraphNode {
+    pub(crate) id: String,
+    pub(crate) kind: NodeKind,
+    pub(crate) label: String,
+    /// The plugin that the ingredient or effect comes from. `None` for potions.
+    pub(crate) plugin: Option<String>,
+    /// The base gold value of an ingredient, or the gold value of a potion. `None` for effects
+    /// and for ingredients in game data exported by older versions.
+    pub(crate) value: Option<u32>,
+    /// The base cost of an effect. `None` for ingredients and potions.
+    pub(crate) base_cost: Option<f32>,
+    /// Whether an effect is hostile or a potion is a poison. `None` for ingredients.
+    pub(crate) hostile: Option<bool>,
+}
+
+pub(crate) struct GraphEdge {
+    pub(crate) source: String,
+    pub(crate) target: String,
+    /// The magnitude and duration of the ingredient's effect, for edges from an ingredient to an
+    /// effect
+    pub(crate) magnitude: Option<f32>,
+    pub(crate) duration: Option<u32>,
+}
+
+/// The bipartite graph of the ingredients and the effects they have, optionally with potions
+/// connected to the ingredients they are made from
+pub(crate) struct AlchemyGraph {
+    pub(crate) nodes: Vec<GraphNode>,
+    pub(crate) edges: Vec<GraphEdge>,
+}
+
+fn get_ingredient_node_id(form_id: GlobalFormId) -> String {
+    format!("ingredient:{}", form_id)
+}
+
+fn get_effect_node_id(form_id: GlobalFormId) -> String {
+    format!("effect:{}", form_id)
+}
+
+impl AlchemyGraph {
+    /// Builds the graph of all ingredients in the game data and their effects, in form ID order so
+    /// that exports of the same game data are the same
+    pub(crate) fn from_game_data(game_data: &GameData) -> Self {
+        let plugin_name =
+            |form_id: &GlobalFormId| game_data.get_plugin_name(form_id).map(String::from);
+
+        let mut magic_effects = game_data.get_magic_effects().values().collect::<Vec<_>>();
+        magic_effects.sort_by_key(|mgef| mgef.get_global_form_id());
+        let mut nodes = magic_effects
+            .into_iter()
+            .map(|mgef| GraphNode {
+                id: get_effect_node_id(mgef.get_global_form_id()),
+                kind: NodeKind::Effect,
+                label: get_magic_effect_name_or_fallback(mgef).into_owned(),
+                plugin: plugin_name(&mgef.get_global_form_id()),
+                value: None,
+                base_cost: Some(mgef.base_cost),
+                hostile: Some(mgef.is_hostile),
+            })
+            .collect::<Vec<_>>();
+
+        let mut ingredients = game_data.get_ingredients().values().collect::<Vec<_>>();
+        ingredients.sort_by_key(|ig| ig.get_global_form_id());
+        let mut edges = Vec::new();
+        for ingredient in ingredients {
+            let id = get_ingredient_node_id(ingredient.get_global_form_id());
+            for effect in &ingredient.effects {
+                edges.push(GraphEdge {
+                    source: id.clone(),
+                    target: get_effect_node_id(effect.get_global_form_id()),
+                    magnitude: Some(effect.magnitude),
+                    duration: Some(effect.duration),
+                });
+            }
+            nodes.push(GraphNode {
+                id,
+                kind: NodeKind::Ingredient,
+                label: get_ingredient_name_or_fallback(ingredient).into_owned(),
+                plugin: plugin_name(&ingredient.get_global_form_id()),
+                value: ingredient.value,
+                base_cost: None,
+                hostile: None,
+            });
+        }
+
+        Self { nodes, edges }
+    }
+
+    /// Adds a node for each potion, connected to the ingredients it is made from
+    pub(crate) fn add_potions<'p, 'a: 'p>(
+        &mut self,
+        game_data: &GameData,
+        potions: impl IntoIterator<Item = &'p Potion<'a>>,
+    ) {
+        for (index, potion) in potions.into_iter().enumerate() {
+            let id = format!("potion:{}", index + 1);
+            for ingredient in &potion.ingredients {
+                self.edges.push(GraphEdge {
+                    source: get_ingredient_node_id(ingredient.get_global_form_id()),
+                    target: id.clone(),
+                    magnitude: None,
+                    duration: None,
+                });
+            }
+            self.nodes.push(GraphNode {
+                id,
+                kind: NodeKind::Potion,
+                label: potion.get_potion_name(game_data),
+                plugin: None,
+                value: Some(potion.gold_value),
+                base_cost: None,
+                hostile: Some(potion.get_potion_type() == PotionType::Poison),
+            });
+        }
+    }
+
+    /// Writes the graph in GraphViz's DOT language. The edges are undirected, and the attributes
+    /// are written as node and edge attributes, which GraphViz ignores but other tools read.
+    fn write_dot<W: Write>(&self, mut writer: W) -> Result<(), anyhow::Error> {
+        writeln!(writer, "graph alchemy {{")?;
+        for node in &self.nodes {
+            let mut attributes = format!(
+                "label={}, kind={}",
+                quote_dot(&node.label),
+                node.kind.as_str()
+            );
+            if let Some(plugin) = &node.plugin {
+                write!(attributes, ", plugin={}", quote_dot(plugin))?;
+            }
+            if let Some(value) = node.value {
+                write!(attributes, ", value={}", value)?;
+            }
+            if let Some(base_cost) = node.base_cost {
+                write!(attributes, ", base_cost={}", base_cost)?;
+            }
+            if let Some(hostile) = node.hostile {
+                write!(attributes, ", hostile={}", hostile)?;
+            }
+            if node.kind != NodeKind::Ingredient {
+                write!(attributes, ", shape=box")?;
+            }
+            writeln!(writer, "  {} [{}];", quote_dot(&node.id), attributes)?;
+        }
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if let Some(magnitude) = edge.magnitude {
+                attributes.push(format!("magnitude={}", magnitude));
+            }
+            if let Some(duration) = edge.duration {
+                attributes.push(format!("duration={}", duration));
+            }
+            write!(
+                writer,
+                "  {} -- {}",
+                quote_dot(&edge.source),
+                quote_dot(&edge.target)
+            )?;
+            match attributes.is_empty() {
+                true => writeln!(writer, ";")?,
+                false => writeln!(writer, " [{}];", attributes.join(", "))?,
+            }
+        }
+        writeln!(writer, "}}")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Writes the graph as GraphML, with the attributes declared as GraphML keys
+    fn write_graphml<W: Write>(&self, mut writer: W) -> Result<(), anyhow::Error> {
+        const NODE_KEYS: &[(&str, &str)] = &[
+            ("label", "string"),
+            ("kind", "string"),
+            ("plugin", "string"),
+            ("value", "int"),
+            ("base_cost", "double"),
+            ("hostile", "boolean"),
+        ];
+        const EDGE_KEYS: &[(&str, &str)] = &[("magnitude", "double"), ("duration", "int")];
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (domain, keys) in [("node", NODE_KEYS), ("edge", EDGE_KEYS)] {
+            for (name, key_type) in keys {
+                writeln!(
+                    writer,
+                    r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+                    name, domain, name, key_type
+                )?;
+            }
+        }
+        writeln!(writer, r#"  <graph id="alchemy" edgedefault="undirected">"#)?;
+
+        let write_data = |writer: &mut W, key: &str, value: &str| -> std::io::Result<()> {
+            writeln!(
+                writer,
+                r#"      <data key="{}">{}</data>"#,
+                key,
+                escape_xml(value)
+            )
+        };
+        for node in &self.nodes {
+            writeln!(writer, r#"    <node id="{}">"#, escape_xml(&node.id))?;
+            write_data(&mut writer, "label", &node.label)?;
+            write_data(&mut writer, "kind", node.kind.as_str())?;
+            if let Some(plugin) = &node.plugin {
+                write_data(&mut writer, "plugin", plugin)?;
+            }
+            if let Some(value) = node.value {
+                write_data(&mut writer, "value", &value.to_string())?;
+            }
+            if let Some(base_cost) = node.base_cost {
+                write_data(&mut writer, "base_cost", &base_cost.to_string())?;
+            }
+            if let Some(hostile) = node.hostile {
+                write_data(&mut writer, "hostile", &hostile.to_string())?;
+            }
+            writeln!(writer, "    </node>")?;
+        }
+        for edge in &self.edges {
+            writeln!(
+                writer,
+                r#"    <edge source="{}" target="{}">"#,
+                escape_xml(&edge.source),
+                escape_xml(&edge.target)
+            )?;
+            if let Some(magnitude) = edge.magnitude {
+                write_data(&mut writer, "magnitude", &magnitude.to_string())?;
+            }
+            if let Some(duration) = edge.duration {
+                write_data(&mut writer, "duration", &duration.to_string())?;
+            }
+            writeln!(writer, "    </edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub(crate) fn write<W: Write>(
+        &self,
+        format: GraphFormat,
+        writer: W,
+    ) -> Result<(), anyhow::Error> {
+        match format {
+            GraphFormat::Dot => self.write_dot(writer),
+            GraphFormat::Graphml => self.write_graphml(writer),
+        }
+    }
+}
+
+/// Quotes the string as a DOT ID
+fn quote_dot(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_to_string(graph: &AlchemyGraph, format: GraphFormat) -> String {
+        let mut bytes = Vec::new();
+        graph.write(format, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn ingredients_are_connected_to_their_effects_and_potions() {
+        let game_data = GameData::example();
+        let mut graph = AlchemyGraph::from_game_data(&game_data);
+        let num_effects = game_data.get_magic_effects().len();
+        let num_ingredients = game_data.get_ingredients().len();
+        assert_eq!(graph.nodes.len(), num_effects + num_ingredients);
+
+        for ingredient in game_data.get_ingredients().values() {
+            let id = get_ingredient_node_id(ingredient.get_global_form_id());
+            let node = graph.nodes.iter().find(|node| node.id == id).unwrap();
+            assert_eq!(node.kind, NodeKind::Ingredient);
+            assert_eq!(node.plugin.as_deref(), Some("Example.esm"));
+
+            let mut targets = graph
+                .edges
+                .iter()
+                .filter(|edge| edge.source == id)
+                .map(|edge| edge.target.clone())
+                .collect::<Vec<_>>();
+            targets.sort();
+            let mut effect_ids = ingredient
+                .effects
+                .iter()
+                .map(|effect| get_effect_node_id(effect.get_global_form_id()))
+                .collect::<Vec<_>>();
+            effect_ids.sort();
+            assert_eq!(targets, effect_ids);
+        }
+        assert!(graph
+            .edges
+            .iter()
+            .all(|edge| graph.nodes.iter().any(|node| node.id == edge.target)));
+
+        let ingredients =
+            [0x800, 0x801].map(|id| game_data.get_ingredient(&GlobalFormId::new(0, id)).unwrap());
+        let potion = Potion::from_ingredients(&ingredients, &game_data, 1.0).unwrap();
+        graph.add_potions(&game_data, [&potion]);
+        let node = graph.nodes.last().unwrap();
+        assert_eq!(node.id, "potion:1");
+        assert_eq!(node.kind, NodeKind::Potion);
+        assert_eq!(node.value, Some(potion.gold_value));
+        let sources = graph
+            .edges
+            .iter()
+            .filter(|edge| edge.target == "potion:1")
+            .map(|edge| edge.source.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            ingredients.map(|ingredient| get_ingredient_node_id(ingredient.get_global_form_id()))
+        );
+    }
+
+    #[test]
+    fn names_are_escaped_in_both_formats() {
+        let game_data = GameData::example();
+        let mut graph = AlchemyGraph::from_game_data(&game_data);
+        graph.nodes.truncate(1);
+        graph.edges.clear();
+        graph.nodes[0].label = r#"Sailor's "Salt" <&> C:\Ingredients"#.to_string();
+
+        let dot = write_to_string(&graph, GraphFormat::Dot);
+        assert!(
+            dot.contains(r#"label="Sailor's \"Salt\" <&> C:\\Ingredients""#),
+            "{}",
+            dot
+        );
+        assert!(dot.starts_with("graph alchemy {\n"));
+        assert!(dot.ends_with("}\n"));
+
+        let graphml = write_to_string(&graph, GraphFormat::Graphml);
+        assert!(
+            graphml.contains(
+                r#"<data key="label">Sailor&apos;s &quot;Salt&quot; &lt;&amp;&gt; C:\Ingredients</data>"#
+            ),
+            "{}",
+            graphml
+        );
+        assert!(!graphml.contains("<&>"));
+    }
+}
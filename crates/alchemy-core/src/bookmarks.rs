@@ -0,0 +1,307 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, GameData},
+    plugin_parser::{
+        form_id::{FormIdContainer, StableFormId},
+        ingredient::Ingredient,
+    },
+    potion::Potion,
+};
+
+/// A potion that the user bookmarked under a name, e.g. "Selling potion"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeBookmark {
+    pub name: String,
+    /// The ingredients by their plugin and their ID within it, sorted, so that the same recipe has
+    /// the same identity whatever the order of its ingredients and the load order
+    pub(crate) ingredients: Vec<StableFormId>,
+    /// The names of the ingredients when the potion was bookmarked, in the same order, to show
+    /// the ingredients that are no longer in the game data
+    pub(crate) ingredient_names: Vec<String>,
+    /// The potion's gold value when it was bookmarked
+    pub(crate) gold_value: u32,
+    /// The power factor the gold value was computed with, so that changes to the ingredients and
+    /// effects can be told apart from changes to the player's skill
+    pub(crate) power_factor: f32,
+}
+
+impl RecipeBookmark {
+    /// Bookmarks the potion, which must be crafted from the game data's ingredients
+    pub(crate) fn new(
+        name: String,
+        potion: &Potion,
+        game_data: &GameData,
+        power_factor: f32,
+    ) -> Result<Self, anyhow::Error> {
+        let mut ingredients = potion
+            .ingredients
+            .iter()
+            .map(|ingredient| {
+                let form_id = StableFormId::from_global(
+                    &ingredient.get_global_form_id(),
+                    game_data.get_load_order(),
+                )
+                .ok_or_else(|| {
+                    anyhow!(
+                        "the plugin of ingredient {} is not in the load order",
+                        get_ingredient_name_or_fallback(ingredient)
+                    )
+                })?;
+                Ok((
+                    form_id,
+                    get_ingredient_name_or_fallback(ingredient).into_owned(),
+                ))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        ingredients.sort_unstable();
+        let (ingredients, ingredient_names) = ingredients.into_iter().unzip();
+
+        Ok(Self {
+            name,
+            ingredients,
+            ingredient_names,
+            gold_value: potion.gold_value,
+            power_factor,
+        })
+    }
+
+    /// Returns the names of the ingredients when the potion was bookmarked
+    pub fn get_ingredient_names(&self) -> &[String] {
+        &self.ingredient_names
+    }
+
+    /// Returns the potion's gold value when it was bookmarked
+    pub fn get_gold_value(&self) -> u32 {
+        self.gold_value
+    }
+
+    /// Looks up the ingredients in the game data. If some of them are not in it, e.g. because their
+    /// plugin is no longer active, returns descriptions of those instead.
+    pub(crate) fn resolve<'a>(
+        &self,
+        game_data: &'a GameData,
+    ) -> Result<Vec<&'a Ingredient>, Vec<String>> {
+        let (found, missing): (Vec<_>, Vec<_>) = self
+            .ingredients
+            .iter()
+            .zip(&self.ingredient_names)
+            .map(|(form_id, name)| {
+                form_id
+                    .to_global(game_data.get_load_order())
+                    .and_then(|global_form_id| game_data.get_ingredient(&global_form_id))
+                    .ok_or_else(|| format!("{} ({})", name, form_id))
+            })
+            .partition(Result::is_ok);
+
+        match missing.is_empty() {
+            true => Ok(found.into_iter().map(Result::unwrap).collect()),
+            false => Err(missing.into_iter().map(Result::unwrap_err).collect()),
+        }
+    }
+}
+
+/// The user's bookmarked potions, stored as JSON
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecipeBookmarks {
+    bookmarks: Vec<RecipeBookmark>,
+}
+
+impl RecipeBookmarks {
+    /// Reads the bookmarks from the file at `path`. A file that doesn't exist has no bookmarks.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => Err(err)?,
+        };
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Failed to read bookmarks file {}", path.display()))
+    }
+
+    /// Writes the bookmarks to the file at `path`
+    pub fn save(&self, path: &Path) -> Result<(), anyhow::Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write bookmarks file {}", path.display()))
+    }
+
+    /// Adds the bookmark, unless another bookmark has the same name, ignoring case, or the same
+    /// ingredients
+    pub(crate) fn add(&mut self, bookmark: RecipeBookmark) -> Result<(), anyhow::Error> {
+        if let Some(existing) = self.bookmarks.iter().find(|existing| {
+            existing.name.eq_ignore_ascii_case(&bookmark.name)
+                || existing.ingredients == bookmark.ingredients
+        }) {
+            Err(match existing.name.eq_ignore_ascii_case(&bookmark.name) {
+                true => anyhow!(
+                    "A bookmark named \"{}\" already exists, remove it first",
+                    existing.name
+                ),
+                false => anyhow!("This potion is already bookmarked as \"{}\"", existing.name),
+            })?
+        }
+
+        self.bookmarks.push(bookmark);
+        Ok(())
+    }
+
+    /// Removes the bookmark with the name, ignoring case. Returns whether there was one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.bookmarks.len();
+        self.bookmarks
+            .retain(|bookmark| !bookmark.name.eq_ignore_ascii_case(name));
+        self.bookmarks.len() != len
+    }
+
+    /// Returns an iterator over the bookmarks in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = &RecipeBookmark> + '_ {
+        self.bookmarks.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::potion::AlchemySkill;
+
+    fn bookmark(name: &str, ingredient_names: &[&str], game_data: &GameData) -> RecipeBookmark {
+        let alchemy_skill = AlchemySkill::default();
+        let potion = game_data.craft(ingredient_names, &alchemy_skill).unwrap();
+        RecipeBookmark::new(
+            name.to_string(),
+            &potion,
+            game_data,
+            alchemy_skill.get_power_factor(),
+        )
+        .unwrap()
+    }
+
+    /// The example game data with a plugin loaded before Example.esm, which moves its records to
+    /// load order index 1
+    fn shifted_game_data() -> GameData {
+        let mut json = serde_json::from_str::<Value>(
+            &include_str!("../fixtures/example_game_data.json").replace("\"0000:", "\"0001:"),
+        )
+        .unwrap();
+        json["load_order"] = json!(["Update.esm", "Example.esm"]);
+        json["plugins"] = json!([
+            { "name": "Update.esm", "is_light": false },
+            { "name": "Example.esm", "is_light": false },
+        ]);
+        GameData::from_json_slice(&serde_json::to_vec(&json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn ingredient_order_does_not_matter() {
+        let game_data = GameData::example();
+        let healing = bookmark("Healing", &["Frost Lily", "Ember Root"], &game_data);
+        let reversed = bookmark("Reversed", &["Ember Root", "Frost Lily"], &game_data);
+        assert_eq!(healing.ingredients, reversed.ingredients);
+        // Sorted by form ID
+        assert_eq!(healing.ingredient_names, ["Frost Lily", "Ember Root"]);
+
+        let mut bookmarks = RecipeBookmarks::default();
+        bookmarks.add(healing).unwrap();
+        let err = bookmarks.add(reversed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "This potion is already bookmarked as \"Healing\""
+        );
+        let err = bookmarks
+            .add(bookmark(
+                "healing",
+                &["Marsh Cap", "Silver Thistle"],
+                &game_data,
+            ))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "A bookmark named \"Healing\" already exists, remove it first"
+        );
+
+        assert!(bookmarks.remove("HEALING"));
+        assert!(bookmarks.is_empty());
+        assert!(!bookmarks.remove("Healing"));
+    }
+
+    #[test]
+    fn bookmarks_resolve_across_load_orders() {
+        let game_data = GameData::example();
+        let healing = bookmark("Healing", &["Frost Lily", "Ember Root"], &game_data);
+
+        let shifted_game_data = shifted_game_data();
+        let ingredients = healing.resolve(&shifted_game_data).unwrap();
+        assert_eq!(
+            ingredients
+                .iter()
+                .map(|ingredient| ingredient.name.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            healing.get_ingredient_names()
+        );
+        assert!(ingredients
+            .iter()
+            .all(|ingredient| ingredient.get_global_form_id().load_order_index() == 1));
+
+        // Bookmarking the same potion in the shifted load order gives the same recipe
+        let shifted = bookmark("Shifted", &["Frost Lily", "Ember Root"], &shifted_game_data);
+        assert_eq!(shifted.ingredients, healing.ingredients);
+    }
+
+    #[test]
+    fn missing_ingredients_are_described() {
+        let healing = bookmark(
+            "Healing",
+            &["Frost Lily", "Ember Root"],
+            &GameData::example(),
+        );
+
+        let mut game_data = GameData::example();
+        game_data.retain_ingredients(|ingredient| ingredient.name.as_deref() != Some("Ember Root"));
+        let missing = healing.resolve(&game_data).unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].starts_with("Ember Root ("), "{}", missing[0]);
+    }
+
+    #[test]
+    fn bookmarks_are_saved_and_loaded() {
+        let dir = std::env::temp_dir().join(format!("alchemy-bookmarks-{}", std::process::id()));
+        let path = dir.join("bookmarks.json");
+        assert!(RecipeBookmarks::load(&path).unwrap().is_empty());
+
+        let game_data = GameData::example();
+        let mut bookmarks = RecipeBookmarks::default();
+        bookmarks
+            .add(bookmark(
+                "Healing",
+                &["Frost Lily", "Ember Root"],
+                &game_data,
+            ))
+            .unwrap();
+        bookmarks.save(&path).unwrap();
+        let loaded = RecipeBookmarks::load(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = loaded.iter().collect::<Vec<_>>();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, "Healing");
+        assert_eq!(loaded[0].ingredients, bookmarks.bookmarks[0].ingredients);
+        assert_eq!(loaded[0].gold_value, bookmarks.bookmarks[0].gold_value);
+        assert!(loaded[0].resolve(&game_data).is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+use ahash::AHashMap;
+
+use crate::{
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId},
+    potion::Potion,
+    skill_xp::{AlchemyXpSettings, SkillProgress},
+};
+
+/// Brewing a potion a number of times
+pub struct BrewStep<'p, 'a> {
+    pub potion: &'p Potion<'a>,
+    pub count: u32,
+}
+
+/// A sequence of potions to brew with the ingredients in the inventory
+pub struct BrewPlan<'p, 'a> {
+    pub steps: Vec<BrewStep<'p, 'a>>,
+    /// The total gold value of the brewed potions
+    pub total_value: u64,
+    /// The total weight of the ingredients consumed by the plan
+    pub ingredients_weight: f32,
+    /// The total weight of the brewed potions
+    pub potions_weight: f32,
+}
+
+/// Returns the total weight of the potion's ingredients
+fn get_ingredients_weight(potion: &Potion) -> f32 {
+    potion
+        .ingredients
+        .iter()
+        .map(|ig| ig.weight.unwrap_or(0.0))
+        .sum()
+}
+
+/// Plans which potions to brew with the ingredients in the inventory. Potions are considered in
+/// the order given, and each is brewed as many times as the remaining ingredients allow. If a
+/// carry budget is given, the total weight of the consumed ingredients stays within it. Each
+/// brewed potion weighs `potion_weight`.
+pub fn plan_brews<'p, 'a>(
+    potions: impl Iterator<Item = &'p Potion<'a>>,
+    inventory: &AHashMap<GlobalFormId, u32>,
+    carry_budget: Option<f32>,
+    potion_weight: f32,
+) -> BrewPlan<'p, 'a>
+where
+    'a: 'p,
+{
+    let mut remaining = inventory.clone();
+    let mut remaining_budget = carry_budget;
+    let mut plan = BrewPlan {
+        steps: Vec::new(),
+        total_value: 0,
+        ingredients_weight: 0.0,
+        potions_weight: 0.0,
+    };
+
+    for potion in potions {
+        let mut count = potion
+            .ingredients
+            .iter()
+            .map(|ig| {
+                remaining
+                    .get(&ig.get_global_form_id())
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .min()
+            .unwrap_or(0);
+
+        let weight = get_ingredients_weight(potion);
+        if let Some(budget) = remaining_budget {
+            if weight > 0.0 {
+                count = count.min((budget / weight).floor() as u32);
+            }
+        }
+
+        if count == 0 {
+            continue;
+        }
+
+        for ig in &potion.ingredients {
+            if let Some(remaining_count) = remaining.get_mut(&ig.get_global_form_id()) {
+                *remaining_count -= count;
+            }
+        }
+        if let Some(budget) = remaining_budget.as_mut() {
+            *budget -= weight * count as f32;
+        }
+
+        plan.total_value += potion.gold_value as u64 * count as u64;
+        plan.ingredients_weight += weight * count as f32;
+        plan.potions_weight += potion_weight * count as f32;
+        plan.steps.push(BrewStep { potion, count });
+    }
+
+    plan
+}
+
+/// How brewing a plan is projected to raise the Alchemy skill
+pub struct XpProjection {
+    /// The XP gained by each step of the plan
+    pub step_xp: Vec<f32>,
+    /// The skill after each step of the plan
+    pub step_progress: Vec<SkillProgress>,
+    /// The XP gained by the whole plan
+    pub total_xp: f32,
+}
+
+/// Projects the Alchemy XP gained by brewing the plan in order, starting from `start`. The potions
+/// are assumed to keep the value they have at the starting skill, so the XP is underestimated a
+/// little, as potions are worth more at higher skill levels.
+pub fn project_xp(
+    plan: &BrewPlan,
+    start: SkillProgress,
+    settings: &AlchemyXpSettings,
+) -> XpProjection {
+    let mut progress = start;
+    let mut projection = XpProjection {
+        step_xp: Vec::with_capacity(plan.steps.len()),
+        step_progress: Vec::with_capacity(plan.steps.len()),
+        total_xp: 0.0,
+    };
+
+    for step in &plan.steps {
+        let xp = settings.get_xp_for_potion(step.potion.gold_value) * step.count as f32;
+        progress.add_xp(xp, settings);
+        projection.total_xp += xp;
+        projection.step_xp.push(xp);
+        projection.step_progress.push(progress);
+    }
+
+    projection
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::GameData;
+
+    #[test]
+    fn projection_levels_up_until_100() {
+        let game_data = GameData::example();
+        let ingredients =
+            [0x800, 0x801].map(|id| game_data.get_ingredient(&GlobalFormId::new(0, id)).unwrap());
+        let mut potion = Potion::from_ingredients(&ingredients, &game_data, 1.0).unwrap();
+        potion.gold_value = 100;
+        let plan = BrewPlan {
+            steps: vec![
+                BrewStep {
+                    potion: &potion,
+                    count: 2,
+                },
+                BrewStep {
+                    potion: &potion,
+                    count: 10,
+                },
+            ],
+            total_value: 1200,
+            ingredients_weight: 0.0,
+            potions_weight: 0.0,
+        };
+        // Each level takes as much XP as the level itself, and each potion gives its gold value
+        let settings = AlchemyXpSettings {
+            skill_use_curve: 1.0,
+            skill_use_mult: 1.0,
+            skill_use_offset: 0.0,
+            skill_improve_mult: 1.0,
+            skill_improve_offset: 0.0,
+        };
+
+        let projection = project_xp(&plan, SkillProgress::new(90), &settings);
+        assert_eq!(projection.step_xp, [200.0, 1000.0]);
+        assert_eq!(projection.total_xp, 1200.0);
+        // Levels 90 and 91 take 181 XP, and the second step is more than enough to reach 100
+        assert_eq!(
+            projection.step_progress,
+            [
+                SkillProgress {
+                    level: 92,
+                    xp: 19.0
+                },
+                SkillProgress::new(100)
+            ]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{ops::Range, str::FromStr};
+
+use crate::{
+    game_data::GameData,
+    plugin_parser::ingredient::Ingredient,
+    potion::{AlchemySkill, Potion},
+};
+
+/// The lowest power factor that calibration considers. Vanilla players start at about 4.3.
+const MIN_POWER_FACTOR: f32 = 1.0;
+
+/// The highest power factor that calibration considers, far above what perks and Fortify Alchemy
+/// equipment give without exploits
+const MAX_POWER_FACTOR: f32 = 1000.0;
+
+/// The number of bisection steps when searching for a power factor, enough to narrow the range
+/// down to the precision of an f32
+const BISECTION_STEPS: usize = 48;
+
+/// A potion that the player crafted in the game, and its gold value as shown in the inventory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CalibrationSample {
+    /// The names or editor IDs of the ingredients, as accepted by `find_ingredient_by_name`
+    pub ingredients: Vec<String>,
+    pub value: u32,
+}
+
+impl FromStr for CalibrationSample {
+    type Err = String;
+
+    /// Parse a value like `Blue Mountain Flower, Wheat = 180`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ingredients, value) = s.rsplit_once('=').ok_or_else(|| {
+            format!(
+                "invalid calibration sample {}, expected ingredients and the potion's value, \
+                 e.g. \"Blue Mountain Flower, Wheat = 180\"",
+                s
+            )
+        })?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid value in calibration sample {}: {}", s, err))?;
+        let ingredients = ingredients
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        if ingredients.is_empty() {
+            return Err(format!("calibration sample {} has no ingredients", s));
+        }
+
+        Ok(Self { ingredients, value })
+    }
+}
+
+/// Returns the power factors for which the potion crafted from the ingredients is worth exactly
+/// `value` gold. The range is empty if no power factor gives that value, e.g. because the value
+/// lies between two that the potion can have. The value grows with the power factor, so the range
+/// is found by bisection.
+pub(crate) fn find_power_factor_range<'a>(
+    ingredients: &[&'a Ingredient],
+    game_data: &'a GameData,
+    value: u32,
+) -> Range<f32> {
+    let get_value = |power_factor| {
+        Potion::from_ingredients(ingredients, game_data, power_factor)
+            .map_or(0, |potion| potion.gold_value)
+    };
+
+    let start = find_lowest_power_factor(|power_factor| get_value(power_factor) >= value);
+    let end = find_lowest_power_factor(|power_factor| get_value(power_factor) > value);
+    match start {
+        Some(start) => start..end.unwrap_or(MAX_POWER_FACTOR),
+        None => MAX_POWER_FACTOR..MAX_POWER_FACTOR,
+    }
+}
+
+/// Returns the lowest power factor for which the predicate holds, or None if it doesn't hold for
+/// any. The predicate must hold for every power factor above one for which it holds.
+fn find_lowest_power_factor<F>(mut predicate: F) -> Option<f32>
+where
+    F: FnMut(f32) -> bool,
+{
+    if !predicate(MAX_POWER_FACTOR) {
+        return None;
+    }
+    if predicate(MIN_POWER_FACTOR) {
+        return Some(MIN_POWER_FACTOR);
+    }
+
+    let (mut low, mut high) = (MIN_POWER_FACTOR, MAX_POWER_FACTOR);
+    for _ in 0..BISECTION_STEPS {
+        let middle = (low + high) / 2.0;
+        match predicate(middle) {
+            true => high = middle,
+            false => low = middle,
+        }
+    }
+    Some(high)
+}
+
+/// Returns the perk bonus that gives the power factor at the alchemy skill's skill level and
+/// Fortify Alchemy bonus. Perks are what mods most often change, so unknown modifiers are folded
+/// into it.
+pub(crate) fn solve_perk_bonus(alchemy_skill: &AlchemySkill, power_factor: f32) -> f32 {
+    let without_perks = AlchemySkill {
+        perk_bonus: 0.0,
+        ..*alchemy_skill
+    };
+    (power_factor / without_perks.get_power_factor() - 1.0) * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::form_id::GlobalFormId;
+
+    /// The pairs of example ingredients that the samples are crafted from
+    const SAMPLE_PAIRS: [[u32; 2]; 3] = [[0x800, 0x801], [0x802, 0x803], [0x804, 0x805]];
+
+    fn get_ingredients(game_data: &GameData, ids: [u32; 2]) -> [&Ingredient; 2] {
+        ids.map(|id| game_data.get_ingredient(&GlobalFormId::new(0, id)).unwrap())
+    }
+
+    #[test]
+    fn samples_are_calibrated_back_to_their_perk_bonus() {
+        let game_data = GameData::example();
+        let alchemy_skill = AlchemySkill {
+            skill_level: 30,
+            fortify_alchemy: 10.0,
+            perk_bonus: 60.0,
+        };
+        let power_factor = alchemy_skill.get_power_factor();
+
+        let mut power_factor_range = MIN_POWER_FACTOR..MAX_POWER_FACTOR;
+        for ids in SAMPLE_PAIRS {
+            let ingredients = get_ingredients(&game_data, ids);
+            let value = Potion::from_ingredients(&ingredients, &game_data, power_factor)
+                .unwrap()
+                .gold_value;
+            let range = find_power_factor_range(&ingredients, &game_data, value);
+            assert!(range.contains(&power_factor), "{:?}", range);
+            power_factor_range =
+                power_factor_range.start.max(range.start)..power_factor_range.end.min(range.end);
+        }
+
+        let perk_bonus_range = solve_perk_bonus(&alchemy_skill, power_factor_range.start)
+            ..solve_perk_bonus(&alchemy_skill, power_factor_range.end);
+        assert!(perk_bonus_range.contains(&60.0), "{:?}", perk_bonus_range);
+    }
+
+    #[test]
+    fn unreachable_values_have_an_empty_range() {
+        let game_data = GameData::example();
+        let ingredients = get_ingredients(&game_data, SAMPLE_PAIRS[0]);
+        assert!(find_power_factor_range(&ingredients, &game_data, u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn perk_bonus_is_solved_from_the_power_factor() {
+        let alchemy_skill = AlchemySkill {
+            skill_level: 45,
+            fortify_alchemy: 25.0,
+            perk_bonus: 0.0,
+        };
+        let with_perks = AlchemySkill {
+            perk_bonus: 80.0,
+            ..alchemy_skill
+        };
+        let perk_bonus = solve_perk_bonus(&alchemy_skill, with_perks.get_power_factor());
+        assert!((perk_bonus - 80.0).abs() < 0.001, "{}", perk_bonus);
+    }
+
+    #[test]
+    fn samples_are_parsed_from_ingredients_and_value() {
+        assert_eq!(
+            "Blue Mountain Flower, Wheat = 180".parse::<CalibrationSample>(),
+            Ok(CalibrationSample {
+                ingredients: vec!["Blue Mountain Flower".to_string(), "Wheat".to_string()],
+                value: 180,
+            })
+        );
+        assert!("Blue Mountain Flower, Wheat"
+            .parse::<CalibrationSample>()
+            .is_err());
+        assert!("Wheat = a lot".parse::<CalibrationSample>().is_err());
+        assert!(" , = 180".parse::<CalibrationSample>().is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use std::str::FromStr;
+
+use ahash::{AHashMap, AHashSet};
+use itertools::Itertools;
+use serde_with::DeserializeFromStr;
+
+use crate::{
+    game_data::GameData,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::Ingredient,
+    },
+};
+
+/// What to minimize when choosing the ingredients that cover the effects
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, DeserializeFromStr)]
+pub enum CoverageCost {
+    /// The number of different ingredients
+    #[default]
+    Count,
+    /// The total base gold value of the ingredients, i.e. roughly what they cost at a merchant
+    Value,
+}
+
+impl FromStr for CoverageCost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "count" => Ok(CoverageCost::Count),
+            "value" => Ok(CoverageCost::Value),
+            other => Err(format!(
+                "unknown coverage cost {}, expected count or value",
+                other
+            )),
+        }
+    }
+}
+
+/// A set of ingredients whose pairs can make potions with each of the desired effects
+pub struct CoveragePlan<'a> {
+    /// The chosen ingredients, sorted by form ID
+    pub ingredients: Vec<&'a Ingredient>,
+    /// The desired effects that fewer than two ingredients have, which no potion can have
+    pub uncoverable_effects: Vec<GlobalFormId>,
+}
+
+impl<'a> CoveragePlan<'a> {
+    /// Returns two of the chosen ingredients that make a potion with the effect, if any
+    pub fn get_pair_for_effect(
+        &self,
+        effect_id: &GlobalFormId,
+    ) -> Option<(&'a Ingredient, &'a Ingredient)> {
+        self.ingredients
+            .iter()
+            .copied()
+            .filter(|ig| has_effect(ig, effect_id))
+            .next_tuple()
+    }
+}
+
+fn has_effect(ingredient: &Ingredient, effect_id: &GlobalFormId) -> bool {
+    ingredient
+        .effects
+        .iter()
+        .any(|eff| eff.get_global_form_id() == *effect_id)
+}
+
+fn get_cost(ingredient: &Ingredient, cost: CoverageCost) -> f32 {
+    match cost {
+        CoverageCost::Count => 1.0,
+        // Free ingredients still take up a slot, so count them as costing something
+        CoverageCost::Value => ingredient.value.unwrap_or(0).max(1) as f32,
+    }
+}
+
+/// Chooses a small set of ingredients such that every desired effect is shared by at least two of
+/// them, so that combining those two makes a potion with the effect. Finding the smallest set is a
+/// set cover problem, so this greedily adds the single ingredient or pair of ingredients that
+/// covers the most effects for its cost, and then drops the ingredients that became redundant.
+pub fn plan_coverage<'a>(
+    game_data: &'a GameData,
+    effect_ids: &AHashSet<GlobalFormId>,
+    cost: CoverageCost,
+) -> CoveragePlan<'a> {
+    let effects_to_ingredients = game_data.effects_to_ingredients();
+    let is_coverable = |effect_id: &GlobalFormId| {
+        effects_to_ingredients
+            .get(effect_id)
+            .map_or(false, |ingredient_ids| ingredient_ids.len() >= 2)
+    };
+    let coverable_effects = effect_ids
+        .iter()
+        .copied()
+        .filter(is_coverable)
+        .collect::<AHashSet<_>>();
+    let uncoverable_effects = effect_ids
+        .iter()
+        .copied()
+        .filter(|effect_id| !is_coverable(effect_id))
+        .sorted()
+        .collect();
+
+    // Sorting the candidates makes the choice between equally good candidates deterministic
+    let candidates = coverable_effects
+        .iter()
+        .flat_map(|effect_id| game_data.get_ingredients_with_effect(effect_id))
+        .unique()
+        .sorted_by_key(|ig| ig.get_global_form_id())
+        .collect::<Vec<_>>();
+
+    let mut chosen = Vec::<&Ingredient>::new();
+    let mut chosen_counts = AHashMap::<GlobalFormId, usize>::new();
+    let mut uncovered = coverable_effects.clone();
+    while !uncovered.is_empty() {
+        let unchosen = candidates
+            .iter()
+            .copied()
+            .filter(|ig| !chosen.contains(ig))
+            .collect::<Vec<_>>();
+        let count_covered = |added: &[&Ingredient]| {
+            uncovered
+                .iter()
+                .filter(|effect_id| {
+                    let num_added = added.iter().filter(|ig| has_effect(ig, effect_id)).count();
+                    chosen_counts.get(*effect_id).copied().unwrap_or(0) + num_added >= 2
+                })
+                .count()
+        };
+
+        let (_, _, added) = unchosen
+            .iter()
+            .map(|ig| vec![*ig])
+            .chain(
+                unchosen
+                    .iter()
+                    .tuple_combinations()
+                    .map(|(a, b)| vec![*a, *b]),
+            )
+            .filter_map(|added| {
+                let num_covered = count_covered(&added);
+                let added_cost: f32 = added.iter().map(|ig| get_cost(ig, cost)).sum();
+                (num_covered > 0).then(|| (num_covered as f32 / added_cost, num_covered, added))
+            })
+            // Prefer covering more effects when the ratio is the same
+            .max_by(|(ratio_a, covered_a, _), (ratio_b, covered_b, _)| {
+                ratio_a.total_cmp(ratio_b).then(covered_a.cmp(covered_b))
+            })
+            .expect("every uncovered effect is shared by at least two ingredients");
+
+        for ingredient in added {
+            for effect in &ingredient.effects {
+                *chosen_counts
+                    .entry(effect.get_global_form_id())
+                    .or_default() += 1;
+            }
+            chosen.push(ingredient);
+        }
+        uncovered.retain(|effect_id| chosen_counts.get(effect_id).copied().unwrap_or(0) < 2);
+    }
+
+    // Later choices can make earlier ones redundant, so drop the most costly ingredients that
+    // aren't needed to keep every effect covered
+    for ingredient in chosen
+        .clone()
+        .into_iter()
+        .sorted_by(|a, b| get_cost(b, cost).total_cmp(&get_cost(a, cost)))
+    {
+        let is_redundant = coverable_effects.iter().all(|effect_id| {
+            chosen
+                .iter()
+                .filter(|ig| **ig != ingredient && has_effect(ig, effect_id))
+                .count()
+                >= 2
+        });
+        if is_redundant {
+            chosen.retain(|ig| *ig != ingredient);
+        }
+    }
+    chosen.sort_by_key(|ig| ig.get_global_form_id());
+
+    CoveragePlan {
+        ingredients: chosen,
+        uncoverable_effects,
+    }
+}
@@ -0,0 +1,83 @@
+use std::str::FromStr;
+
+use serde_with::DeserializeFromStr;
+
+use crate::potion::{MAX_EFFECTS, MIN_INGREDIENTS};
+
+/// How the primary effect of a potion is chosen, which determines its name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PrimaryEffectRule {
+    /// The most valuable effect, as in the vanilla game
+    MostValuable,
+    /// The most valuable effect that matches the potion's type, so that e.g. a poison is never
+    /// named after a beneficial effect
+    MostValuableOfType,
+}
+
+/// How a potion is classified as a poison
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PoisonRule {
+    /// A poison if the most valuable effect is hostile, as in the vanilla game
+    PrimaryEffect,
+    /// A poison if more than half of its effects are hostile
+    MajorityHostile,
+}
+
+/// The rules by which ingredients are combined into potions. Mods such as Complete Alchemy &
+/// Cooking Overhaul change some of these, so alternate rule sets can be selected by name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, DeserializeFromStr)]
+pub struct CraftingRules {
+    /// Minimum number of ingredients per potion. Must be at least `MIN_INGREDIENTS`.
+    pub min_ingredients: usize,
+    /// Maximum number of effects per potion. Must be at most `MAX_EFFECTS`.
+    pub max_effects: usize,
+    pub primary_effect: PrimaryEffectRule,
+    pub poison: PoisonRule,
+    /// When ingredients share more than `max_effects` effects, keep the most valuable effects of
+    /// the potion's type rather than the most valuable effects overall
+    pub prefer_effects_of_type: bool,
+}
+
+impl CraftingRules {
+    /// The rules of the vanilla game
+    pub fn vanilla() -> Self {
+        Self {
+            min_ingredients: MIN_INGREDIENTS,
+            max_effects: MAX_EFFECTS,
+            primary_effect: PrimaryEffectRule::MostValuable,
+            poison: PoisonRule::PrimaryEffect,
+            prefer_effects_of_type: false,
+        }
+    }
+
+    /// The rules of Complete Alchemy & Cooking Overhaul, in which a potion with mostly hostile
+    /// effects is a poison named after its most valuable hostile effect
+    pub fn caco() -> Self {
+        Self {
+            primary_effect: PrimaryEffectRule::MostValuableOfType,
+            poison: PoisonRule::MajorityHostile,
+            ..Self::vanilla()
+        }
+    }
+}
+
+impl Default for CraftingRules {
+    fn default() -> Self {
+        Self::vanilla()
+    }
+}
+
+impl FromStr for CraftingRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vanilla" => Ok(CraftingRules::vanilla()),
+            "caco" => Ok(CraftingRules::caco()),
+            other => Err(format!(
+                "unknown crafting rules {}, expected vanilla or caco",
+                other
+            )),
+        }
+    }
+}
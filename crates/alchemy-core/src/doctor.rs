@@ -0,0 +1,591 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    load_order::LoadOrder,
+    plugin_parser,
+    save_parser::{find_save_files, get_saves_path, SavesLocation},
+};
+
+/// The name of the game's executable, which is in the game directory
+const GAME_EXE_NAME: &str = "SkyrimSE.exe";
+
+/// Options for `run_doctor`
+#[derive(Default)]
+pub struct DoctorOptions {
+    /// Path to the game directory containing SkyrimSE.exe, if known. The game and load order
+    /// checks are skipped without it.
+    pub game_path: Option<PathBuf>,
+    /// Path to the directory containing plugins.txt, or `None` for the default one
+    pub local_path: Option<PathBuf>,
+    /// Where the save files are
+    pub saves_location: SavesLocation,
+    /// Directories that need to be writable, e.g. the cache directory and the profile's directory
+    pub writable_dirs: Vec<PathBuf>,
+}
+
+/// How serious the outcome of a check is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FindingSeverity {
+    Ok,
+    Warning,
+    Problem,
+}
+
+impl fmt::Display for FindingSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FindingSeverity::Ok => "ok",
+            FindingSeverity::Warning => "warning",
+            FindingSeverity::Problem => "problem",
+        })
+    }
+}
+
+/// The outcome of one check, along with what to do about it if it failed
+#[derive(Clone, Debug)]
+pub struct DoctorFinding {
+    pub severity: FindingSeverity,
+    pub message: String,
+    /// What to do about a warning or problem
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for DoctorFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.severity, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n    {}", hint)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the outcome of each check
+#[derive(Default)]
+struct Report {
+    findings: Vec<DoctorFinding>,
+}
+
+impl Report {
+    fn ok(&mut self, message: impl fmt::Display) {
+        self.push(FindingSeverity::Ok, message, None);
+    }
+
+    fn warning(&mut self, message: impl fmt::Display, hint: &str) {
+        self.push(FindingSeverity::Warning, message, Some(hint));
+    }
+
+    fn problem(&mut self, message: impl fmt::Display, hint: &str) {
+        self.push(FindingSeverity::Problem, message, Some(hint));
+    }
+
+    fn push(&mut self, severity: FindingSeverity, message: impl fmt::Display, hint: Option<&str>) {
+        self.findings.push(DoctorFinding {
+            severity,
+            message: message.to_string(),
+            hint: hint.map(String::from),
+        });
+    }
+}
+
+/// Checks the game directory and returns its Data directory if it looks like a Skyrim Special
+/// Edition installation
+fn check_game_path(report: &mut Report, game_path: &Path) -> Option<PathBuf> {
+    if !game_path.is_dir() {
+        report.problem(
+            format!("Game directory {} does not exist", game_path.display()),
+            "Pass the directory that contains SkyrimSE.exe as --game-path.",
+        );
+        return None;
+    }
+    if !game_path.join(GAME_EXE_NAME).is_file() {
+        report.warning(
+            format!(
+                "Game directory {} does not contain {}",
+                game_path.display(),
+                GAME_EXE_NAME
+            ),
+            "Make sure --game-path is the directory that contains SkyrimSE.exe, not its Data \
+             directory.",
+        );
+    }
+
+    let game_plugins_path = game_path.join("Data");
+    if !game_plugins_path.join("Skyrim.esm").is_file() {
+        report.problem(
+            format!(
+                "{} does not contain Skyrim.esm",
+                game_plugins_path.display()
+            ),
+            "Verify the game files, or check that --game-path points to the installation that \
+             your mod manager uses.",
+        );
+        return None;
+    }
+
+    report.ok(format!("Game directory {}", game_path.display()));
+    Some(game_plugins_path)
+}
+
+/// Checks that plugins.txt can be read and that the active plugins are in the Data directory, and
+/// returns the names of the active plugins
+fn check_load_order(
+    report: &mut Report,
+    game_path: &Path,
+    local_path: Option<&Path>,
+    game_plugins_path: &Path,
+) -> Option<Vec<String>> {
+    let game_settings = match local_path {
+        Some(local_path) => loadorder::GameSettings::with_local_path(
+            loadorder::GameId::SkyrimSE,
+            game_path,
+            local_path,
+        ),
+        None => loadorder::GameSettings::new(loadorder::GameId::SkyrimSE, game_path),
+    };
+    let game_settings = match game_settings {
+        Ok(game_settings) => game_settings,
+        Err(err) => {
+            report.problem(
+                format!("Cannot find the load order: {}", err),
+                "Pass the directory that contains plugins.txt as --local-path, e.g. \
+                 %LocalAppData%/Skyrim Special Edition, or your mod manager's profile directory.",
+            );
+            return None;
+        }
+    };
+
+    let plugins_file_path = game_settings.active_plugins_file().clone();
+    let plugins_file = match fs::read(&plugins_file_path) {
+        Ok(plugins_file) => plugins_file,
+        Err(err) => {
+            report.problem(
+                format!("Cannot read {}: {}", plugins_file_path.display(), err),
+                "Start the game's launcher or your mod manager once to create it, or pass the \
+                 directory that contains plugins.txt as --local-path.",
+            );
+            return None;
+        }
+    };
+    report.ok(format!("Plugins file {}", plugins_file_path.display()));
+
+    let mut load_order = game_settings.into_load_order();
+    if let Err(err) = load_order.load() {
+        report.problem(
+            format!("Cannot read the load order: {}", err),
+            "Check that plugins.txt is not corrupted, e.g. by letting your mod manager write it \
+             again.",
+        );
+        return None;
+    }
+    let active_plugin_names = load_order
+        .active_plugin_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    // The load order leaves out the plugins that aren't installed, so look for them in plugins.txt
+    let missing_plugin_names = get_listed_active_plugin_names(&plugins_file)
+        .filter(|plugin_name| !game_plugins_path.join(plugin_name).is_file())
+        .collect::<Vec<_>>();
+    if !missing_plugin_names.is_empty() {
+        report.problem(
+            format!(
+                "{} active plugins are not in {}: {}",
+                missing_plugin_names.len(),
+                game_plugins_path.display(),
+                missing_plugin_names.join(", ")
+            ),
+            "If you use a mod manager, run the program through it so that it sees the mods' \
+             files (see run-within-modorganizer.sh), or deactivate the missing plugins.",
+        );
+    }
+    match LoadOrder::new(active_plugin_names.clone()).check_len() {
+        Ok(()) => report.ok(format!("{} active plugins", active_plugin_names.len())),
+        Err(err) => report.problem(
+            err,
+            "Deactivate some plugins, or flag some of them as light masters (ESL).",
+        ),
+    }
+
+    Some(
+        active_plugin_names
+            .into_iter()
+            .filter(|plugin_name| game_plugins_path.join(plugin_name).is_file())
+            .collect(),
+    )
+}
+
+/// Returns the names of the plugins that plugins.txt marks as active with an asterisk
+fn get_listed_active_plugin_names(plugins_file: &[u8]) -> impl Iterator<Item = String> + '_ {
+    plugins_file
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| {
+            Some(
+                String::from_utf8_lossy(line)
+                    .trim()
+                    .strip_prefix('*')?
+                    .to_string(),
+            )
+        })
+}
+
+/// Checks that the localized plugins have strings files to read their ingredient and effect names
+/// from
+fn check_strings_files(report: &mut Report, plugin_names: &[String], game_plugins_path: &Path) {
+    let mut num_localized = 0;
+    let mut unnamed_plugin_names = Vec::new();
+    for plugin_name in plugin_names {
+        let plugin_path = game_plugins_path.join(plugin_name);
+        let plugin_mmap = match File::open(&plugin_path)
+            .and_then(|plugin_file| unsafe { memmap2::MmapOptions::new().map(&plugin_file) })
+        {
+            Ok(plugin_mmap) => plugin_mmap,
+            Err(err) => {
+                report.problem(
+                    format!("Cannot read {}: {}", plugin_path.display(), err),
+                    "Check that the file is not locked by another program and that you may \
+                     read it.",
+                );
+                continue;
+            }
+        };
+        if !plugin_parser::is_localized(&plugin_mmap) {
+            continue;
+        }
+        num_localized += 1;
+        if plugin_parser::find_strings_source(plugin_name, game_plugins_path).is_none() {
+            unnamed_plugin_names.push(plugin_name.as_str());
+        }
+    }
+
+    if unnamed_plugin_names.is_empty() {
+        report.ok(format!(
+            "Strings files or BSAs found for all {} localized plugins",
+            num_localized
+        ));
+    } else {
+        report.warning(
+            format!(
+                "{} localized plugins have neither strings files nor a BSA, so their ingredients \
+                 and effects will have no names: {}",
+                unnamed_plugin_names.len(),
+                unnamed_plugin_names.join(", ")
+            ),
+            "Reinstall these mods, making sure their Strings directory or BSA is installed, or \
+             run the program through your mod manager.",
+        );
+    }
+}
+
+fn check_saves_path(report: &mut Report, saves_location: &SavesLocation) {
+    let saves_path = match get_saves_path(saves_location) {
+        Ok(saves_path) => saves_path,
+        Err(err) => {
+            report.problem(
+                err,
+                "Pass the directory that contains your .ess files as --saves-path, or set it in \
+                 the profile.",
+            );
+            return;
+        }
+    };
+    if !saves_path.is_dir() {
+        report.problem(
+            format!("Saves directory {} does not exist", saves_path.display()),
+            "Pass the directory that contains your .ess files as --saves-path, or set it in the \
+             profile.",
+        );
+        return;
+    }
+
+    match find_save_files(&saves_path) {
+        Ok(saves) if saves.is_empty() => report.warning(
+            format!("Saves directory {} contains no saves", saves_path.display()),
+            "Save the game once, or pass the directory that contains your .ess files as \
+             --saves-path if the game uses another one.",
+        ),
+        Ok(saves) => report.ok(format!(
+            "Saves directory {} with {} saves",
+            saves_path.display(),
+            saves.len()
+        )),
+        Err(err) => report.problem(
+            format!(
+                "Cannot read saves directory {}: {}",
+                saves_path.display(),
+                err
+            ),
+            "Check that you may read the directory.",
+        ),
+    }
+}
+
+/// Checks that a file can be created in the directory, creating the directory if it doesn't exist
+fn check_writable(report: &mut Report, dir: &Path) {
+    let probe_path = dir.join(".skyrim-alchemy-rs-doctor");
+    let result = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&probe_path, b""))
+        .and_then(|()| fs::remove_file(&probe_path));
+    match result {
+        Ok(()) => report.ok(format!("Can write to {}", dir.display())),
+        Err(err) => report.problem(
+            format!("Cannot write to {}: {}", dir.display(), err),
+            "Check the directory's permissions. Pass --no-cache to suggest-potions to do without \
+             the potions cache.",
+        ),
+    }
+}
+
+/// Checks the environment for the problems that most often keep the program from working: a wrong
+/// game directory, a missing or unreadable plugins.txt, missing plugins or strings files, a
+/// missing saves directory and cache directories that can't be written to. Returns the outcome of
+/// each check, with what to do about each problem.
+pub fn run_doctor(options: &DoctorOptions) -> Vec<DoctorFinding> {
+    let DoctorOptions {
+        game_path,
+        local_path,
+        saves_location,
+        writable_dirs,
+    } = options;
+
+    let mut report = Report::default();
+    match game_path {
+        Some(game_path) => {
+            if let Some(game_plugins_path) = check_game_path(&mut report, game_path) {
+                if let Some(plugin_names) = check_load_order(
+                    &mut report,
+                    game_path,
+                    local_path.as_deref(),
+                    &game_plugins_path,
+                ) {
+                    check_strings_files(&mut report, &plugin_names, &game_plugins_path);
+                }
+            }
+        }
+        None => report.warning(
+            "Skipped the game and load order checks",
+            "Pass the directory that contains SkyrimSE.exe as --game-path to check them too.",
+        ),
+    }
+
+    check_saves_path(&mut report, saves_location);
+
+    let mut checked_dirs = Vec::new();
+    for dir in writable_dirs {
+        if !checked_dirs.contains(&dir) {
+            check_writable(&mut report, dir);
+            checked_dirs.push(dir);
+        }
+    }
+
+    report.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::header_bytes;
+
+    /// Returns a new temporary directory for the test
+    fn temp_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "alchemy-doctor-{}-{}",
+            test_name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Creates a game directory in `dir` with the plugins in its Data directory, localized if
+    /// their flag is set, and an empty directory for plugins.txt. Returns the options to check it.
+    fn create_game(dir: &Path, plugins: &[(&str, bool)]) -> DoctorOptions {
+        let game_path = dir.join("game");
+        let local_path = dir.join("local");
+        fs::create_dir_all(game_path.join("Data")).unwrap();
+        fs::create_dir_all(&local_path).unwrap();
+        fs::write(game_path.join(GAME_EXE_NAME), b"").unwrap();
+        for &(plugin_name, is_localized) in plugins {
+            let flags = if is_localized { 0x80 } else { 0 };
+            fs::write(
+                game_path.join("Data").join(plugin_name),
+                header_bytes(1.71, flags),
+            )
+            .unwrap();
+        }
+
+        let saves_path = dir.join("Saves");
+        fs::create_dir_all(&saves_path).unwrap();
+        DoctorOptions {
+            game_path: Some(game_path),
+            local_path: Some(local_path),
+            saves_location: SavesLocation {
+                saves_path: Some(saves_path),
+                ..SavesLocation::default()
+            },
+            writable_dirs: Vec::new(),
+        }
+    }
+
+    fn write_plugins_file(options: &DoctorOptions, contents: &str) {
+        let local_path = options.local_path.as_ref().unwrap();
+        fs::write(local_path.join("Plugins.txt"), contents).unwrap();
+    }
+
+    /// Returns the finding whose message contains `text`, checking that a warning or problem
+    /// comes with a hint
+    fn find_finding<'a>(findings: &'a [DoctorFinding], text: &str) -> &'a DoctorFinding {
+        let finding = findings
+            .iter()
+            .find(|finding| finding.message.contains(text))
+            .unwrap_or_else(|| panic!("no finding contains {:?} in {:#?}", text, findings));
+        assert_eq!(
+            finding.hint.is_some(),
+            finding.severity != FindingSeverity::Ok,
+            "{:?}",
+            finding
+        );
+        finding
+    }
+
+    #[test]
+    fn a_game_directory_without_skyrim_esm_is_a_problem() {
+        let dir = temp_dir("no-skyrim-esm");
+        let options = create_game(&dir, &[("Update.esm", false)]);
+        write_plugins_file(&options, "");
+
+        let findings = run_doctor(&options);
+        let finding = find_finding(&findings, "does not contain Skyrim.esm");
+        assert_eq!(finding.severity, FindingSeverity::Problem);
+        // The load order isn't checked without the game's plugins
+        assert!(findings
+            .iter()
+            .all(|finding| !finding.message.contains("Plugins file")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_missing_plugins_file_is_a_problem() {
+        let dir = temp_dir("no-plugins-file");
+        let options = create_game(&dir, &[("Skyrim.esm", false)]);
+
+        let findings = run_doctor(&options);
+        assert_eq!(
+            find_finding(&findings, "Game directory").severity,
+            FindingSeverity::Ok
+        );
+        let finding = find_finding(&findings, "Cannot read");
+        assert_eq!(finding.severity, FindingSeverity::Problem);
+        assert!(finding.message.to_lowercase().contains("plugins.txt"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn active_plugins_missing_from_data_are_a_problem() {
+        let dir = temp_dir("missing-plugins");
+        let options = create_game(&dir, &[("Skyrim.esm", false), ("Present.esp", false)]);
+        write_plugins_file(
+            &options,
+            "# This file is used by Skyrim to keep track of your downloaded content.\n\
+             *Present.esp\n*Missing.esp\nInactive.esp\n",
+        );
+
+        let findings = run_doctor(&options);
+        let finding = find_finding(&findings, "active plugins are not in");
+        assert_eq!(finding.severity, FindingSeverity::Problem);
+        assert!(finding.message.starts_with("1 active plugins"));
+        assert!(finding.message.ends_with(": Missing.esp"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn localized_plugins_without_strings_are_a_warning() {
+        let dir = temp_dir("no-strings");
+        let options = create_game(&dir, &[("Skyrim.esm", false), ("Present.esp", true)]);
+        write_plugins_file(&options, "*Present.esp\n");
+
+        let findings = run_doctor(&options);
+        let finding = find_finding(&findings, "have neither strings files nor a BSA");
+        assert_eq!(finding.severity, FindingSeverity::Warning);
+        assert!(finding.message.ends_with(": Present.esp"));
+
+        let strings_path = options
+            .game_path
+            .as_ref()
+            .unwrap()
+            .join("Data")
+            .join("strings");
+        fs::create_dir_all(&strings_path).unwrap();
+        fs::write(strings_path.join("present_english.strings"), b"").unwrap();
+        let findings = run_doctor(&options);
+        assert_eq!(
+            find_finding(
+                &findings,
+                "Strings files or BSAs found for all 1 localized plugins"
+            )
+            .severity,
+            FindingSeverity::Ok
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unwritable_directories_are_a_problem() {
+        let dir = temp_dir("unwritable");
+        // A directory can't be created inside a file
+        let file_path = dir.join("file");
+        fs::write(&file_path, b"").unwrap();
+        let unwritable_path = file_path.join("cache");
+        let writable_path = dir.join("cache");
+        let options = DoctorOptions {
+            saves_location: SavesLocation {
+                saves_path: Some(dir.clone()),
+                ..SavesLocation::default()
+            },
+            writable_dirs: vec![
+                unwritable_path.clone(),
+                writable_path.clone(),
+                writable_path.clone(),
+            ],
+            ..DoctorOptions::default()
+        };
+
+        let findings = run_doctor(&options);
+        assert_eq!(
+            find_finding(&findings, "Skipped the game and load order checks").severity,
+            FindingSeverity::Warning
+        );
+        let finding = find_finding(
+            &findings,
+            &format!("Cannot write to {}", unwritable_path.display()),
+        );
+        assert_eq!(finding.severity, FindingSeverity::Problem);
+        let writable_message = format!("Can write to {}", writable_path.display());
+        assert_eq!(
+            find_finding(&findings, &writable_message).severity,
+            FindingSeverity::Ok
+        );
+        // Each directory is checked once, and the probe file is removed
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|finding| finding.message == writable_message)
+                .count(),
+            1
+        );
+        assert_eq!(fs::read_dir(&writable_path).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use ahash::AHashMap;
+use arrayvec::ArrayVec;
+
+use crate::{
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId},
+    potion::{Potion, PotionType, MAX_EFFECTS},
+};
+
+/// The potion type and sorted effects of a potion. Potions are only compared with potions of the
+/// same type, since a poison with the effects of a potion and more is no use to drink.
+type EffectSetKey = (PotionType, ArrayVec<GlobalFormId, MAX_EFFECTS>);
+
+/// The (ingredient cost, gold value) pairs that aren't beaten by another pair, sorted by cost
+/// ascending. The values then increase strictly, so the last pair that costs at most a given cost
+/// has the highest value for that cost.
+type Frontier = Vec<(u32, u32)>;
+
+fn get_effect_set_key(potion: &Potion) -> EffectSetKey {
+    let mut effect_ids = potion
+        .effects
+        .iter()
+        .map(|eff| eff.get_global_form_id())
+        .collect::<ArrayVec<_, MAX_EFFECTS>>();
+    effect_ids.sort_unstable();
+    (potion.get_potion_type(), effect_ids)
+}
+
+/// Reduces the pairs to their frontier, see `Frontier`
+fn into_frontier(mut pairs: Vec<(u32, u32)>) -> Frontier {
+    // Among pairs of equal cost, the most valuable comes first and hides the others
+    pairs.sort_unstable_by(|(cost_a, value_a), (cost_b, value_b)| {
+        cost_a.cmp(cost_b).then(value_b.cmp(value_a))
+    });
+    let mut frontier = Frontier::new();
+    for (cost, value) in pairs {
+        if frontier
+            .last()
+            .map_or(true, |&(_, best_value)| value > best_value)
+        {
+            frontier.push((cost, value));
+        }
+    }
+    frontier
+}
+
+/// Finds the potions that are dominated by another potion, i.e. another potion of the same type
+/// has at least the same effects, costs at most as much in ingredients (by their base value) and is
+/// worth more.
+///
+/// Rather than comparing all pairs of potions, the potions are grouped by their effects, and each
+/// group's best trade-offs between cost and value are added to the groups of all subsets of its
+/// effects. A potion has at most `MAX_EFFECTS` effects, so there are at most 64 subsets.
+pub(crate) struct DominanceIndex {
+    /// The frontier of the potions that have at least the effects of the key
+    dominating: AHashMap<EffectSetKey, Frontier>,
+}
+
+impl DominanceIndex {
+    pub(crate) fn new<'a, 'b: 'a>(potions: impl Iterator<Item = &'a Potion<'b>>) -> Self {
+        let mut pairs_by_effects = AHashMap::<EffectSetKey, Vec<(u32, u32)>>::new();
+        for potion in potions {
+            pairs_by_effects
+                .entry(get_effect_set_key(potion))
+                .or_default()
+                .push((potion.get_ingredients_value(), potion.gold_value));
+        }
+        let frontiers = pairs_by_effects
+            .into_iter()
+            .map(|(key, pairs)| (key, into_frontier(pairs)))
+            .collect::<AHashMap<_, _>>();
+
+        // Only subsets that are the effects of some potion are looked up later
+        let mut dominating = AHashMap::<EffectSetKey, Vec<(u32, u32)>>::new();
+        for ((potion_type, effect_ids), frontier) in &frontiers {
+            for mask in 1..(1u32 << effect_ids.len()) {
+                let subset = (
+                    *potion_type,
+                    effect_ids
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| mask & (1 << index) != 0)
+                        .map(|(_, effect_id)| *effect_id)
+                        .collect::<ArrayVec<_, MAX_EFFECTS>>(),
+                );
+                if frontiers.contains_key(&subset) {
+                    dominating
+                        .entry(subset)
+                        .or_default()
+                        .extend_from_slice(frontier);
+                }
+            }
+        }
+
+        Self {
+            dominating: dominating
+                .into_iter()
+                .map(|(key, pairs)| (key, into_frontier(pairs)))
+                .collect(),
+        }
+    }
+
+    /// Returns whether another potion of the same type has at least the potion's effects, costs at
+    /// most as much and is worth more. `potion` must be one of the potions the index was built from.
+    pub(crate) fn is_dominated(&self, potion: &Potion) -> bool {
+        let frontier = match self.dominating.get(&get_effect_set_key(potion)) {
+            Some(frontier) => frontier,
+            None => return false,
+        };
+        let cost = potion.get_ingredients_value();
+        let num_affordable = frontier.partition_point(|&(other_cost, _)| other_cost <= cost);
+        num_affordable > 0 && frontier[num_affordable - 1].1 > potion.gold_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_data::GameData,
+        plugin_parser::{ingredient::Ingredient, magic_effect::MagicEffect},
+        potion::AlchemySkill,
+        test_utils::{self, ingredient_effect},
+    };
+
+    use super::*;
+
+    const RESTORE_HEALTH: u32 = 0x900;
+    const RESTORE_STAMINA: u32 = 0x901;
+    const DAMAGE_HEALTH: u32 = 0x902;
+
+    fn ingredient(id: u32, value: u32, effects: &[(u32, f32)]) -> Ingredient {
+        let effects = effects.iter().map(|&(effect_id, magnitude)| {
+            ingredient_effect(GlobalFormId::new(0, effect_id), magnitude, 0)
+        });
+        Ingredient {
+            value: Some(value),
+            ..test_utils::ingredient(GlobalFormId::new(0, id), effects)
+        }
+    }
+
+    fn magic_effect(id: u32, is_hostile: bool, base_cost: f32) -> MagicEffect {
+        MagicEffect {
+            is_hostile,
+            base_cost,
+            ..test_utils::magic_effect(GlobalFormId::new(0, id))
+        }
+    }
+
+    fn game_data() -> GameData {
+        let ab = [(RESTORE_HEALTH, 10.0), (RESTORE_STAMINA, 10.0)];
+        GameData::from_vecs(
+            vec![String::from("Test.esm")],
+            Vec::new(),
+            vec![
+                // Two pairs that make the same potion for the same cost
+                ingredient(1, 2, &[(RESTORE_HEALTH, 5.0)]),
+                ingredient(2, 2, &[(RESTORE_HEALTH, 5.0)]),
+                ingredient(3, 2, &[(RESTORE_HEALTH, 5.0)]),
+                ingredient(4, 2, &[(RESTORE_HEALTH, 5.0)]),
+                // Pairs that make a stronger potion with an extra effect for less, the same and
+                // more
+                ingredient(11, 1, &ab),
+                ingredient(12, 1, &ab),
+                ingredient(13, 2, &ab),
+                ingredient(14, 2, &ab),
+                ingredient(15, 50, &ab),
+                ingredient(16, 50, &ab),
+                // A pair that makes a poison with the effect of the first potions
+                ingredient(21, 1, &[(RESTORE_HEALTH, 10.0), (DAMAGE_HEALTH, 50.0)]),
+                ingredient(22, 1, &[(RESTORE_HEALTH, 10.0), (DAMAGE_HEALTH, 50.0)]),
+            ],
+            vec![
+                magic_effect(RESTORE_HEALTH, false, 0.5),
+                magic_effect(RESTORE_STAMINA, false, 0.5),
+                magic_effect(DAMAGE_HEALTH, true, 3.0),
+            ],
+            Vec::new(),
+        )
+    }
+
+    fn potion(game_data: &GameData, ids: [u32; 2]) -> Potion {
+        let ingredients = ids
+            .map(|id| game_data.get_ingredient(&GlobalFormId::new(0, id)).unwrap())
+            .to_vec();
+        Potion::from_ingredients(
+            &ingredients,
+            game_data,
+            AlchemySkill::default().get_power_factor(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn potion_is_dominated_by_cheaper_or_equally_cheap_potion_with_more_effects() {
+        let game_data = game_data();
+        let restore_health = potion(&game_data, [1, 2]);
+        let cheaper = potion(&game_data, [11, 12]);
+        let equally_cheap = potion(&game_data, [13, 14]);
+        assert!(cheaper.gold_value > restore_health.gold_value);
+
+        let index = DominanceIndex::new([&restore_health, &cheaper].into_iter());
+        assert!(index.is_dominated(&restore_health));
+        assert!(!index.is_dominated(&cheaper));
+
+        let index = DominanceIndex::new([&restore_health, &equally_cheap].into_iter());
+        assert!(index.is_dominated(&restore_health));
+        assert!(!index.is_dominated(&equally_cheap));
+    }
+
+    #[test]
+    fn potion_is_not_dominated_by_more_expensive_potion() {
+        let game_data = game_data();
+        let restore_health = potion(&game_data, [1, 2]);
+        let more_expensive = potion(&game_data, [15, 16]);
+
+        let index = DominanceIndex::new([&restore_health, &more_expensive].into_iter());
+        assert!(!index.is_dominated(&restore_health));
+        assert!(!index.is_dominated(&more_expensive));
+    }
+
+    #[test]
+    fn potion_is_not_dominated_by_potion_of_equal_value() {
+        let game_data = game_data();
+        let restore_health = potion(&game_data, [1, 2]);
+        let same = potion(&game_data, [3, 4]);
+
+        let index = DominanceIndex::new([&restore_health, &same].into_iter());
+        assert!(!index.is_dominated(&restore_health));
+        assert!(!index.is_dominated(&same));
+    }
+
+    #[test]
+    fn potion_is_not_dominated_by_poison() {
+        let game_data = game_data();
+        let restore_health = potion(&game_data, [1, 2]);
+        let poison = potion(&game_data, [21, 22]);
+        assert_eq!(poison.get_potion_type(), PotionType::Poison);
+        assert!(poison.gold_value > restore_health.gold_value);
+
+        let index = DominanceIndex::new([&restore_health, &poison].into_iter());
+        assert!(!index.is_dominated(&restore_health));
+        assert!(!index.is_dominated(&poison));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use ahash::{AHashMap, AHashSet};
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::name_matching::normalize_name;
+
+/// Groups of names that refer to the same effect in different mods, e.g. overhauls which rename
+/// vanilla effects
+const BUILTIN_ALIASES: &[&[&str]] = &[
+    // CACO
+    &["Fortify Health", "Fortify Vitality"],
+];
+
+#[derive(Deserialize)]
+struct EffectAliasesFile {
+    #[serde(default)]
+    aliases: HashMap<String, Vec<String>>,
+}
+
+/// Resolves effect names to all of their aliases, so that effect filters work across mods which
+/// rename effects
+#[derive(Debug, Default)]
+pub struct EffectAliases {
+    /// Maps each lowercase name to the lowercase names of the effect's aliases, including itself
+    aliases: AHashMap<String, AHashSet<String>>,
+}
+
+impl EffectAliases {
+    /// Returns the built-in aliases
+    pub fn builtin() -> Self {
+        let mut effect_aliases = Self::default();
+        for group in BUILTIN_ALIASES {
+            effect_aliases.add_group(group.iter().copied());
+        }
+        effect_aliases
+    }
+
+    /// Returns the built-in aliases extended with the aliases in the TOML file at `path`. The file
+    /// contains an `[aliases]` table mapping effect names to lists of alternative names, e.g.
+    /// `"Fortify Health" = ["Fortify Vitality"]`.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let file: EffectAliasesFile = toml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse effect aliases file {}", path.display()))?;
+
+        let mut effect_aliases = Self::builtin();
+        for (name, aliases) in &file.aliases {
+            effect_aliases.add_group(
+                std::iter::once(name.as_str()).chain(aliases.iter().map(|alias| alias.as_str())),
+            );
+        }
+        Ok(effect_aliases)
+    }
+
+    /// Adds a group of names that all refer to the same effect. Groups which share a name are
+    /// merged.
+    fn add_group<'a>(&mut self, names: impl Iterator<Item = &'a str>) {
+        let mut group = names.map(normalize_name).collect::<AHashSet<_>>();
+        for name in group.clone() {
+            if let Some(existing) = self.aliases.get(&name) {
+                group.extend(existing.iter().cloned());
+            }
+        }
+        for name in &group {
+            self.aliases.insert(name.clone(), group.clone());
+        }
+    }
+
+    /// Returns the names of all aliases of the effect name, including the name itself, normalized
+    /// by `normalize_name`
+    pub fn resolve(&self, name: &str) -> AHashSet<String> {
+        let name = normalize_name(name);
+        match self.aliases.get(&name) {
+            Some(aliases) => aliases.clone(),
+            None => [name].into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::{fmt, str::FromStr};
+
+use serde_with::DeserializeFromStr;
+
+use crate::plugin_parser::magic_effect::{
+    EffectArchetype, MagicEffect, ACTOR_VALUE_HEALTH, ACTOR_VALUE_HEAL_RATE, ACTOR_VALUE_MAGICKA,
+    ACTOR_VALUE_MAGICKA_RATE, ACTOR_VALUE_STAMINA, ACTOR_VALUE_STAMINA_RATE,
+};
+
+/// One of the player's resources that effects can target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, DeserializeFromStr)]
+pub enum Target {
+    Health,
+    Magicka,
+    Stamina,
+}
+
+impl Target {
+    const ALL: [Target; 3] = [Target::Health, Target::Magicka, Target::Stamina];
+
+    fn get_actor_value(self) -> i32 {
+        match self {
+            Target::Health => ACTOR_VALUE_HEALTH,
+            Target::Magicka => ACTOR_VALUE_MAGICKA,
+            Target::Stamina => ACTOR_VALUE_STAMINA,
+        }
+    }
+
+    /// Returns the actor value for the rate at which the resource regenerates
+    fn get_rate_actor_value(self) -> i32 {
+        match self {
+            Target::Health => ACTOR_VALUE_HEAL_RATE,
+            Target::Magicka => ACTOR_VALUE_MAGICKA_RATE,
+            Target::Stamina => ACTOR_VALUE_STAMINA_RATE,
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "health" => Ok(Target::Health),
+            "magicka" => Ok(Target::Magicka),
+            "stamina" => Ok(Target::Stamina),
+            other => Err(format!(
+                "unknown target {}, expected health, magicka or stamina",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Target::Health => "health",
+            Target::Magicka => "magicka",
+            Target::Stamina => "stamina",
+        })
+    }
+}
+
+/// What an effect does to the resource it targets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum TargetBucket {
+    /// Restores the resource once, e.g. Restore Health
+    Restore,
+    /// Raises the resource's maximum for a while, e.g. Fortify Health
+    Fortify,
+    /// Lowers the resource, e.g. Damage Health or Ravage Health
+    Damage,
+    /// Changes how fast the resource regenerates, e.g. Regenerate Health or Damage Magicka Regen
+    Regen,
+}
+
+/// Returns the resource that the effect targets and what it does to it, or `None` if it doesn't
+/// target health, magicka or stamina.
+///
+/// Regen effects usually modify a regen rate multiplier rather than the rate itself, and which
+/// actor value that is differs between versions of the game, so effects that don't modify a regen
+/// rate are also recognized by a name that contains "regen" and the resource, e.g. "Regenerate
+/// Stamina".
+pub(crate) fn classify_effect(mgef: &MagicEffect) -> Option<(Target, TargetBucket)> {
+    // "Recover" flag, which restores the actor value when the effect ends
+    let is_recover = mgef.flags & 0x00000002 != 0;
+
+    if let Some(target) = Target::ALL
+        .into_iter()
+        .find(|target| target.get_actor_value() == mgef.actor_value)
+    {
+        return match mgef.archetype {
+            EffectArchetype::Absorb => Some((target, TargetBucket::Damage)),
+            EffectArchetype::ValueModifier | EffectArchetype::PeakValueModifier => Some((
+                target,
+                match (mgef.is_hostile, is_recover) {
+                    (true, _) => TargetBucket::Damage,
+                    (false, true) => TargetBucket::Fortify,
+                    (false, false) => TargetBucket::Restore,
+                },
+            )),
+            _ => None,
+        };
+    }
+
+    if let Some(target) = Target::ALL
+        .into_iter()
+        .find(|target| target.get_rate_actor_value() == mgef.actor_value)
+    {
+        return Some((target, TargetBucket::Regen));
+    }
+
+    let name = mgef.name.as_deref()?.to_lowercase();
+    if !name.contains("regen") {
+        return None;
+    }
+    Target::ALL
+        .into_iter()
+        .find(|target| name.contains(&target.to_string()))
+        .map(|target| (target, TargetBucket::Regen))
+}
+
+/// Returns the heading of the group of potions whose effect on the resource falls in the bucket,
+/// e.g. "Restore health" or "Health regen"
+pub(crate) fn get_bucket_heading(target: Target, bucket: TargetBucket) -> String {
+    match bucket {
+        TargetBucket::Restore => format!("Restore {}", target),
+        TargetBucket::Fortify => format!("Fortify {}", target),
+        TargetBucket::Damage => format!("Damage {}", target),
+        TargetBucket::Regen => {
+            let target = target.to_string();
+            format!("{}{} regen", target[..1].to_uppercase(), &target[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_data::GameData, plugin_parser::form_id::GlobalFormId};
+
+    /// "Recover" flag, which restores the actor value when the effect ends
+    const RECOVER: u32 = 0x00000002;
+
+    fn example_effect(game_data: &GameData, id: u32) -> MagicEffect {
+        game_data
+            .get_magic_effect(&GlobalFormId::new(0, id))
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn vital_effects_fall_in_their_buckets() {
+        let game_data = GameData::example();
+        let restore_health = example_effect(&game_data, 0x900);
+        let damage_health = example_effect(&game_data, 0x902);
+
+        assert_eq!(
+            classify_effect(&restore_health),
+            Some((Target::Health, TargetBucket::Restore))
+        );
+        assert_eq!(
+            classify_effect(&example_effect(&game_data, 0x901)),
+            Some((Target::Stamina, TargetBucket::Restore))
+        );
+        let fortify_magicka = MagicEffect {
+            name: Some("Fortify Magicka".to_string()),
+            flags: restore_health.flags | RECOVER,
+            actor_value: ACTOR_VALUE_MAGICKA,
+            ..restore_health
+        };
+        assert_eq!(
+            classify_effect(&fortify_magicka),
+            Some((Target::Magicka, TargetBucket::Fortify))
+        );
+
+        assert_eq!(
+            classify_effect(&damage_health),
+            Some((Target::Health, TargetBucket::Damage))
+        );
+        let ravage_health = MagicEffect {
+            name: Some("Ravage Health".to_string()),
+            flags: damage_health.flags | RECOVER,
+            archetype: EffectArchetype::PeakValueModifier,
+            ..damage_health.clone()
+        };
+        assert_eq!(
+            classify_effect(&ravage_health),
+            Some((Target::Health, TargetBucket::Damage))
+        );
+        let absorb_stamina = MagicEffect {
+            archetype: EffectArchetype::Absorb,
+            actor_value: ACTOR_VALUE_STAMINA,
+            ..damage_health
+        };
+        assert_eq!(
+            classify_effect(&absorb_stamina),
+            Some((Target::Stamina, TargetBucket::Damage))
+        );
+    }
+
+    #[test]
+    fn regen_effects_are_found_by_rate_or_name() {
+        let game_data = GameData::example();
+        let fortify_smithing = example_effect(&game_data, 0x903);
+
+        let by_rate = MagicEffect {
+            name: Some("Fortify Magicka Rate".to_string()),
+            actor_value: ACTOR_VALUE_MAGICKA_RATE,
+            ..fortify_smithing.clone()
+        };
+        assert_eq!(
+            classify_effect(&by_rate),
+            Some((Target::Magicka, TargetBucket::Regen))
+        );
+
+        // Regen effects that modify a rate multiplier are only recognized by their name
+        for (name, target) in [
+            ("Regenerate Stamina", Target::Stamina),
+            ("Damage Magicka Regen", Target::Magicka),
+            ("REGENERATE HEALTH", Target::Health),
+        ] {
+            let by_name = MagicEffect {
+                name: Some(name.to_string()),
+                actor_value: -1,
+                ..fortify_smithing.clone()
+            };
+            assert_eq!(
+                classify_effect(&by_name),
+                Some((target, TargetBucket::Regen)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn other_effects_are_not_classified() {
+        let game_data = GameData::example();
+        let fortify_smithing = example_effect(&game_data, 0x903);
+        assert_eq!(classify_effect(&fortify_smithing), None);
+        assert_eq!(
+            classify_effect(&example_effect(&game_data, 0x904)),
+            None,
+            "Resist Fire"
+        );
+
+        // Effects on health that don't change it, e.g. scripted ones
+        let scripted = MagicEffect {
+            archetype: EffectArchetype::Script,
+            ..example_effect(&game_data, 0x900)
+        };
+        assert_eq!(classify_effect(&scripted), None);
+
+        // Regen of something other than health, magicka or stamina
+        let regen_shouts = MagicEffect {
+            name: Some("Regenerate Shouts".to_string()),
+            ..fortify_smithing.clone()
+        };
+        assert_eq!(classify_effect(&regen_shouts), None);
+        let nameless = MagicEffect {
+            name: None,
+            ..fortify_smithing
+        };
+        assert_eq!(classify_effect(&nameless), None);
+    }
+
+    #[test]
+    fn bucket_headings_name_the_target() {
+        assert_eq!(
+            get_bucket_heading(Target::Health, TargetBucket::Restore),
+            "Restore health"
+        );
+        assert_eq!(
+            get_bucket_heading(Target::Stamina, TargetBucket::Regen),
+            "Stamina regen"
+        );
+    }
+}
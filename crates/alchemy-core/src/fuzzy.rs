@@ -0,0 +1,85 @@
+use std::cmp::{max, min};
+
+use itertools::Itertools;
+
+use crate::name_matching::normalize_name;
+
+/// The maximum number of names suggested for a name that doesn't match anything
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the number of characters that must be inserted, deleted or replaced to turn one string
+/// into the other, i.e. the Levenshtein distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = diagonal + usize::from(a_char != b_char);
+            diagonal = distances[j + 1];
+            distances[j + 1] = min(replace, min(distances[j], distances[j + 1]) + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Returns the candidates that are most similar to the name as normalized by `normalize_name`,
+/// most similar first, to
+/// suggest when the name doesn't match anything. Candidates that differ in more than about a third
+/// of the name's characters are not similar.
+pub(crate) fn find_similar<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Vec<&'c str> {
+    let name = normalize_name(name).chars().collect::<Vec<_>>();
+    let max_distance = max(1, name.len() / 3);
+
+    candidates
+        .into_iter()
+        .unique()
+        .filter_map(|candidate| {
+            let distance = edit_distance(
+                &name,
+                &normalize_name(candidate).chars().collect::<Vec<_>>(),
+            );
+            (distance <= max_distance).then(|| (distance, candidate))
+        })
+        .sorted()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns how well the query matches the candidate as normalized by `normalize_name`, lower is
+/// better, or `None` if it
+/// doesn't match. Candidates containing the query match best, earlier occurrences first. Otherwise
+/// the query's characters must appear in the candidate in order, e.g. "frsal" matches "Frost
+/// Salts", and candidates with fewer characters between them match better.
+pub(crate) fn fuzzy_match_score(query: &str, candidate: &str) -> Option<(u8, usize)> {
+    let query = normalize_name(query);
+    let candidate = normalize_name(candidate);
+    if let Some(position) = candidate.find(&query) {
+        return Some((0, position));
+    }
+
+    let mut skipped = 0;
+    let mut candidate_chars = candidate.chars();
+    for query_char in query.chars().filter(|c| !c.is_whitespace()) {
+        skipped += candidate_chars.position(|c| c == query_char)?;
+    }
+    Some((1, skipped))
+}
+
+/// Formats the suggestions to append to a message, e.g. ` (did you mean 'Restore Health'?)`, or
+/// returns an empty string if there are none
+pub(crate) fn format_suggestions<S: AsRef<str>>(suggestions: &[S]) -> String {
+    let quoted = suggestions
+        .iter()
+        .map(|suggestion| format!("'{}'", suggestion.as_ref()))
+        .collect::<Vec<_>>();
+    match &quoted[..] {
+        [] => String::new(),
+        [suggestion] => format!(" (did you mean {}?)", suggestion),
+        [rest @ .., last] => format!(" (did you mean {} or {}?)", rest.join(", "), last),
+    }
+}
@@ -0,0 +1,1931 @@
+use ahash::{AHashMap, AHashSet};
+use anyhow::anyhow;
+use arrayvec::ArrayVec;
+use itertools::Itertools;
+use once_cell::sync::OnceCell;
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::value::RawValue;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    crafting_rules::CraftingRules,
+    fuzzy::{find_similar, format_suggestions},
+    hostility_overrides::HostilityOverrides,
+    ingredient_names::{get_plugin_stem, DuplicateNameStyle},
+    ingredient_overlay::{IngredientOverlay, OverlayIngredient, OVERLAY_PLUGIN_NAME},
+    ingredient_overrides::IngredientOverride,
+    load_order::{LoadOrder, LoadOrderEntry},
+    load_order_hash::LoadOrderHash,
+    locale::NumberLocale,
+    name_matching::normalize_name,
+    player_context::PlayerContext,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::{Ingredient, IngredientEffect},
+        magic_effect::MagicEffect,
+    },
+    potion::{AlchemySkill, Potion, PotionCraftError},
+    potion_name::PotionNameTemplate,
+    simulation::{CraftResult, CraftingContext},
+    skill_xp::AlchemyXpSettings,
+};
+
+/// Base costs above this are almost certainly mistakes in a mod. The highest base cost of a vanilla
+/// alchemy effect is 500 (Paralysis).
+const MAX_PLAUSIBLE_BASE_COST: f32 = 10_000.0;
+
+#[derive(thiserror::Error, Debug)]
+#[error("the form ID {} is unknown", .form_id)]
+pub struct UnknownFormIdError {
+    pub form_id: GlobalFormId,
+}
+
+// TODO: validate more invalid data conditions
+#[derive(thiserror::Error, Debug)]
+pub enum IngredientError<'a> {
+    #[error("ingredient {} references unknown magic effects: {}", get_ingredient_name_or_fallback(.0), .1.iter().join(", "))]
+    ReferencesUnknownMagicEffects(&'a Ingredient, Vec<UnknownFormIdError>),
+}
+
+impl<'a> IngredientError<'a> {
+    /// Formats the error with the plugin names of the form IDs, see
+    /// `GlobalFormId::to_display_with`
+    pub fn to_display_with(&self, load_order: &LoadOrder) -> String {
+        match self {
+            IngredientError::ReferencesUnknownMagicEffects(ingredient, errors) => format!(
+                "ingredient {} ({}) references unknown magic effects: {}",
+                get_ingredient_name_or_fallback(ingredient),
+                ingredient.get_global_form_id().to_display_with(load_order),
+                errors
+                    .iter()
+                    .map(|err| err.form_id.to_display_with(load_order))
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FindIngredientError {
+    #[error("no ingredient found matching \"{0}\"{}", format_suggestions(.1))]
+    NotFound(String, Vec<String>),
+    #[error("\"{0}\" matches multiple ingredients: {}", .1.join(", "))]
+    Ambiguous(String, Vec<String>),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CraftError<'a> {
+    #[error(transparent)]
+    FindIngredient(#[from] FindIngredientError),
+    #[error(transparent)]
+    PotionCraft(#[from] PotionCraftError<'a>),
+}
+
+/// Returns the record's name, or its editor ID if it has no name, or its form ID if it has neither,
+/// so that records from plugins that leave out the name still show up as something meaningful
+fn get_name_or_fallback<'a>(
+    name: Option<&'a str>,
+    editor_id: &'a str,
+    form_id: GlobalFormId,
+) -> Cow<'a, str> {
+    match name.filter(|name| !name.is_empty()) {
+        Some(name) => Cow::Borrowed(name),
+        None if !editor_id.is_empty() => Cow::Borrowed(editor_id),
+        None => Cow::Owned(form_id.to_string()),
+    }
+}
+
+/// Returns the name to show for the ingredient: its qualified name if another ingredient has the
+/// same name, otherwise its name, or its editor ID or form ID if it has no name
+pub(crate) fn get_ingredient_name_or_fallback(ingredient: &Ingredient) -> Cow<str> {
+    get_name_or_fallback(
+        ingredient
+            .qualified_name
+            .as_deref()
+            .or(ingredient.name.as_deref()),
+        &ingredient.editor_id,
+        ingredient.get_global_form_id(),
+    )
+}
+
+/// Returns the name to show for the magic effect: its name, or its editor ID or form ID if it has
+/// no name
+pub(crate) fn get_magic_effect_name_or_fallback(magic_effect: &MagicEffect) -> Cow<str> {
+    get_name_or_fallback(
+        magic_effect.name.as_deref(),
+        &magic_effect.editor_id,
+        magic_effect.get_global_form_id(),
+    )
+}
+
+// TODO: when serializing/deserializing game data, keep load order
+pub struct GameData {
+    load_order: LoadOrder,
+    /// All active plugins at the time the game data was read. Unlike `load_order`, this includes
+    /// plugins that don't contain any ingredients or magic effects.
+    plugins: LoadOrder,
+    ingredients: AHashMap<GlobalFormId, Ingredient>,
+    magic_effects: AHashMap<GlobalFormId, MagicEffect>,
+    /// Ingredients whose effects were removed or changed by an overriding plugin
+    ingredient_overrides: Vec<IngredientOverride>,
+    /// Identifies the plugins the game data was exported from. `None` for game data exported by
+    /// older versions.
+    load_order_hash: Option<LoadOrderHash>,
+    /// The settings that the Alchemy XP depends on. `None` for game data exported by older
+    /// versions.
+    alchemy_xp_settings: Option<AlchemyXpSettings>,
+    /// What we know about the player, if anything. This is not serialized.
+    player_context: Option<PlayerContext>,
+    /// The rules by which potions are made from the ingredients. This is not serialized.
+    crafting_rules: CraftingRules,
+    /// The IDs of the ingredients that have each magic effect, built on first use and reset when
+    /// the ingredients change. This is not serialized.
+    effects_to_ingredients: OnceCell<AHashMap<GlobalFormId, Vec<GlobalFormId>>>,
+    /// The template that potions made from the game data are named with, or `None` for the game's
+    /// naming scheme. This is not serialized.
+    potion_name_template: Option<PotionNameTemplate>,
+    /// The locale that numbers are formatted with when potions and reports about the game data are
+    /// output. This is not serialized.
+    number_locale: NumberLocale,
+    /// The soft memory budget in bytes for building potions from the game data, if any. This is
+    /// not serialized.
+    memory_budget: Option<u64>,
+}
+
+impl Serialize for GameData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut gd = serializer.serialize_struct("GameData", 7)?;
+        gd.serialize_field("load_order", &self.load_order.iter().collect::<Vec<_>>())?;
+        gd.serialize_field("plugins", self.plugins.entries())?;
+        gd.serialize_field("ingredients", &SerializeValues(&self.ingredients))?;
+        gd.serialize_field("magic_effects", &SerializeValues(&self.magic_effects))?;
+        gd.serialize_field("ingredient_overrides", &self.ingredient_overrides)?;
+        gd.serialize_field("load_order_hash", &self.load_order_hash)?;
+        gd.serialize_field("alchemy_xp_settings", &self.alchemy_xp_settings)?;
+        gd.end()
+    }
+}
+
+/// Serializes the values of a map as a sequence, one at a time
+struct SerializeValues<'a, K, V>(&'a AHashMap<K, V>);
+
+impl<'a, K, V> Serialize for SerializeValues<'a, K, V>
+where
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.values())
+    }
+}
+
+impl<'de> Deserialize<'de> for GameData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        enum Field {
+            LoadOrder,
+            Plugins,
+            Ingredients,
+            MagicEffects,
+            IngredientOverrides,
+            LoadOrderHash,
+            AlchemyXpSettings,
+        }
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                struct FieldVisitor;
+
+                impl<'de> Visitor<'de> for FieldVisitor {
+                    type Value = Field;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str(
+                            "`load_order` or `plugins` or `ingredients` or `magic_effects` or \
+                             `ingredient_overrides` or `load_order_hash` or \
+                             `alchemy_xp_settings`",
+                        )
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<Field, E>
+                    where
+                        E: de::Error,
+                    {
+                        match value {
+                            "load_order" => Ok(Field::LoadOrder),
+                            "plugins" => Ok(Field::Plugins),
+                            "ingredients" => Ok(Field::Ingredients),
+                            "magic_effects" => Ok(Field::MagicEffects),
+                            "ingredient_overrides" => Ok(Field::IngredientOverrides),
+                            "load_order_hash" => Ok(Field::LoadOrderHash),
+                            "alchemy_xp_settings" => Ok(Field::AlchemyXpSettings),
+                            _ => Err(de::Error::unknown_field(value, FIELDS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(FieldVisitor)
+            }
+        }
+
+        struct GameDataVisitor;
+
+        impl<'de> Visitor<'de> for GameDataVisitor {
+            type Value = GameData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct GameData")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<GameData, V::Error>
+            where
+                V: SeqAccess<'de>,
+            {
+                let load_order = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let plugins = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let ingredients = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let magic_effects = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                let ingredient_overrides = seq.next_element()?.unwrap_or_default();
+                let load_order_hash = seq.next_element()?.flatten();
+                let alchemy_xp_settings = seq.next_element()?.flatten();
+                let mut game_data = GameData::from_vecs(
+                    load_order,
+                    plugins,
+                    ingredients,
+                    magic_effects,
+                    ingredient_overrides,
+                );
+                game_data.load_order_hash = load_order_hash;
+                game_data.alchemy_xp_settings = alchemy_xp_settings;
+                game_data
+                    .plugins
+                    .check_limits()
+                    .map_err(de::Error::custom)?;
+                Ok(game_data)
+            }
+
+            fn visit_map<V>(self, mut map: V) -> Result<GameData, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut load_order = None;
+                let mut plugins = None;
+                let mut ingredients = None;
+                let mut magic_effects = None;
+                let mut ingredient_overrides = None;
+                let mut load_order_hash = None;
+                let mut alchemy_xp_settings = None;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        Field::LoadOrder => {
+                            if load_order.is_some() {
+                                return Err(de::Error::duplicate_field("load_order"));
+                            }
+                            load_order = Some(map.next_value()?);
+                        }
+                        Field::Plugins => {
+                            if plugins.is_some() {
+                                return Err(de::Error::duplicate_field("plugins"));
+                            }
+                            plugins = Some(map.next_value()?);
+                        }
+                        Field::Ingredients => {
+                            if ingredients.is_some() {
+                                return Err(de::Error::duplicate_field("ingredients"));
+                            }
+                            ingredients = Some(map.next_value()?);
+                        }
+                        Field::MagicEffects => {
+                            if magic_effects.is_some() {
+                                return Err(de::Error::duplicate_field("magic_effects"));
+                            }
+                            magic_effects = Some(map.next_value()?);
+                        }
+                        Field::IngredientOverrides => {
+                            if ingredient_overrides.is_some() {
+                                return Err(de::Error::duplicate_field("ingredient_overrides"));
+                            }
+                            ingredient_overrides = Some(map.next_value()?);
+                        }
+                        Field::LoadOrderHash => {
+                            if load_order_hash.is_some() {
+                                return Err(de::Error::duplicate_field("load_order_hash"));
+                            }
+                            load_order_hash = Some(map.next_value()?);
+                        }
+                        Field::AlchemyXpSettings => {
+                            if alchemy_xp_settings.is_some() {
+                                return Err(de::Error::duplicate_field("alchemy_xp_settings"));
+                            }
+                            alchemy_xp_settings = Some(map.next_value()?);
+                        }
+                    }
+                }
+                let load_order =
+                    load_order.ok_or_else(|| de::Error::missing_field("load_order"))?;
+                // Game data exported by older versions doesn't contain the list of plugins
+                let plugins = plugins.unwrap_or_else(|| {
+                    log::warn!("Game data does not contain the list of active plugins");
+                    Vec::new()
+                });
+                let ingredients =
+                    ingredients.ok_or_else(|| de::Error::missing_field("ingredients"))?;
+                let magic_effects =
+                    magic_effects.ok_or_else(|| de::Error::missing_field("magic_effects"))?;
+                // Game data exported by older versions doesn't contain the ingredient overrides
+                let ingredient_overrides = ingredient_overrides.unwrap_or_default();
+                let mut game_data = GameData::from_vecs(
+                    load_order,
+                    plugins,
+                    ingredients,
+                    magic_effects,
+                    ingredient_overrides,
+                );
+                // Game data exported by older versions doesn't contain the load order hash
+                game_data.load_order_hash = load_order_hash.flatten();
+                // Game data exported by older versions doesn't contain the Alchemy XP settings
+                game_data.alchemy_xp_settings = alchemy_xp_settings.flatten();
+                game_data
+                    .plugins
+                    .check_limits()
+                    .map_err(de::Error::custom)?;
+                Ok(game_data)
+            }
+        }
+
+        const FIELDS: &[&str] = &[
+            "load_order",
+            "plugins",
+            "ingredients",
+            "magic_effects",
+            "ingredient_overrides",
+            "load_order_hash",
+            "alchemy_xp_settings",
+        ];
+        deserializer.deserialize_struct("GameData", FIELDS, GameDataVisitor)
+    }
+}
+
+/// The sections of exported game data, of which the large ones are left unparsed so that they can
+/// be parsed on separate threads
+#[derive(Deserialize)]
+struct GameDataSections<'a> {
+    load_order: Vec<String>,
+    plugins: Option<Vec<LoadOrderEntry>>,
+    #[serde(borrow)]
+    ingredients: &'a RawValue,
+    #[serde(borrow)]
+    magic_effects: &'a RawValue,
+    #[serde(borrow)]
+    ingredient_overrides: Option<&'a RawValue>,
+    #[serde(default)]
+    load_order_hash: Option<LoadOrderHash>,
+    #[serde(default)]
+    alchemy_xp_settings: Option<AlchemyXpSettings>,
+}
+
+impl GameData {
+    /// Parses exported game data like its `Deserialize` implementation does, but parses the
+    /// ingredients, magic effects and ingredient overrides in parallel, which is noticeably faster
+    /// for large load orders. The JSON is first only scanned to find where each section starts
+    /// and ends, without building any values.
+    pub fn from_json_slice(json: &[u8]) -> Result<Self, serde_json::Error> {
+        let sections: GameDataSections = serde_json::from_slice(json)?;
+
+        let (ingredients, (magic_effects, ingredient_overrides)) = rayon::join(
+            || serde_json::from_str::<Vec<Ingredient>>(sections.ingredients.get()),
+            || {
+                rayon::join(
+                    || serde_json::from_str::<Vec<MagicEffect>>(sections.magic_effects.get()),
+                    || {
+                        sections
+                            .ingredient_overrides
+                            .map(|raw| serde_json::from_str(raw.get()))
+                            .transpose()
+                    },
+                )
+            },
+        );
+
+        // Game data exported by older versions doesn't contain the list of plugins
+        let plugins = sections.plugins.unwrap_or_else(|| {
+            log::warn!("Game data does not contain the list of active plugins");
+            Vec::new()
+        });
+        let mut game_data = GameData::from_vecs(
+            sections.load_order,
+            plugins,
+            ingredients?,
+            magic_effects?,
+            // Game data exported by older versions doesn't contain the ingredient overrides
+            ingredient_overrides?.unwrap_or_default(),
+        );
+        // Game data exported by older versions doesn't contain the load order hash
+        game_data.load_order_hash = sections.load_order_hash;
+        // Game data exported by older versions doesn't contain the Alchemy XP settings
+        game_data.alchemy_xp_settings = sections.alchemy_xp_settings;
+        game_data
+            .plugins
+            .check_limits()
+            .map_err(<serde_json::Error as de::Error>::custom)?;
+        Ok(game_data)
+    }
+
+    pub fn from_hashmaps(
+        mut load_order: LoadOrder,
+        plugins: LoadOrder,
+        mut ingredients: AHashMap<GlobalFormId, Ingredient>,
+        mut magic_effects: AHashMap<GlobalFormId, MagicEffect>,
+        ingredient_overrides: Vec<IngredientOverride>,
+    ) -> Self {
+        // Remove unused entries from the load order. The effects of ingredients count as used
+        // even if their magic effect is missing, e.g. because it is injected into a master by a
+        // plugin that isn't active, so that they can be remapped and then purged as invalid.
+        let used_indexes = ingredients
+            .keys()
+            .chain(magic_effects.keys())
+            .chain(
+                ingredients
+                    .values()
+                    .flat_map(|ing| ing.effects.iter().map(|eff| &eff.global_form_id)),
+            )
+            .map(|k| k.load_order_index);
+        let index_remap_data = load_order.drain_unused(used_indexes);
+
+        if let Some(index_remap_data) = index_remap_data {
+            // Remap load order indexes in ingredient global form IDs
+            for ingredient in ingredients.values_mut() {
+                let new_index = *index_remap_data
+                    .get(&ingredient.global_form_id.load_order_index)
+                    .unwrap();
+                ingredient.global_form_id.set_load_order_index(new_index);
+
+                for ingredient_effect in ingredient.effects.iter_mut() {
+                    let new_index = *index_remap_data
+                        .get(&ingredient_effect.global_form_id.load_order_index)
+                        .unwrap();
+                    ingredient_effect
+                        .global_form_id
+                        .set_load_order_index(new_index);
+                }
+            }
+
+            // Remap load order indexes in magic_effect global form IDs
+            for magic_effect in magic_effects.values_mut() {
+                let new_index = *index_remap_data
+                    .get(&magic_effect.global_form_id.load_order_index)
+                    .unwrap();
+                magic_effect.global_form_id.set_load_order_index(new_index);
+            }
+
+            // Create new ingredients hashmap with remapped global form IDs
+            ingredients = ingredients
+                .into_iter()
+                .map(|(_k, v)| (v.get_global_form_id(), v))
+                .collect();
+
+            // Create new magic_effects hashmap with remapped global form IDs
+            magic_effects = magic_effects
+                .into_iter()
+                .map(|(_k, v)| (v.get_global_form_id(), v))
+                .collect();
+        }
+
+        Self {
+            load_order,
+            plugins,
+            ingredients,
+            magic_effects,
+            ingredient_overrides,
+            load_order_hash: None,
+            alchemy_xp_settings: None,
+            player_context: None,
+            crafting_rules: CraftingRules::default(),
+            effects_to_ingredients: OnceCell::new(),
+            potion_name_template: None,
+            number_locale: NumberLocale::PLAIN,
+            memory_budget: None,
+        }
+    }
+
+    pub fn from_vecs(
+        load_order: Vec<String>,
+        plugins: Vec<LoadOrderEntry>,
+        mut ingredients: Vec<Ingredient>,
+        mut magic_effects: Vec<MagicEffect>,
+        ingredient_overrides: Vec<IngredientOverride>,
+    ) -> Self {
+        let mut load_order = LoadOrder::new(load_order);
+        let plugins = LoadOrder::from_entries(plugins);
+
+        // Whether a plugin is a light master is only stored in the full list of plugins
+        for index in 0..load_order.len() as u16 {
+            let is_light = load_order
+                .get(index)
+                .and_then(|name| plugins.find_index(name))
+                .and_then(|plugins_index| plugins.is_light(plugins_index))
+                .unwrap_or(false);
+            load_order.set_light(index, is_light);
+        }
+
+        // Remove unused entries from the load order, keeping those of missing effects like
+        // `from_hashmaps` does
+        let used_indexes = ingredients
+            .iter()
+            .map(|x| x.get_global_form_id())
+            .chain(magic_effects.iter().map(|x| x.get_global_form_id()))
+            .chain(
+                ingredients
+                    .iter()
+                    .flat_map(|x| x.effects.iter().map(|eff| eff.get_global_form_id())),
+            )
+            .map(|x| x.load_order_index);
+        let index_remap_data = load_order.drain_unused(used_indexes);
+
+        if let Some(index_remap_data) = index_remap_data {
+            // Remap load order indexes in ingredient global form IDs
+            for ingredient in ingredients.iter_mut() {
+                let new_index = *index_remap_data
+                    .get(&ingredient.global_form_id.load_order_index)
+                    .unwrap();
+                ingredient.global_form_id.set_load_order_index(new_index);
+
+                for ingredient_effect in ingredient.effects.iter_mut() {
+                    let new_index = *index_remap_data
+                        .get(&ingredient_effect.global_form_id.load_order_index)
+                        .unwrap();
+                    ingredient_effect
+                        .global_form_id
+                        .set_load_order_index(new_index);
+                }
+            }
+
+            // Remap load order indexes in magic_effect global form IDs
+            for magic_effect in magic_effects.iter_mut() {
+                let new_index = *index_remap_data
+                    .get(&magic_effect.global_form_id.load_order_index)
+                    .unwrap();
+                magic_effect.global_form_id.set_load_order_index(new_index);
+            }
+        }
+
+        // Create the ingredients and magic_effects hashmaps concurrently
+        let (ingredients, magic_effects) = rayon::join(
+            || {
+                ingredients
+                    .into_iter()
+                    .map(|ing| (ing.get_global_form_id(), ing))
+                    .collect()
+            },
+            || {
+                magic_effects
+                    .into_iter()
+                    .map(|mgef| (mgef.get_global_form_id(), mgef))
+                    .collect()
+            },
+        );
+
+        Self {
+            load_order,
+            plugins,
+            ingredients,
+            magic_effects,
+            ingredient_overrides,
+            load_order_hash: None,
+            alchemy_xp_settings: None,
+            player_context: None,
+            crafting_rules: CraftingRules::default(),
+            effects_to_ingredients: OnceCell::new(),
+            potion_name_template: None,
+            number_locale: NumberLocale::PLAIN,
+            memory_budget: None,
+        }
+    }
+
+    /// Returns the load order that the load order indexes of the `GlobalFormId`s in this
+    /// `GameData` refer to. Only contains plugins that have ingredients or magic effects, including
+    /// masters that other plugins inject them into.
+    pub fn get_load_order(&self) -> &LoadOrder {
+        &self.load_order
+    }
+
+    /// Returns all active plugins at the time the game data was read, in load order
+    pub fn get_plugins(&self) -> &LoadOrder {
+        &self.plugins
+    }
+
+    /// Returns the hash that identifies the plugins the game data was exported from, or `None` if
+    /// it was exported by an older version
+    pub fn get_load_order_hash(&self) -> Option<LoadOrderHash> {
+        self.load_order_hash
+    }
+
+    /// Returns the settings that the Alchemy XP depends on, or those of the base game if the game
+    /// data was exported by an older version
+    pub fn get_alchemy_xp_settings(&self) -> AlchemyXpSettings {
+        self.alchemy_xp_settings.unwrap_or_default()
+    }
+
+    pub fn set_alchemy_xp_settings(&mut self, alchemy_xp_settings: AlchemyXpSettings) {
+        self.alchemy_xp_settings = Some(alchemy_xp_settings);
+    }
+
+    pub fn set_load_order_hash(&mut self, load_order_hash: LoadOrderHash) {
+        self.load_order_hash = Some(load_order_hash);
+    }
+
+    /// Returns the name of the plugin that the form ID belongs to
+    pub fn get_plugin_name(&self, global_form_id: &GlobalFormId) -> Option<&str> {
+        self.load_order.get(global_form_id.load_order_index)
+    }
+
+    /// Returns the form ID as the game sees it at runtime, e.g. `0003AD66` for a record from
+    /// Skyrim.esm or `FE001803` for a record from the first light master. Returns `None` if the
+    /// plugin is not in the list of active plugins.
+    pub fn get_runtime_form_id(&self, global_form_id: &GlobalFormId) -> Option<u32> {
+        // See https://en.uesp.net/wiki/Skyrim:Form_ID
+        let plugins_index = self
+            .plugins
+            .find_index(self.get_plugin_name(global_form_id)?)?;
+        let runtime_index = u32::from(self.plugins.get_runtime_index(plugins_index)?);
+
+        if self.plugins.is_light(plugins_index)? {
+            Some(0xFE000000 | (runtime_index << 12) | (global_form_id.id & 0x00000FFF))
+        } else {
+            Some((runtime_index << 24) | global_form_id.id)
+        }
+    }
+
+    /// The inverse of `get_runtime_form_id`. Returns `None` if the form ID doesn't belong to any
+    /// plugin in the load order.
+    pub fn get_global_form_id_from_runtime(&self, runtime_form_id: u32) -> Option<GlobalFormId> {
+        // See https://en.uesp.net/wiki/Skyrim:Form_ID
+        let (is_light, runtime_index, id) = if runtime_form_id >> 24 == 0xFE {
+            (
+                true,
+                (runtime_form_id >> 12) & 0x00000FFF,
+                runtime_form_id & 0x00000FFF,
+            )
+        } else {
+            (false, runtime_form_id >> 24, runtime_form_id & 0x00FFFFFF)
+        };
+
+        let plugin_name = self
+            .plugins
+            .iter_entries()
+            .filter(|(_, entry)| entry.is_light == is_light)
+            .nth(runtime_index as usize)
+            .map(|(_, entry)| &entry.name)?;
+        let load_order_index = self.load_order.find_index(plugin_name)?;
+
+        Some(GlobalFormId::new(load_order_index, id))
+    }
+
+    /// Returns what we know about the player, if the context was set
+    pub fn get_player_context(&self) -> Option<&PlayerContext> {
+        self.player_context.as_ref()
+    }
+
+    pub fn set_player_context(&mut self, player_context: PlayerContext) {
+        self.player_context = Some(player_context);
+    }
+
+    pub fn get_crafting_rules(&self) -> &CraftingRules {
+        &self.crafting_rules
+    }
+
+    pub fn set_crafting_rules(&mut self, crafting_rules: CraftingRules) {
+        self.crafting_rules = crafting_rules;
+    }
+
+    /// Returns the template that potions are named with, or `None` if they are named like in the
+    /// game
+    pub fn get_potion_name_template(&self) -> Option<&PotionNameTemplate> {
+        self.potion_name_template.as_ref()
+    }
+
+    pub fn set_potion_name_template(&mut self, template: Option<PotionNameTemplate>) {
+        self.potion_name_template = template;
+    }
+
+    /// Returns the locale that numbers are formatted with in the output. Numbers are plain unless it
+    /// was set.
+    pub fn get_number_locale(&self) -> NumberLocale {
+        self.number_locale
+    }
+
+    pub fn set_number_locale(&mut self, locale: NumberLocale) {
+        self.number_locale = locale;
+    }
+
+    /// Returns the soft memory budget in bytes for building potions, if there is one
+    pub fn get_memory_budget(&self) -> Option<u64> {
+        self.memory_budget
+    }
+
+    /// Sets a soft memory budget in bytes for building potions. When storing all combinations of
+    /// ingredients would exceed it, the potions are computed while streaming the combinations
+    /// instead, which is slower but uses far less memory.
+    pub fn set_memory_budget(&mut self, budget: Option<u64>) {
+        self.memory_budget = budget;
+    }
+
+    /// Removes all ingredients that are not in the player's inventory. Does nothing if the player
+    /// context is not set.
+    pub fn retain_inventory_ingredients(&mut self) {
+        if let Some(player_context) = &self.player_context {
+            self.ingredients
+                .retain(|form_id, _| player_context.get_inventory_count(form_id) > 0);
+            self.effects_to_ingredients.take();
+        }
+    }
+
+    /// Removes all ingredient effects that the player hasn't discovered. Ingredients that are left
+    /// without any effects are removed entirely. Does nothing if the player context is not set.
+    pub fn retain_known_ingredient_effects(&mut self) {
+        if let Some(player_context) = &self.player_context {
+            for (form_id, ingredient) in self.ingredients.iter_mut() {
+                ingredient
+                    .effects
+                    .retain(|effect| player_context.knows_effect(form_id, &effect.global_form_id));
+            }
+
+            self.ingredients
+                .retain(|_, ingredient| !ingredient.effects.is_empty());
+            self.effects_to_ingredients.take();
+        }
+    }
+
+    /// Removes all ingredients for which the predicate returns false
+    pub fn retain_ingredients<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&Ingredient) -> bool,
+    {
+        self.ingredients
+            .retain(|_, ingredient| predicate(ingredient));
+        self.effects_to_ingredients.take();
+    }
+
+    /// Removes all ingredient effects for which the predicate returns false. Ingredients that are
+    /// left without any effects are removed entirely.
+    pub fn retain_ingredient_effects<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&Ingredient, &IngredientEffect) -> bool,
+    {
+        for ingredient in self.ingredients.values_mut() {
+            let retained_effects = ingredient
+                .effects
+                .iter()
+                .filter(|effect| predicate(ingredient, effect))
+                .cloned()
+                .collect();
+            ingredient.effects = retained_effects;
+        }
+
+        self.ingredients
+            .retain(|_, ingredient| !ingredient.effects.is_empty());
+        self.effects_to_ingredients.take();
+    }
+
+    /// Sets whether each magic effect in the overrides is hostile, regardless of its hostile flag.
+    /// Returns the number of magic effects that changed.
+    pub fn apply_hostility_overrides(&mut self, overrides: &HostilityOverrides) -> usize {
+        let mut num_changed = 0;
+        for magic_effect in self.magic_effects.values_mut() {
+            match overrides.get(&magic_effect.editor_id) {
+                Some(is_hostile) if is_hostile != magic_effect.is_hostile => {
+                    log::debug!(
+                        "Overriding hostility of magic effect {} ({}): is_hostile = {}",
+                        magic_effect.editor_id,
+                        magic_effect.global_form_id,
+                        is_hostile
+                    );
+                    magic_effect.is_hostile = is_hostile;
+                    num_changed += 1;
+                }
+                _ => {}
+            }
+        }
+        num_changed
+    }
+
+    /// Merges the overlay into the ingredients: the fields that the overlay sets replace those of
+    /// the ingredient with the same editor ID, and ingredients that don't exist yet are added as
+    /// if by a plugin named `OVERLAY_PLUGIN_NAME` at the end of the load order. Returns the number
+    /// of changed and added ingredients.
+    pub fn apply_ingredient_overlay(
+        &mut self,
+        overlay: &IngredientOverlay,
+    ) -> Result<(usize, usize), anyhow::Error> {
+        let mut effect_ids = AHashMap::<_, Vec<_>>::new();
+        for mgef in self.magic_effects.values() {
+            effect_ids
+                .entry(mgef.editor_id.to_lowercase())
+                .or_default()
+                .push(mgef.get_global_form_id());
+        }
+        // Borrowing only the load order lets the closure be used while ingredients are changed
+        let load_order = &self.load_order;
+        let resolve_effects = |overlay_ingredient: &OverlayIngredient| -> Result<_, anyhow::Error> {
+            let effects = match &overlay_ingredient.effects {
+                Some(effects) => effects,
+                None => return Ok(None),
+            };
+            let mut resolved = effects
+                .iter()
+                .enumerate()
+                .map(|(index, effect)| {
+                    let global_form_id = match effect_ids
+                        .get(&effect.effect.to_lowercase())
+                        .map(Vec::as_slice)
+                    {
+                        Some([global_form_id]) => *global_form_id,
+                        Some(global_form_ids) => Err(anyhow!(
+                            "Ingredient {} in the ingredient overlay has effect {}, which is the \
+                             editor ID of several magic effects: {}",
+                            overlay_ingredient.editor_id,
+                            effect.effect,
+                            global_form_ids
+                                .iter()
+                                .map(|form_id| form_id.to_display_with(load_order))
+                                .join(", ")
+                        ))?,
+                        None => Err(anyhow!(
+                            "Ingredient {} in the ingredient overlay has unknown effect {}, only \
+                             effects that some ingredient has can be used",
+                            overlay_ingredient.editor_id,
+                            effect.effect
+                        ))?,
+                    };
+                    Ok(IngredientEffect {
+                        global_form_id,
+                        duration: effect.duration,
+                        magnitude: effect.magnitude,
+                        record_index: Some(index as u8),
+                    })
+                })
+                .collect::<Result<ArrayVec<_, 4>, anyhow::Error>>()?;
+            if resolved
+                .iter()
+                .map(|eff| eff.global_form_id)
+                .unique()
+                .count()
+                < resolved.len()
+            {
+                Err(anyhow!(
+                    "Ingredient {} in the ingredient overlay has the same effect twice",
+                    overlay_ingredient.editor_id
+                ))?
+            }
+            // Like parsed ingredients, the effects are sorted by form ID
+            resolved.sort_by_key(|eff| eff.get_global_form_id());
+            Ok(Some(resolved))
+        };
+
+        let mut num_changed = 0;
+        let mut added = Vec::new();
+        for overlay_ingredient in overlay.get_ingredients() {
+            let effects = resolve_effects(overlay_ingredient)?;
+            let ingredient_id =
+                match self.find_ingredients_by_editor_id(&overlay_ingredient.editor_id)[..] {
+                    [] => None,
+                    [ingredient] => Some(ingredient.get_global_form_id()),
+                    ref ingredients => Err(anyhow!(
+                        "Ingredient {} in the ingredient overlay is ambiguous, it is the editor ID \
+                         of several ingredients: {}",
+                        overlay_ingredient.editor_id,
+                        self.format_form_ids(ingredients.iter().map(|ig| ig.get_global_form_id()))
+                    ))?,
+                };
+            let ingredient = ingredient_id.and_then(|id| self.ingredients.get_mut(&id));
+            match (ingredient, effects) {
+                (Some(ingredient), effects) => {
+                    log::debug!(
+                        "Overriding ingredient {} ({}) with the ingredient overlay",
+                        ingredient.editor_id,
+                        ingredient.global_form_id
+                    );
+                    if let Some(name) = &overlay_ingredient.name {
+                        ingredient.name = Some(name.clone());
+                    }
+                    if let Some(value) = overlay_ingredient.value {
+                        ingredient.value = Some(value);
+                    }
+                    if let Some(weight) = overlay_ingredient.weight {
+                        ingredient.weight = Some(weight);
+                    }
+                    if let Some(effects) = effects {
+                        ingredient.effects = effects;
+                    }
+                    num_changed += 1;
+                }
+                (None, Some(effects)) => added.push((overlay_ingredient, effects)),
+                (None, None) => Err(anyhow!(
+                    "Ingredient {} in the ingredient overlay is not in the game data, so it must \
+                     have effects",
+                    overlay_ingredient.editor_id
+                ))?,
+            }
+        }
+
+        if !added.is_empty() {
+            let load_order_index = self.load_order.push(OVERLAY_PLUGIN_NAME.to_string());
+            for (id, (overlay_ingredient, effects)) in added.iter().enumerate() {
+                // IDs below 0x800 are reserved by the game, like in plugins
+                let global_form_id = GlobalFormId::new(load_order_index, 0x800 + id as u32);
+                log::debug!(
+                    "Adding ingredient {} ({}) from the ingredient overlay",
+                    overlay_ingredient.editor_id,
+                    global_form_id
+                );
+                self.ingredients.insert(
+                    global_form_id,
+                    Ingredient {
+                        global_form_id,
+                        editor_id: overlay_ingredient.editor_id.clone(),
+                        name: overlay_ingredient.name.clone(),
+                        effects: effects.clone(),
+                        weight: overlay_ingredient.weight,
+                        value: overlay_ingredient.value,
+                        is_food: Some(false),
+                        qualified_name: None,
+                    },
+                );
+            }
+        }
+        self.effects_to_ingredients.take();
+
+        Ok((num_changed, added.len()))
+    }
+
+    /// Sets the qualified names of the ingredients that share their name with another ingredient,
+    /// as compared by `normalize_name`, so that the output can tell them apart. If the plugins don't tell them
+    /// apart, e.g. because one plugin defines both, their form IDs are used instead. Returns the
+    /// number of names that several ingredients share.
+    pub fn qualify_duplicate_names(&mut self, style: DuplicateNameStyle) -> usize {
+        let mut ids_by_name = AHashMap::<String, Vec<GlobalFormId>>::new();
+        for ingredient in self.ingredients.values_mut() {
+            ingredient.qualified_name = None;
+            if let Some(name) = &ingredient.name {
+                ids_by_name
+                    .entry(normalize_name(name))
+                    .or_default()
+                    .push(ingredient.get_global_form_id());
+            }
+        }
+        let duplicates = ids_by_name
+            .into_values()
+            .filter(|ids| ids.len() > 1)
+            .collect::<Vec<_>>();
+
+        if style != DuplicateNameStyle::None {
+            for ids in &duplicates {
+                let use_plugins = style == DuplicateNameStyle::Plugin
+                    && ids.iter().map(|id| id.load_order_index).unique().count() == ids.len();
+                for id in ids {
+                    let qualifier = match self.load_order.get(id.load_order_index) {
+                        Some(plugin_name) if use_plugins => {
+                            get_plugin_stem(plugin_name).to_string()
+                        }
+                        _ => id.to_display_with(&self.load_order),
+                    };
+                    let ingredient = self.ingredients.get_mut(id).unwrap();
+                    ingredient.qualified_name = Some(format!(
+                        "{} [{}]",
+                        ingredient.name.as_deref().unwrap_or_default(),
+                        qualifier
+                    ));
+                }
+            }
+        }
+
+        duplicates.len()
+    }
+
+    /// Removes the names of the ingredients and magic effects and the descriptions of the magic
+    /// effects, which come from the plugins' strings, keeping only their numeric data and editor
+    /// IDs. Everything that shows a name falls back to the editor ID. The ingredient overrides are
+    /// removed too, since they describe the effects by name.
+    pub fn strip_strings(&mut self) {
+        for ingredient in self.ingredients.values_mut() {
+            ingredient.name = None;
+            ingredient.qualified_name = None;
+        }
+        for magic_effect in self.magic_effects.values_mut() {
+            magic_effect.name = None;
+            magic_effect.description.clear();
+        }
+        self.ingredient_overrides.clear();
+    }
+
+    /// Returns the ingredients whose effects were removed or changed by an overriding plugin, as
+    /// found when the game data was read
+    pub fn get_ingredient_overrides(&self) -> &[IngredientOverride] {
+        &self.ingredient_overrides
+    }
+
+    pub fn get_ingredients(&self) -> &AHashMap<GlobalFormId, Ingredient> {
+        &self.ingredients
+    }
+
+    pub fn get_magic_effects(&self) -> &AHashMap<GlobalFormId, MagicEffect> {
+        &self.magic_effects
+    }
+
+    pub fn get_magic_effect(&self, global_form_id: &GlobalFormId) -> Option<&MagicEffect> {
+        self.magic_effects.get(global_form_id)
+    }
+
+    pub fn get_ingredient(&self, global_form_id: &GlobalFormId) -> Option<&Ingredient> {
+        self.ingredients.get(global_form_id)
+    }
+
+    pub fn has_ingredient(&self, global_form_id: &GlobalFormId) -> bool {
+        self.ingredients.contains_key(global_form_id)
+    }
+
+    /// Returns the IDs of the ingredients that have each magic effect, sorted. The index is built
+    /// on first use and kept until the ingredients change, so that everything looking up
+    /// ingredients by effect shares it.
+    pub fn effects_to_ingredients(&self) -> &AHashMap<GlobalFormId, Vec<GlobalFormId>> {
+        self.effects_to_ingredients.get_or_init(|| {
+            let mut effects_to_ingredients = AHashMap::<_, Vec<_>>::new();
+            for (ingredient_id, ingredient) in &self.ingredients {
+                for effect in &ingredient.effects {
+                    effects_to_ingredients
+                        .entry(effect.get_global_form_id())
+                        .or_default()
+                        .push(*ingredient_id);
+                }
+            }
+            for ingredient_ids in effects_to_ingredients.values_mut() {
+                ingredient_ids.sort_unstable();
+            }
+            effects_to_ingredients
+        })
+    }
+
+    /// Returns the ingredients with the effect, in form ID order
+    pub fn get_ingredients_with_effect(
+        &self,
+        effect_id: &GlobalFormId,
+    ) -> impl Iterator<Item = &Ingredient> + '_ {
+        self.effects_to_ingredients()
+            .get(effect_id)
+            .into_iter()
+            .flatten()
+            .map(|ingredient_id| &self.ingredients[ingredient_id])
+    }
+
+    /// Returns the other ingredients that share at least one effect with the ingredient
+    pub fn get_neighbors(&self, ingredient: &Ingredient) -> AHashSet<&Ingredient> {
+        ingredient
+            .effects
+            .iter()
+            .flat_map(|effect| self.get_ingredients_with_effect(&effect.get_global_form_id()))
+            .filter(|other| *other != ingredient)
+            .collect()
+    }
+
+    /// Returns the ingredients with the editor ID, ignoring case, in form ID order. Unlike form
+    /// IDs, editor IDs don't change when the load order changes, but they are not guaranteed to be
+    /// unique: two plugins can define different ingredients with the same editor ID.
+    pub fn find_ingredients_by_editor_id(&self, editor_id: &str) -> Vec<&Ingredient> {
+        let editor_id = editor_id.trim();
+        self.ingredients
+            .values()
+            .filter(|ig| ig.editor_id.eq_ignore_ascii_case(editor_id))
+            .sorted_by_key(|ig| ig.get_global_form_id())
+            .collect()
+    }
+
+    /// Formats form IDs with their plugin names for messages, e.g. about records that share an
+    /// editor ID: `Skyrim.esm|03AD66, MyMod.esp|000D62`
+    pub(crate) fn format_form_ids(
+        &self,
+        form_ids: impl IntoIterator<Item = GlobalFormId>,
+    ) -> String {
+        form_ids
+            .into_iter()
+            .map(|form_id| form_id.to_display_with(&self.load_order))
+            .join(", ")
+    }
+
+    /// Finds an ingredient by its name or editor ID, as compared by `normalize_name`, so that e.g.
+    /// "giants toe" finds "Giant's Toe". Exact matches on the qualified
+    /// name of an ingredient that shares its name with others (e.g. "Frost Salts [CACO]") are
+    /// preferred over exact matches on the name, then on the editor ID, then partial matches on
+    /// the name. A name that several ingredients share, or a partial match, is only returned if it
+    /// is the only one.
+    pub fn find_ingredient_by_name(&self, name: &str) -> Result<&Ingredient, FindIngredientError> {
+        let needle = normalize_name(name);
+        let ingredients = self
+            .ingredients
+            .values()
+            .sorted_by_key(|ig| ig.get_global_form_id())
+            .collect::<Vec<&Ingredient>>();
+
+        let qualified_name_matches = |ig: &&Ingredient| {
+            ig.qualified_name
+                .as_deref()
+                .map_or(false, |qualified_name| {
+                    normalize_name(qualified_name) == needle
+                })
+        };
+        let name_matches = |ig: &&Ingredient| {
+            ig.name
+                .as_deref()
+                .map_or(false, |ig_name| normalize_name(ig_name) == needle)
+        };
+        let editor_id_matches = |ig: &&Ingredient| normalize_name(&ig.editor_id) == needle;
+        let name_contains = |ig: &&Ingredient| {
+            ig.name
+                .as_deref()
+                .map_or(false, |ig_name| normalize_name(ig_name).contains(&needle))
+        };
+
+        if let Some(ig) = ingredients.iter().copied().find(qualified_name_matches) {
+            return Ok(ig);
+        }
+        match ingredients
+            .iter()
+            .copied()
+            .filter(name_matches)
+            .collect::<Vec<_>>()[..]
+        {
+            [] => {}
+            [ig] => return Ok(ig),
+            ref candidates => {
+                return Err(FindIngredientError::Ambiguous(
+                    name.to_string(),
+                    candidates
+                        .iter()
+                        .map(|ig| get_ingredient_name_or_fallback(ig).to_string())
+                        .collect(),
+                ))
+            }
+        }
+        if let Some(ig) = ingredients.iter().copied().find(editor_id_matches) {
+            return Ok(ig);
+        }
+
+        match ingredients
+            .iter()
+            .copied()
+            .filter(name_contains)
+            .collect::<Vec<_>>()[..]
+        {
+            [] => Err(FindIngredientError::NotFound(
+                name.to_string(),
+                self.find_similar_ingredient_names(name)
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+            )),
+            [ig] => Ok(ig),
+            ref candidates => Err(FindIngredientError::Ambiguous(
+                name.to_string(),
+                candidates
+                    .iter()
+                    .map(|ig| get_ingredient_name_or_fallback(ig).to_string())
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Returns the ingredient names that are most similar to the name, to suggest when it doesn't
+    /// match any ingredient
+    pub fn find_similar_ingredient_names(&self, name: &str) -> Vec<&str> {
+        find_similar(
+            name,
+            self.ingredients
+                .values()
+                .filter_map(|ig| ig.name.as_deref()),
+        )
+    }
+
+    /// Returns the magic effect names that are most similar to the name, to suggest when it
+    /// doesn't match any magic effect
+    pub fn find_similar_effect_names(&self, name: &str) -> Vec<&str> {
+        find_similar(
+            name,
+            self.magic_effects
+                .values()
+                .filter_map(|mgef| mgef.name.as_deref()),
+        )
+    }
+
+    /// Crafts a potion from the ingredients with the given names, as looked up by
+    /// `find_ingredient_by_name`
+    pub fn craft(
+        &self,
+        names: &[&str],
+        alchemy_skill: &AlchemySkill,
+    ) -> Result<Potion, CraftError> {
+        let ingredients = names
+            .iter()
+            .map(|name| self.find_ingredient_by_name(name))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Potion::from_ingredients(
+            &ingredients,
+            self,
+            alchemy_skill.get_power_factor(),
+        )?)
+    }
+
+    /// Crafts a potion from the ingredients with the given editor IDs, ignoring case, and returns
+    /// exactly what the game would produce with the context, e.g. to test a rebalance patch. Unlike
+    /// `craft`, ingredients are only matched by their full editor ID, and an editor ID that several
+    /// ingredients share is an error. See `CraftResult` for how the result is deterministic.
+    ///
+    /// The example uses the bundled example game data, so it only runs with the `test-fixtures`
+    /// feature:
+    ///
+    #[cfg_attr(feature = "test-fixtures", doc = "```")]
+    #[cfg_attr(not(feature = "test-fixtures"), doc = "```ignore")]
+    /// use alchemy_core::{game_data::GameData, AlchemySkill, CraftingContext, PotionType};
+    ///
+    /// let game_data = GameData::example();
+    /// let result = game_data
+    ///     .simulate_craft(
+    ///         &["ExampleFrostLily", "ExampleEmberRoot"],
+    ///         &CraftingContext::default(),
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(result.name, "Potion of Resist Fire");
+    /// assert_eq!(result.potion_type, PotionType::Potion);
+    /// assert_eq!(result.value, 107);
+    /// let effects = result
+    ///     .effects
+    ///     .iter()
+    ///     .map(|eff| (eff.editor_id.as_str(), eff.magnitude, eff.duration, eff.value))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     effects,
+    ///     [("ExampleResistFire", 18, 60, 86), ("ExampleRestoreHealth", 30, 0, 21)]
+    /// );
+    ///
+    /// // The Alchemist perk at 100% doubles the magnitudes
+    /// let context = CraftingContext {
+    ///     alchemy_skill: AlchemySkill {
+    ///         perk_bonus: 100.0,
+    ///         ..AlchemySkill::default()
+    ///     },
+    /// };
+    /// let result = game_data
+    ///     .simulate_craft(&["ExampleFrostLily", "ExampleEmberRoot"], &context)
+    ///     .unwrap();
+    /// assert_eq!(result.effects[0].magnitude, 36);
+    /// assert_eq!(result.value, 184 + 45);
+    /// ```
+    pub fn simulate_craft(
+        &self,
+        editor_ids: &[&str],
+        context: &CraftingContext,
+    ) -> Result<CraftResult, CraftError> {
+        let ingredients = editor_ids
+            .iter()
+            .map(
+                |editor_id| match self.find_ingredients_by_editor_id(editor_id)[..] {
+                    [] => Err(FindIngredientError::NotFound(
+                        editor_id.to_string(),
+                        Vec::new(),
+                    )),
+                    [ingredient] => Ok(ingredient),
+                    ref ingredients => Err(FindIngredientError::Ambiguous(
+                        editor_id.to_string(),
+                        ingredients
+                            .iter()
+                            .map(|ig| ig.get_global_form_id().to_display_with(&self.load_order))
+                            .collect(),
+                    )),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let potion =
+            Potion::from_ingredients(&ingredients, self, context.alchemy_skill.get_power_factor())?;
+
+        Ok(CraftResult::from_potion(&potion))
+    }
+
+    /// Returns the magic effects used by ingredients whose base cost is negative, not a number or
+    /// implausibly high, sorted by form ID. The gold values of potions with these effects are
+    /// meaningless, and values too large to represent are clamped.
+    pub fn find_implausible_base_costs(&self) -> Vec<&MagicEffect> {
+        let used_form_ids = self
+            .ingredients
+            .values()
+            .flat_map(|ing| ing.effects.iter().map(|ingef| ingef.get_global_form_id()))
+            .collect::<HashSet<_>>();
+        self.magic_effects
+            .values()
+            .filter(|mgef| used_form_ids.contains(&mgef.get_global_form_id()))
+            .filter(|mgef| !(0.0..=MAX_PLAUSIBLE_BASE_COST).contains(&mgef.base_cost))
+            .sorted_by_key(|mgef| mgef.get_global_form_id())
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<IngredientError>> {
+        let ings_with_unknown_mgefs = self
+            .ingredients
+            .values()
+            .filter_map(|ing| {
+                let unknown_ingefs = ing
+                    .effects
+                    .iter()
+                    .filter_map(|ingef| {
+                        if self.magic_effects.contains_key(&ingef.get_global_form_id()) {
+                            None
+                        } else {
+                            Some(UnknownFormIdError {
+                                form_id: ingef.get_global_form_id(),
+                            })
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if unknown_ingefs.is_empty() {
+                    None
+                } else {
+                    Some(IngredientError::ReferencesUnknownMagicEffects(
+                        ing,
+                        unknown_ingefs,
+                    ))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if !ings_with_unknown_mgefs.is_empty() {
+            return Err(ings_with_unknown_mgefs);
+        }
+
+        Ok(())
+    }
+
+    // TODO: maybe use pattern where you return different kind of struct to disallow improper usage
+    // TODO: avoid double validate when calling purge_invalid after validate
+    /// Purges invalid data from the `GameData` struct
+    pub fn purge_invalid(&mut self) {
+        let ingredients_form_ids_to_remove = {
+            let ingredient_errors = match self.validate() {
+                Ok(_) => return,
+                Err(err) => err,
+            };
+
+            log::warn!(
+                "Ignoring {} invalid ingredients: {}",
+                ingredient_errors.len(),
+                ingredient_errors
+                    .iter()
+                    .map(|err| err.to_display_with(&self.load_order))
+                    .join("\n")
+            );
+
+            ingredient_errors
+                .iter()
+                .map(|ing_err| match ing_err {
+                    IngredientError::ReferencesUnknownMagicEffects(ing, _) => {
+                        ing.get_global_form_id()
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for form_id in ingredients_form_ids_to_remove {
+            self.ingredients.remove(&form_id);
+        }
+        self.effects_to_ingredients.take();
+    }
+}
+
+#[cfg(any(test, feature = "test-fixtures"))]
+impl GameData {
+    /// Returns a small made-up game data set of six ingredients with six vanilla-like effects, for
+    /// examples and tests that can't use the game files.
+    ///
+    /// ```
+    /// use alchemy_core::{game_data::GameData, AlchemySkill, PotionType};
+    ///
+    /// let game_data = GameData::example();
+    /// let potion = game_data
+    ///     .craft(&["Frost Lily", "Ember Root"], &AlchemySkill::default())
+    ///     .unwrap();
+    /// assert_eq!(potion.get_potion_type(), PotionType::Potion);
+    /// assert_eq!(potion.get_potion_name(&game_data), "Potion of Resist Fire");
+    /// ```
+    pub fn example() -> Self {
+        Self::from_json_slice(include_bytes!("../fixtures/example_game_data.json"))
+            .expect("bundled example game data should be valid")
+    }
+}
+
+/// The vanilla game data bundled with the `vanilla-data` feature, gzipped
+#[cfg(feature = "vanilla-data")]
+pub(crate) const VANILLA_GAME_DATA: &[u8] = include_bytes!(env!("SKYRIM_ALCHEMY_VANILLA_DATA"));
+
+#[cfg(feature = "vanilla-data")]
+impl GameData {
+    /// Returns the game data of the base game and its official DLCs that was bundled at build
+    /// time, for planning without the game files. It has no names, so ingredients and effects are
+    /// shown by their editor IDs.
+    pub fn vanilla() -> Self {
+        use std::io::Read;
+
+        let mut json = Vec::new();
+        flate2::read::GzDecoder::new(VANILLA_GAME_DATA)
+            .read_to_end(&mut json)
+            .expect("bundled vanilla game data should be gzipped");
+        Self::from_json_slice(&json).expect("bundled vanilla game data should be valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::potions_list::IngredientFilter;
+    use crate::test_utils::{self, ingredient_with_effect_ids};
+
+    /// Exported game data with a light master, localized names and ingredients whose effects come
+    /// from several masters. Its load order only contains used plugins, so exporting it again
+    /// gives the same JSON.
+    const GOLDEN_JSON: &[u8] = include_bytes!("../fixtures/game_data_golden.json");
+
+    /// Parses the JSON with the records sorted by ID, since the order of the ingredients and magic
+    /// effects in exported game data is unspecified
+    fn normalized(json: &[u8]) -> Value {
+        let mut value: Value = serde_json::from_slice(json).unwrap();
+        for section in ["ingredients", "magic_effects"] {
+            value[section]
+                .as_array_mut()
+                .unwrap()
+                .sort_by_key(|record| record["global_form_id"].as_str().unwrap().to_owned());
+        }
+        value
+    }
+
+    fn check_golden_game_data(game_data: &GameData) {
+        let load_order = game_data.get_load_order();
+        assert_eq!(
+            load_order.iter().collect::<Vec<_>>(),
+            [
+                "Skyrim.esm",
+                "Dawnguard.esm",
+                "ccExampleHerbs.esl",
+                "Traduction.esp"
+            ]
+        );
+        assert_eq!(load_order.is_light(0), Some(false));
+        assert_eq!(load_order.is_light(2), Some(true));
+        assert_eq!(game_data.get_plugins().len(), 5);
+
+        let ingredient = game_data
+            .get_ingredient(&GlobalFormId::new(3, 0x800))
+            .unwrap();
+        assert_eq!(ingredient.name.as_deref(), Some("Œil de Vaermina"));
+        assert_eq!(
+            ingredient
+                .effects
+                .iter()
+                .map(|eff| eff.global_form_id)
+                .collect::<Vec<_>>(),
+            [
+                GlobalFormId::new(0, 0x3eb15),
+                GlobalFormId::new(1, 0xc1a2),
+                GlobalFormId::new(2, 0x802),
+                GlobalFormId::new(3, 0x801),
+            ]
+        );
+        assert_eq!(
+            game_data
+                .get_magic_effect(&GlobalFormId::new(3, 0x801))
+                .and_then(|mgef| mgef.name.as_deref()),
+            Some("Régénération de magie")
+        );
+        assert_eq!(game_data.get_ingredient_overrides().len(), 1);
+        assert_eq!(
+            game_data.get_load_order_hash().map(|hash| hash.to_string()),
+            Some("9ae16a3b2f90d4c1".to_owned())
+        );
+    }
+
+    #[test]
+    fn from_json_slice_round_trips_golden_game_data() {
+        let game_data = GameData::from_json_slice(GOLDEN_JSON).unwrap();
+        check_golden_game_data(&game_data);
+
+        let exported = serde_json::to_vec(&game_data).unwrap();
+        assert_eq!(normalized(&exported), normalized(GOLDEN_JSON));
+    }
+
+    #[test]
+    fn deserialize_round_trips_golden_game_data() {
+        let game_data: GameData = serde_json::from_slice(GOLDEN_JSON).unwrap();
+        check_golden_game_data(&game_data);
+
+        let exported = serde_json::to_vec(&game_data).unwrap();
+        assert_eq!(normalized(&exported), normalized(GOLDEN_JSON));
+    }
+
+    #[test]
+    fn find_ingredient_by_name_compares_normalized_names() {
+        let game_data = GameData::from_json_slice(GOLDEN_JSON).unwrap();
+        for name in [
+            "Œil de Vaermina",
+            "oeil de vaermina",
+            "  OEIL  de Vaermina ",
+        ] {
+            assert_eq!(
+                game_data.find_ingredient_by_name(name).unwrap().editor_id,
+                "TraductionOeilDeVaermina"
+            );
+        }
+        assert!(game_data
+            .find_ingredient_by_name("Oeil de Vaerminas")
+            .is_err());
+    }
+
+    #[test]
+    fn ingredients_with_effects_injected_by_inactive_plugins_are_purged() {
+        // Dawnguard.esm has no records of its own, but an ingredient refers to an effect that an
+        // inactive plugin injects into it
+        let load_order = LoadOrder::new(vec![
+            String::from("Skyrim.esm"),
+            String::from("Dawnguard.esm"),
+            String::from("Update.esm"),
+            String::from("Mod.esp"),
+        ]);
+        let restore_health = GlobalFormId::new(0, 0x3eb15);
+        let ingredients = [
+            ingredient_with_effect_ids(GlobalFormId::new(3, 0x800), &[restore_health]),
+            ingredient_with_effect_ids(
+                GlobalFormId::new(3, 0x801),
+                &[restore_health, GlobalFormId::new(1, 0x900)],
+            ),
+        ];
+        let magic_effect = MagicEffect {
+            editor_id: String::from("AlchRestoreHealth"),
+            base_cost: 0.5,
+            actor_value: 24,
+            ..test_utils::magic_effect(restore_health)
+        };
+
+        let mut game_data = GameData::from_hashmaps(
+            load_order.clone(),
+            load_order,
+            ingredients
+                .into_iter()
+                .map(|ing| (ing.get_global_form_id(), ing))
+                .collect(),
+            [(restore_health, magic_effect)].into_iter().collect(),
+            Vec::new(),
+        );
+        assert_eq!(
+            game_data.get_load_order().iter().collect::<Vec<_>>(),
+            ["Skyrim.esm", "Dawnguard.esm", "Mod.esp"]
+        );
+
+        game_data.purge_invalid();
+        assert_eq!(
+            game_data.get_ingredients().keys().collect::<Vec<_>>(),
+            [&GlobalFormId::new(2, 0x800)]
+        );
+        assert!(game_data.validate().is_ok());
+    }
+
+    #[test]
+    fn unused_plugins_are_removed_from_the_load_order() {
+        let json = r#"{
+            "load_order": ["Skyrim.esm", "Update.esm", "ccExampleHerbs.esl"],
+            "plugins": [
+                {"name": "Skyrim.esm", "is_light": false},
+                {"name": "Update.esm", "is_light": false},
+                {"name": "ccExampleHerbs.esl", "is_light": true}
+            ],
+            "ingredients": [
+                {
+                    "global_form_id": "0002:000801",
+                    "editor_id": "ccExampleHerbsWispStalk",
+                    "name": "Wisp Stalk",
+                    "effects": [
+                        {"global_form_id": "0000:03eb15", "duration": 0, "magnitude": 1.0},
+                        {"global_form_id": "0002:000802", "duration": 30, "magnitude": 5.0}
+                    ]
+                }
+            ],
+            "magic_effects": []
+        }"#;
+        let game_data: GameData = serde_json::from_str(json).unwrap();
+
+        let load_order = game_data.get_load_order();
+        assert_eq!(
+            load_order.iter().collect::<Vec<_>>(),
+            ["Skyrim.esm", "ccExampleHerbs.esl"]
+        );
+        assert_eq!(load_order.is_light(1), Some(true));
+        assert_eq!(game_data.get_plugins().len(), 3);
+
+        let ingredient = game_data
+            .get_ingredient(&GlobalFormId::new(1, 0x801))
+            .unwrap();
+        assert_eq!(
+            ingredient
+                .effects
+                .iter()
+                .map(|eff| eff.global_form_id)
+                .collect::<Vec<_>>(),
+            [GlobalFormId::new(0, 0x3eb15), GlobalFormId::new(1, 0x802)]
+        );
+    }
+
+    /// Returns the example game data with CACO.esp after Example.esm, and ingredients with the
+    /// given form IDs and names added to it
+    fn with_named_ingredients(ingredients: &[(&str, &str)]) -> GameData {
+        let mut json: Value =
+            serde_json::from_slice(include_bytes!("../fixtures/example_game_data.json")).unwrap();
+        json["load_order"] = serde_json::json!(["Example.esm", "CACO.esp"]);
+        json["plugins"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "name": "CACO.esp", "is_light": false }));
+        let template = json["ingredients"][0].clone();
+        for (form_id, name) in ingredients {
+            let mut ingredient = template.clone();
+            ingredient["global_form_id"] = Value::from(*form_id);
+            ingredient["editor_id"] = Value::from(name.replace(' ', ""));
+            ingredient["name"] = Value::from(*name);
+            json["ingredients"].as_array_mut().unwrap().push(ingredient);
+        }
+        GameData::from_json_slice(&serde_json::to_vec(&json).unwrap()).unwrap()
+    }
+
+    fn get_qualified_name(game_data: &GameData, form_id: &str) -> Option<String> {
+        game_data
+            .get_ingredient(&form_id.parse().unwrap())
+            .unwrap()
+            .qualified_name
+            .clone()
+    }
+
+    #[test]
+    fn duplicate_names_are_qualified_by_plugin() {
+        let mut game_data = with_named_ingredients(&[
+            ("0000:000806", "Frost Salts"),
+            ("0001:000d62", "frost  salts"),
+        ]);
+
+        assert_eq!(
+            game_data.qualify_duplicate_names(DuplicateNameStyle::Plugin),
+            1
+        );
+        assert_eq!(
+            get_qualified_name(&game_data, "0000:000806").as_deref(),
+            Some("Frost Salts [Example]")
+        );
+        assert_eq!(
+            get_qualified_name(&game_data, "0001:000d62").as_deref(),
+            Some("frost  salts [CACO]")
+        );
+        assert_eq!(get_qualified_name(&game_data, "0000:000800"), None);
+
+        assert_eq!(
+            game_data.qualify_duplicate_names(DuplicateNameStyle::FormId),
+            1
+        );
+        assert_eq!(
+            get_qualified_name(&game_data, "0001:000d62").as_deref(),
+            Some("frost  salts [CACO.esp|000D62]")
+        );
+
+        assert_eq!(
+            game_data.qualify_duplicate_names(DuplicateNameStyle::None),
+            1
+        );
+        assert_eq!(get_qualified_name(&game_data, "0000:000806"), None);
+    }
+
+    #[test]
+    fn duplicate_names_in_one_plugin_fall_back_to_form_ids() {
+        let mut game_data = with_named_ingredients(&[
+            ("0000:000806", "Frost Salts"),
+            ("0000:000807", "Frost Salts"),
+            ("0001:000d62", "Frost Salts"),
+        ]);
+
+        // The plugins can't tell the first two apart, so all three are qualified by form ID
+        assert_eq!(
+            game_data.qualify_duplicate_names(DuplicateNameStyle::Plugin),
+            1
+        );
+        assert_eq!(
+            ["0000:000806", "0000:000807", "0001:000d62"]
+                .map(|form_id| get_qualified_name(&game_data, form_id).unwrap()),
+            [
+                "Frost Salts [Example.esm|000806]",
+                "Frost Salts [Example.esm|000807]",
+                "Frost Salts [CACO.esp|000D62]",
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_duplicate_names_match_all_ingredients_with_the_name() {
+        let mut game_data = with_named_ingredients(&[
+            ("0000:000806", "Frost Salts"),
+            ("0001:000d62", "Frost Salts"),
+        ]);
+        game_data.qualify_duplicate_names(DuplicateNameStyle::Plugin);
+
+        let caco_frost_salts = game_data
+            .find_ingredient_by_name("frost salts [caco]")
+            .unwrap();
+        assert_eq!(
+            caco_frost_salts.get_global_form_id(),
+            GlobalFormId::new(1, 0xd62)
+        );
+        match game_data.find_ingredient_by_name("Frost Salts") {
+            Err(FindIngredientError::Ambiguous(_, candidates)) => {
+                assert_eq!(candidates, ["Frost Salts [Example]", "Frost Salts [CACO]"])
+            }
+            other => panic!("expected an ambiguous name, got {:?}", other),
+        }
+
+        let allowed_ids = |whitelist: &[&str]| {
+            let whitelist = whitelist.iter().map(|name| name.to_string()).collect();
+            let filter = IngredientFilter::new(&game_data, &whitelist, &AHashSet::new());
+            game_data
+                .get_ingredients()
+                .values()
+                .filter(|ig| filter.allows_ingredient(ig))
+                .map(|ig| ig.get_global_form_id().to_string())
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(allowed_ids(&["Frost Salts [CACO]"]), ["0001:000d62"]);
+        assert_eq!(
+            allowed_ids(&["frost salts"]),
+            ["0000:000806", "0001:000d62"]
+        );
+    }
+
+    /// Asserts that the game data's effects to ingredients index matches its ingredients
+    fn assert_effects_index_is_fresh(game_data: &GameData) {
+        let mut expected = AHashMap::<_, Vec<_>>::new();
+        for ingredient in game_data.get_ingredients().values() {
+            for effect in &ingredient.effects {
+                expected
+                    .entry(effect.get_global_form_id())
+                    .or_default()
+                    .push(ingredient.get_global_form_id());
+            }
+        }
+        for ingredient_ids in expected.values_mut() {
+            ingredient_ids.sort_unstable();
+        }
+        assert_eq!(
+            game_data
+                .effects_to_ingredients()
+                .iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            expected.iter().sorted().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn effects_index_is_rebuilt_when_the_ingredients_change() {
+        let mut game_data = GameData::example();
+        let restore_health = GlobalFormId::new(0, 0x900);
+        let fortify_smithing = GlobalFormId::new(0, 0x903);
+        let frost_lily = GlobalFormId::new(0, 0x800);
+        let ember_root = GlobalFormId::new(0, 0x801);
+        assert_effects_index_is_fresh(&game_data);
+
+        game_data.retain_ingredients(|ig| ig.get_global_form_id() != frost_lily);
+        assert_effects_index_is_fresh(&game_data);
+        assert!(!game_data.effects_to_ingredients()[&restore_health].contains(&frost_lily));
+
+        let dir =
+            std::env::temp_dir().join(format!("alchemy-effects-index-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let overlay_path = dir.join("overlay.toml");
+        std::fs::write(
+            &overlay_path,
+            r#"
+            [[ingredient]]
+            editor_id = "ExampleEmberRoot"
+            effects = [{ effect = "ExampleFortifySmithing", magnitude = 1.0 }]
+
+            [[ingredient]]
+            editor_id = "ExampleAshBloom"
+            name = "Ash Bloom"
+            effects = [{ effect = "ExampleRestoreHealth", magnitude = 2.0 }]
+            "#,
+        )
+        .unwrap();
+        let overlay = IngredientOverlay::load(&overlay_path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            game_data.apply_ingredient_overlay(&overlay).unwrap(),
+            (1, 1)
+        );
+        assert_effects_index_is_fresh(&game_data);
+        assert!(game_data.effects_to_ingredients()[&fortify_smithing].contains(&ember_root));
+        assert!(!game_data.effects_to_ingredients()[&restore_health].contains(&ember_root));
+        assert!(game_data
+            .get_ingredients_with_effect(&restore_health)
+            .any(|ig| ig.editor_id == "ExampleAshBloom"));
+
+        game_data
+            .retain_ingredient_effects(|_, effect| effect.get_global_form_id() != restore_health);
+        assert_effects_index_is_fresh(&game_data);
+        assert!(!game_data
+            .effects_to_ingredients()
+            .contains_key(&restore_health));
+    }
+
+    #[test]
+    fn strip_strings_leaves_no_names_or_descriptions() {
+        let mut game_data = with_named_ingredients(&[("0001:000d62", "Frost Lily")]);
+        game_data.qualify_duplicate_names(DuplicateNameStyle::Plugin);
+        game_data.ingredient_overrides.push(IngredientOverride {
+            ingredient: "Ember Root".to_string(),
+            original_plugin: "Example.esm".to_string(),
+            overriding_plugin: "CACO.esp".to_string(),
+            removed_effects: vec!["Resist Fire".to_string()],
+            added_effects: vec!["Weakness to Frost".to_string()],
+            changed_effects: vec!["Restore Health (magnitude 2 -> 1)".to_string()],
+        });
+
+        let ingredient_strings = game_data
+            .ingredients
+            .values()
+            .flat_map(|ingredient| [&ingredient.name, &ingredient.qualified_name])
+            .flatten()
+            .cloned();
+        let magic_effect_strings = game_data.magic_effects.values().flat_map(|magic_effect| {
+            magic_effect
+                .name
+                .iter()
+                .chain([&magic_effect.description])
+                .cloned()
+        });
+        let strings = ingredient_strings
+            .chain(magic_effect_strings)
+            .filter(|string| !string.is_empty())
+            .collect::<Vec<_>>();
+        assert!(strings.contains(&"Frost Lily [CACO]".to_string()));
+        assert!(strings.contains(&"Restore <mag> points of Health.".to_string()));
+
+        game_data.strip_strings();
+
+        assert!(game_data
+            .ingredients
+            .values()
+            .all(|ingredient| ingredient.name.is_none() && ingredient.qualified_name.is_none()));
+        assert!(
+            game_data
+                .magic_effects
+                .values()
+                .all(|magic_effect| magic_effect.name.is_none()
+                    && magic_effect.description.is_empty())
+        );
+        assert!(game_data.get_ingredient_overrides().is_empty());
+
+        // Nothing that gets bundled still holds a name or description
+        let json = serde_json::to_string(&game_data).unwrap();
+        for string in &strings {
+            assert!(!json.contains(string.as_str()), "{:?} was kept", string);
+        }
+        assert!(json.contains("ExampleFrostLily"));
+    }
+}
@@ -0,0 +1,444 @@
+use std::fmt;
+
+use ahash::{AHashMap, AHashSet};
+use itertools::Itertools;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData},
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::{Ingredient, IngredientEffect},
+        magic_effect::MagicEffect,
+    },
+};
+
+/// Identifies a record across game data exported with different load orders, in which the same
+/// record can have a different load order index: the lowercase name of its plugin and its ID
+/// within the plugin
+type RecordKey = (String, u32);
+
+fn get_record_key(game_data: &GameData, global_form_id: &GlobalFormId) -> RecordKey {
+    (
+        game_data
+            .get_plugin_name(global_form_id)
+            .unwrap_or_default()
+            .to_lowercase(),
+        global_form_id.local_id(),
+    )
+}
+
+fn get_keyed_ingredients(game_data: &GameData) -> AHashMap<RecordKey, &Ingredient> {
+    game_data
+        .get_ingredients()
+        .values()
+        .map(|ig| (get_record_key(game_data, &ig.get_global_form_id()), ig))
+        .collect()
+}
+
+/// How one ingredient or magic effect changed, e.g. "Blue Mountain Flower: value 2 -> 4"
+#[derive(Clone, Debug)]
+pub struct RecordChange {
+    /// The name (or editor ID) of the record in the newer game data
+    pub name: String,
+    /// Descriptions of what changed, e.g. "value 2 -> 4"
+    pub changes: Vec<String>,
+}
+
+impl fmt::Display for RecordChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.changes.iter().join(", "))
+    }
+}
+
+/// The differences between two exports of the game data, e.g. before and after updating the mod
+/// list. Records are matched by their plugin and their ID within it, so a change in load order
+/// alone changes nothing.
+#[derive(Clone, Debug, Default)]
+pub struct GameDataDiff {
+    pub added_plugins: Vec<String>,
+    pub removed_plugins: Vec<String>,
+    /// The names of the ingredients that only the newer game data has, with their plugin
+    pub added_ingredients: Vec<String>,
+    /// The names of the ingredients that only the older game data has, with their plugin
+    pub removed_ingredients: Vec<String>,
+    pub changed_ingredients: Vec<RecordChange>,
+    /// The magic effects whose name, base cost or hostility changed. Only effects that ingredients
+    /// have in both are compared.
+    pub changed_effects: Vec<RecordChange>,
+}
+
+/// Adds a description of the change to `changes` if the values differ, e.g. "value 2 -> 4"
+fn push_change<T: PartialEq + fmt::Display>(changes: &mut Vec<String>, what: &str, old: T, new: T) {
+    if old != new {
+        changes.push(format!("{} {} -> {}", what, old, new));
+    }
+}
+
+fn compare_ingredients(
+    old_data: &GameData,
+    old: &Ingredient,
+    new_data: &GameData,
+    new: &Ingredient,
+) -> Vec<String> {
+    let mut changes = Vec::new();
+    let old_name = get_ingredient_name_or_fallback(old);
+    let new_name = get_ingredient_name_or_fallback(new);
+    if old_name != new_name {
+        changes.push(format!("renamed from {}", old_name));
+    }
+
+    let effect_name = |game_data: &GameData, effect: &IngredientEffect| {
+        game_data
+            .get_magic_effect(&effect.get_global_form_id())
+            .map(|mgef| get_magic_effect_name_or_fallback(mgef).to_string())
+            .unwrap_or_else(|| {
+                effect
+                    .get_global_form_id()
+                    .to_display_with(game_data.get_load_order())
+            })
+    };
+    let old_effects = old
+        .effects
+        .iter()
+        .map(|effect| {
+            (
+                get_record_key(old_data, &effect.get_global_form_id()),
+                effect,
+            )
+        })
+        .collect::<AHashMap<_, _>>();
+    let new_effects = new
+        .effects
+        .iter()
+        .map(|effect| {
+            (
+                get_record_key(new_data, &effect.get_global_form_id()),
+                effect,
+            )
+        })
+        .collect::<AHashMap<_, _>>();
+
+    for effect in &old.effects {
+        let key = get_record_key(old_data, &effect.get_global_form_id());
+        if !new_effects.contains_key(&key) {
+            changes.push(format!("lost {}", effect_name(old_data, effect)));
+        }
+    }
+    for effect in &new.effects {
+        let key = get_record_key(new_data, &effect.get_global_form_id());
+        match old_effects.get(&key) {
+            None => changes.push(format!("gained {}", effect_name(new_data, effect))),
+            Some(old_effect) => {
+                let mut effect_changes = Vec::new();
+                push_change(
+                    &mut effect_changes,
+                    "magnitude",
+                    old_effect.magnitude,
+                    effect.magnitude,
+                );
+                push_change(
+                    &mut effect_changes,
+                    "duration",
+                    old_effect.duration,
+                    effect.duration,
+                );
+                if !effect_changes.is_empty() {
+                    changes.push(format!(
+                        "{} ({})",
+                        effect_name(new_data, effect),
+                        effect_changes.join(", ")
+                    ));
+                }
+            }
+        }
+    }
+
+    // Game data exported by older versions doesn't have these, which is not a change
+    if let (Some(old_value), Some(new_value)) = (old.value, new.value) {
+        push_change(&mut changes, "value", old_value, new_value);
+    }
+    if let (Some(old_weight), Some(new_weight)) = (old.weight, new.weight) {
+        push_change(&mut changes, "weight", old_weight, new_weight);
+    }
+    if let (Some(old_is_food), Some(new_is_food)) = (old.is_food, new.is_food) {
+        if old_is_food != new_is_food {
+            changes.push(match new_is_food {
+                true => "now food".to_string(),
+                false => "no longer food".to_string(),
+            });
+        }
+    }
+    changes
+}
+
+fn compare_magic_effects(old: &MagicEffect, new: &MagicEffect) -> Vec<String> {
+    let mut changes = Vec::new();
+    let old_name = get_magic_effect_name_or_fallback(old);
+    if old_name != get_magic_effect_name_or_fallback(new) {
+        changes.push(format!("renamed from {}", old_name));
+    }
+    push_change(&mut changes, "base cost", old.base_cost, new.base_cost);
+    if old.is_hostile != new.is_hostile {
+        changes.push(match new.is_hostile {
+            true => "now hostile".to_string(),
+            false => "no longer hostile".to_string(),
+        });
+    }
+    changes
+}
+
+impl GameDataDiff {
+    /// Compares the older game data with the newer game data
+    pub fn compute(old: &GameData, new: &GameData) -> Self {
+        let get_plugins_not_in = |game_data: &GameData, other: &GameData| {
+            let other_plugins = other
+                .get_plugins()
+                .iter()
+                .map(|plugin_name| plugin_name.to_lowercase())
+                .collect::<AHashSet<_>>();
+            game_data
+                .get_plugins()
+                .iter()
+                .filter(|plugin_name| !other_plugins.contains(&plugin_name.to_lowercase()))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let added_plugins = get_plugins_not_in(new, old);
+        let removed_plugins = get_plugins_not_in(old, new);
+
+        let old_ingredients = get_keyed_ingredients(old);
+        let new_ingredients = get_keyed_ingredients(new);
+        let describe = |game_data: &GameData, ingredient: &Ingredient| {
+            format!(
+                "{} ({})",
+                get_ingredient_name_or_fallback(ingredient),
+                game_data
+                    .get_plugin_name(&ingredient.get_global_form_id())
+                    .unwrap_or("<UNKNOWN_PLUGIN>")
+            )
+        };
+
+        let added_ingredients = new_ingredients
+            .iter()
+            .filter(|(key, _)| !old_ingredients.contains_key(*key))
+            .map(|(_, ingredient)| describe(new, ingredient))
+            .sorted()
+            .collect();
+        let removed_ingredients = old_ingredients
+            .iter()
+            .filter(|(key, _)| !new_ingredients.contains_key(*key))
+            .map(|(_, ingredient)| describe(old, ingredient))
+            .sorted()
+            .collect();
+        let changed_ingredients = new_ingredients
+            .iter()
+            .filter_map(|(key, new_ingredient)| {
+                let old_ingredient = old_ingredients.get(key)?;
+                let changes = compare_ingredients(old, old_ingredient, new, new_ingredient);
+                match changes.is_empty() {
+                    true => None,
+                    false => Some(RecordChange {
+                        name: get_ingredient_name_or_fallback(new_ingredient).to_string(),
+                        changes,
+                    }),
+                }
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+
+        let old_effects = old
+            .get_magic_effects()
+            .values()
+            .map(|mgef| (get_record_key(old, &mgef.get_global_form_id()), mgef))
+            .collect::<AHashMap<_, _>>();
+        let changed_effects = new
+            .get_magic_effects()
+            .values()
+            .filter_map(|new_effect| {
+                let old_effect =
+                    old_effects.get(&get_record_key(new, &new_effect.get_global_form_id()))?;
+                let changes = compare_magic_effects(old_effect, new_effect);
+                match changes.is_empty() {
+                    true => None,
+                    false => Some(RecordChange {
+                        name: get_magic_effect_name_or_fallback(new_effect).to_string(),
+                        changes,
+                    }),
+                }
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+
+        Self {
+            added_plugins,
+            removed_plugins,
+            added_ingredients,
+            removed_ingredients,
+            changed_ingredients,
+            changed_effects,
+        }
+    }
+
+    /// Returns whether the game data is the same, as far as alchemy is concerned
+    pub fn is_empty(&self) -> bool {
+        self.added_ingredients.is_empty()
+            && self.removed_ingredients.is_empty()
+            && self.changed_ingredients.is_empty()
+            && self.changed_effects.is_empty()
+    }
+}
+
+impl fmt::Display for GameDataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut write_section = |heading: &str, lines: Vec<String>| -> fmt::Result {
+            if lines.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "{} ({}):", heading, lines.len())?;
+            for line in lines {
+                writeln!(f, "  {}", line)?;
+            }
+            Ok(())
+        };
+        write_section("Plugins added", self.added_plugins.clone())?;
+        write_section("Plugins removed", self.removed_plugins.clone())?;
+        write_section("Ingredients added", self.added_ingredients.clone())?;
+        write_section("Ingredients removed", self.removed_ingredients.clone())?;
+        write_section(
+            "Ingredients changed",
+            self.changed_ingredients
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        )?;
+        write_section(
+            "Effects changed",
+            self.changed_effects
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        )?;
+        if self.is_empty() {
+            writeln!(f, "No changes to ingredients or effects")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const EXAMPLE_GAME_DATA: &str = include_str!("../fixtures/example_game_data.json");
+
+    fn example_json() -> Value {
+        serde_json::from_str(EXAMPLE_GAME_DATA).unwrap()
+    }
+
+    fn to_game_data(json: &Value) -> GameData {
+        GameData::from_json_slice(&serde_json::to_vec(json).unwrap()).unwrap()
+    }
+
+    /// Returns the record with the given name in the list of ingredients or magic effects
+    fn record_mut<'j>(json: &'j mut Value, list: &str, name: &str) -> &'j mut Value {
+        json[list]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .find(|record| record["name"] == name)
+            .unwrap_or_else(|| panic!("example game data should have {}", name))
+    }
+
+    #[test]
+    fn same_game_data_has_no_changes() {
+        let diff = GameDataDiff::compute(&GameData::example(), &GameData::example());
+        assert!(diff.is_empty());
+        assert!(diff.added_plugins.is_empty() && diff.removed_plugins.is_empty());
+        assert_eq!(diff.to_string(), "No changes to ingredients or effects\n");
+    }
+
+    #[test]
+    fn records_are_matched_across_load_orders() {
+        // A plugin loaded before Example.esm moves all of its records to load order index 1
+        let mut new =
+            serde_json::from_str::<Value>(&EXAMPLE_GAME_DATA.replace("\"0000:", "\"0001:"))
+                .unwrap();
+        new["load_order"] = json!(["Update.esm", "Example.esm"]);
+        new["plugins"] = json!([
+            { "name": "Update.esm", "is_light": false },
+            { "name": "Example.esm", "is_light": false },
+        ]);
+
+        let diff = GameDataDiff::compute(&GameData::example(), &to_game_data(&new));
+        assert_eq!(diff.added_plugins, ["Update.esm"]);
+        assert!(diff.removed_plugins.is_empty());
+        assert!(diff.is_empty(), "unexpected changes: {}", diff);
+
+        let diff = GameDataDiff::compute(&to_game_data(&new), &GameData::example());
+        assert_eq!(diff.removed_plugins, ["Update.esm"]);
+        assert!(diff.is_empty(), "unexpected changes: {}", diff);
+    }
+
+    #[test]
+    fn added_removed_and_changed_records_are_found() {
+        let mut new = example_json();
+        let ingredients = new["ingredients"].as_array_mut().unwrap();
+        ingredients.retain(|ingredient| ingredient["name"] != "Marsh Cap");
+        let mut ash_bloom = ingredients[0].clone();
+        ash_bloom["global_form_id"] = json!("0000:000806");
+        ash_bloom["editor_id"] = json!("ExampleAshBloom");
+        ash_bloom["name"] = json!("Ash Bloom");
+        ingredients.push(ash_bloom);
+
+        let frost_lily = record_mut(&mut new, "ingredients", "Frost Lily");
+        frost_lily["value"] = json!(8);
+        frost_lily["effects"][0]["magnitude"] = json!(6.0);
+        // Weakness to Frost
+        frost_lily["effects"].as_array_mut().unwrap().pop();
+        record_mut(&mut new, "ingredients", "Salt Crystal")["name"] = json!("Rock Salt");
+        record_mut(&mut new, "magic_effects", "Resist Fire")["base_cost"] = json!(0.75);
+        record_mut(&mut new, "magic_effects", "Damage Health")["is_hostile"] = json!(false);
+
+        let diff = GameDataDiff::compute(&GameData::example(), &to_game_data(&new));
+        assert_eq!(diff.added_ingredients, ["Ash Bloom (Example.esm)"]);
+        assert_eq!(diff.removed_ingredients, ["Marsh Cap (Example.esm)"]);
+        assert_eq!(
+            diff.changed_ingredients
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "Frost Lily: lost Weakness to Frost, Restore Health (magnitude 5 -> 6), value 4 -> 8",
+                "Rock Salt: renamed from Salt Crystal",
+            ]
+        );
+        assert_eq!(
+            diff.changed_effects
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "Damage Health: no longer hostile",
+                "Resist Fire: base cost 0.5 -> 0.75",
+            ]
+        );
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn fields_missing_from_older_exports_are_not_changes() {
+        let mut old = example_json();
+        for ingredient in old["ingredients"].as_array_mut().unwrap() {
+            let ingredient = ingredient.as_object_mut().unwrap();
+            ingredient.remove("value");
+            ingredient.remove("weight");
+        }
+        let mut new = example_json();
+        record_mut(&mut new, "ingredients", "Ember Root")["is_food"] = json!(true);
+
+        let diff = GameDataDiff::compute(&to_game_data(&old), &to_game_data(&new));
+        assert!(diff.is_empty(), "unexpected changes: {}", diff);
+    }
+}
@@ -0,0 +1,190 @@
+use std::{hash::Hash, str::FromStr};
+
+use ahash::{AHashMap, AHashSet};
+use serde_with::DeserializeFromStr;
+
+use crate::{
+    effect_targets::{classify_effect, get_bucket_heading, Target},
+    game_data::get_magic_effect_name_or_fallback,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::Ingredient,
+    },
+    potion::{Potion, PotionType},
+};
+
+/// How to group suggested potions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, DeserializeFromStr)]
+pub enum GroupBy {
+    /// Don't group potions
+    #[default]
+    None,
+    /// Group potions by their primary effect, e.g. all Fortify Barter potions together
+    PrimaryEffect,
+    /// Group potions by what they do to health, magicka or stamina, e.g. all potions that restore
+    /// health together
+    Target,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(GroupBy::None),
+            "primary-effect" => Ok(GroupBy::PrimaryEffect),
+            "target" => Ok(GroupBy::Target),
+            other => Err(format!(
+                "unknown grouping {}, expected none, primary-effect or target",
+                other
+            )),
+        }
+    }
+}
+
+/// Potions that share a primary effect
+pub struct PotionGroup<'p, 'a> {
+    /// Describes the group, e.g. "Damage Health poisons"
+    pub heading: String,
+    pub potions: Vec<&'p Potion<'a>>,
+}
+
+/// Groups the potions by their primary effect, skipping the first `offset_per_group` potions of
+/// each group and keeping at most `limit_per_group` potions after them. Both the groups and the
+/// potions within them keep the order of the given potions, so the group with the best potion
+/// comes first. Groups without any potions after the skipped ones are left out.
+pub fn group_by_primary_effect<'p, 'a>(
+    potions: impl Iterator<Item = &'p Potion<'a>>,
+    offset_per_group: usize,
+    limit_per_group: usize,
+) -> Vec<PotionGroup<'p, 'a>>
+where
+    'a: 'p,
+{
+    group_potions(potions, offset_per_group, limit_per_group, |potion| {
+        let primary_effect = potion.get_primary_effect();
+        let potion_type = match potion.get_potion_type() {
+            PotionType::Potion => "potions",
+            PotionType::Poison => "poisons",
+        };
+        Some((primary_effect.get_global_form_id(), || {
+            format!(
+                "{} {}",
+                get_magic_effect_name_or_fallback(primary_effect.magic_effect),
+                potion_type
+            )
+        }))
+    })
+}
+
+/// Groups the potions by what their first effect on one of the targets (any of health, magicka
+/// and stamina if empty) does to it: restore, fortify, damage or change its regen, in the same way
+/// as `group_by_primary_effect`. Potions without an effect on the targets are left out.
+pub fn group_by_target<'p, 'a>(
+    potions: impl Iterator<Item = &'p Potion<'a>>,
+    targets: &[Target],
+    offset_per_group: usize,
+    limit_per_group: usize,
+) -> Vec<PotionGroup<'p, 'a>>
+where
+    'a: 'p,
+{
+    group_potions(potions, offset_per_group, limit_per_group, |potion| {
+        let (target, bucket) = potion
+            .effects
+            .iter()
+            .filter_map(|eff| classify_effect(eff.magic_effect))
+            .find(|(target, _)| targets.is_empty() || targets.contains(target))?;
+        Some(((target, bucket), move || get_bucket_heading(target, bucket)))
+    })
+}
+
+/// Groups the potions by the key that `get_group` returns for each of them, along with a function
+/// that makes the heading of the key's group. Potions without a key are left out.
+fn group_potions<'p, 'a, K, H>(
+    potions: impl Iterator<Item = &'p Potion<'a>>,
+    offset_per_group: usize,
+    limit_per_group: usize,
+    get_group: impl Fn(&'p Potion<'a>) -> Option<(K, H)>,
+) -> Vec<PotionGroup<'p, 'a>>
+where
+    'a: 'p,
+    K: Hash + Eq,
+    H: FnOnce() -> String,
+{
+    let mut groups: Vec<PotionGroup> = Vec::new();
+    let mut group_indexes = AHashMap::<K, usize>::new();
+    let mut num_skipped = Vec::<usize>::new();
+
+    for potion in potions {
+        let (key, get_heading) = match get_group(potion) {
+            Some(group) => group,
+            None => continue,
+        };
+        let index = *group_indexes.entry(key).or_insert_with(|| {
+            groups.push(PotionGroup {
+                heading: get_heading(),
+                potions: Vec::new(),
+            });
+            num_skipped.push(0);
+            groups.len() - 1
+        });
+
+        let group = &mut groups[index];
+        if num_skipped[index] < offset_per_group {
+            num_skipped[index] += 1;
+        } else if group.potions.len() < limit_per_group {
+            group.potions.push(potion);
+        }
+    }
+    groups.retain(|group| !group.potions.is_empty());
+
+    groups
+}
+
+/// Orders the potions so that each one needs as few ingredients as possible besides those of the
+/// potions before it, keeping the given order between potions that need equally few. The first
+/// potions of the result can thus be crafted from a short list of ingredients. Finding the best
+/// order is a set cover problem, so this greedily picks one potion at a time.
+pub fn order_by_shared_ingredients<'p, 'a>(mut potions: Vec<&'p Potion<'a>>) -> Vec<&'p Potion<'a>>
+where
+    'a: 'p,
+{
+    let mut used_ingredient_ids = AHashSet::<GlobalFormId>::new();
+    let mut ordered = Vec::with_capacity(potions.len());
+
+    while !potions.is_empty() {
+        // If several potions need equally few new ingredients, the first of them is picked
+        let (index, _) = potions
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, potion)| {
+                potion
+                    .ingredients
+                    .iter()
+                    .filter(|ig| !used_ingredient_ids.contains(&ig.get_global_form_id()))
+                    .count()
+            })
+            .expect("potions should not be empty");
+        let potion = potions.remove(index);
+        used_ingredient_ids.extend(potion.ingredients.iter().map(|ig| ig.get_global_form_id()));
+        ordered.push(potion);
+    }
+
+    ordered
+}
+
+/// Returns the different ingredients of the potions, in the order they are first needed
+pub fn get_distinct_ingredients<'p, 'a>(
+    potions: impl IntoIterator<Item = &'p Potion<'a>>,
+) -> Vec<&'a Ingredient>
+where
+    'a: 'p,
+{
+    let mut seen_ingredient_ids = AHashSet::<GlobalFormId>::new();
+    potions
+        .into_iter()
+        .flat_map(|potion| potion.ingredients.iter().copied())
+        .filter(|ig| seen_ingredient_ids.insert(ig.get_global_form_id()))
+        .collect()
+}
@@ -0,0 +1,119 @@
+use std::{fs, path::Path};
+
+use ahash::AHashSet;
+use anyhow::Context;
+use serde::Deserialize;
+
+/// Name prefixes of effects that are hostile, i.e. make a poison
+const HOSTILE_NAME_PREFIXES: &[&str] = &[
+    "damage",
+    "drain",
+    "fear",
+    "frenzy",
+    "lingering damage",
+    "paralysis",
+    "ravage",
+    "slow",
+    "weakness to",
+];
+
+/// Name prefixes of effects that are beneficial, i.e. make a potion
+const BENEFICIAL_NAME_PREFIXES: &[&str] = &[
+    "cure",
+    "fortify",
+    "invisibility",
+    "regenerate",
+    "resist",
+    "restore",
+    "waterbreathing",
+];
+
+#[derive(Deserialize)]
+struct HostilityOverridesFile {
+    #[serde(default)]
+    hostile: Vec<String>,
+    #[serde(default)]
+    beneficial: Vec<String>,
+}
+
+/// Effects whose hostile flag is set incorrectly by the plugin that defines them, which would turn
+/// potions into poisons or vice versa. Effects are referred to by their editor ID.
+#[derive(Debug, Default)]
+pub struct HostilityOverrides {
+    /// Lowercase editor IDs of effects that are hostile
+    hostile: AHashSet<String>,
+    /// Lowercase editor IDs of effects that are beneficial
+    beneficial: AHashSet<String>,
+}
+
+impl HostilityOverrides {
+    /// Reads the overrides from the TOML file at `path`. The file contains a `hostile` and a
+    /// `beneficial` list of editor IDs, e.g. `hostile = ["AlchDamageHealth"]`.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let file: HostilityOverridesFile = toml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| {
+                format!(
+                    "Failed to parse hostility overrides file {}",
+                    path.display()
+                )
+            })?;
+
+        let hostile = file
+            .hostile
+            .iter()
+            .map(|editor_id| editor_id.to_lowercase())
+            .collect::<AHashSet<_>>();
+        let beneficial = file
+            .beneficial
+            .iter()
+            .map(|editor_id| editor_id.to_lowercase())
+            .collect::<AHashSet<_>>();
+        if let Some(editor_id) = hostile.intersection(&beneficial).next() {
+            Err(anyhow::anyhow!(
+                "Effect {} is both hostile and beneficial in hostility overrides file {}",
+                editor_id,
+                path.display()
+            ))?
+        }
+
+        Ok(Self {
+            hostile,
+            beneficial,
+        })
+    }
+
+    /// Returns whether the effect with the editor ID is hostile, or `None` if it isn't overridden
+    pub fn get(&self, editor_id: &str) -> Option<bool> {
+        let editor_id = editor_id.to_lowercase();
+        if self.hostile.contains(&editor_id) {
+            Some(true)
+        } else if self.beneficial.contains(&editor_id) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hostile.is_empty() && self.beneficial.is_empty()
+    }
+}
+
+/// Guesses whether an effect is hostile from its name, e.g. "Damage Health" is hostile and
+/// "Restore Health" is not. Returns `None` if the name gives no clue.
+pub fn guess_hostility_from_name(name: &str) -> Option<bool> {
+    let name = name.to_lowercase();
+    if HOSTILE_NAME_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        Some(true)
+    } else if BENEFICIAL_NAME_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+    {
+        Some(false)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,40 @@
+use std::str::FromStr;
+
+use serde_with::DeserializeFromStr;
+
+/// How to qualify the names of different ingredients with the same name, which modlists often
+/// contain
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, DeserializeFromStr)]
+pub enum DuplicateNameStyle {
+    /// Append the name of the plugin that defines the ingredient without its extension, e.g.
+    /// "Frost Salts [CACO]"
+    #[default]
+    Plugin,
+    /// Append the ingredient's form ID with its plugin, e.g. "Frost Salts [CACO.esp|000D62]"
+    FormId,
+    /// Leave the names as they are
+    None,
+}
+
+impl FromStr for DuplicateNameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plugin" => Ok(DuplicateNameStyle::Plugin),
+            "form-id" => Ok(DuplicateNameStyle::FormId),
+            "none" => Ok(DuplicateNameStyle::None),
+            other => Err(format!(
+                "unknown duplicate name style {}, expected plugin, form-id or none",
+                other
+            )),
+        }
+    }
+}
+
+/// Returns the part of a plugin name before its extension, e.g. `CACO` for `CACO.esp`
+pub(crate) fn get_plugin_stem(plugin_name: &str) -> &str {
+    plugin_name
+        .rsplit_once('.')
+        .map_or(plugin_name, |(stem, _)| stem)
+}
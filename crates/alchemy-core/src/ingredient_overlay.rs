@@ -0,0 +1,162 @@
+use std::{fs, path::Path};
+
+use ahash::AHashSet;
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+/// The plugin name that ingredients added by the overlay are attributed to, since they aren't
+/// defined by any plugin
+pub const OVERLAY_PLUGIN_NAME: &str = "<ingredient overlay>";
+
+/// An effect of an ingredient in the overlay
+#[derive(Clone, Debug, Deserialize)]
+pub struct OverlayEffect {
+    /// The editor ID of the magic effect, e.g. `AlchRestoreHealth`
+    pub effect: String,
+    pub magnitude: f32,
+    /// The duration in seconds, 0 for effects without a duration
+    #[serde(default)]
+    pub duration: u32,
+}
+
+/// An ingredient in the overlay. If the game data has an ingredient with the editor ID, the fields
+/// that are set replace its fields. Otherwise the ingredient is added, in which case it must have
+/// effects.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OverlayIngredient {
+    pub editor_id: String,
+    pub name: Option<String>,
+    pub value: Option<u32>,
+    pub weight: Option<f32>,
+    /// The effects in record order, i.e. the order in which eating reveals them. Replaces all of
+    /// the ingredient's effects.
+    pub effects: Option<Vec<OverlayEffect>>,
+}
+
+#[derive(Deserialize)]
+struct IngredientOverlayFile {
+    #[serde(default, rename = "ingredient")]
+    ingredients: Vec<OverlayIngredient>,
+}
+
+/// Ingredients defined by the user that are merged into the game data, to fix a broken record or
+/// try out a change to a mod without editing the plugin. The overlay takes precedence over all
+/// plugins.
+#[derive(Debug, Default)]
+pub struct IngredientOverlay {
+    ingredients: Vec<OverlayIngredient>,
+}
+
+impl IngredientOverlay {
+    /// Reads the overlay from the TOML file at `path`, or from the JSON file if the path ends in
+    /// `.json`. The file contains a list of ingredients, e.g. a `[[ingredient]]` table with
+    /// `editor_id = "DeathBell"` and `effects = [{ effect = "AlchDamageHealth", magnitude = 1.5
+    /// }]`.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("json"));
+        let file: IngredientOverlayFile = match is_json {
+            true => serde_json::from_str(&contents).map_err(anyhow::Error::from),
+            false => toml::from_str(&contents).map_err(anyhow::Error::from),
+        }
+        .with_context(|| format!("Failed to parse ingredient overlay file {}", path.display()))?;
+
+        let mut editor_ids = AHashSet::new();
+        for ingredient in &file.ingredients {
+            if !editor_ids.insert(ingredient.editor_id.to_lowercase()) {
+                Err(anyhow!(
+                    "Ingredient {} is listed more than once in ingredient overlay file {}",
+                    ingredient.editor_id,
+                    path.display()
+                ))?
+            }
+            if let Some(effects) = &ingredient.effects {
+                if !(1..=4).contains(&effects.len()) {
+                    Err(anyhow!(
+                        "Ingredient {} must have between 1 and 4 effects in ingredient overlay \
+                         file {}",
+                        ingredient.editor_id,
+                        path.display()
+                    ))?
+                }
+                if let Some(effect) = effects
+                    .iter()
+                    .find(|effect| !effect.magnitude.is_finite() || effect.magnitude < 0.0)
+                {
+                    Err(anyhow!(
+                        "Effect {} of ingredient {} has an invalid magnitude of {} in ingredient \
+                         overlay file {}, it must be a number of at least 0",
+                        effect.effect,
+                        ingredient.editor_id,
+                        effect.magnitude,
+                        path.display()
+                    ))?
+                }
+            }
+        }
+
+        Ok(Self {
+            ingredients: file.ingredients,
+        })
+    }
+
+    pub(crate) fn get_ingredients(&self) -> &[OverlayIngredient] {
+        &self.ingredients
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ingredients.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads the overlay from a TOML file with the contents
+    fn load_toml(name: &str, contents: &str) -> Result<IngredientOverlay, anyhow::Error> {
+        let dir =
+            std::env::temp_dir().join(format!("alchemy-ingredient-overlay-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.toml", name));
+        fs::write(&path, contents).unwrap();
+        let result = IngredientOverlay::load(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn overlay_with_magnitude(magnitude: &str) -> String {
+        format!(
+            "[[ingredient]]\neditor_id = \"DeathBell\"\neffects = [{{ effect = \"AlchDamageHealth\", \
+             magnitude = {} }}]\n",
+            magnitude
+        )
+    }
+
+    #[test]
+    fn valid_magnitudes_are_loaded() {
+        for magnitude in ["0.0", "1.5"] {
+            let overlay = load_toml("valid", &overlay_with_magnitude(magnitude)).unwrap();
+            assert_eq!(overlay.get_ingredients().len(), 1);
+        }
+    }
+
+    #[test]
+    fn invalid_magnitudes_are_rejected() {
+        for magnitude in ["-1.5", "nan", "inf", "-inf"] {
+            let err = load_toml("invalid", &overlay_with_magnitude(magnitude))
+                .expect_err("overlay with an invalid magnitude should be rejected")
+                .to_string();
+            assert!(
+                err.contains(
+                    "Effect AlchDamageHealth of ingredient DeathBell has an invalid magnitude"
+                ),
+                "unexpected error for magnitude {}: {}",
+                magnitude,
+                err
+            );
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use ahash::AHashMap;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_data::get_ingredient_name_or_fallback,
+    load_order::LoadOrder,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::{Ingredient, IngredientEffect},
+        magic_effect::MagicEffect,
+    },
+};
+
+/// An ingredient whose effects were removed or changed by a plugin overriding it, which is the
+/// usual reason a favorite combination is no longer suggested after installing an overhaul
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IngredientOverride {
+    /// The name (or editor ID) of the winning version of the ingredient
+    pub ingredient: String,
+    /// The plugin that originally defined the ingredient
+    pub original_plugin: String,
+    /// The plugin whose version of the ingredient wins
+    pub overriding_plugin: String,
+    /// Names of the effects that the original ingredient has but the winning version doesn't
+    pub removed_effects: Vec<String>,
+    /// Names of the effects that the winning version has but the original ingredient doesn't
+    pub added_effects: Vec<String>,
+    /// Descriptions of the effects whose magnitude or duration differs, e.g.
+    /// "Restore Health (magnitude 2 -> 1)"
+    pub changed_effects: Vec<String>,
+}
+
+impl IngredientOverride {
+    /// Compares the winning version of an ingredient with its original version. Returns `None` if
+    /// the winning version has the same effects with the same magnitudes and durations.
+    pub(crate) fn compare(
+        original: &Ingredient,
+        winner: &Ingredient,
+        overriding_plugin_index: u16,
+        load_order: &LoadOrder,
+        magic_effects: &AHashMap<GlobalFormId, MagicEffect>,
+    ) -> Option<Self> {
+        let effect_name = |effect: &IngredientEffect| {
+            magic_effects
+                .get(&effect.get_global_form_id())
+                .and_then(|mgef| mgef.name.clone())
+                .unwrap_or_else(|| effect.get_global_form_id().to_display_with(load_order))
+        };
+        let find_effect = |effects: &[IngredientEffect], effect: &IngredientEffect| {
+            effects
+                .iter()
+                .find(|other| other.get_global_form_id() == effect.get_global_form_id())
+                .cloned()
+        };
+
+        let removed_effects = original
+            .effects
+            .iter()
+            .filter(|effect| find_effect(&winner.effects, effect).is_none())
+            .map(effect_name)
+            .collect::<Vec<_>>();
+        let added_effects = winner
+            .effects
+            .iter()
+            .filter(|effect| find_effect(&original.effects, effect).is_none())
+            .map(effect_name)
+            .collect::<Vec<_>>();
+        let changed_effects = original
+            .effects
+            .iter()
+            .filter_map(|effect| {
+                let winner_effect = find_effect(&winner.effects, effect)?;
+                let mut changes = Vec::new();
+                if winner_effect.magnitude != effect.magnitude {
+                    changes.push(format!(
+                        "magnitude {} -> {}",
+                        effect.magnitude, winner_effect.magnitude
+                    ));
+                }
+                if winner_effect.duration != effect.duration {
+                    changes.push(format!(
+                        "duration {} -> {}",
+                        effect.duration, winner_effect.duration
+                    ));
+                }
+                match changes.is_empty() {
+                    true => None,
+                    false => Some(format!("{} ({})", effect_name(effect), changes.join(", "))),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if removed_effects.is_empty() && changed_effects.is_empty() {
+            return None;
+        }
+
+        let plugin_name = |index| {
+            load_order
+                .get(index)
+                .unwrap_or("<UNKNOWN_PLUGIN>")
+                .to_string()
+        };
+        Some(Self {
+            ingredient: get_ingredient_name_or_fallback(winner).to_string(),
+            original_plugin: plugin_name(original.get_global_form_id().load_order_index),
+            overriding_plugin: plugin_name(overriding_plugin_index),
+            removed_effects,
+            added_effects,
+            changed_effects,
+        })
+    }
+}
+
+impl fmt::Display for IngredientOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (from {}) is overridden by {}",
+            self.ingredient, self.original_plugin, self.overriding_plugin
+        )?;
+        if !self.removed_effects.is_empty() {
+            write!(f, "\n  removed: {}", self.removed_effects.iter().join(", "))?;
+        }
+        if !self.added_effects.is_empty() {
+            write!(f, "\n  added: {}", self.added_effects.iter().join(", "))?;
+        }
+        if !self.changed_effects.is_empty() {
+            write!(f, "\n  changed: {}", self.changed_effects.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+use itertools::Itertools;
+
+use crate::{fuzzy::fuzzy_match_score, game_data::GameData};
+
+/// The maximum number of search results listed at once
+const MAX_RESULTS: usize = 20;
+
+const HELP: &str = "\
+Type part of a name to search, e.g. \"frost\" or \"frsal\" for Frost Salts.
+Type the numbers of search results to check or uncheck them, e.g. \"1 3\".
+Commands: :a checks all results, :n unchecks all results, :s lists the checked ingredients,
+:w writes the file and quits, :q quits without writing, :h shows this help.";
+
+/// How a picker session ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PickerOutcome {
+    /// The user chose to write the checked ingredients
+    Write,
+    /// The user quit without writing, or the input ended
+    Quit,
+}
+
+/// Returns the names that filters can match the ingredients by, sorted: the qualified name for
+/// ingredients that share their name with another ingredient, otherwise the name. Ingredients
+/// without a name are left out, since filters can't match them.
+pub(crate) fn get_pickable_names(game_data: &GameData) -> Vec<&str> {
+    game_data
+        .get_ingredients()
+        .values()
+        .filter_map(|ig| ig.qualified_name.as_deref().or(ig.name.as_deref()))
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// Lets the user check ingredients in a list by searching for them, one command per line of
+/// `input`. `checked` holds the names that are checked, which may include names that aren't in
+/// `names`, e.g. from an existing filter file; those stay checked.
+pub(crate) fn run_picker<R: BufRead, W: Write>(
+    names: &[&str],
+    checked: &mut BTreeSet<String>,
+    mut input: R,
+    mut output: W,
+) -> io::Result<PickerOutcome> {
+    writeln!(output, "{}\n", HELP)?;
+
+    let mut results: Vec<&str> = Vec::new();
+    let mut line = String::new();
+    loop {
+        write!(output, "{} checked> ", checked.len())?;
+        output.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(PickerOutcome::Quit);
+        }
+        let line = line.trim();
+
+        match line {
+            "" => continue,
+            ":w" => return Ok(PickerOutcome::Write),
+            ":q" => return Ok(PickerOutcome::Quit),
+            ":h" => writeln!(output, "{}", HELP)?,
+            ":a" => checked.extend(results.iter().map(|name| name.to_string())),
+            ":n" => {
+                for name in &results {
+                    checked.remove(*name);
+                }
+            }
+            ":s" => {
+                for name in checked.iter() {
+                    writeln!(output, "  [x] {}", name)?;
+                }
+                continue;
+            }
+            _ if line.starts_with(':') => {
+                writeln!(output, "Unknown command {}, type :h for help", line)?;
+                continue;
+            }
+            _ if line
+                .split_whitespace()
+                .all(|word| word.parse::<usize>().is_ok()) =>
+            {
+                for number in line
+                    .split_whitespace()
+                    .filter_map(|word| word.parse::<usize>().ok())
+                {
+                    match results.get(number.wrapping_sub(1)) {
+                        Some(name) if checked.contains(*name) => {
+                            checked.remove(*name);
+                        }
+                        Some(name) => {
+                            checked.insert(name.to_string());
+                        }
+                        None => writeln!(output, "There is no result {}", number)?,
+                    }
+                }
+            }
+            query => {
+                results = names
+                    .iter()
+                    .filter_map(|name| fuzzy_match_score(query, name).map(|score| (score, *name)))
+                    .sorted()
+                    .take(MAX_RESULTS)
+                    .map(|(_, name)| name)
+                    .collect();
+                if results.is_empty() {
+                    writeln!(output, "No ingredients match {}", query)?;
+                    continue;
+                }
+            }
+        }
+
+        for (index, name) in results.iter().enumerate() {
+            let checkbox = if checked.contains(*name) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            writeln!(output, "{:>3} {} {}", index + 1, checkbox, name)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the picker on the example ingredients with the lines of `script` as input, starting
+    /// with Giant's Toe checked, which isn't among them
+    fn run_script(script: &str) -> (PickerOutcome, BTreeSet<String>, String) {
+        let game_data = GameData::example();
+        let names = get_pickable_names(&game_data);
+        let mut checked = BTreeSet::from(["Giant's Toe".to_string()]);
+        let mut output = Vec::new();
+        let outcome = run_picker(&names, &mut checked, script.as_bytes(), &mut output).unwrap();
+        (outcome, checked, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn ingredients_are_checked_by_searching() {
+        let (outcome, checked, output) = run_script(
+            "frost\n1\n\
+             salt\n1 5\n\
+             t\n:n\n\
+             t\n:a\n\
+             lily\n1\n\
+             zzz\n:x\n:s\n:w\n",
+        );
+
+        assert_eq!(outcome, PickerOutcome::Write);
+        assert_eq!(
+            checked.iter().map(String::as_str).collect::<Vec<_>>(),
+            [
+                "Dusk Moth Wing",
+                "Ember Root",
+                "Giant's Toe",
+                "Salt Crystal",
+                "Silver Thistle"
+            ]
+        );
+        assert!(output.contains("  1 [x] Frost Lily\n"), "{}", output);
+        assert!(output.contains("There is no result 5\n"));
+        assert!(output.contains("  1 [ ] Frost Lily\n"));
+        assert!(output.contains("No ingredients match zzz\n"));
+        assert!(output.contains("Unknown command :x, type :h for help\n"));
+        assert!(output.contains("  [x] Giant's Toe\n"));
+        assert!(output.ends_with("5 checked> "));
+    }
+
+    #[test]
+    fn quitting_does_not_write() {
+        let (outcome, checked, _) = run_script("frost\n1\n:q\n");
+        assert_eq!(outcome, PickerOutcome::Quit);
+        assert!(checked.contains("Frost Lily"));
+
+        // The input ending quits too
+        let (outcome, _, output) = run_script("frost\n");
+        assert_eq!(outcome, PickerOutcome::Quit);
+        assert!(output.starts_with(HELP));
+    }
+
+    #[test]
+    fn nameless_ingredients_are_not_pickable() {
+        let mut game_data = GameData::example();
+        game_data.strip_strings();
+        assert!(get_pickable_names(&game_data).is_empty());
+        assert_eq!(get_pickable_names(&GameData::example()).len(), 6);
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use ahash::AHashMap;
+use anyhow::Context;
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, GameData},
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId, StableFormId},
+    potion::Potion,
+    potion_notes::PotionNotesProvider,
+};
+
+#[derive(Deserialize)]
+struct IngredientTagsFile {
+    #[serde(default)]
+    tags: HashMap<String, Vec<String>>,
+}
+
+/// How the tags file refers to an ingredient. Neither changes when the load order changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IngredientRef {
+    FormId(StableFormId),
+    /// The lowercase editor ID, e.g. `deathbell`, which also keeps working when the ingredient's
+    /// plugin is renamed or merged into another, but may be shared by ingredients of different
+    /// plugins
+    EditorId(String),
+}
+
+impl FromStr for IngredientRef {
+    type Err = String;
+
+    /// Parse a value like `Skyrim.esm|03AD66` or `DeathBell`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.contains('|') {
+            return s.parse::<StableFormId>().map(IngredientRef::FormId);
+        }
+        if s.is_empty() || s.contains(char::is_whitespace) {
+            return Err(format!(
+                "expected an editor ID like DeathBell or a value like Skyrim.esm|03AD66, got {}",
+                s
+            ));
+        }
+
+        Ok(IngredientRef::EditorId(s.to_lowercase()))
+    }
+}
+
+impl Display for IngredientRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IngredientRef::FormId(form_id) => write!(f, "{}", form_id),
+            IngredientRef::EditorId(editor_id) => write!(f, "{}", editor_id),
+        }
+    }
+}
+
+/// The user's tags for ingredients, e.g. "farmable" or "vendor-trash". Ingredients are referred to
+/// by their plugin and their ID within it or by their editor ID, so the tags keep working when the
+/// load order changes.
+#[derive(Debug, Default)]
+pub struct IngredientTags {
+    tags: AHashMap<IngredientRef, BTreeSet<String>>,
+}
+
+impl IngredientTags {
+    /// Reads the tags from the TOML file at `path`. The file contains a `[tags]` table mapping
+    /// ingredients to lists of tags, e.g. `"Skyrim.esm|03AD66" = ["farmable"]` or `DeathBell =
+    /// ["vendor-trash"]`.
+    pub fn load(path: &Path) -> Result<Self, anyhow::Error> {
+        let file: IngredientTagsFile = toml::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Failed to parse ingredient tags file {}", path.display()))?;
+
+        let mut ingredient_tags = Self::default();
+        for (key, tags) in file.tags {
+            let ingredient = key.parse::<IngredientRef>().map_err(|err| {
+                anyhow::anyhow!(
+                    "Invalid ingredient {} in ingredient tags file {}: {}",
+                    key,
+                    path.display(),
+                    err
+                )
+            })?;
+            ingredient_tags.add_tags(ingredient, tags);
+        }
+        Ok(ingredient_tags)
+    }
+
+    /// Reads and merges the tags from all of the files that exist, e.g. the user's default file
+    /// and a profile's file
+    pub fn load_all<'p>(paths: impl IntoIterator<Item = &'p Path>) -> Result<Self, anyhow::Error> {
+        let mut ingredient_tags = Self::default();
+        for path in paths.into_iter().filter(|path| path.exists()) {
+            ingredient_tags.merge(Self::load(path)?);
+        }
+        Ok(ingredient_tags)
+    }
+
+    /// Adds the other tags to these tags. An ingredient tagged in both keeps all of its tags.
+    pub fn merge(&mut self, other: Self) {
+        for (ingredient, tags) in other.tags {
+            self.add_tags(ingredient, tags);
+        }
+    }
+
+    fn add_tags(&mut self, ingredient: IngredientRef, tags: impl IntoIterator<Item = String>) {
+        self.tags
+            .entry(ingredient)
+            .or_default()
+            .extend(tags.into_iter().map(|tag| tag.trim().to_lowercase()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Looks up the tagged ingredients in the game data. Tagged ingredients that are not in the
+    /// game data, e.g. because their plugin is no longer active, are logged and skipped, as are
+    /// editor IDs that several ingredients share.
+    pub fn resolve(&self, game_data: &GameData) -> ResolvedIngredientTags {
+        let tags = self
+            .tags
+            .iter()
+            .filter_map(
+                |(ingredient, tags)| match resolve_ingredient(ingredient, game_data) {
+                    Ok(global_form_id) => Some((global_form_id, tags.clone())),
+                    Err(reason) => {
+                        log::warn!("Ignoring tags of {}", reason);
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        ResolvedIngredientTags { tags }
+    }
+}
+
+/// Returns the form ID of the ingredient in the game data, or why its tags are ignored
+fn resolve_ingredient(
+    ingredient: &IngredientRef,
+    game_data: &GameData,
+) -> Result<GlobalFormId, String> {
+    let global_form_id = match ingredient {
+        IngredientRef::FormId(form_id) => form_id
+            .to_global(game_data.get_load_order())
+            .filter(|global_form_id| game_data.has_ingredient(global_form_id)),
+        IngredientRef::EditorId(editor_id) => {
+            match game_data.find_ingredients_by_editor_id(editor_id)[..] {
+                [] => None,
+                [ingredient] => Some(ingredient.get_global_form_id()),
+                ref ingredients => {
+                    return Err(format!(
+                        "{}, which is the editor ID of several ingredients: {}. Refer to one of \
+                         them by its plugin and ID instead",
+                        editor_id,
+                        game_data
+                            .format_form_ids(ingredients.iter().map(|ig| ig.get_global_form_id()))
+                    ))
+                }
+            }
+        }
+    };
+
+    global_form_id.ok_or_else(|| {
+        format!(
+            "{}, which is not an ingredient in the game data",
+            ingredient
+        )
+    })
+}
+
+/// Ingredient tags keyed by the form IDs used in the game data
+#[derive(Debug, Default, Clone)]
+pub struct ResolvedIngredientTags {
+    tags: AHashMap<GlobalFormId, BTreeSet<String>>,
+}
+
+impl ResolvedIngredientTags {
+    /// Returns the ingredient's tags in alphabetical order
+    pub fn get_tags(&self, ingredient_id: &GlobalFormId) -> impl Iterator<Item = &str> + '_ {
+        self.tags
+            .get(ingredient_id)
+            .into_iter()
+            .flat_map(|tags| tags.iter().map(|tag| tag.as_str()))
+    }
+
+    /// Returns whether the ingredient has the tag, ignoring case
+    pub fn has_tag(&self, ingredient_id: &GlobalFormId, tag: &str) -> bool {
+        self.tags
+            .get(ingredient_id)
+            .map_or(false, |tags| tags.contains(&tag.to_lowercase()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// Notes the user's tags of each of the potion's ingredients
+pub struct IngredientTagsNotesProvider {
+    pub tags: ResolvedIngredientTags,
+}
+
+impl PotionNotesProvider for IngredientTagsNotesProvider {
+    fn get_notes(&self, potion: &Potion) -> Vec<String> {
+        potion
+            .ingredients
+            .iter()
+            .filter_map(|ingredient| {
+                let tags = self
+                    .tags
+                    .get_tags(&ingredient.get_global_form_id())
+                    .join(", ");
+                if tags.is_empty() {
+                    return None;
+                }
+                Some(format!(
+                    "{} is tagged {}",
+                    get_ingredient_name_or_fallback(ingredient),
+                    tags
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn frost_lily() -> GlobalFormId {
+        GlobalFormId::new(0, 0x800)
+    }
+
+    /// The example game data with Other.esp, which adds an ingredient with Frost Lily's editor ID
+    fn game_data_with_shared_editor_id() -> GameData {
+        let mut json =
+            serde_json::from_str::<Value>(include_str!("../fixtures/example_game_data.json"))
+                .unwrap();
+        json["load_order"] = json!(["Example.esm", "Other.esp"]);
+        json["plugins"] = json!([
+            { "name": "Example.esm", "is_light": false },
+            { "name": "Other.esp", "is_light": false },
+        ]);
+        let ingredients = json["ingredients"].as_array_mut().unwrap();
+        let mut other_lily = ingredients[0].clone();
+        other_lily["global_form_id"] = json!("0001:000D62");
+        other_lily["name"] = json!("Other Lily");
+        ingredients.push(other_lily);
+        GameData::from_json_slice(&serde_json::to_vec(&json).unwrap()).unwrap()
+    }
+
+    fn parse_ref(s: &str) -> IngredientRef {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ingredients_are_resolved_by_form_id_or_editor_id() {
+        let game_data = GameData::example();
+        assert_eq!(
+            resolve_ingredient(&parse_ref("Example.esm|000800"), &game_data),
+            Ok(frost_lily())
+        );
+        // Editor IDs are matched ignoring case
+        assert_eq!(
+            resolve_ingredient(&parse_ref("examplefrostlily"), &game_data),
+            Ok(frost_lily())
+        );
+        assert_eq!(
+            resolve_ingredient(&parse_ref("ExampleDeathBell"), &game_data),
+            Err("exampledeathbell, which is not an ingredient in the game data".to_string())
+        );
+        // A form ID of a plugin that isn't loaded, and of a record that isn't an ingredient
+        for form_id in ["Missing.esp|000800", "Example.esm|000900"] {
+            assert!(resolve_ingredient(&parse_ref(form_id), &game_data).is_err());
+        }
+        assert!("Death Bell".parse::<IngredientRef>().is_err());
+    }
+
+    #[test]
+    fn shared_editor_ids_are_skipped() {
+        let game_data = game_data_with_shared_editor_id();
+        assert_eq!(
+            resolve_ingredient(&parse_ref("ExampleFrostLily"), &game_data),
+            Err(
+                "examplefrostlily, which is the editor ID of several ingredients: \
+                 Example.esm|000800, Other.esp|000D62. Refer to one of them by its plugin and ID \
+                 instead"
+                    .to_string()
+            )
+        );
+
+        let mut ingredient_tags = IngredientTags::default();
+        ingredient_tags.add_tags(parse_ref("ExampleFrostLily"), ["Farmable".to_string()]);
+        ingredient_tags.add_tags(parse_ref("Other.esp|000D62"), ["rare".to_string()]);
+        let resolved = ingredient_tags.resolve(&game_data);
+        assert_eq!(resolved.get_tags(&frost_lily()).count(), 0);
+        let other_lily = GlobalFormId::new(1, 0xD62);
+        assert_eq!(resolved.get_tags(&other_lily).collect::<Vec<_>>(), ["rare"]);
+        assert!(resolved.has_tag(&other_lily, "RARE"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, GameData},
+    plugin_parser::form_id::FormIdContainer,
+    potions_export::escape_csv_field,
+};
+
+const CSV_HEADER: &[&str] = &["name", "editor_id", "plugin", "form_id"];
+
+/// Writes all ingredients to the writer as CSV, one row per ingredient sorted by name. The form ID
+/// is written without the load order index, so together with the plugin it identifies the
+/// ingredient regardless of the load order.
+pub fn write_ingredients<W: Write>(
+    game_data: &GameData,
+    mut writer: W,
+) -> Result<(), anyhow::Error> {
+    let load_order = game_data.get_load_order();
+
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+    for ingredient in game_data
+        .get_ingredients()
+        .values()
+        .sorted_by_key(|ig| get_ingredient_name_or_fallback(ig))
+    {
+        let form_id = ingredient.get_global_form_id();
+        let row = [
+            ingredient.name.as_deref().unwrap_or_default(),
+            ingredient.editor_id.as_str(),
+            load_order
+                .get(form_id.load_order_index())
+                .unwrap_or_default(),
+            format!("{:06X}", form_id.local_id()).as_str(),
+        ]
+        .iter()
+        .map(|field| escape_csv_field(field))
+        .join(",");
+        writeln!(writer, "{}", row)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes an ingredient filter file with the names of all ingredients that the plugins add, i.e.
+/// the ingredients defined by one of them rather than overridden by one of them. Ingredients
+/// without a name are skipped, since filters match ingredients by name.
+pub fn write_plugins_filter<W: Write>(
+    game_data: &GameData,
+    plugins: &[String],
+    mut writer: W,
+) -> Result<(), anyhow::Error> {
+    let load_order = game_data.get_load_order();
+
+    let mut load_order_indexes = Vec::new();
+    for plugin in plugins {
+        match load_order.find_index(plugin) {
+            Some(index) => load_order_indexes.push(index),
+            // The load order of the game data only contains plugins with ingredients or effects
+            None if game_data
+                .get_plugins()
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(plugin)) =>
+            {
+                log::warn!("Plugin {} doesn't add any ingredients", plugin)
+            }
+            None => Err(anyhow!(
+                "Plugin {} was not active when the game data was exported",
+                plugin
+            ))?,
+        }
+    }
+
+    for name in game_data
+        .get_ingredients()
+        .values()
+        .filter(|ig| load_order_indexes.contains(&ig.get_global_form_id().load_order_index()))
+        .filter_map(|ig| ig.name.as_deref())
+        .sorted()
+        .dedup()
+    {
+        writeln!(writer, "{}", name)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
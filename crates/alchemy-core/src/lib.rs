@@ -0,0 +1,3270 @@
+#![feature(hash_drain_filter, drain_filter)]
+
+use ahash::{AHashMap, AHashSet};
+use anyhow::{anyhow, Context};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use itertools::Itertools;
+use load_order::LoadOrder;
+use save_parser::{read_character_saves, read_saves};
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::brew_plan::{plan_brews, project_xp};
+use crate::calibration::{find_power_factor_range, solve_perk_bonus};
+use crate::coverage_plan::plan_coverage;
+use crate::effect_targets::classify_effect;
+use crate::fuzzy::format_suggestions;
+use crate::game_data::{
+    get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData,
+};
+use crate::game_data_diff::GameDataDiff;
+use crate::grouping::{
+    get_distinct_ingredients, group_by_primary_effect, group_by_target, order_by_shared_ingredients,
+};
+use crate::hostility_overrides::guess_hostility_from_name;
+use crate::ingredient_tags::{IngredientTagsNotesProvider, ResolvedIngredientTags};
+use crate::load_order::is_base_game_plugin;
+use crate::name_matching::normalize_name;
+use crate::output::{AnnotatedPotionGroup, QueryResults};
+use crate::plugin_parser::{
+    form_id::FormIdContainer, ingredient::Ingredient, magic_effect::MagicEffect,
+};
+use crate::potion::{MAX_SUPPORTED_INGREDIENTS, MIN_INGREDIENTS};
+use crate::potions_list::{
+    find_best_potion_with, BuildFilter, IngredientFilter, PotionsList, PotionsPage,
+};
+use crate::sampling::sample_stratified;
+use crate::stock_trends::{compute_stock_trends, parse_playtime_hours, StockSnapshot, StockTrend};
+
+pub use crate::alchemy_graph::GraphFormat;
+pub use crate::bookmarks::{RecipeBookmark, RecipeBookmarks};
+pub use crate::calibration::CalibrationSample;
+pub use crate::coverage_plan::CoverageCost;
+pub use crate::crafting_rules::{CraftingRules, PoisonRule, PrimaryEffectRule};
+pub use crate::doctor::{run_doctor, DoctorFinding, DoctorOptions, FindingSeverity};
+pub use crate::effect_aliases::EffectAliases;
+pub use crate::effect_targets::Target;
+pub use crate::game_data_diff::{GameDataDiff, RecordChange};
+pub use crate::grouping::GroupBy;
+pub use crate::hostility_overrides::HostilityOverrides;
+pub use crate::ingredient_names::DuplicateNameStyle;
+pub use crate::ingredient_overlay::{IngredientOverlay, OVERLAY_PLUGIN_NAME};
+pub use crate::ingredient_overrides::IngredientOverride;
+pub use crate::ingredient_tags::IngredientTags;
+pub use crate::load_order_hash::LoadOrderHash;
+pub use crate::locale::NumberLocale;
+pub use crate::output::{OutputStyle, PotionFormatter};
+pub use crate::pair_matrix::MatrixFormat;
+pub use crate::player_context::{IngredientReserve, InventoryLimits, PlayerContext};
+pub use crate::plugin_dump::{dump_plugin, PluginDump};
+pub use crate::plugin_parser::form_id::GlobalFormId;
+pub use crate::potion::{
+    AlchemySkill, EffectContribution, PoisonRanking, Potion, PotionCraftError, PotionRanking,
+    PotionType, PotionWarning, Ranking, DEFAULT_MAX_INGREDIENTS, DEFAULT_POTION_WEIGHT,
+};
+pub use crate::potion_name::PotionNameTemplate;
+pub use crate::potion_notes::{
+    get_default_notes_providers, AnnotatedPotion, MerchantNotesProvider, PotionNotesProvider,
+};
+pub use crate::potions_export::ExportFormat;
+pub use crate::potions_list::suggest_for_ingredients;
+pub use crate::save_parser::{SaveMetadata, SavesLocation};
+pub use crate::server::{serve, ServeOptions, DEFAULT_SERVER_ADDRESS};
+pub use crate::simulation::{CraftResult, CraftedEffect, CraftingContext};
+pub use crate::skill_xp::{AlchemyXpSettings, SkillProgress, MAX_SKILL_LEVEL};
+pub use crate::suggestion_sink::{
+    QuerySuggestions, SuggestedPotion, SuggestedQuery, SuggestionSink, WriterSink,
+};
+pub use crate::vanilla_data::unpack_vanilla_game_data;
+
+mod alchemy_graph;
+mod bookmarks;
+mod brew_plan;
+mod calibration;
+mod coverage_plan;
+mod crafting_rules;
+mod doctor;
+mod dominance;
+mod effect_aliases;
+mod effect_targets;
+mod fuzzy;
+pub mod game_data;
+mod game_data_diff;
+mod grouping;
+mod hostility_overrides;
+mod ingredient_names;
+mod ingredient_overlay;
+mod ingredient_overrides;
+mod ingredient_picker;
+mod ingredient_tags;
+mod ingredients_export;
+pub mod load_order;
+mod load_order_hash;
+mod locale;
+mod name_matching;
+mod output;
+mod pair_matrix;
+mod player_context;
+mod plugin_dump;
+mod plugin_parser;
+mod potion;
+mod potion_name;
+mod potion_notes;
+mod potions_cache;
+mod potions_export;
+mod potions_list;
+mod sampling;
+mod save_parser;
+mod server;
+mod simulation;
+mod skill_xp;
+mod stock_trends;
+mod suggestion_sink;
+#[cfg(test)]
+pub(crate) mod test_utils;
+mod vanilla_data;
+
+fn get_load_order<PGame, PLocal>(
+    game_path: PGame,
+    local_path: Option<PLocal>,
+) -> Result<LoadOrder, anyhow::Error>
+where
+    PGame: AsRef<Path>,
+    PLocal: AsRef<Path>,
+{
+    let game_settings = match local_path {
+        Some(local_path) => loadorder::GameSettings::with_local_path(
+            loadorder::GameId::SkyrimSE,
+            game_path.as_ref(),
+            local_path.as_ref(),
+        ),
+        None => loadorder::GameSettings::new(loadorder::GameId::SkyrimSE, game_path.as_ref()),
+    }?;
+    let mut load_order = game_settings.into_load_order();
+    // Read load order file contents
+    load_order.load()?;
+    log::debug!(
+        "plugins file path: {:?}",
+        load_order.game_settings().active_plugins_file()
+    );
+    let active_plugin_names = load_order.active_plugin_names();
+    let load_order = LoadOrder::new(active_plugin_names.iter().map(|&s| s.into()).collect());
+    load_order.check_len()?;
+    Ok(load_order)
+}
+
+/// The ingredients and magic effects of the plugins parsed so far, of which the records of later
+/// plugins override those of earlier ones
+#[derive(Default)]
+struct PluginRecords {
+    magic_effects: AHashMap<GlobalFormId, MagicEffect>,
+    ingredients: AHashMap<GlobalFormId, Ingredient>,
+    /// The IDs of the magic effects that the ingredients have
+    ingredient_effect_ids: AHashSet<GlobalFormId>,
+    /// The original versions of overridden ingredients
+    original_ingredients: AHashMap<GlobalFormId, Ingredient>,
+    /// The plugins that last overrode the overridden ingredients
+    overriding_plugin_indexes: AHashMap<GlobalFormId, u16>,
+}
+
+impl PluginRecords {
+    /// Adds the records of the plugin at the given index in the load order. Returns the number of
+    /// ingredients and magic effects that the plugin injects into its masters.
+    fn add_plugin(
+        &mut self,
+        plugin_index: u16,
+        plugin_ingredients: Vec<Ingredient>,
+        plugin_magic_effects: Vec<MagicEffect>,
+    ) -> (usize, usize) {
+        // Records with the form ID of a master that the master doesn't define are injected into
+        // it by this plugin. They are keyed by the master like the master's own records, so that
+        // the plugins that refer to them and the plugins later in the load order that override
+        // them agree on their form ID.
+        let is_injected = |form_id: &GlobalFormId, is_known: bool| {
+            form_id.load_order_index() != plugin_index && !is_known
+        };
+        let mut num_injected_magic_effects = 0;
+        let mut num_injected_ingredients = 0;
+
+        for plugin_magic_effect in plugin_magic_effects.into_iter() {
+            let form_id = plugin_magic_effect.get_global_form_id();
+            if is_injected(&form_id, self.magic_effects.contains_key(&form_id)) {
+                num_injected_magic_effects += 1;
+            }
+            // Insert into magic effects hashmap, overwriting existing entry from previous plugins
+            self.magic_effects.insert(form_id, plugin_magic_effect);
+        }
+
+        for plugin_ingredient in plugin_ingredients.into_iter() {
+            // Add ingredient effect IDs to set of known used effects
+            for plugin_ingredient_effect_id in plugin_ingredient
+                .effects
+                .iter()
+                .map(|eff| eff.get_global_form_id())
+            {
+                self.ingredient_effect_ids
+                    .insert(plugin_ingredient_effect_id);
+            }
+
+            // Insert into ingredients hashmap, overwriting existing entry from previous plugins
+            let form_id = plugin_ingredient.get_global_form_id();
+            if is_injected(&form_id, self.ingredients.contains_key(&form_id)) {
+                num_injected_ingredients += 1;
+            }
+            if let Some(overridden_ingredient) = self.ingredients.insert(form_id, plugin_ingredient)
+            {
+                self.original_ingredients
+                    .entry(form_id)
+                    .or_insert(overridden_ingredient);
+                self.overriding_plugin_indexes.insert(form_id, plugin_index);
+            }
+        }
+
+        (num_injected_ingredients, num_injected_magic_effects)
+    }
+}
+
+fn load_ingredients_and_effects_from_plugins<PGame>(
+    game_path: PGame,
+    mut load_order: LoadOrder,
+    skip_bad_plugins: bool,
+) -> Result<GameData, anyhow::Error>
+where
+    PGame: AsRef<Path>,
+{
+    if load_order.is_empty() {
+        Err(anyhow!("Load order empty!"))?
+    }
+
+    let game_plugins_path = game_path.as_ref().join("Data");
+
+    let mut records = PluginRecords::default();
+    let mut light_plugin_indexes = Vec::new();
+    let mut alchemy_xp_settings = AlchemyXpSettings::default();
+
+    // All plugins are mapped up front, so that the strings tables of the localized ones can be
+    // loaded in a single parallel pass before parsing
+    let plugin_mmaps = load_order
+        .iter()
+        .map(|plugin_name| -> Result<_, anyhow::Error> {
+            let plugin_file = File::open(game_plugins_path.join(plugin_name))?;
+            // TODO: implement better (safer, streaming) file loading
+            Ok(unsafe { memmap2::MmapOptions::new().map(&plugin_file)? })
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    let localized_plugin_names = load_order
+        .iter()
+        .zip(&plugin_mmaps)
+        .filter(|(_, plugin_mmap)| plugin_parser::is_localized(plugin_mmap))
+        .map(|(plugin_name, _)| plugin_name.as_str())
+        .collect::<Vec<_>>();
+    let strings_tables =
+        plugin_parser::load_strings_tables(&localized_plugin_names, &game_plugins_path);
+
+    for ((plugin_index, plugin_name), plugin_mmap) in
+        load_order.iter().enumerate().zip(&plugin_mmaps)
+    {
+        let parsed_plugin = match plugin_parser::parse_plugin(
+            plugin_mmap,
+            plugin_name,
+            strings_tables.get(&plugin_name.to_lowercase()),
+            &load_order,
+        ) {
+            Ok(parsed_plugin) => parsed_plugin,
+            Err(err) if skip_bad_plugins => {
+                match err.downcast_ref::<plugin_parser::PluginHeaderError>() {
+                    Some(_) => log::warn!(
+                        "Skipping plugin {} with invalid header: {}",
+                        plugin_name,
+                        err
+                    ),
+                    None => log::warn!("Skipping plugin {}: {}", plugin_name, err),
+                }
+                continue;
+            }
+            Err(err) => Err(err.context(format!(
+                "Failed to parse plugin {} (pass --skip-bad-plugins to skip it)",
+                plugin_name
+            )))?,
+        };
+        let plugin_parser::ParsedPlugin {
+            ingredients: plugin_ingredients,
+            magic_effects: plugin_magic_effects,
+            alchemy_xp_records,
+            is_light,
+            num_skipped_records,
+        } = parsed_plugin;
+
+        // Later plugins override the settings of earlier ones
+        alchemy_xp_records.apply_to(&mut alchemy_xp_settings);
+
+        if is_light {
+            light_plugin_indexes.push(plugin_index as u16);
+        }
+
+        log::debug!(
+            "Plugin {:?} has {:?} ingredients and {:?} magic effects, skipped {:?} deleted or \
+             ignored records.",
+            plugin_name,
+            plugin_ingredients.len(),
+            plugin_magic_effects.len(),
+            num_skipped_records
+        );
+
+        let (num_injected_ingredients, num_injected_magic_effects) = records.add_plugin(
+            plugin_index as u16,
+            plugin_ingredients,
+            plugin_magic_effects,
+        );
+        if num_injected_magic_effects > 0 || num_injected_ingredients > 0 {
+            log::debug!(
+                "Plugin {:?} injects {} ingredients and {} magic effects into its masters.",
+                plugin_name,
+                num_injected_ingredients,
+                num_injected_magic_effects
+            );
+        }
+    }
+
+    for plugin_index in light_plugin_indexes {
+        load_order.set_light(plugin_index, true);
+    }
+    // Skipped plugins count as regular plugins, as whether they are light masters is unknown
+    load_order.check_limits()?;
+
+    let PluginRecords {
+        mut magic_effects,
+        ingredients,
+        ingredient_effect_ids,
+        original_ingredients,
+        overriding_plugin_indexes,
+    } = records;
+
+    // Remove from the magic effects all those that are not used by ingredients
+    log::debug!("Number of ingredients: {}", ingredients.len());
+    log::debug!(
+        "Number of magic effects before filtering: {}",
+        magic_effects.len()
+    );
+    magic_effects.drain_filter(|key, _| !ingredient_effect_ids.contains(key));
+    log::debug!(
+        "Number of magic effects after filtering: {}",
+        magic_effects.len()
+    );
+
+    let ingredient_overrides = original_ingredients
+        .iter()
+        .filter_map(|(form_id, original)| {
+            IngredientOverride::compare(
+                original,
+                ingredients.get(form_id)?,
+                *overriding_plugin_indexes.get(form_id)?,
+                &load_order,
+                &magic_effects,
+            )
+        })
+        .sorted_by(|a, b| a.ingredient.cmp(&b.ingredient))
+        .collect::<Vec<_>>();
+    if !ingredient_overrides.is_empty() {
+        log::warn!(
+            "{} ingredients had effects removed or changed by overriding plugins, see the \
+             check-overrides subcommand for details",
+            ingredient_overrides.len()
+        );
+    }
+
+    let plugins = load_order.clone();
+    let mut game_data = GameData::from_hashmaps(
+        load_order,
+        plugins,
+        ingredients,
+        magic_effects,
+        ingredient_overrides,
+    );
+    game_data.purge_invalid();
+    game_data.set_alchemy_xp_settings(alchemy_xp_settings);
+
+    let load_order_hash = LoadOrderHash::compute(&game_plugins_path, game_data.get_plugins());
+    log::info!("Load order hash: {}", load_order_hash);
+    game_data.set_load_order_hash(load_order_hash);
+
+    Ok(game_data)
+}
+
+/// Returns whether the game data file is gzipped, which is decided by its extension, e.g.
+/// `game_data.json.gz`
+fn is_gzipped(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+fn serialize_game_data<W: Write>(
+    game_data: &GameData,
+    writer: W,
+    compact: bool,
+) -> Result<(), serde_json::Error> {
+    match compact {
+        true => serde_json::to_writer(writer, game_data),
+        false => serde_json::to_writer_pretty(writer, game_data),
+    }
+}
+
+/// Writes the game data to the file as it is serialized, rather than serializing it to a string
+/// first, which would need as much memory again for large load orders
+fn write_game_data(
+    game_data: &GameData,
+    export_path: &Path,
+    compact: bool,
+) -> Result<(), anyhow::Error> {
+    let file = File::create(export_path)
+        .with_context(|| format!("Failed to create game data file {}", export_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    if is_gzipped(export_path) {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        serialize_game_data(game_data, &mut encoder, compact)?;
+        writer = encoder.finish()?;
+    } else {
+        serialize_game_data(game_data, &mut writer, compact)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Returns the path that the `n`th previous export is kept at, e.g. `game_data.2.json.gz` for the
+/// second to last export to `game_data.json.gz`. The number goes before the extensions so that the
+/// file is still recognized as gzipped.
+fn get_previous_export_path(export_path: &Path, n: usize) -> PathBuf {
+    let file_name = export_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let previous_file_name = match file_name.split_once('.') {
+        Some((stem, extensions)) => format!("{}.{}.{}", stem, n, extensions),
+        None => format!("{}.{}", file_name, n),
+    };
+    export_path.with_file_name(previous_file_name)
+}
+
+/// Moves the existing export and the previous exports kept before it one place back, keeping at
+/// most `keep_previous` of them
+fn rotate_exports(export_path: &Path, keep_previous: usize) -> Result<(), anyhow::Error> {
+    if keep_previous == 0 {
+        return Ok(());
+    }
+    let oldest_path = get_previous_export_path(export_path, keep_previous);
+    if oldest_path.exists() {
+        fs::remove_file(&oldest_path)?;
+    }
+    for n in (1..keep_previous).rev() {
+        let path = get_previous_export_path(export_path, n);
+        if path.exists() {
+            fs::rename(&path, get_previous_export_path(export_path, n + 1))?;
+        }
+    }
+    fs::rename(export_path, get_previous_export_path(export_path, 1)).with_context(|| {
+        format!(
+            "Failed to keep the previous export {}",
+            export_path.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// Options for `parse_and_export_game_data`
+#[derive(Default)]
+pub struct ExportGameDataOptions {
+    /// Whether to skip plugins that cannot be parsed instead of failing
+    pub skip_bad_plugins: bool,
+    /// Whether to only write a CSV list of the ingredients' names, editor IDs and plugins, e.g. for
+    /// building ingredient filters
+    pub ingredients_only: bool,
+    /// Whether to write the JSON without indentation and line breaks
+    pub compact: bool,
+    /// Whether to only read the plugins of the base game and its official DLCs and leave out the
+    /// names and descriptions, so that the game data holds no text from the game and can be
+    /// bundled with the `vanilla-data` feature
+    pub vanilla: bool,
+    /// How many of the previous exports to the same path to keep, numbered from newest to oldest,
+    /// e.g. `game_data.1.json.gz`
+    pub keep_previous: usize,
+}
+
+/// Reads the game data from the active plugins and writes it to `export_path` as JSON, which is
+/// gzipped if the path ends in `.gz`.
+///
+/// If there is game data at `export_path` already, what changed since then is returned, e.g. after
+/// updating the mod list, and it is kept as a previous export if `keep_previous` is set.
+pub fn parse_and_export_game_data<PGame, PLocal, PExport>(
+    game_path: PGame,
+    local_path: Option<PLocal>,
+    export_path: PExport,
+    options: &ExportGameDataOptions,
+) -> Result<Option<GameDataDiff>, anyhow::Error>
+where
+    PGame: AsRef<Path>,
+    PLocal: AsRef<Path>,
+    PExport: AsRef<Path>,
+{
+    let ExportGameDataOptions {
+        skip_bad_plugins,
+        ingredients_only,
+        compact,
+        vanilla,
+        keep_previous,
+    } = *options;
+
+    let mut load_order = get_load_order(&game_path, local_path)?;
+    if vanilla {
+        load_order = load_order.base_game_only();
+    }
+    log::debug!("Load order:\n{}", &load_order);
+
+    let mut game_data =
+        load_ingredients_and_effects_from_plugins(&game_path, load_order, skip_bad_plugins)?;
+    if vanilla {
+        game_data.strip_strings();
+    }
+    if ingredients_only {
+        let file = File::create(export_path.as_ref()).with_context(|| {
+            format!(
+                "Failed to create export file {}",
+                export_path.as_ref().display()
+            )
+        })?;
+        ingredients_export::write_ingredients(&game_data, BufWriter::new(file))?;
+        return Ok(None);
+    }
+
+    let export_path = export_path.as_ref();
+    let mut diff = None;
+    if export_path.is_file() {
+        match read_game_data(export_path) {
+            Ok(previous_game_data) => {
+                diff = Some(GameDataDiff::compute(&previous_game_data, &game_data));
+            }
+            Err(err) => log::warn!(
+                "Cannot compare with the previous export {}: {}",
+                export_path.display(),
+                err
+            ),
+        }
+        rotate_exports(export_path, keep_previous)?;
+    }
+
+    write_game_data(&game_data, export_path, compact)?;
+
+    Ok(diff)
+}
+
+/// Returns what changed between two exports of the game data: the ingredients that were added,
+/// removed or changed and the effects whose base cost or hostility changed
+pub fn diff_game_data<POld, PNew>(
+    old_path: POld,
+    new_path: PNew,
+) -> Result<GameDataDiff, anyhow::Error>
+where
+    POld: AsRef<Path>,
+    PNew: AsRef<Path>,
+{
+    let old_game_data = read_game_data(old_path.as_ref())?;
+    let new_game_data = read_game_data(new_path.as_ref())?;
+
+    Ok(GameDataDiff::compute(&old_game_data, &new_game_data))
+}
+
+/// Lets the user pick ingredients from the game data by searching for them, reading their input
+/// from `input` and showing the picker on `output`, then writes their names to an ingredient filter
+/// file, one per line. The names in `initial` start out checked, e.g. those of the filter file
+/// being edited. Nothing is written if the user quits.
+pub fn pick_ingredients<PImport, POutput, R, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    output_path: POutput,
+    initial: AHashSet<String>,
+    input: R,
+    mut output: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    POutput: AsRef<Path>,
+    R: BufRead,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+    let names = ingredient_picker::get_pickable_names(&game_data);
+
+    // Names shared by several ingredients aren't listed, but still match them in filters
+    let unknown_names = initial
+        .iter()
+        .filter(|name| {
+            let name = normalize_name(name);
+            !game_data.get_ingredients().values().any(|ig| {
+                [&ig.name, &ig.qualified_name]
+                    .into_iter()
+                    .flatten()
+                    .any(|ig_name| normalize_name(ig_name) == name)
+            })
+        })
+        .sorted()
+        .collect::<Vec<_>>();
+    if !unknown_names.is_empty() {
+        log::warn!(
+            "{} of the ingredients in the filter file are not in the game data and stay checked: {}",
+            unknown_names.len(),
+            unknown_names.iter().join(", ")
+        );
+    }
+
+    let mut checked = initial.into_iter().collect::<BTreeSet<_>>();
+    let outcome = ingredient_picker::run_picker(&names, &mut checked, input, &mut output)?;
+    if outcome == ingredient_picker::PickerOutcome::Quit {
+        writeln!(
+            output,
+            "Quit without writing {}",
+            output_path.as_ref().display()
+        )?;
+        return Ok(());
+    }
+
+    let file = File::create(output_path.as_ref()).with_context(|| {
+        format!(
+            "Failed to create filter file {}",
+            output_path.as_ref().display()
+        )
+    })?;
+    let mut writer = BufWriter::new(file);
+    for name in &checked {
+        writeln!(writer, "{}", name)?;
+    }
+    writer.flush()?;
+    writeln!(
+        output,
+        "Wrote {} ingredients to {}",
+        checked.len(),
+        output_path.as_ref().display()
+    )?;
+
+    Ok(())
+}
+
+/// Writes an ingredient filter with the names of all ingredients that the plugins add to the
+/// writer, e.g. to blacklist the ingredients of a mod
+pub fn generate_plugins_filter<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    plugins: &[String],
+    writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+
+    ingredients_export::write_plugins_filter(&game_data, plugins, writer)
+}
+
+/// Reads the game data as it was exported, without the crafting rules and the ingredient overlay
+fn read_game_data(import_path: &Path) -> Result<GameData, anyhow::Error> {
+    // The whole file is read into memory first, so that its sections can be parsed in parallel
+    let mut json = Vec::new();
+    let file = File::open(import_path)?;
+    match is_gzipped(import_path) {
+        true => GzDecoder::new(BufReader::new(file)).read_to_end(&mut json)?,
+        false => BufReader::new(file).read_to_end(&mut json)?,
+    };
+    GameData::from_json_slice(&json).map_err(|err| anyhow!(err.to_string()))
+}
+
+/// How game data is prepared when it is imported. The defaults import the game data as it was
+/// exported.
+#[derive(Default)]
+pub struct ImportOptions {
+    /// The rules by which potions are made from the ingredients
+    pub crafting_rules: CraftingRules,
+    /// Changes to the ingredients and added ingredients, e.g. to fix mistakes in a mod
+    pub ingredient_overlay: Option<IngredientOverlay>,
+    /// Which magic effects are hostile, overriding the game data
+    pub hostility_overrides: Option<HostilityOverrides>,
+    /// How ingredients that share a name are told apart in the output
+    pub duplicate_name_style: DuplicateNameStyle,
+    /// The effects, by name or editor ID, that are left out of all ingredients, e.g. near-worthless
+    /// filler effects that a mod adds. Potions are then made as if the ingredients didn't have
+    /// them.
+    pub junk_effects: Vec<String>,
+    /// The aliases that the junk effects are resolved with, or `None` for the built-in aliases
+    pub effect_aliases: Option<EffectAliases>,
+    /// The template that potions are named with, or `None` to name them like the game does
+    pub potion_name_template: Option<PotionNameTemplate>,
+    /// The locale that numbers are formatted with in the output, or `None` for plain numbers
+    pub number_locale: Option<NumberLocale>,
+    /// A soft memory budget in bytes for building potions, see `GameData::set_memory_budget`
+    pub memory_budget: Option<u64>,
+}
+
+/// Reads the game data and prepares it according to the options
+pub fn import_game_data<PImport>(
+    import_path: PImport,
+    options: &ImportOptions,
+) -> Result<GameData, anyhow::Error>
+where
+    PImport: AsRef<Path>,
+{
+    let mut game_data = read_game_data(import_path.as_ref())?;
+    match game_data.get_load_order_hash() {
+        Some(load_order_hash) => log::debug!("Game data load order hash: {}", load_order_hash),
+        None => {
+            log::debug!("Game data has no load order hash, it was exported by an older version")
+        }
+    }
+
+    game_data.set_crafting_rules(options.crafting_rules.clone());
+    game_data.set_potion_name_template(options.potion_name_template.clone());
+    if let Some(locale) = options.number_locale {
+        game_data.set_number_locale(locale);
+    }
+    game_data.set_memory_budget(options.memory_budget);
+    if let Some(overlay) = &options.ingredient_overlay {
+        let (num_changed, num_added) = game_data
+            .apply_ingredient_overlay(overlay)
+            .context("Failed to apply the ingredient overlay")?;
+        if num_changed > 0 || num_added > 0 {
+            log::info!(
+                "Changed {} and added {} ingredients with the ingredient overlay",
+                num_changed,
+                num_added
+            );
+        }
+    }
+    let implausible_base_costs = game_data.find_implausible_base_costs();
+    if !implausible_base_costs.is_empty() {
+        log::warn!(
+            "{} magic effects have implausible base costs, so the values of potions with them are \
+             unreliable: {}",
+            implausible_base_costs.len(),
+            implausible_base_costs
+                .iter()
+                .map(|mgef| format!(
+                    "{} ({}): {}",
+                    get_magic_effect_name_or_fallback(mgef),
+                    mgef.get_global_form_id()
+                        .to_display_with(game_data.get_load_order()),
+                    mgef.base_cost
+                ))
+                .join(", ")
+        );
+    }
+    let num_duplicate_names = game_data.qualify_duplicate_names(options.duplicate_name_style);
+    if num_duplicate_names > 0 {
+        log::debug!(
+            "{} ingredient names are shared by several ingredients",
+            num_duplicate_names
+        );
+    }
+    if let Some(overrides) = &options.hostility_overrides {
+        let num_changed = game_data.apply_hostility_overrides(overrides);
+        if num_changed > 0 {
+            log::info!("Overrode the hostility of {} magic effects", num_changed);
+        }
+    }
+    // Leaving the effects out of the ingredients makes the potions as if they never had them, so
+    // ingredients that only shared a junk effect no longer make a potion together
+    if !options.junk_effects.is_empty() {
+        let builtin_aliases;
+        let effect_aliases = match &options.effect_aliases {
+            Some(effect_aliases) => effect_aliases,
+            None => {
+                builtin_aliases = EffectAliases::builtin();
+                &builtin_aliases
+            }
+        };
+        let (junk_effect_ids, unresolved_names) =
+            resolve_magic_effect_names(&game_data, &options.junk_effects, effect_aliases);
+        for name in unresolved_names {
+            log::warn!(
+                "Junk effect \"{}\" matches no effect in the game data, so it isn't left out{}",
+                name,
+                format_suggestions(&game_data.find_similar_effect_names(name))
+            );
+        }
+        let num_ingredients = game_data.get_ingredients().len();
+        game_data.retain_ingredient_effects(|_, effect| {
+            !junk_effect_ids.contains(&effect.get_global_form_id())
+        });
+        log::info!(
+            "Left out {} junk effects from all ingredients, which removed {} ingredients without \
+             any other effects",
+            junk_effect_ids.len(),
+            num_ingredients - game_data.get_ingredients().len()
+        );
+    }
+
+    Ok(game_data)
+}
+
+/// Applies the inventory limits to the player context, looking up the reserved ingredients in the
+/// game data
+fn limit_inventory(
+    game_data: &GameData,
+    player_context: &mut PlayerContext,
+    limits: &InventoryLimits,
+) -> Result<(), anyhow::Error> {
+    let mut reserves = AHashMap::new();
+    for reserve in &limits.reserves {
+        let ingredient = game_data
+            .find_ingredient_by_name(&reserve.ingredient)
+            .map_err(|err| anyhow!("Cannot reserve ingredient: {}", err))?;
+        *reserves.entry(ingredient.get_global_form_id()).or_insert(0) += reserve.count;
+    }
+
+    let num_ingredients = player_context.inventory.len();
+    player_context.limit_inventory(limits.min_count, &reserves);
+    log::debug!(
+        "{} of {} ingredients in the inventory remain after applying the inventory limits",
+        player_context.inventory.len(),
+        num_ingredients
+    );
+    Ok(())
+}
+
+fn check_max_ingredients(max_ingredients: usize) -> Result<(), anyhow::Error> {
+    if !(MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS).contains(&max_ingredients) {
+        Err(anyhow!(
+            "Max ingredients must be between {} and {}",
+            MIN_INGREDIENTS,
+            MAX_SUPPORTED_INGREDIENTS
+        ))?
+    }
+
+    Ok(())
+}
+
+/// A query for potions to suggest. Several queries can be answered in one run, e.g. to generate
+/// themed lists of potions, without computing the potions again for each query.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PotionQuery {
+    /// Name of the query, which is printed before its results
+    pub name: Option<String>,
+    /// If not empty, only potions with at least one of these effects (by name or editor ID) will
+    /// be suggested
+    pub effects: Vec<String>,
+    /// If not empty, only potions whose primary effect (the one they're named after) is one of
+    /// these effects will be suggested
+    pub primary_effects: Vec<String>,
+    /// If not empty, only potions with at least one effect that restores, fortifies, damages or
+    /// changes the regen of one of these resources will be suggested, grouped by what the effect
+    /// does unless another grouping is specified
+    pub targets: Vec<Target>,
+    /// If specified, only potions or only poisons will be suggested
+    pub potion_type: Option<PotionType>,
+    /// Potions containing any of these ingredients will not be suggested
+    #[serde(deserialize_with = "deserialize_string_set")]
+    pub ingredients_blacklist: AHashSet<String>,
+    /// If not empty, only potions containing only these ingredients will be suggested
+    #[serde(deserialize_with = "deserialize_string_set")]
+    pub ingredients_whitelist: AHashSet<String>,
+    /// Potions containing ingredients with any of these tags will not be suggested
+    pub exclude_tags: Vec<String>,
+    /// Whether to leave out potions containing ingredients that are flagged as food
+    pub exclude_food: bool,
+    /// The maximum number of potions to suggest
+    pub limit: Option<usize>,
+    /// The number of potions to skip before the suggested ones, to page through the results
+    pub offset: usize,
+    /// If specified, only potions worth at least this much gold will be suggested
+    pub min_value: Option<u32>,
+    /// How to rank the suggested potions and poisons
+    pub ranking: Ranking,
+    /// If specified, suggest a random sample of this many potions spread across the ranking
+    /// instead of only the best ones
+    pub sample: Option<usize>,
+    /// Seed for the random sample, so that it can be reproduced. A seed is picked at random if not
+    /// specified.
+    pub seed: Option<u64>,
+    /// How to group the suggested potions. When grouping, the offset and limit apply to each
+    /// group.
+    pub group_by: GroupBy,
+    /// Whether to order the suggested potions (within each group) so that each needs as few
+    /// ingredients as possible besides those of the potions before it, and list the ingredients
+    /// they need
+    pub batch_by_ingredients: bool,
+}
+
+impl PotionQuery {
+    /// Returns whether the query only needs a page of its potions in order of gold value, which
+    /// can be found without computing or reading all potions
+    fn is_page_by_value(&self) -> bool {
+        self.sample.is_none()
+            && self.group_by == GroupBy::None
+            // Potions found by target are grouped by it
+            && self.targets.is_empty()
+            && self.primary_effects.is_empty()
+            && self.ranking.is_by_value()
+    }
+}
+
+fn deserialize_string_set<'de, D>(deserializer: D) -> Result<AHashSet<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct PotionQueriesFile {
+    #[serde(default, rename = "query")]
+    queries: Vec<PotionQuery>,
+}
+
+/// Reads potion queries from a TOML file containing a `[[query]]` table for each query
+pub fn read_potion_queries<P>(path: P) -> Result<Vec<PotionQuery>, anyhow::Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let file: PotionQueriesFile = toml::from_str(&fs::read_to_string(path)?)
+        .with_context(|| format!("Failed to parse queries file {}", path.display()))?;
+
+    if file.queries.is_empty() {
+        Err(anyhow!(
+            "Queries file {} contains no queries",
+            path.display()
+        ))?
+    }
+
+    Ok(file.queries)
+}
+
+/// Options for `suggest_potions`
+pub struct SuggestPotionsOptions {
+    /// The queries to answer. Each query's results are delivered separately.
+    pub queries: Vec<PotionQuery>,
+    /// The maximum number of potions to suggest for queries that don't specify a limit
+    pub limit: usize,
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions. Saves don't store
+    /// it, so it is the same whether or not the save is read.
+    pub alchemy_skill: AlchemySkill,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to only consider the ingredient effects that the player has discovered
+    pub only_known_effects: bool,
+    /// Where to cache the computed potions between runs, or `None` to always compute them
+    pub cache_path: Option<PathBuf>,
+    /// How `suggest_potions` prints the suggested potions
+    pub formatter: PotionFormatter,
+    /// Aliases used to resolve the effect names in the queries
+    pub effect_aliases: EffectAliases,
+    /// Whether to fail instead of warn when ingredient filters contain unknown ingredient names or
+    /// names that several ingredients share
+    pub strict_filters: bool,
+    /// Ingredients with any of these effects (by name or editor ID) are left out before the
+    /// potions are computed, for all queries
+    pub exclude_ingredients_with_effects: Vec<String>,
+    /// The user's ingredient tags, which are noted for each potion and can be used to exclude
+    /// ingredients
+    pub ingredient_tags: IngredientTags,
+    /// Limits on the ingredients in the inventory. Unless they are empty, only the ingredients in
+    /// the inventory that they leave are used.
+    pub inventory_limits: InventoryLimits,
+    /// Whether to leave out potions that another potion beats, i.e. one with at least the same
+    /// effects whose ingredients cost at most as much and that is worth more
+    pub prune_dominated: bool,
+    /// Whether to read the latest save for the inventory and the discovered effects. Without it,
+    /// the inventory is empty, so the inventory limits and `only_known_effects` can't be used.
+    pub read_saves: bool,
+}
+
+/// Checks that every name in the queries' ingredient filters matches an ingredient in the game
+/// data, since a typo would otherwise silently make the filter do nothing, and that it matches only
+/// one, since a name that several ingredients share filters all of them
+fn check_ingredient_filters(
+    game_data: &GameData,
+    queries: &[PotionQuery],
+    strict: bool,
+) -> Result<(), anyhow::Error> {
+    let mut ingredients_by_name = AHashMap::<String, Vec<&Ingredient>>::new();
+    for ingredient in game_data.get_ingredients().values() {
+        if let Some(name) = ingredient.name.as_deref() {
+            ingredients_by_name
+                .entry(normalize_name(name))
+                .or_default()
+                .push(ingredient);
+        }
+    }
+    let qualified_names = game_data
+        .get_ingredients()
+        .values()
+        .filter_map(|ing| ing.qualified_name.as_deref())
+        .map(normalize_name)
+        .collect::<AHashSet<_>>();
+
+    let filter_names = queries
+        .iter()
+        .flat_map(|query| {
+            query
+                .ingredients_blacklist
+                .iter()
+                .chain(query.ingredients_whitelist.iter())
+        })
+        .map(String::as_str)
+        .unique()
+        .sorted()
+        .collect::<Vec<_>>();
+    let unmatched_names = filter_names
+        .iter()
+        .filter(|name| {
+            let name = normalize_name(name);
+            !ingredients_by_name.contains_key(&name) && !qualified_names.contains(&name)
+        })
+        .collect::<Vec<_>>();
+    let ambiguous_names = filter_names
+        .iter()
+        .filter_map(|name| {
+            let ingredients = ingredients_by_name.get(&normalize_name(name))?;
+            (ingredients.len() > 1).then(|| (name, ingredients))
+        })
+        .collect::<Vec<_>>();
+
+    let mut messages = Vec::new();
+    if !unmatched_names.is_empty() {
+        messages.push(format!(
+            "Ingredient filters contain {} names that don't match any ingredient: {}",
+            unmatched_names.len(),
+            unmatched_names
+                .iter()
+                .map(|name| format!(
+                    "{}{}",
+                    name,
+                    format_suggestions(&game_data.find_similar_ingredient_names(name))
+                ))
+                .join(", ")
+        ));
+    }
+    if !ambiguous_names.is_empty() {
+        messages.push(format!(
+            "Ingredient filters contain {} names that several ingredients share, which filter all \
+             of them. Use the qualified name of one of them instead: {}",
+            ambiguous_names.len(),
+            ambiguous_names
+                .iter()
+                .map(|(name, ingredients)| format!(
+                    "{} ({})",
+                    name,
+                    ingredients
+                        .iter()
+                        .sorted_by_key(|ig| ig.get_global_form_id())
+                        .map(|ig| ig.qualified_name.clone().unwrap_or_else(|| ig
+                            .get_global_form_id()
+                            .to_display_with(game_data.get_load_order())))
+                        .join(", ")
+                ))
+                .join(", ")
+        ));
+    }
+
+    if messages.is_empty() {
+        return Ok(());
+    }
+    if strict {
+        Err(anyhow!(messages.join("\n")))?
+    }
+    for message in messages {
+        log::warn!("{}", message);
+    }
+
+    Ok(())
+}
+
+/// Returns the form IDs of the magic effects matching any of the given names or editor IDs, or
+/// any of their aliases, as compared by `normalize_name`. Warns about the names that don't match
+/// any magic effect, suggesting similar effect names.
+fn find_magic_effect_ids(
+    game_data: &GameData,
+    names: &[String],
+    effect_aliases: &EffectAliases,
+) -> AHashSet<GlobalFormId> {
+    let (effect_ids, unresolved_names) =
+        resolve_magic_effect_names(game_data, names, effect_aliases);
+    for name in unresolved_names {
+        log::warn!(
+            "No effect found matching \"{}\"{}",
+            name,
+            format_suggestions(&game_data.find_similar_effect_names(name))
+        );
+    }
+
+    effect_ids
+}
+
+/// Returns the form IDs of the magic effects whose names or editor IDs match any of the names or
+/// their aliases, and the names that match no magic effect
+fn resolve_magic_effect_names<'n>(
+    game_data: &GameData,
+    names: &'n [String],
+    effect_aliases: &EffectAliases,
+) -> (AHashSet<GlobalFormId>, Vec<&'n str>) {
+    let mut effect_ids = AHashSet::new();
+    let mut unresolved_names = Vec::new();
+    for name in names {
+        let aliases = effect_aliases.resolve(name);
+        let matching_ids = game_data
+            .get_magic_effects()
+            .values()
+            .filter(|mgef| {
+                aliases.contains(&normalize_name(&mgef.editor_id))
+                    || mgef
+                        .name
+                        .as_deref()
+                        .map_or(false, |name| aliases.contains(&normalize_name(name)))
+            })
+            .map(|mgef| mgef.get_global_form_id())
+            .collect::<Vec<_>>();
+        if matching_ids.is_empty() {
+            unresolved_names.push(name.as_str());
+        }
+        effect_ids.extend(matching_ids);
+    }
+
+    (effect_ids, unresolved_names)
+}
+
+/// Suggests potions for each of the queries and sends each query's suggestions to the sink as soon
+/// as they are found
+pub fn suggest_potions<PImport>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    options: &SuggestPotionsOptions,
+    sink: &mut dyn SuggestionSink,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+{
+    let SuggestPotionsOptions {
+        queries,
+        max_ingredients,
+        alchemy_skill,
+        character,
+        strict_load_order,
+        only_known_effects,
+        cache_path,
+        strict_filters,
+        exclude_ingredients_with_effects,
+        effect_aliases,
+        ingredient_tags,
+        inventory_limits,
+        prune_dominated,
+        read_saves: should_read_saves,
+        ..
+    } = options;
+
+    check_max_ingredients(*max_ingredients)?;
+    if !should_read_saves && (*only_known_effects || !inventory_limits.is_empty()) {
+        Err(anyhow!(
+            "The known effects and inventory limits need the save, which is not read"
+        ))?
+    }
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+    check_ingredient_filters(&game_data, queries, *strict_filters)?;
+    let ingredient_tags = ingredient_tags.resolve(&game_data);
+    if (*prune_dominated
+        || queries
+            .iter()
+            .any(|query| query.ranking.is_by_ingredients_value()))
+        && game_data
+            .get_ingredients()
+            .values()
+            .any(|ing| ing.value.is_none())
+    {
+        log::warn!(
+            "Game data was exported by an older version without ingredient values, re-export it to \
+             rank by the cheapest ingredients or prune dominated potions"
+        );
+    }
+
+    // The inventory is only used to rank by value density, for which the save's inventory will do,
+    // so there is no need to read the co-save
+    let mut player_context = match should_read_saves {
+        true => {
+            let save_data = read_saves(
+                saves_location,
+                &game_data,
+                character.as_deref(),
+                *strict_load_order,
+                false,
+            )?;
+            PlayerContext::from_save_data(save_data, *alchemy_skill)
+        }
+        false => PlayerContext::new(*alchemy_skill),
+    };
+    if !inventory_limits.is_empty() {
+        limit_inventory(&game_data, &mut player_context, inventory_limits)?;
+    }
+    game_data.set_player_context(player_context);
+    if !inventory_limits.is_empty() {
+        game_data.retain_inventory_ingredients();
+        log::debug!(
+            "{} ingredients in the inventory are within the inventory limits",
+            game_data.get_ingredients().len()
+        );
+    }
+
+    // Ingredients are excluded by all of their effects, even the ones the player doesn't know yet
+    if !exclude_ingredients_with_effects.is_empty() {
+        let excluded_effect_ids =
+            find_magic_effect_ids(&game_data, exclude_ingredients_with_effects, effect_aliases);
+        game_data.retain_ingredients(|ing| {
+            !ing.effects
+                .iter()
+                .any(|eff| excluded_effect_ids.contains(&eff.get_global_form_id()))
+        });
+        log::debug!(
+            "Excluding ingredients with effects {}, {} ingredients remain",
+            exclude_ingredients_with_effects.join(", "),
+            game_data.get_ingredients().len()
+        );
+    }
+
+    if *only_known_effects {
+        if game_data
+            .get_ingredients()
+            .values()
+            .flat_map(|ing| ing.effects.iter())
+            .any(|eff| eff.record_index.is_none())
+        {
+            Err(anyhow!(
+                "Game data was exported by an older version, re-export it to use known effects"
+            ))?
+        }
+
+        game_data.retain_known_ingredient_effects();
+        log::debug!(
+            "Only considering known effects, {} ingredients remain",
+            game_data.get_ingredients().len()
+        );
+    }
+
+    let ingredient_filters = queries
+        .iter()
+        .map(|query| get_ingredient_filter(&game_data, query, &ingredient_tags))
+        .collect::<Vec<_>>();
+
+    let mut potions_list = PotionsList::new(&game_data, *max_ingredients);
+    match (&queries[..], &ingredient_filters[..], cache_path) {
+        // A single query's filters can skip the potions it would leave out anyway. That beats
+        // reading the cache of all potions if the filter only allows a few ingredients.
+        ([query], [ingredient_filter], cache_path)
+            if !ingredient_filter.is_empty()
+                && (cache_path.is_none() || ingredient_filter.has_whitelist()) =>
+        {
+            let min_value = query.min_value.unwrap_or(0);
+            let is_match = |p: &Potion| {
+                p.gold_value >= min_value
+                    && query
+                        .potion_type
+                        .map_or(true, |potion_type| p.get_potion_type() == potion_type)
+            };
+            potions_list.build_potions_filtered(&BuildFilter {
+                ingredients: ingredient_filter,
+                is_match: &is_match,
+            });
+        }
+        // Pages of potions by gold value can be found in the mapped cache without reading all of
+        // it, which is much faster for a few potions of a large cache
+        (_, _, Some(cache_path))
+            if !*prune_dominated
+                && queries.iter().all(PotionQuery::is_page_by_value)
+                && potions_list.map_potions_cached(cache_path) => {}
+        (_, _, Some(cache_path)) => potions_list.build_potions_cached(cache_path),
+        (_, _, None) => potions_list.build_potions(),
+    }
+    // The cache keeps all potions, so that it doesn't depend on this option
+    if *prune_dominated {
+        potions_list.prune_dominated();
+    }
+
+    let mut notes_providers = get_default_notes_providers();
+    if !ingredient_tags.is_empty() {
+        notes_providers.push(Box::new(IngredientTagsNotesProvider {
+            tags: ingredient_tags.clone(),
+        }));
+    }
+    for (query, ingredient_filter) in queries.iter().zip(&ingredient_filters) {
+        send_query_results(
+            &game_data,
+            &potions_list,
+            query,
+            ingredient_filter,
+            options,
+            &notes_providers,
+            sink,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Resolves the query's ingredient filters, tags and food exclusion to the ingredients that its
+/// potions may contain
+fn get_ingredient_filter(
+    game_data: &GameData,
+    query: &PotionQuery,
+    ingredient_tags: &ResolvedIngredientTags,
+) -> IngredientFilter {
+    let PotionQuery {
+        ingredients_blacklist,
+        ingredients_whitelist,
+        exclude_tags,
+        exclude_food,
+        ..
+    } = query;
+
+    if !ingredients_blacklist.is_empty() {
+        log::debug!(
+            "Applying ingredients blacklist: {}",
+            ingredients_blacklist.iter().sorted().join(", ")
+        );
+    } else if !ingredients_whitelist.is_empty() {
+        log::debug!(
+            "Applying ingredients whitelist: {}",
+            ingredients_whitelist.iter().sorted().join(", ")
+        );
+    }
+
+    let mut ingredient_filter =
+        IngredientFilter::new(game_data, ingredients_whitelist, ingredients_blacklist);
+    if !exclude_tags.is_empty() {
+        ingredient_filter.block(game_data.get_ingredients().keys().copied().filter(
+            |ingredient_id| {
+                exclude_tags
+                    .iter()
+                    .any(|tag| ingredient_tags.has_tag(ingredient_id, tag))
+            },
+        ));
+    }
+    if *exclude_food {
+        if game_data
+            .get_ingredients()
+            .values()
+            .any(|ingredient| ingredient.is_food.is_none())
+        {
+            log::warn!(
+                "The game data was exported by an older version that doesn't record which \
+                 ingredients are food, export it again to exclude them"
+            );
+        }
+        ingredient_filter.block(
+            game_data
+                .get_ingredients()
+                .values()
+                .filter(|ingredient| ingredient.is_food == Some(true))
+                .map(|ingredient| ingredient.get_global_form_id()),
+        );
+    }
+
+    ingredient_filter
+}
+
+/// Finds the potions for the query and sends them to the sink
+fn send_query_results(
+    game_data: &GameData,
+    potions_list: &PotionsList,
+    query: &PotionQuery,
+    ingredient_filter: &IngredientFilter,
+    options: &SuggestPotionsOptions,
+    notes_providers: &[Box<dyn PotionNotesProvider>],
+    sink: &mut dyn SuggestionSink,
+) -> Result<(), anyhow::Error> {
+    let SuggestPotionsOptions {
+        limit: default_limit,
+        effect_aliases,
+        ..
+    } = options;
+    let PotionQuery {
+        effects,
+        primary_effects,
+        targets,
+        potion_type,
+        limit,
+        offset,
+        min_value,
+        ranking,
+        sample,
+        seed,
+        group_by,
+        batch_by_ingredients,
+        name,
+        ..
+    } = query;
+
+    // Names that don't match any effect are warned about
+    let effect_ids = find_magic_effect_ids(game_data, effects, effect_aliases);
+
+    // Use the potions list's indexes for the filters they cover, to avoid scanning all potions
+    let primary_effect_ids = find_magic_effect_ids(game_data, primary_effects, effect_aliases);
+    let min_value = min_value.unwrap_or(0);
+    let is_match = |p: &Potion| {
+        p.gold_value >= min_value
+            && potion_type.map_or(true, |potion_type| p.get_potion_type() == potion_type)
+            // If there's an effects filter, the potion must have at least one of the effects.
+            && (effects.is_empty()
+                || p.effects
+                    .iter()
+                    .any(|eff| effect_ids.contains(&eff.get_global_form_id())))
+            && (targets.is_empty()
+                || p.effects.iter().any(|eff| {
+                    classify_effect(eff.magic_effect)
+                        .map_or(false, |(target, _)| targets.contains(&target))
+                }))
+            && ingredient_filter.allows(p)
+    };
+    // Potions found by target are grouped by what they do to it, unless grouped otherwise
+    let group_by = match (group_by, targets.is_empty()) {
+        (GroupBy::None, false) => &GroupBy::Target,
+        (group_by, _) => group_by,
+    };
+    let get_candidates = || match (primary_effects.is_empty(), potion_type) {
+        (false, _) => potions_list.get_potions_with_primary_effects(&primary_effect_ids, ranking),
+        (true, Some(potion_type)) => potions_list.get_potions_of_type(*potion_type, ranking),
+        (true, None) => potions_list.get_potions(ranking),
+    };
+
+    // When sampling, the sample size takes the place of the limit
+    let limit = match sample {
+        Some(_) => usize::MAX,
+        None => limit.unwrap_or(*default_limit),
+    };
+    // The offset is applied by the potions list if it finds the potions, otherwise below
+    let mapped_potions;
+    let (potions, offset): (Box<dyn Iterator<Item = &Potion> + '_>, usize) = match sample {
+        Some(sample_size) => {
+            let seed = seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_nanos() as u64)
+            });
+            log::info!("Sampling {} potions with seed {}", sample_size, seed);
+            (
+                Box::new(
+                    sample_stratified(
+                        get_candidates().filter(|p| is_match(p)).collect(),
+                        *sample_size,
+                        seed,
+                    )
+                    .into_iter(),
+                ),
+                *offset,
+            )
+        }
+        // Only the first matches are needed, which the potions list can find in parallel
+        None if *group_by == GroupBy::None
+            && ranking.is_by_value()
+            && primary_effects.is_empty() =>
+        {
+            let page = PotionsPage {
+                offset: *offset,
+                limit,
+                min_value,
+            };
+            match potions_list.find_mapped_potions_by_value(page, &is_match) {
+                Some(potions) => {
+                    mapped_potions = potions.context(
+                        "Failed to read the potions cache, delete it to compute the potions again",
+                    )?;
+                    (Box::new(mapped_potions.iter()), 0)
+                }
+                None => (
+                    Box::new(
+                        potions_list
+                            .find_potions_by_value(*potion_type, page, is_match)
+                            .into_iter(),
+                    ),
+                    0,
+                ),
+            }
+        }
+        None => (
+            Box::new(get_candidates().filter(move |p| is_match(p))),
+            *offset,
+        ),
+    };
+
+    let power_factor = potions_list.get_power_factor();
+    let annotate =
+        |p| AnnotatedPotion::new(p, game_data, notes_providers).with_contributions(power_factor);
+    let mut suggested_potions = Vec::new();
+    let results = match group_by {
+        GroupBy::None => {
+            let mut potions = potions.skip(offset).take(limit).collect::<Vec<_>>();
+            // Batching only reorders the potions that would be suggested anyway
+            if *batch_by_ingredients {
+                potions = order_by_shared_ingredients(potions);
+            }
+            suggested_potions.extend(potions.iter().copied());
+            QueryResults::Potions(potions.into_iter().map(annotate).collect())
+        }
+        GroupBy::PrimaryEffect | GroupBy::Target => QueryResults::Groups(
+            match group_by {
+                GroupBy::Target => group_by_target(potions, targets, offset, limit),
+                _ => group_by_primary_effect(potions, offset, limit),
+            }
+            .into_iter()
+            .map(|group| {
+                let mut potions = group.potions;
+                if *batch_by_ingredients {
+                    potions = order_by_shared_ingredients(potions);
+                }
+                suggested_potions.extend(potions.iter().copied());
+                AnnotatedPotionGroup {
+                    heading: group.heading,
+                    potions: potions.into_iter().map(annotate).collect(),
+                }
+            })
+            .collect(),
+        ),
+    };
+    sink.send(QuerySuggestions {
+        name: name.as_deref(),
+        load_order_hash: game_data.get_load_order_hash(),
+        results,
+        needed_ingredients: batch_by_ingredients
+            .then(|| get_distinct_ingredients(suggested_potions)),
+    })
+}
+
+/// The magic effects used in the "fortify loop" exploit, where Fortify Restoration potions and
+/// Fortify Alchemy/Enchanting gear are used to boost each other.
+/// See https://en.uesp.net/wiki/Skyrim:Restoration_Loop
+pub const DEFAULT_FORTIFY_LOOP_EFFECTS: &[&str] = &[
+    "Fortify Restoration",
+    "Fortify Enchanting",
+    "Fortify Alchemy",
+];
+
+/// Options for `suggest_fortify_loop_potions`
+pub struct FortifyLoopOptions {
+    /// Names or editor IDs of the magic effects to look for
+    pub effects: Vec<String>,
+    /// The number of loop iterations to count the required ingredients for
+    pub iterations: u32,
+    /// The maximum number of potions to suggest
+    pub limit: usize,
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions
+    pub alchemy_skill: AlchemySkill,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventory from the save's SKSE co-save
+    pub use_cosave: bool,
+    /// Limits on the ingredients in the inventory that may be used
+    pub inventory_limits: InventoryLimits,
+    /// Aliases used to resolve the effect names
+    pub effect_aliases: EffectAliases,
+}
+
+/// Suggests the potions with the strongest fortify loop effects that can be crafted with the
+/// ingredients in the player's inventory, along with the ingredients needed for the requested
+/// number of loop iterations.
+pub fn suggest_fortify_loop_potions<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    options: &FortifyLoopOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    check_max_ingredients(options.max_ingredients)?;
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+
+    let save_data = read_saves(
+        saves_location,
+        &game_data,
+        options.character.as_deref(),
+        options.strict_load_order,
+        options.use_cosave,
+    )?;
+    let mut player_context = PlayerContext::from_save_data(save_data, options.alchemy_skill);
+    limit_inventory(&game_data, &mut player_context, &options.inventory_limits)?;
+    game_data.set_player_context(player_context);
+
+    let loop_effect_ids =
+        find_magic_effect_ids(&game_data, &options.effects, &options.effect_aliases);
+    if loop_effect_ids.is_empty() {
+        Err(anyhow!(
+            "None of the effects {} exist in the game data",
+            options.effects.join(", ")
+        ))?
+    }
+
+    // Only potions that can be crafted with the ingredients in the inventory are relevant
+    game_data.retain_inventory_ingredients();
+    log::debug!(
+        "{} ingredients in the inventory",
+        game_data.get_ingredients().len()
+    );
+
+    let player_context = game_data
+        .get_player_context()
+        .expect("player context was set above");
+    let mut potions_list = PotionsList::new(&game_data, options.max_ingredients);
+    potions_list.build_potions();
+    let locale = game_data.get_number_locale();
+
+    let get_loop_effect_magnitude = |potion: &Potion| {
+        potion
+            .effects
+            .iter()
+            .filter(|eff| loop_effect_ids.contains(&eff.get_global_form_id()))
+            .map(|eff| eff.get_magnitude())
+            .max()
+    };
+
+    potions_list
+        .get_potions(&Ranking::default())
+        .filter_map(|p| Some((get_loop_effect_magnitude(p)?, p)))
+        // Sort by loop effect magnitude, then by gold value, from largest to smallest
+        .sorted_by(|(magnitude_a, a), (magnitude_b, b)| {
+            magnitude_a
+                .cmp(magnitude_b)
+                .then(a.gold_value.cmp(&b.gold_value))
+                .reverse()
+        })
+        .take(options.limit)
+        .try_for_each(|(_, p)| {
+            let get_count =
+                |ing: &Ingredient| player_context.get_inventory_count(&ing.get_global_form_id());
+            let possible_iterations = p
+                .ingredients
+                .iter()
+                .map(|ing| get_count(ing))
+                .min()
+                .unwrap_or(0);
+
+            writeln!(
+                writer,
+                "{}\n{}\nValue: {} gold\nIngredients (needed for {} iterations / in inventory):\n{}\nPossible iterations with your inventory: {}\n",
+                p.get_potion_name(&game_data),
+                p.get_potion_description(locale),
+                locale.format_integer(p.gold_value),
+                options.iterations,
+                p.ingredients
+                    .iter()
+                    .map(|ing| format!(
+                        "- {}: {} / {}",
+                        get_ingredient_name_or_fallback(ing),
+                        options.iterations,
+                        get_count(ing)
+                    ))
+                    .join("\n"),
+                possible_iterations
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Prints the potion that would be crafted from the ingredients with the given names, along with
+/// the magnitude, duration and gold value of each of its effects.
+pub fn explain_potion<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    ingredient_names: &[String],
+    alchemy_skill: &AlchemySkill,
+    formatter: &PotionFormatter,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+
+    let names = ingredient_names
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>();
+    let potion = game_data
+        .craft(&names, alchemy_skill)
+        .map_err(|err| anyhow!("Cannot craft potion: {}", err))?;
+
+    writeln!(
+        writer,
+        "{}",
+        formatter.format_block(
+            &AnnotatedPotion::new(&potion, &game_data, &get_default_notes_providers())
+                .with_contributions(alchemy_skill.get_power_factor())
+        )
+    )?;
+    for warning in potion.check(&game_data, alchemy_skill.get_power_factor()) {
+        writeln!(writer, "Warning: {}", warning)?;
+    }
+
+    Ok(())
+}
+
+/// Checks which of the potions the player crafted in the game have the values that the alchemy
+/// skill predicts, and solves for the perk bonus that reproduces all of the values at the skill
+/// level and Fortify Alchemy bonus, for mod setups whose modifiers the player doesn't know. Returns
+/// the alchemy skill with that perk bonus.
+///
+/// Perks that only strengthen some effects, such as Physician, can't be folded into the perk
+/// bonus, so the samples should either all or none have effects that they strengthen.
+pub fn calibrate_alchemy_skill<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    samples: &[CalibrationSample],
+    alchemy_skill: &AlchemySkill,
+    mut writer: W,
+) -> Result<AlchemySkill, anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    if samples.is_empty() {
+        Err(anyhow!(
+            "At least one crafted potion is needed to calibrate"
+        ))?
+    }
+    let game_data = import_game_data(import_path, import_options)?;
+    let power_factor = alchemy_skill.get_power_factor();
+    let locale = game_data.get_number_locale();
+
+    let mut power_factor_range = f32::MIN..f32::MAX;
+    let mut num_mismatches = 0;
+    for sample in samples {
+        let ingredients = sample
+            .ingredients
+            .iter()
+            .map(|name| game_data.find_ingredient_by_name(name))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| anyhow!("Cannot calibrate: {}", err))?;
+        let potion = Potion::from_ingredients(&ingredients, &game_data, power_factor)
+            .map_err(|err| anyhow!("Cannot calibrate: {}", err))?;
+        let ingredient_names = ingredients
+            .iter()
+            .map(|ig| get_ingredient_name_or_fallback(ig))
+            .join(", ");
+
+        if potion.gold_value != sample.value {
+            num_mismatches += 1;
+        }
+        writeln!(
+            writer,
+            "{} ({}): {} gold in the game, {} gold with the current settings",
+            potion.get_potion_name(&game_data),
+            ingredient_names,
+            locale.format_integer(sample.value),
+            locale.format_integer(potion.gold_value)
+        )?;
+
+        let sample_range = find_power_factor_range(&ingredients, &game_data, sample.value);
+        if sample_range.is_empty() {
+            Err(anyhow!(
+                "{} ({}) can't be worth {} gold with any alchemy skill, check its value and \
+                 ingredients",
+                potion.get_potion_name(&game_data),
+                ingredient_names,
+                locale.format_integer(sample.value)
+            ))?
+        }
+        power_factor_range = power_factor_range.start.max(sample_range.start)
+            ..power_factor_range.end.min(sample_range.end);
+        if power_factor_range.is_empty() {
+            Err(anyhow!(
+                "No alchemy skill gives all of the values, the value of {} ({}) contradicts the \
+                 ones before it. Perks that only strengthen some effects, such as Physician, can \
+                 cause this.",
+                potion.get_potion_name(&game_data),
+                ingredient_names
+            ))?
+        }
+    }
+
+    match num_mismatches {
+        0 => writeln!(writer, "\nThe current settings give all of the values")?,
+        _ => writeln!(
+            writer,
+            "\nThe current settings give the wrong value for {} of {} potions",
+            num_mismatches,
+            samples.len()
+        )?,
+    }
+
+    let calibrated_power_factor = (power_factor_range.start + power_factor_range.end) / 2.0;
+    let calibrated_skill = AlchemySkill {
+        perk_bonus: solve_perk_bonus(alchemy_skill, calibrated_power_factor),
+        ..*alchemy_skill
+    };
+    writeln!(
+        writer,
+        "At alchemy skill {} with a Fortify Alchemy bonus of {}%, a perk bonus of {}% gives all of \
+         the values (anything from {}% to {}% does)",
+        alchemy_skill.skill_level,
+        locale.format_decimal(alchemy_skill.fortify_alchemy, 1),
+        locale.format_decimal(calibrated_skill.perk_bonus, 1),
+        locale.format_decimal(solve_perk_bonus(alchemy_skill, power_factor_range.start), 1),
+        locale.format_decimal(solve_perk_bonus(alchemy_skill, power_factor_range.end), 1)
+    )?;
+
+    Ok(calibrated_skill)
+}
+
+/// Options for `export_potions`
+pub struct ExportPotionsOptions {
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions
+    pub alchemy_skill: AlchemySkill,
+    pub format: ExportFormat,
+}
+
+/// Computes all possible potions and writes them to a file, for analysis with other tools
+pub fn export_potions<PImport, PExport>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    export_path: PExport,
+    options: &ExportPotionsOptions,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    PExport: AsRef<Path>,
+{
+    check_max_ingredients(options.max_ingredients)?;
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+    game_data.set_player_context(PlayerContext::new(options.alchemy_skill));
+    let mut potions_list = PotionsList::new(&game_data, options.max_ingredients);
+    potions_list.build_potions();
+
+    let file = File::create(export_path.as_ref()).with_context(|| {
+        format!(
+            "Failed to create export file {}",
+            export_path.as_ref().display()
+        )
+    })?;
+    potions_export::write_potions(&potions_list, options.format, BufWriter::new(file))?;
+
+    Ok(())
+}
+
+/// Options for `export_pair_matrix`
+pub struct ExportMatrixOptions {
+    /// The player's alchemy skill, which determines the value of the potions
+    pub alchemy_skill: AlchemySkill,
+    pub format: MatrixFormat,
+}
+
+/// Writes an N×N matrix of the ingredients with the gold value of the potion that each pair of
+/// them makes, e.g. for spreadsheets or heatmaps of which ingredients go well together
+pub fn export_pair_matrix<PImport, PExport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    export_path: PExport,
+    options: &ExportMatrixOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    PExport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+    let min_ingredients = game_data.get_crafting_rules().min_ingredients;
+    if min_ingredients > MIN_INGREDIENTS {
+        Err(anyhow!(
+            "The crafting rules require at least {} ingredients per potion, so no pair of \
+             ingredients makes a potion",
+            min_ingredients
+        ))?
+    }
+
+    let file = File::create(export_path.as_ref()).with_context(|| {
+        format!(
+            "Failed to create export file {}",
+            export_path.as_ref().display()
+        )
+    })?;
+    pair_matrix::write_pair_matrix(
+        &game_data,
+        options.alchemy_skill.get_power_factor(),
+        options.format,
+        BufWriter::new(file),
+    )?;
+    let locale = game_data.get_number_locale();
+    writeln!(
+        writer,
+        "Wrote the pairs of {} ingredients to {}",
+        locale.format_integer(game_data.get_ingredients().len() as u64),
+        export_path.as_ref().display()
+    )?;
+
+    Ok(())
+}
+
+/// Options for `export_graph`
+pub struct ExportGraphOptions {
+    pub format: GraphFormat,
+    /// How many of the most valuable potions to add to the graph, each connected to its
+    /// ingredients, or 0 for none
+    pub num_potions: usize,
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the value of the potions
+    pub alchemy_skill: AlchemySkill,
+}
+
+/// Writes the graph of the ingredients and the effects they have, with their plugins, values and
+/// hostility as attributes, e.g. to visualize the alchemy of a mod list in Gephi
+pub fn export_graph<PImport, PExport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    export_path: PExport,
+    options: &ExportGraphOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    PExport: AsRef<Path>,
+    W: Write,
+{
+    let mut game_data = import_game_data(import_path, import_options)?;
+    game_data.set_player_context(PlayerContext::new(options.alchemy_skill));
+    let mut graph = alchemy_graph::AlchemyGraph::from_game_data(&game_data);
+    if options.num_potions > 0 {
+        check_max_ingredients(options.max_ingredients)?;
+        let mut potions_list = PotionsList::new(&game_data, options.max_ingredients);
+        potions_list.build_potions();
+        graph.add_potions(
+            &game_data,
+            potions_list
+                .get_potions(&Ranking::default())
+                .take(options.num_potions),
+        );
+    }
+
+    let file = File::create(export_path.as_ref()).with_context(|| {
+        format!(
+            "Failed to create export file {}",
+            export_path.as_ref().display()
+        )
+    })?;
+    graph.write(options.format, BufWriter::new(file))?;
+    let locale = game_data.get_number_locale();
+    writeln!(
+        writer,
+        "Wrote a graph of {} nodes and {} edges to {}",
+        locale.format_integer(graph.nodes.len() as u64),
+        locale.format_integer(graph.edges.len() as u64),
+        export_path.as_ref().display()
+    )?;
+
+    Ok(())
+}
+
+/// Prints where the game data came from: its load order hash, which identifies the plugins it was
+/// exported from, and the number of plugins, ingredients and magic effects in it
+pub fn print_game_data_info<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path.as_ref(), import_options)?;
+    let locale = game_data.get_number_locale();
+
+    writeln!(writer, "Game data: {}", import_path.as_ref().display())?;
+    match game_data.get_load_order_hash() {
+        Some(load_order_hash) => writeln!(writer, "Load order hash: {}", load_order_hash)?,
+        None => writeln!(
+            writer,
+            "Load order hash: unknown, export the game data again to add it"
+        )?,
+    }
+    writeln!(
+        writer,
+        "Active plugins: {}",
+        locale.format_integer(game_data.get_plugins().len() as u64)
+    )?;
+    writeln!(
+        writer,
+        "Ingredients: {}",
+        locale.format_integer(game_data.get_ingredients().len() as u64)
+    )?;
+    writeln!(
+        writer,
+        "Magic effects: {}",
+        locale.format_integer(game_data.get_magic_effects().len() as u64)
+    )?;
+
+    Ok(())
+}
+
+/// Prints the ingredients whose effects were removed or changed by an overriding plugin
+pub fn check_ingredient_overrides<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+    let ingredient_overrides = game_data.get_ingredient_overrides();
+
+    if ingredient_overrides.is_empty() {
+        writeln!(
+            writer,
+            "No ingredients had effects removed or changed by overriding plugins"
+        )?;
+        return Ok(());
+    }
+
+    for ingredient_override in ingredient_overrides {
+        writeln!(writer, "{}", ingredient_override)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the magic effects of ingredients whose hostile flag disagrees with their name, e.g. a
+/// "Damage" effect that is not hostile. Such effects turn poisons into potions or vice versa, and
+/// can be corrected with hostility overrides, e.g. in the file at `overrides_path`.
+pub fn check_effect_hostility<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    overrides_path: Option<&Path>,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+    let used_effect_ids = game_data
+        .get_ingredients()
+        .values()
+        .flat_map(|ing| ing.effects.iter().map(|eff| eff.get_global_form_id()))
+        .collect::<AHashSet<_>>();
+
+    let describe = |is_hostile: bool| if is_hostile { "hostile" } else { "beneficial" };
+    let mut num_mismatches = 0;
+    for magic_effect in used_effect_ids
+        .iter()
+        .filter_map(|form_id| game_data.get_magic_effect(form_id))
+        .sorted_by_key(|mgef| mgef.get_global_form_id())
+    {
+        let guessed_is_hostile = match magic_effect
+            .name
+            .as_deref()
+            .and_then(guess_hostility_from_name)
+        {
+            Some(guessed_is_hostile) => guessed_is_hostile,
+            None => continue,
+        };
+        if guessed_is_hostile == magic_effect.is_hostile {
+            continue;
+        }
+
+        let is_overridden = import_options
+            .hostility_overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(&magic_effect.editor_id))
+            .is_some();
+        writeln!(
+            writer,
+            "{} ({}, {}) is {}{}, but its name suggests it is {}",
+            magic_effect.name.as_deref().unwrap_or_default(),
+            magic_effect.editor_id,
+            magic_effect
+                .get_global_form_id()
+                .to_display_with(game_data.get_load_order()),
+            describe(magic_effect.is_hostile),
+            if is_overridden {
+                " according to the hostility overrides"
+            } else {
+                ""
+            },
+            describe(guessed_is_hostile)
+        )?;
+        num_mismatches += 1;
+    }
+
+    if num_mismatches == 0 {
+        writeln!(
+            writer,
+            "The hostility of all ingredient effects matches their names"
+        )?;
+    } else if let Some(path) = overrides_path {
+        writeln!(
+            writer,
+            "\nTo correct an effect, add its editor ID to the hostile or beneficial list in {}",
+            path.display()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Options for `suggest_brew_plan`
+pub struct BrewPlanOptions {
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions
+    pub alchemy_skill: AlchemySkill,
+    /// Which potions and poisons to brew first
+    pub ranking: Ranking,
+    /// If specified, the total weight of the consumed ingredients stays within this budget
+    pub carry_budget: Option<f32>,
+    /// The weight of a single crafted potion or poison, usually `DEFAULT_POTION_WEIGHT`
+    pub potion_weight: f32,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventory from the save's SKSE co-save
+    pub use_cosave: bool,
+    /// Limits on the ingredients in the inventory that may be brewed with
+    pub inventory_limits: InventoryLimits,
+}
+
+/// Prints how much Alchemy XP a brew plan gives and which level it takes the skill from and to
+fn print_xp_projection<W: Write>(
+    start: SkillProgress,
+    end: SkillProgress,
+    total_xp: f32,
+    settings: &AlchemyXpSettings,
+    locale: NumberLocale,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let total_xp = locale.format_integer(total_xp.round() as u64);
+    match end.level {
+        _ if start.level >= MAX_SKILL_LEVEL => writeln!(
+            writer,
+            "Brewing them gives {} Alchemy XP, but your Alchemy skill is already {}",
+            total_xp, MAX_SKILL_LEVEL
+        ),
+        MAX_SKILL_LEVEL => writeln!(
+            writer,
+            "Brewing them gives {} Alchemy XP, which takes your Alchemy skill from {} to {}",
+            total_xp, start.level, MAX_SKILL_LEVEL
+        ),
+        level => writeln!(
+            writer,
+            "Brewing them gives {} Alchemy XP, which takes your Alchemy skill from {} to {} ({}% \
+             of the way to {})",
+            total_xp,
+            start.level,
+            level,
+            (end.get_fraction(settings) * 100.0).floor(),
+            level + 1
+        ),
+    }
+}
+
+/// Plans which potions to brew with the ingredients in the player's inventory, brewing the best
+/// potions according to the ranking first, and prints the plan.
+pub fn suggest_brew_plan<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    options: &BrewPlanOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    check_max_ingredients(options.max_ingredients)?;
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+
+    let save_data = read_saves(
+        saves_location,
+        &game_data,
+        options.character.as_deref(),
+        options.strict_load_order,
+        options.use_cosave,
+    )?;
+    let mut player_context = PlayerContext::from_save_data(save_data, options.alchemy_skill);
+    limit_inventory(&game_data, &mut player_context, &options.inventory_limits)?;
+    game_data.set_player_context(player_context);
+
+    game_data.retain_inventory_ingredients();
+    log::debug!(
+        "{} ingredients in the inventory",
+        game_data.get_ingredients().len()
+    );
+
+    if options.carry_budget.is_some()
+        && game_data
+            .get_ingredients()
+            .values()
+            .any(|ing| ing.weight.is_none())
+    {
+        Err(anyhow!(
+            "Game data was exported by an older version, re-export it to use a carry budget"
+        ))?
+    }
+
+    let player_context = game_data
+        .get_player_context()
+        .expect("player context was set above");
+    let mut potions_list = PotionsList::new(&game_data, options.max_ingredients);
+    potions_list.build_potions();
+
+    let plan = plan_brews(
+        potions_list.get_potions(&options.ranking),
+        &player_context.inventory,
+        options.carry_budget,
+        options.potion_weight,
+    );
+
+    if plan.steps.is_empty() {
+        writeln!(
+            writer,
+            "You can't brew any potions with the ingredients in your inventory"
+        )?;
+        return Ok(());
+    }
+
+    let locale = game_data.get_number_locale();
+    let xp_settings = game_data.get_alchemy_xp_settings();
+    let start = SkillProgress::new(options.alchemy_skill.skill_level);
+    let projection = project_xp(&plan, start, &xp_settings);
+    let mut level = start.level;
+    for ((step, xp), progress) in plan
+        .steps
+        .iter()
+        .zip(&projection.step_xp)
+        .zip(&projection.step_progress)
+    {
+        writeln!(
+            writer,
+            "{}x {} ({} gold each, {} XP in total)\n{}{}\n",
+            step.count,
+            step.potion.get_potion_name(&game_data),
+            locale.format_integer(step.potion.gold_value),
+            locale.format_integer(xp.round() as u64),
+            step.potion
+                .ingredients
+                .iter()
+                .map(|ing| format!("- {}", get_ingredient_name_or_fallback(ing)))
+                .join("\n"),
+            match progress.level > level {
+                true => format!("\nYour Alchemy skill reaches {}", progress.level),
+                false => String::new(),
+            }
+        )?;
+        level = progress.level;
+    }
+
+    writeln!(
+        writer,
+        "Total: {} potions worth {} gold, using {} weight of ingredients{}",
+        locale.format_integer(plan.steps.iter().map(|step| step.count).sum::<u32>()),
+        locale.format_integer(plan.total_value),
+        locale.format_decimal(plan.ingredients_weight, 1),
+        match options.carry_budget {
+            Some(budget) => format!(" (budget: {})", locale.format_decimal(budget, 1)),
+            None => String::new(),
+        }
+    )?;
+    let weight_change = plan.potions_weight - plan.ingredients_weight;
+    writeln!(
+        writer,
+        "The potions weigh {}, so brewing them changes your carry weight by {}{}",
+        locale.format_decimal(plan.potions_weight, 1),
+        if weight_change > 0.0 { "+" } else { "" },
+        locale.format_decimal(weight_change, 1)
+    )?;
+    // The XP gained towards the next level at the start isn't known, so the projection starts at
+    // the beginning of the level
+    print_xp_projection(
+        start,
+        projection.step_progress.last().copied().unwrap_or(start),
+        projection.total_xp,
+        &xp_settings,
+        locale,
+        &mut writer,
+    )?;
+
+    Ok(())
+}
+
+/// Options for `plan_effect_coverage`
+pub struct CoveragePlanOptions {
+    /// Names or editor IDs of the effects that potions should be able to have
+    pub effects: Vec<String>,
+    /// What to minimize when choosing the ingredients
+    pub cost: CoverageCost,
+    /// Aliases used to resolve the effect names
+    pub effect_aliases: EffectAliases,
+}
+
+/// Prints the fewest (or cheapest) ingredients whose pairs can make potions with each of the
+/// effects, e.g. to stock an alchemy bag for the potions you usually need, along with a pair of
+/// ingredients for each effect.
+pub fn plan_effect_coverage<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    options: &CoveragePlanOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+
+    if options.cost == CoverageCost::Value
+        && game_data
+            .get_ingredients()
+            .values()
+            .any(|ing| ing.value.is_none())
+    {
+        Err(anyhow!(
+            "Game data was exported by an older version, re-export it to minimize the value"
+        ))?
+    }
+
+    let effect_ids = find_magic_effect_ids(&game_data, &options.effects, &options.effect_aliases);
+    if effect_ids.is_empty() {
+        Err(anyhow!(
+            "None of the effects {} exist in the game data",
+            options.effects.join(", ")
+        ))?
+    }
+
+    let plan = plan_coverage(&game_data, &effect_ids, options.cost);
+    let locale = game_data.get_number_locale();
+    let get_effect_name = |effect_id: &GlobalFormId| {
+        game_data.get_magic_effect(effect_id).map_or_else(
+            || Cow::Owned(effect_id.to_display_with(game_data.get_load_order())),
+            get_magic_effect_name_or_fallback,
+        )
+    };
+
+    writeln!(
+        writer,
+        "{} ingredients worth {} gold:",
+        plan.ingredients.len(),
+        locale.format_integer(
+            plan.ingredients
+                .iter()
+                .map(|ing| ing.value.unwrap_or(0))
+                .sum::<u32>()
+        )
+    )?;
+    for ingredient in &plan.ingredients {
+        writeln!(writer, "- {}", get_ingredient_name_or_fallback(ingredient))?;
+    }
+
+    writeln!(writer, "\nEffects:")?;
+    for effect_id in effect_ids.iter().sorted_by_key(|id| get_effect_name(id)) {
+        if let Some((a, b)) = plan.get_pair_for_effect(effect_id) {
+            writeln!(
+                writer,
+                "- {}: {} + {}",
+                get_effect_name(effect_id),
+                get_ingredient_name_or_fallback(a),
+                get_ingredient_name_or_fallback(b)
+            )?;
+        }
+    }
+    for effect_id in &plan.uncoverable_effects {
+        writeln!(
+            writer,
+            "- {}: fewer than two ingredients have this effect, so no potion can have it",
+            get_effect_name(effect_id)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Prints the character, level, location and playtime of each save file, most recently modified
+/// first. If a character name is given, only that character's saves are printed.
+pub fn list_saves<W: Write>(
+    saves_location: &SavesLocation,
+    character_name: Option<&str>,
+    mut writer: W,
+) -> Result<(), anyhow::Error> {
+    let saves = save_parser::list_saves(saves_location)?
+        .into_iter()
+        .filter(|save| character_name.map_or(true, |name| save.is_character(name)))
+        .collect::<Vec<_>>();
+
+    if saves.is_empty() {
+        writeln!(writer, "No save files found")?;
+        return Ok(());
+    }
+
+    for save in saves {
+        writeln!(
+            writer,
+            "{}: Save {}, {} (level {}), {}, played {}",
+            save.path.file_name().map_or_else(
+                || save.path.display().to_string(),
+                |name| name.to_string_lossy().to_string()
+            ),
+            save.save_number,
+            save.character_name,
+            save.character_level,
+            save.location,
+            save.playtime
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Options for `report_stock_trends`
+pub struct StockTrendsOptions {
+    /// If specified, the saves of the character with this name are read instead of the saves of
+    /// the character of the latest save
+    pub character: Option<String>,
+    /// The number of most recent saves to compare
+    pub max_saves: usize,
+    /// The maximum number of ingredients to list as accumulated and as consumed
+    pub limit: usize,
+    /// Whether to fail instead of warn when the saves' plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventories from the saves' SKSE co-saves
+    pub use_cosave: bool,
+}
+
+/// Formats the number with a sign, e.g. `+12` or `-3`
+fn format_signed(n: i64, locale: NumberLocale) -> String {
+    format!(
+        "{}{}",
+        if n < 0 { "-" } else { "+" },
+        locale.format_integer(n.unsigned_abs())
+    )
+}
+
+/// Compares the ingredients in the inventories of several saves of one character and prints which
+/// ingredients the player accumulates and which they consume, to help decide what to farm or buy
+pub fn report_stock_trends<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    options: &StockTrendsOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    if options.max_saves < 2 {
+        Err(anyhow!(
+            "At least 2 saves are needed to compare the ingredient stock"
+        ))?
+    }
+
+    let game_data = import_game_data(import_path, import_options)?;
+    let saves = read_character_saves(
+        saves_location,
+        &game_data,
+        options.character.as_deref(),
+        options.max_saves,
+        options.strict_load_order,
+        options.use_cosave,
+    )?;
+    let (first_save, last_save) = match (saves.first(), saves.last()) {
+        (Some((first_save, _)), Some((last_save, _))) if saves.len() >= 2 => {
+            (first_save, last_save)
+        }
+        _ => Err(anyhow!(
+            "Found only {} save of {}, at least 2 are needed to compare the ingredient stock",
+            saves.len(),
+            saves.first().map_or("the character", |(metadata, _)| {
+                metadata.character_name.as_str()
+            })
+        ))?,
+    };
+
+    let snapshots = saves
+        .iter()
+        .map(|(metadata, save_data)| StockSnapshot {
+            playtime_hours: parse_playtime_hours(&metadata.playtime),
+            inventory: save_data
+                .inventory
+                .iter()
+                .map(|entry| (entry.form_id, entry.count))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    let trends = compute_stock_trends(&snapshots);
+    let locale = game_data.get_number_locale();
+
+    writeln!(
+        writer,
+        "Ingredient stock of {} over {} saves, from save {} ({}, played {}) to save {} ({}, played \
+         {})\n",
+        last_save.character_name,
+        saves.len(),
+        first_save.save_number,
+        first_save.location,
+        first_save.playtime,
+        last_save.save_number,
+        last_save.location,
+        last_save.playtime
+    )?;
+
+    let format_trend = |trend: &StockTrend| {
+        format!(
+            "- {}: {} (now {}, {} gained and {} used{})",
+            game_data
+                .get_ingredient(&trend.ingredient_id)
+                .map_or(Cow::Owned(trend.ingredient_id.to_string()), |ig| {
+                    get_ingredient_name_or_fallback(ig)
+                }),
+            format_signed(trend.get_net_change(), locale),
+            locale.format_integer(trend.counts.last().copied().unwrap_or(0)),
+            locale.format_integer(trend.gained),
+            locale.format_integer(trend.used),
+            trend.change_per_day.map_or(String::new(), |change| format!(
+                ", {}{} per in-game day",
+                if change < 0.0 { "" } else { "+" },
+                locale.format_decimal(change, 1)
+            ))
+        )
+    };
+    let accumulated = trends
+        .iter()
+        .filter(|trend| trend.get_net_change() > 0)
+        .take(options.limit)
+        .map(format_trend)
+        .collect::<Vec<_>>();
+    let consumed = trends
+        .iter()
+        .rev()
+        .filter(|trend| trend.get_net_change() < 0)
+        .take(options.limit)
+        .map(format_trend)
+        .collect::<Vec<_>>();
+
+    match accumulated.is_empty() {
+        true => writeln!(writer, "No ingredients were accumulated\n")?,
+        false => writeln!(writer, "Accumulated:\n{}\n", accumulated.join("\n"))?,
+    }
+    match consumed.is_empty() {
+        true => writeln!(writer, "No ingredients were consumed")?,
+        false => writeln!(writer, "Consumed:\n{}", consumed.join("\n"))?,
+    }
+
+    Ok(())
+}
+
+/// Options for `appraise_ingredient`
+pub struct AppraiseOptions {
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions
+    pub alchemy_skill: AlchemySkill,
+    /// Whether to also find the best potion the ingredient makes with the ingredients in the
+    /// player's inventory, which requires reading the save
+    pub use_inventory: bool,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventory from the save's SKSE co-save
+    pub use_cosave: bool,
+    /// How to print the potions
+    pub formatter: PotionFormatter,
+}
+
+/// Prints what one unit of the ingredient is worth: the most valuable potion it makes with
+/// ingredients from the base game, which are available in every playthrough, and optionally the
+/// most valuable potion it makes with the ingredients in the player's inventory. Only the
+/// combinations containing the ingredient are computed.
+pub fn appraise_ingredient<PImport, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    ingredient_name: &str,
+    options: &AppraiseOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    W: Write,
+{
+    check_max_ingredients(options.max_ingredients)?;
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+    if options.use_inventory {
+        let save_data = read_saves(
+            saves_location,
+            &game_data,
+            options.character.as_deref(),
+            options.strict_load_order,
+            options.use_cosave,
+        )?;
+        game_data.set_player_context(PlayerContext::from_save_data(
+            save_data,
+            options.alchemy_skill,
+        ));
+    }
+
+    let ingredient = game_data
+        .find_ingredient_by_name(ingredient_name)
+        .map_err(|err| anyhow!("Cannot appraise ingredient: {}", err))?;
+    let ingredient_name = get_ingredient_name_or_fallback(ingredient);
+    let power_factor = options.alchemy_skill.get_power_factor();
+    let notes_providers = get_default_notes_providers();
+    let locale = game_data.get_number_locale();
+
+    let print_best_potion = |writer: &mut W, heading: &str, potion: Option<Potion>| match potion {
+        Some(potion) => writeln!(
+            writer,
+            "{}: {} gold\n{}\n",
+            heading,
+            locale.format_integer(potion.gold_value),
+            options.formatter.format_block(&AnnotatedPotion::new(
+                &potion,
+                &game_data,
+                &notes_providers
+            ))
+        ),
+        None => writeln!(writer, "{}: no potion\n", heading),
+    };
+
+    if let Some(revealed_effect) = ingredient
+        .get_effects_revealed_by_eating(0)
+        .and_then(|effects| effects.first().copied())
+        .and_then(|eff| game_data.get_magic_effect(&eff.get_global_form_id()))
+    {
+        writeln!(
+            writer,
+            "Eating {} reveals {}\n",
+            ingredient_name,
+            get_magic_effect_name_or_fallback(revealed_effect)
+        )?;
+    }
+
+    print_best_potion(
+        &mut writer,
+        &format!(
+            "Best potion with {} and base game ingredients",
+            ingredient_name
+        ),
+        find_best_potion_with(
+            &game_data,
+            ingredient,
+            options.max_ingredients,
+            power_factor,
+            |partner| {
+                game_data
+                    .get_plugin_name(&partner.get_global_form_id())
+                    .map_or(false, is_base_game_plugin)
+            },
+        ),
+    )?;
+
+    if let Some(player_context) = game_data.get_player_context() {
+        print_best_potion(
+            &mut writer,
+            &format!("Best potion with {} and your inventory", ingredient_name),
+            find_best_potion_with(
+                &game_data,
+                ingredient,
+                options.max_ingredients,
+                power_factor,
+                |partner| player_context.get_inventory_count(&partner.get_global_form_id()) > 0,
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Bookmarks the potion crafted from the ingredients under the name, in the bookmarks file at
+/// `bookmarks_path`. The ingredients are stored by their plugin and their ID within it, so the
+/// bookmark keeps working when the load order changes.
+pub fn add_recipe_bookmark<PImport, PBookmarks, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    bookmarks_path: PBookmarks,
+    name: &str,
+    ingredient_names: &[String],
+    alchemy_skill: &AlchemySkill,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    PBookmarks: AsRef<Path>,
+    W: Write,
+{
+    let game_data = import_game_data(import_path, import_options)?;
+
+    let names = ingredient_names
+        .iter()
+        .map(|name| name.as_str())
+        .collect::<Vec<_>>();
+    let potion = game_data
+        .craft(&names, alchemy_skill)
+        .map_err(|err| anyhow!("Cannot bookmark potion: {}", err))?;
+
+    let mut bookmarks = RecipeBookmarks::load(bookmarks_path.as_ref())?;
+    bookmarks.add(RecipeBookmark::new(
+        name.to_string(),
+        &potion,
+        &game_data,
+        alchemy_skill.get_power_factor(),
+    )?)?;
+    bookmarks.save(bookmarks_path.as_ref())?;
+
+    let locale = game_data.get_number_locale();
+    writeln!(
+        writer,
+        "Bookmarked {} ({} gold) as \"{}\"",
+        potion.get_potion_name(&game_data),
+        locale.format_integer(potion.gold_value),
+        name
+    )?;
+
+    Ok(())
+}
+
+/// Removes the bookmark with the name, ignoring case, from the bookmarks file at `bookmarks_path`
+pub fn remove_recipe_bookmark<PBookmarks, W>(
+    bookmarks_path: PBookmarks,
+    name: &str,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PBookmarks: AsRef<Path>,
+    W: Write,
+{
+    let mut bookmarks = RecipeBookmarks::load(bookmarks_path.as_ref())?;
+    if !bookmarks.remove(name) {
+        Err(anyhow!("There is no bookmark named \"{}\"", name))?
+    }
+    bookmarks.save(bookmarks_path.as_ref())?;
+
+    writeln!(writer, "Removed bookmark \"{}\"", name)?;
+
+    Ok(())
+}
+
+/// Prints the bookmarks in the bookmarks file at `bookmarks_path` as they were when they were
+/// added, without reading the game data. The gold values are formatted with the locale.
+pub fn list_recipe_bookmarks<PBookmarks, W>(
+    bookmarks_path: PBookmarks,
+    locale: NumberLocale,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PBookmarks: AsRef<Path>,
+    W: Write,
+{
+    let bookmarks = RecipeBookmarks::load(bookmarks_path.as_ref())?;
+    if bookmarks.is_empty() {
+        writeln!(writer, "No bookmarks, add one with `bookmarks add`")?;
+    }
+    for bookmark in bookmarks.iter() {
+        writeln!(
+            writer,
+            "{}: {} ({} gold when bookmarked)",
+            bookmark.name,
+            bookmark.get_ingredient_names().join(", "),
+            locale.format_integer(bookmark.get_gold_value())
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Options for `check_recipe_bookmarks`
+pub struct CheckBookmarksOptions {
+    /// The player's alchemy skill, which determines the current values of the potions
+    pub alchemy_skill: AlchemySkill,
+    /// Whether to also check that the ingredients are in the player's inventory, which requires
+    /// reading the save
+    pub use_inventory: bool,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventory from the save's SKSE co-save
+    pub use_cosave: bool,
+}
+
+/// Checks the bookmarks in the bookmarks file at `bookmarks_path` against the current game data
+/// and prints what changed: ingredients that are no longer in the game data, potions that can no
+/// longer be crafted, and potions whose value at the skill they were bookmarked with changed
+/// because a mod changed their ingredients or effects. Optionally also checks that the ingredients
+/// are in the player's inventory and prints how many of each potion the player can brew.
+pub fn check_recipe_bookmarks<PImport, PBookmarks, W>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    bookmarks_path: PBookmarks,
+    options: &CheckBookmarksOptions,
+    mut writer: W,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+    PBookmarks: AsRef<Path>,
+    W: Write,
+{
+    let bookmarks = RecipeBookmarks::load(bookmarks_path.as_ref())?;
+    if bookmarks.is_empty() {
+        writeln!(writer, "No bookmarks, add one with `bookmarks add`")?;
+        return Ok(());
+    }
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+    if options.use_inventory {
+        let save_data = read_saves(
+            saves_location,
+            &game_data,
+            options.character.as_deref(),
+            options.strict_load_order,
+            options.use_cosave,
+        )?;
+        game_data.set_player_context(PlayerContext::from_save_data(
+            save_data,
+            options.alchemy_skill,
+        ));
+    }
+    let power_factor = options.alchemy_skill.get_power_factor();
+    let locale = game_data.get_number_locale();
+
+    for bookmark in bookmarks.iter() {
+        let ingredients = match bookmark.resolve(&game_data) {
+            Ok(ingredients) => ingredients,
+            Err(missing) => {
+                writeln!(
+                    writer,
+                    "{}: ingredients no longer in the game data: {}",
+                    bookmark.name,
+                    missing.join(", ")
+                )?;
+                continue;
+            }
+        };
+        let potion = match Potion::from_ingredients(&ingredients, &game_data, bookmark.power_factor)
+        {
+            Ok(potion) => potion,
+            Err(err) => {
+                writeln!(
+                    writer,
+                    "{}: can no longer be crafted: {}",
+                    bookmark.name, err
+                )?;
+                continue;
+            }
+        };
+
+        let mut notes = Vec::new();
+        if potion.gold_value != bookmark.gold_value {
+            notes.push(format!(
+                "worth {} gold instead of {} gold at the skill it was bookmarked with, a mod \
+                 changed its ingredients or effects",
+                locale.format_integer(potion.gold_value),
+                locale.format_integer(bookmark.gold_value)
+            ));
+        }
+        if let Some(player_context) = game_data.get_player_context() {
+            let counts = ingredients
+                .iter()
+                .map(|ig| player_context.get_inventory_count(&ig.get_global_form_id()))
+                .collect::<Vec<_>>();
+            let missing = ingredients
+                .iter()
+                .zip(&counts)
+                .filter(|(_, count)| **count == 0)
+                .map(|(ig, _)| get_ingredient_name_or_fallback(ig))
+                .collect::<Vec<_>>();
+            match missing.is_empty() {
+                true => notes.push(format!(
+                    "you can brew {}",
+                    locale.format_integer(counts.iter().min().copied().unwrap_or(0))
+                )),
+                false => notes.push(format!("not in your inventory: {}", missing.join(", "))),
+            }
+        }
+
+        let current_value = Potion::from_ingredients(&ingredients, &game_data, power_factor)
+            .map_or(0, |potion| potion.gold_value);
+        writeln!(
+            writer,
+            "{}: {}, {} gold{}",
+            bookmark.name,
+            potion.get_potion_name(&game_data),
+            locale.format_integer(current_value),
+            notes.iter().map(|note| format!("\n  - {}", note)).join("")
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{ingredient_with_effect_ids, magic_effect};
+
+    use super::*;
+
+    fn ingredient(
+        form_id: GlobalFormId,
+        editor_id: &str,
+        effect_ids: &[GlobalFormId],
+    ) -> Ingredient {
+        Ingredient {
+            editor_id: editor_id.to_owned(),
+            ..ingredient_with_effect_ids(form_id, effect_ids)
+        }
+    }
+
+    #[test]
+    fn previous_exports_are_numbered_before_the_extensions() {
+        assert_eq!(
+            get_previous_export_path(Path::new("out/game_data.json.gz"), 2),
+            Path::new("out/game_data.2.json.gz")
+        );
+        assert_eq!(
+            get_previous_export_path(Path::new("game_data"), 1),
+            Path::new("game_data.1")
+        );
+    }
+
+    #[test]
+    fn rotate_exports_keeps_at_most_keep_previous_exports() {
+        let dir =
+            std::env::temp_dir().join(format!("alchemy-rotate-exports-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let export_path = dir.join("game_data.json.gz");
+        let read = |file_name: &str| fs::read_to_string(dir.join(file_name)).ok();
+
+        for export in ["first", "second", "third", "fourth"] {
+            if export_path.exists() {
+                rotate_exports(&export_path, 2).unwrap();
+            }
+            fs::write(&export_path, export).unwrap();
+        }
+        assert_eq!(read("game_data.json.gz").as_deref(), Some("fourth"));
+        assert_eq!(read("game_data.1.json.gz").as_deref(), Some("third"));
+        assert_eq!(read("game_data.2.json.gz").as_deref(), Some("second"));
+        assert_eq!(read("game_data.3.json.gz"), None);
+
+        // Without previous exports to keep, the export is left to be overwritten
+        rotate_exports(&export_path, 0).unwrap();
+        assert_eq!(read("game_data.json.gz").as_deref(), Some("fourth"));
+        assert_eq!(read("game_data.1.json.gz").as_deref(), Some("third"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn injected_records_are_keyed_by_their_master() {
+        // Skyrim.esm, Injector.esp, Overrider.esp
+        let restore_health = GlobalFormId::new(0, 0x3eb15);
+        let injected_effect = GlobalFormId::new(0, 0x800);
+        let injected_ingredient = GlobalFormId::new(0, 0x801);
+        let mut records = PluginRecords::default();
+
+        let num_injected = records.add_plugin(
+            0,
+            vec![ingredient(
+                GlobalFormId::new(0, 0x77e1c),
+                "MountainFlower01Blue",
+                &[restore_health],
+            )],
+            vec![magic_effect(restore_health)],
+        );
+        assert_eq!(num_injected, (0, 0));
+
+        let num_injected = records.add_plugin(
+            1,
+            vec![ingredient(
+                injected_ingredient,
+                "InjectedIngredient",
+                &[restore_health, injected_effect],
+            )],
+            vec![magic_effect(injected_effect)],
+        );
+        assert_eq!(num_injected, (1, 1));
+
+        // Overriding an injected record doesn't inject it again
+        let num_injected = records.add_plugin(
+            2,
+            vec![ingredient(
+                injected_ingredient,
+                "InjectedIngredientOverride",
+                &[injected_effect],
+            )],
+            vec![],
+        );
+        assert_eq!(num_injected, (0, 0));
+
+        assert_eq!(records.ingredients.len(), 2);
+        assert_eq!(
+            records.ingredients[&injected_ingredient].editor_id,
+            "InjectedIngredientOverride"
+        );
+        assert_eq!(
+            records.original_ingredients[&injected_ingredient].editor_id,
+            "InjectedIngredient"
+        );
+        assert_eq!(
+            records.overriding_plugin_indexes.get(&injected_ingredient),
+            Some(&2)
+        );
+        assert!(records.magic_effects.contains_key(&injected_effect));
+        assert!(records.ingredient_effect_ids.contains(&injected_effect));
+    }
+
+    /// Writes the example game data to a new temporary directory, and returns the directory and
+    /// the path of the game data
+    fn write_example_game_data(test_name: &str) -> (PathBuf, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("alchemy-{}-{}", test_name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let import_path = dir.join("game_data.json");
+        fs::write(
+            &import_path,
+            include_bytes!("../fixtures/example_game_data.json"),
+        )
+        .unwrap();
+        (dir, import_path)
+    }
+
+    #[test]
+    fn junk_effects_are_left_out_of_potions() {
+        let (dir, import_path) = write_example_game_data("junk-effects");
+
+        // Junk effects can be given by name or editor ID, and unknown ones are only warned about
+        let options = ImportOptions {
+            junk_effects: vec![
+                "Fortify Smithing".to_string(),
+                "ExampleWeaknessToFrost".to_string(),
+                "Fortify Nonexistent".to_string(),
+            ],
+            ..Default::default()
+        };
+        let game_data = import_game_data(&import_path, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Frost Lily and Marsh Cap only shared the junk effects
+        assert!(game_data
+            .craft(&["Frost Lily", "Marsh Cap"], &AlchemySkill::default())
+            .is_err());
+        // Frost Lily and Salt Crystal still share Restore Health
+        let potion = game_data
+            .craft(&["Frost Lily", "Salt Crystal"], &AlchemySkill::default())
+            .unwrap();
+        assert_eq!(
+            potion
+                .effects
+                .iter()
+                .map(|eff| eff.magic_effect.editor_id.as_str())
+                .collect::<Vec<_>>(),
+            ["ExampleRestoreHealth"]
+        );
+
+        let mut potions_list = PotionsList::new(&game_data, MAX_SUPPORTED_INGREDIENTS);
+        potions_list.build_potions();
+        assert!(potions_list
+            .get_potions(&Ranking::default())
+            .flat_map(|potion| &potion.effects)
+            .all(|eff| !matches!(
+                eff.magic_effect.editor_id.as_str(),
+                "ExampleFortifySmithing" | "ExampleWeaknessToFrost"
+            )));
+    }
+
+    #[test]
+    fn calibration_finds_the_perk_bonus_of_crafted_potions() {
+        let (dir, import_path) = write_example_game_data("calibration");
+        let game_data = import_game_data(&import_path, &ImportOptions::default()).unwrap();
+        let crafted_skill = AlchemySkill {
+            skill_level: 30,
+            fortify_alchemy: 10.0,
+            perk_bonus: 60.0,
+        };
+        let pairs = [
+            ["Frost Lily", "Ember Root"],
+            ["Marsh Cap", "Silver Thistle"],
+            ["Dusk Moth Wing", "Salt Crystal"],
+        ];
+        let get_value = |pair: &[&str; 2], alchemy_skill: &AlchemySkill| {
+            game_data.craft(pair, alchemy_skill).unwrap().gold_value
+        };
+        let samples = pairs
+            .iter()
+            .map(|pair| CalibrationSample {
+                ingredients: pair.map(String::from).to_vec(),
+                value: get_value(pair, &crafted_skill),
+            })
+            .collect::<Vec<_>>();
+
+        let uncalibrated_skill = AlchemySkill {
+            perk_bonus: 0.0,
+            ..crafted_skill
+        };
+        let mut output = Vec::new();
+        let calibrated_skill = calibrate_alchemy_skill(
+            &import_path,
+            &ImportOptions::default(),
+            &samples,
+            &uncalibrated_skill,
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.contains("The current settings give the wrong value for 3 of 3 potions"),
+            "{}",
+            output
+        );
+        assert_eq!(calibrated_skill.skill_level, crafted_skill.skill_level);
+        assert_eq!(
+            calibrated_skill.fortify_alchemy,
+            crafted_skill.fortify_alchemy
+        );
+        assert!(
+            (calibrated_skill.perk_bonus - 60.0).abs() < 10.0,
+            "{:?}",
+            calibrated_skill
+        );
+        for (pair, sample) in pairs.iter().zip(&samples) {
+            assert_eq!(get_value(pair, &calibrated_skill), sample.value);
+        }
+
+        // A potion crafted without the perks contradicts the others
+        let mut contradicting_samples = samples.clone();
+        contradicting_samples.push(CalibrationSample {
+            value: get_value(&pairs[1], &uncalibrated_skill),
+            ..samples[1].clone()
+        });
+        let err = calibrate_alchemy_skill(
+            &import_path,
+            &ImportOptions::default(),
+            &contradicting_samples,
+            &uncalibrated_skill,
+            std::io::sink(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("contradicts the ones before it"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn nameless_records_are_shown_by_editor_id_then_form_id() {
+        fn record_mut<'j>(
+            json: &'j mut serde_json::Value,
+            list: &str,
+            name: &str,
+        ) -> &'j mut serde_json::Value {
+            json[list]
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .find(|record| record["name"] == name)
+                .unwrap()
+        }
+
+        let mut json = serde_json::from_str::<serde_json::Value>(include_str!(
+            "../fixtures/example_game_data.json"
+        ))
+        .unwrap();
+        record_mut(&mut json, "ingredients", "Ember Root")["name"] = serde_json::Value::Null;
+        record_mut(&mut json, "magic_effects", "Restore Health")["name"] = serde_json::Value::Null;
+        let resist_fire = record_mut(&mut json, "magic_effects", "Resist Fire");
+        resist_fire["name"] = serde_json::Value::Null;
+        resist_fire["editor_id"] = serde_json::json!("");
+        let game_data = GameData::from_json_slice(&serde_json::to_vec(&json).unwrap()).unwrap();
+
+        // Frost Lily and Ember Root share only Restore Health and Resist Fire
+        let potion = game_data
+            .craft(
+                &["Frost Lily", "ExampleEmberRoot"],
+                &AlchemySkill::default(),
+            )
+            .unwrap();
+        let effect_names = potion
+            .effects
+            .iter()
+            .map(|eff| get_magic_effect_name_or_fallback(eff.magic_effect).into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effect_names.iter().sorted().collect::<Vec<_>>(),
+            ["0000:000904", "ExampleRestoreHealth"]
+        );
+        // The primary effect comes first
+        let potion_name = format!("Potion of {}", effect_names[0]);
+        assert_eq!(potion.get_potion_name(&game_data), potion_name);
+
+        let annotated = AnnotatedPotion::new(&potion, &game_data, &[]);
+        let block = PotionFormatter::new(OutputStyle::Block, false).format_block(&annotated);
+        assert!(block.contains(&potion_name), "{}", block);
+        assert!(block.contains("- ExampleEmberRoot"), "{}", block);
+        assert!(effect_names
+            .iter()
+            .all(|name| block.contains(name.as_str())));
+
+        let json = serde_json::to_value(&annotated).unwrap();
+        assert_eq!(json["name"], potion_name);
+        assert_eq!(
+            json["ingredients"],
+            serde_json::json!(["Frost Lily", "ExampleEmberRoot"])
+        );
+        assert_eq!(
+            json["effects"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|effect| effect["name"].as_str().unwrap())
+                .collect::<Vec<_>>(),
+            effect_names
+        );
+
+        let mut potions_list = PotionsList::new(&game_data, 2);
+        potions_list.build_potions();
+        let mut csv = Vec::new();
+        potions_export::write_potions(&potions_list, ExportFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let row = csv
+            .lines()
+            .find(|row| row.contains("Frost Lily") && row.contains("ExampleEmberRoot"))
+            .unwrap_or_else(|| panic!("no Frost Lily and Ember Root potion in {}", csv));
+        assert!(row.contains(&format!(",{},", potion_name)), "{}", row);
+        assert!(row.contains(&effect_names.join(";")), "{}", row);
+    }
+}
@@ -0,0 +1,376 @@
+use ahash::AHashMap;
+use itertools::{EitherOrBoth::*, Itertools};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+/// The masters of the base game and its official DLCs, which are part of every load order
+const BASE_GAME_PLUGINS: &[&str] = &[
+    "Skyrim.esm",
+    "Update.esm",
+    "Dawnguard.esm",
+    "HearthFires.esm",
+    "Dragonborn.esm",
+];
+
+/// Returns whether the plugin is one of the masters of the base game and its official DLCs
+pub fn is_base_game_plugin(plugin_name: &str) -> bool {
+    BASE_GAME_PLUGINS
+        .iter()
+        .any(|base_game_plugin| base_game_plugin.eq_ignore_ascii_case(plugin_name))
+}
+
+/// The most regular plugins the game can load. Their runtime form IDs start with their index,
+/// where `FE` is taken by light masters and `FF` by records created at runtime.
+pub const MAX_REGULAR_PLUGINS: usize = 0xFE;
+
+/// The most light masters the game can load. Their runtime form IDs start with `FE` followed by
+/// their 12-bit index.
+pub const MAX_LIGHT_PLUGINS: usize = 0x1000;
+
+/// The most plugins the game can load, regular plugins and light masters combined
+pub const MAX_PLUGINS: usize = MAX_REGULAR_PLUGINS + MAX_LIGHT_PLUGINS;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadOrderLimitError {
+    #[error(
+        "the load order has {0} plugins, but the game can load at most {}",
+        MAX_PLUGINS
+    )]
+    TooManyPlugins(usize),
+    #[error(
+        "the load order has {0} regular plugins, but the game can load at most {}; consider \
+         flagging some of them as light masters (ESL)",
+        MAX_REGULAR_PLUGINS
+    )]
+    TooManyRegularPlugins(usize),
+    #[error(
+        "the load order has {0} light masters, but the game can load at most {}",
+        MAX_LIGHT_PLUGINS
+    )]
+    TooManyLightPlugins(usize),
+}
+
+/// A plugin in the `LoadOrder`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadOrderEntry {
+    /// The plugin's file name, e.g. `Skyrim.esm`
+    pub name: String,
+    /// Whether the plugin is a light master (ESL)
+    #[serde(default)]
+    pub is_light: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoadOrder {
+    load_order: Vec<LoadOrderEntry>,
+}
+
+impl LoadOrder {
+    pub fn new(load_order: Vec<String>) -> Self {
+        Self {
+            load_order: load_order
+                .into_iter()
+                .map(|name| LoadOrderEntry {
+                    name,
+                    is_light: false,
+                })
+                .collect::<Vec<_>>(),
+        }
+    }
+
+    pub fn from_entries(load_order: Vec<LoadOrderEntry>) -> Self {
+        Self { load_order }
+    }
+
+    /// Appends a plugin that isn't a light master to the load order and returns its index
+    pub fn push(&mut self, name: String) -> u16 {
+        self.load_order.push(LoadOrderEntry {
+            name,
+            is_light: false,
+        });
+        // The limits checked by `check_limits` keep the load order far below this
+        u16::try_from(self.load_order.len() - 1)
+            .expect("load order has more plugins than form IDs can refer to")
+    }
+
+    /// Returns the load order with only the masters of the base game and its official DLCs, in
+    /// the same order
+    pub fn base_game_only(&self) -> LoadOrder {
+        Self::from_entries(
+            self.load_order
+                .iter()
+                .filter(|entry| is_base_game_plugin(&entry.name))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Returns an error if the load order has more plugins than the game can load. Whether each
+    /// plugin is a light master is not known before it is parsed, so this only checks the total.
+    pub fn check_len(&self) -> Result<(), LoadOrderLimitError> {
+        match self.load_order.len() {
+            len if len > MAX_PLUGINS => Err(LoadOrderLimitError::TooManyPlugins(len)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the load order has more regular plugins or light masters than the game
+    /// can load, beyond which their runtime form IDs would overlap
+    pub fn check_limits(&self) -> Result<(), LoadOrderLimitError> {
+        self.check_len()?;
+        let num_light = self
+            .load_order
+            .iter()
+            .filter(|entry| entry.is_light)
+            .count();
+        let num_regular = self.load_order.len() - num_light;
+        if num_regular > MAX_REGULAR_PLUGINS {
+            Err(LoadOrderLimitError::TooManyRegularPlugins(num_regular))
+        } else if num_light > MAX_LIGHT_PLUGINS {
+            Err(LoadOrderLimitError::TooManyLightPlugins(num_light))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn find_index(&self, mod_name: &str) -> Option<u16> {
+        self.load_order
+            .iter()
+            .enumerate()
+            .find_map(|(index, entry)| {
+                if matches!(
+                    cmp_ignore_case_ascii(&entry.name, mod_name),
+                    Ordering::Equal
+                ) {
+                    Some(index as u16)
+                } else {
+                    None
+                }
+            })
+    }
+
+    pub fn get(&self, index: u16) -> Option<&str> {
+        self.load_order.get(index as usize).map(|x| x.name.as_str())
+    }
+
+    pub fn get_entry(&self, index: u16) -> Option<&LoadOrderEntry> {
+        self.load_order.get(index as usize)
+    }
+
+    /// Returns whether the plugin at the given index is a light master
+    pub fn is_light(&self, index: u16) -> Option<bool> {
+        self.get_entry(index).map(|entry| entry.is_light)
+    }
+
+    /// Sets whether the plugin at the given index is a light master
+    pub fn set_light(&mut self, index: u16, is_light: bool) {
+        if let Some(entry) = self.load_order.get_mut(index as usize) {
+            entry.is_light = is_light;
+        }
+    }
+
+    /// Returns the index the game assigns to the plugin at the given index at runtime. Light
+    /// masters and regular plugins are indexed separately.
+    ///
+    /// Note: this is only accurate if the load order contains all active plugins.
+    pub fn get_runtime_index(&self, index: u16) -> Option<u16> {
+        let is_light = self.is_light(index)?;
+        Some(
+            self.load_order
+                .iter()
+                .take(index as usize)
+                .filter(|entry| entry.is_light == is_light)
+                .count() as u16,
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.load_order.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.load_order.len()
+    }
+
+    /// Returns an iterator over the plugin names in load order
+    pub fn iter(&self) -> impl Iterator<Item = &String> + '_ {
+        self.load_order.iter().map(|entry| &entry.name)
+    }
+
+    /// Returns an iterator over the plugins in load order, along with their index
+    pub fn iter_entries(&self) -> impl Iterator<Item = (u16, &LoadOrderEntry)> + '_ {
+        self.load_order
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (index as u16, entry))
+    }
+
+    pub fn entries(&self) -> &[LoadOrderEntry] {
+        &self.load_order
+    }
+
+    /// Removes unused entries from the LoadOrder based on the used indexes returned by the iterator
+    /// that is passed in. If nothing was removed, returns None. Otherwise returns Some(HashMap) of
+    /// old index to new index which must be used to update any existing indexes into the LoadOrder.
+    #[must_use]
+    pub fn drain_unused(
+        &mut self,
+        used_indexes: impl Iterator<Item = u16>,
+    ) -> Option<AHashMap<u16, u16>> {
+        let used_entries_with_old_indexes = used_indexes
+            .sorted_unstable()
+            .dedup()
+            .map(|index| (self.get(index).unwrap().to_string(), index))
+            .collect::<AHashMap<String, u16>>();
+
+        let num_removed = self
+            .load_order
+            .drain_filter(|entry| !used_entries_with_old_indexes.contains_key(&entry.name))
+            .count();
+
+        if num_removed == 0 {
+            return None;
+        }
+
+        log::debug!("Removed {} unused entries from load order", num_removed);
+        Some(
+            used_entries_with_old_indexes
+                .iter()
+                // Create map from old index to new index
+                .map(|(entry, old_index)| (*old_index, self.find_index(entry).unwrap()))
+                .collect(),
+        )
+    }
+}
+
+impl Display for LoadOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.load_order
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| format!(
+                    "{:04}: {}{}",
+                    index,
+                    entry.name,
+                    if entry.is_light { " (light)" } else { "" }
+                ))
+                .join("\n")
+        )
+    }
+}
+
+// https://stackoverflow.com/a/63871901/1233003
+/// Efficient way to compare two string slices case-insensitively
+fn cmp_ignore_case_ascii(a: &str, b: &str) -> Ordering {
+    a.bytes()
+        .zip_longest(b.bytes())
+        .map(|ab| match ab {
+            Left(_) => Ordering::Greater,
+            Right(_) => Ordering::Less,
+            Both(a, b) => a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()),
+        })
+        .find(|&ordering| ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_order(num_regular: usize, num_light: usize) -> LoadOrder {
+        let regular = (0..num_regular).map(|i| LoadOrderEntry {
+            name: format!("Regular{}.esp", i),
+            is_light: false,
+        });
+        let light = (0..num_light).map(|i| LoadOrderEntry {
+            name: format!("Light{}.esl", i),
+            is_light: true,
+        });
+        LoadOrder::from_entries(regular.chain(light).collect())
+    }
+
+    #[test]
+    fn check_limits_allows_full_load_orders() {
+        assert!(load_order(254, 0).check_limits().is_ok());
+        assert!(load_order(0, 4096).check_limits().is_ok());
+        assert!(load_order(254, 4096).check_limits().is_ok());
+    }
+
+    #[test]
+    fn check_limits_rejects_too_many_regular_plugins() {
+        assert!(matches!(
+            load_order(255, 0).check_limits(),
+            Err(LoadOrderLimitError::TooManyRegularPlugins(255))
+        ));
+    }
+
+    #[test]
+    fn check_limits_rejects_too_many_light_plugins() {
+        assert!(matches!(
+            load_order(0, 4097).check_limits(),
+            Err(LoadOrderLimitError::TooManyLightPlugins(4097))
+        ));
+    }
+
+    #[test]
+    fn check_limits_rejects_too_many_plugins() {
+        assert!(matches!(
+            load_order(255, 4096).check_limits(),
+            Err(LoadOrderLimitError::TooManyPlugins(4351))
+        ));
+        assert!(matches!(
+            load_order(254, 4097).check_limits(),
+            Err(LoadOrderLimitError::TooManyPlugins(4351))
+        ));
+        assert!(matches!(
+            load_order(4351, 0).check_len(),
+            Err(LoadOrderLimitError::TooManyPlugins(4351))
+        ));
+        assert!(load_order(4350, 0).check_len().is_ok());
+    }
+
+    #[test]
+    fn base_game_plugins_are_matched_ignoring_case() {
+        assert!(is_base_game_plugin("Skyrim.esm"));
+        assert!(is_base_game_plugin("update.esm"));
+        assert!(is_base_game_plugin("DRAGONBORN.ESM"));
+        assert!(!is_base_game_plugin("Skyrim.esp"));
+        assert!(!is_base_game_plugin("ccBGSSSE001-Fish.esm"));
+        assert!(!is_base_game_plugin(
+            "Unofficial Skyrim Special Edition Patch.esp"
+        ));
+    }
+
+    #[test]
+    fn base_game_only_keeps_only_the_base_game_in_order() {
+        let load_order = LoadOrder::new(
+            [
+                "Skyrim.esm",
+                "Update.esm",
+                "Dawnguard.esm",
+                "ccBGSSSE001-Fish.esm",
+                "HearthFires.esm",
+                "Dragonborn.esm",
+                "Unofficial Skyrim Special Edition Patch.esp",
+                "CACO.esp",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        assert_eq!(
+            load_order.base_game_only().iter().collect::<Vec<_>>(),
+            [
+                "Skyrim.esm",
+                "Update.esm",
+                "Dawnguard.esm",
+                "HearthFires.esm",
+                "Dragonborn.esm",
+            ]
+        );
+    }
+}
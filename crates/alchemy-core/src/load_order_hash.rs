@@ -0,0 +1,155 @@
+use std::{fmt::Display, fs, hash::Hasher, path::Path, str::FromStr, time::UNIX_EPOCH};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::load_order::LoadOrder;
+
+/// Identifies the plugins that game data was exported from: a hash of the names of the active
+/// plugins, in load order, and the sizes and modification times of their files. Embedded in the
+/// outputs so that users and bug reports can tell which data a list of potions came from.
+///
+/// Unlike `DefaultHasher`, the hash function is fixed, so the same plugins give the same hash with
+/// every build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, DeserializeFromStr, SerializeDisplay)]
+pub struct LoadOrderHash(u64);
+
+impl LoadOrderHash {
+    /// Hashes the active plugins, whose files are in `plugins_path`, i.e. the game's Data
+    /// directory. Plugins whose files can't be read are hashed by name only.
+    pub(crate) fn compute(plugins_path: &Path, plugins: &LoadOrder) -> Self {
+        let mut hasher = Fnv1aHasher::default();
+        for plugin in plugins.iter() {
+            // Windows file names are case-insensitive, so the case of the plugins file doesn't
+            // matter
+            hasher.write(plugin.to_ascii_lowercase().as_bytes());
+            hasher.write_u8(0);
+
+            let (size, modified) = match fs::metadata(plugins_path.join(plugin)) {
+                Ok(metadata) => (
+                    metadata.len(),
+                    metadata
+                        .modified()
+                        .ok()
+                        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                        .map_or(0, |since_epoch| since_epoch.as_secs()),
+                ),
+                Err(err) => {
+                    log::debug!("Failed to read metadata of plugin {}: {}", plugin, err);
+                    (0, 0)
+                }
+            };
+            hasher.write(&size.to_le_bytes());
+            hasher.write(&modified.to_le_bytes());
+        }
+
+        Self(hasher.finish())
+    }
+
+    pub(crate) fn from_u64(hash: u64) -> Self {
+        Self(hash)
+    }
+
+    pub(crate) fn to_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for LoadOrderHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for LoadOrderHash {
+    type Err = String;
+
+    /// Parse a value like `9f86d081884c7d65`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16)
+            .map(Self)
+            .map_err(|err| format!("invalid load order hash {}: {}", s, err))
+    }
+}
+
+/// The 64-bit FNV-1a hash function, see http://www.isthe.com/chongo/tech/comp/fnv/
+pub(crate) struct Fnv1aHasher(u64);
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv1aHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_order(plugins: &[&str]) -> LoadOrder {
+        LoadOrder::new(plugins.iter().map(|plugin| plugin.to_string()).collect())
+    }
+
+    #[test]
+    fn hash_changes_with_the_plugins() {
+        let dir =
+            std::env::temp_dir().join(format!("alchemy-load-order-hash-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for plugin in ["A.esm", "B.esp", "C.esp"] {
+            fs::write(dir.join(plugin), plugin).unwrap();
+        }
+        let hash = |plugins: &[&str]| LoadOrderHash::compute(&dir, &load_order(plugins));
+
+        let original = hash(&["A.esm", "B.esp"]);
+        assert_eq!(hash(&["A.esm", "B.esp"]), original);
+        assert_ne!(hash(&["A.esm", "B.esp", "C.esp"]), original, "added");
+        assert_ne!(hash(&["B.esp", "A.esm"]), original, "reordered");
+        assert_ne!(hash(&["A.esm"]), original, "removed");
+
+        fs::write(dir.join("B.esp"), "B.esp, but bigger").unwrap();
+        assert_ne!(hash(&["A.esm", "B.esp"]), original, "resized");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn hash_ignores_the_case_of_plugin_names() {
+        // The plugins aren't found, so only their names are hashed
+        let dir = Path::new("nonexistent-data-directory");
+        assert_eq!(
+            LoadOrderHash::compute(dir, &load_order(&["Skyrim.esm", "Unofficial Patch.esp"])),
+            LoadOrderHash::compute(dir, &load_order(&["skyrim.esm", "UNOFFICIAL PATCH.ESP"]))
+        );
+    }
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv1aHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn hash_round_trips_through_its_string() {
+        let hash = LoadOrderHash::from_u64(0x09f86d081884c7d6);
+        assert_eq!(hash.to_string(), "09f86d081884c7d6");
+        assert_eq!("09f86d081884c7d6".parse::<LoadOrderHash>(), Ok(hash));
+        assert!("not a hash".parse::<LoadOrderHash>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::str::FromStr;
+
+use serde_with::DeserializeFromStr;
+
+/// Languages that group thousands with a comma and use a decimal point, e.g. 1,234.5
+const COMMA_GROUPING_LANGUAGES: &[&str] = &["en", "ga", "he", "ja", "ko", "th", "zh"];
+/// Languages that group thousands with a period and use a decimal comma, e.g. 1.234,5
+const PERIOD_GROUPING_LANGUAGES: &[&str] = &[
+    "da", "de", "el", "es", "hr", "id", "it", "nl", "pt", "ro", "sl", "sr", "tr",
+];
+/// Languages that group thousands with a space and use a decimal comma, e.g. 1 234,5
+const SPACE_GROUPING_LANGUAGES: &[&str] = &[
+    "bg", "cs", "et", "fi", "fr", "hu", "lt", "lv", "nb", "nn", "no", "pl", "ru", "sk", "sv", "uk",
+];
+
+/// How to format numbers for a locale, i.e. how to group thousands and which decimal separator to
+/// use. Only the language of the locale is taken into account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, DeserializeFromStr)]
+pub struct NumberLocale {
+    thousands_separator: Option<char>,
+    decimal_separator: char,
+}
+
+impl NumberLocale {
+    /// No thousands separator and a decimal point, like the C locale
+    pub const PLAIN: NumberLocale = NumberLocale {
+        thousands_separator: None,
+        decimal_separator: '.',
+    };
+
+    /// Detects the locale from the `LC_ALL`, `LC_NUMERIC` and `LANG` environment variables, in
+    /// that order. Falls back to `PLAIN` if none of them are set to a known locale.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| {
+                value
+                    .parse()
+                    .map_err(|err| log::debug!("Not formatting numbers by locale: {}", err))
+                    .ok()
+            })
+            .unwrap_or(Self::PLAIN)
+    }
+
+    /// Formats a whole number such as a gold value or magnitude with its digits grouped by
+    /// thousands
+    pub fn format_integer(&self, n: impl Into<u64>) -> String {
+        let digits = n.into().to_string();
+        let separator = match self.thousands_separator {
+            Some(separator) => separator,
+            None => return digits,
+        };
+
+        let mut formatted =
+            String::with_capacity(digits.len() + digits.len() / 3 * separator.len_utf8());
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                formatted.push(separator);
+            }
+            formatted.push(digit);
+        }
+        formatted
+    }
+
+    /// Formats a fractional number such as a weight with the given number of decimals and its
+    /// integer part grouped by thousands
+    pub fn format_decimal(&self, n: f32, precision: usize) -> String {
+        let formatted = format!("{:.*}", precision, n.abs());
+        let (integer, fraction) = formatted
+            .split_once('.')
+            .map_or((formatted.as_str(), None), |(integer, fraction)| {
+                (integer, Some(fraction))
+            });
+        let integer = integer
+            .parse::<u64>()
+            .map_or_else(|_| integer.to_string(), |n| self.format_integer(n));
+
+        let sign = if n.is_sign_negative() && n != 0.0 {
+            "-"
+        } else {
+            ""
+        };
+        match fraction {
+            Some(fraction) => format!("{}{}{}{}", sign, integer, self.decimal_separator, fraction),
+            None => format!("{}{}", sign, integer),
+        }
+    }
+}
+
+impl FromStr for NumberLocale {
+    type Err = String;
+
+    /// Parses a locale such as `de_DE.UTF-8`, `pt-BR` or `fr`. `C`, `POSIX` and `plain` mean no
+    /// thousands separator.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s
+            .split(|c| c == '_' || c == '-' || c == '.' || c == '@')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if ["c", "posix", "plain"].contains(&language.as_str()) {
+            Ok(Self::PLAIN)
+        } else if COMMA_GROUPING_LANGUAGES.contains(&language.as_str()) {
+            Ok(Self {
+                thousands_separator: Some(','),
+                decimal_separator: '.',
+            })
+        } else if PERIOD_GROUPING_LANGUAGES.contains(&language.as_str()) {
+            Ok(Self {
+                thousands_separator: Some('.'),
+                decimal_separator: ',',
+            })
+        } else if SPACE_GROUPING_LANGUAGES.contains(&language.as_str()) {
+            Ok(Self {
+                // A no-break space, so numbers aren't split across lines
+                thousands_separator: Some('\u{a0}'),
+                decimal_separator: ',',
+            })
+        } else {
+            Err(format!(
+                "unknown locale {}, expected a locale such as en_US, de or plain",
+                s
+            ))
+        }
+    }
+}
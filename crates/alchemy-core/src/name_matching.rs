@@ -0,0 +1,85 @@
+/// Returns the form of the name that name-keyed lookups compare: lowercase, without apostrophes
+/// or diacritics, and with runs of whitespace collapsed into single spaces, e.g. "giants toe" for
+/// " Giant’s  Toe". Ingredient and effect names, editor IDs, filter entries and aliases are all
+/// compared this way, so that they match each other the same way everywhere.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match c {
+            '\'' | '’' | '‘' | '`' | '´' => {}
+            c => match fold_diacritic(c) {
+                Some(replacement) => folded.push_str(replacement),
+                None => folded.push(c),
+            },
+        }
+    }
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the letters without diacritics that a lowercase Latin letter with diacritics is
+/// written as, e.g. "e" for 'é' and "ss" for 'ß', or `None` if it has none
+fn fold_diacritic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_name_ignores_case_and_whitespace() {
+        assert_eq!(
+            normalize_name("Blue Mountain Flower"),
+            "blue mountain flower"
+        );
+        assert_eq!(
+            normalize_name("  Blue\tMountain   Flower\n"),
+            "blue mountain flower"
+        );
+        assert_eq!(normalize_name("   "), "");
+    }
+
+    #[test]
+    fn normalize_name_ignores_apostrophes() {
+        assert_eq!(normalize_name("Giant's Toe"), "giants toe");
+        assert_eq!(normalize_name("Giant’s Toe"), "giants toe");
+        assert_eq!(normalize_name("Giant`s Toe"), "giants toe");
+        assert_eq!(normalize_name("giants toe"), "giants toe");
+    }
+
+    #[test]
+    fn normalize_name_ignores_diacritics() {
+        assert_eq!(normalize_name("Œil de Vaermina"), "oeil de vaermina");
+        assert_eq!(
+            normalize_name("Régénération de magie"),
+            "regeneration de magie"
+        );
+        assert_eq!(
+            normalize_name("Fliegenpilzhut-Weiß"),
+            "fliegenpilzhut-weiss"
+        );
+        // Letters of other scripts are kept
+        assert_eq!(normalize_name("Корень Нирна"), "корень нирна");
+    }
+}
@@ -0,0 +1,347 @@
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback},
+    load_order_hash::LoadOrderHash,
+    locale::NumberLocale,
+    potion::{EffectContribution, PotionEffect, PotionType},
+    potion_notes::AnnotatedPotion,
+};
+
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_DIM: &str = "\x1b[2m";
+
+/// How to print potions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Each potion as a block of lines
+    Block,
+    /// Each potion on a single line
+    Oneline,
+    /// JSON, for processing by other tools
+    Json,
+}
+
+/// Potions under a heading, e.g. all potions with the same primary effect
+#[derive(Serialize)]
+pub struct AnnotatedPotionGroup<'p, 'a> {
+    pub heading: String,
+    pub potions: Vec<AnnotatedPotion<'p, 'a>>,
+}
+
+/// The potions suggested for a query, either as a single list or in groups
+pub enum QueryResults<'p, 'a> {
+    Potions(Vec<AnnotatedPotion<'p, 'a>>),
+    Groups(Vec<AnnotatedPotionGroup<'p, 'a>>),
+}
+
+impl<'p, 'a> QueryResults<'p, 'a> {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            QueryResults::Potions(potions) => potions.is_empty(),
+            QueryResults::Groups(groups) => groups.is_empty(),
+        }
+    }
+}
+
+/// Formats potions for printing to the terminal
+pub struct PotionFormatter {
+    /// Whether to use ANSI colors: red for hostile effects and green for beneficial effects
+    pub color: bool,
+    pub style: OutputStyle,
+    /// Whether to show each effect's in-game description next to its magnitude, duration and
+    /// value. In the oneline style, this adds the effects table below each potion.
+    pub show_effect_table: bool,
+}
+
+impl PotionFormatter {
+    /// Creates a formatter which uses colors if `color` is true, unless the output is JSON
+    pub fn new(style: OutputStyle, color: bool) -> Self {
+        Self {
+            color: color && style != OutputStyle::Json,
+            style,
+            show_effect_table: false,
+        }
+    }
+
+    /// Returns the formatter with the effect table shown or hidden
+    pub fn with_effect_table(mut self, show_effect_table: bool) -> Self {
+        self.show_effect_table = show_effect_table;
+        self
+    }
+
+    /// Formats the results of a query, preceded by the query's name if it has one. The JSON output
+    /// also records the load order hash of the game data the potions came from.
+    pub fn format_query_results(
+        &self,
+        name: Option<&str>,
+        load_order_hash: Option<LoadOrderHash>,
+        results: &QueryResults,
+    ) -> String {
+        if self.style == OutputStyle::Json {
+            let json = match results {
+                QueryResults::Potions(potions) => serde_json::json!({
+                    "name": name,
+                    "load_order_hash": load_order_hash,
+                    "potions": potions
+                }),
+                QueryResults::Groups(groups) => serde_json::json!({
+                    "name": name,
+                    "load_order_hash": load_order_hash,
+                    "groups": groups
+                }),
+            };
+            return json.to_string();
+        }
+
+        let body = match results {
+            QueryResults::Potions(potions) => self.format_potions(potions),
+            QueryResults::Groups(groups) => groups
+                .iter()
+                .map(|group| {
+                    format!(
+                        "{}\n\n{}",
+                        self.paint(ANSI_BOLD, &format!("## {}", group.heading)),
+                        self.format_potions(&group.potions)
+                    )
+                })
+                .join("\n\n"),
+        };
+
+        match name {
+            Some(name) => format!("=== {} ===\n\n{}", name, body),
+            None => body,
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, ANSI_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn paint_by_hostility(&self, is_hostile: bool, text: &str) -> String {
+        self.paint(if is_hostile { ANSI_RED } else { ANSI_GREEN }, text)
+    }
+
+    /// Formats the potions in the formatter's style. Columns are aligned across all of the
+    /// potions.
+    pub fn format_potions(&self, potions: &[AnnotatedPotion]) -> String {
+        match self.style {
+            OutputStyle::Block => potions
+                .iter()
+                .map(|potion| self.format_block(potion))
+                .join("\n\n"),
+            OutputStyle::Oneline => self.format_oneline(potions),
+            OutputStyle::Json => serde_json::to_string(potions).unwrap_or_default(),
+        }
+    }
+
+    /// Formats a potion as a block of lines, with its effects as aligned columns
+    pub fn format_block(&self, annotated: &AnnotatedPotion) -> String {
+        let potion = annotated.potion;
+        let locale = annotated.game_data.get_number_locale();
+        let is_poison = potion.get_potion_type() == PotionType::Poison;
+
+        let mut lines = vec![
+            self.paint(
+                ANSI_BOLD,
+                &self.paint_by_hostility(is_poison, &potion.get_potion_name(annotated.game_data)),
+            ),
+            potion.get_potion_description(locale),
+            format!("Value: {} gold", locale.format_integer(potion.gold_value)),
+            "Effects:".to_string(),
+        ];
+        lines.extend(self.format_effects(
+            &potion.effects,
+            annotated.contributions.as_deref(),
+            locale,
+        ));
+
+        lines.push("Ingredients:".to_string());
+        lines.extend(
+            potion
+                .ingredients
+                .iter()
+                .map(|ig| format!("- {}", get_ingredient_name_or_fallback(ig))),
+        );
+
+        if !annotated.notes.is_empty() {
+            lines.push("Notes:".to_string());
+            lines.extend(
+                annotated
+                    .notes
+                    .iter()
+                    .map(|note| self.paint(ANSI_DIM, &format!("- {}", note))),
+            );
+        }
+
+        lines.join("\n")
+    }
+
+    /// Formats the effects as aligned columns. With the effect table, each effect is followed by
+    /// the ingredient it came from and the weaker versions of the other ingredients, if the
+    /// contributions are given. Numbers are formatted with the locale.
+    fn format_effects(
+        &self,
+        effects: &[PotionEffect],
+        contributions: Option<&[Vec<EffectContribution>]>,
+        locale: NumberLocale,
+    ) -> Vec<String> {
+        let names = effects
+            .iter()
+            .map(|eff| get_magic_effect_name_or_fallback(eff.magic_effect))
+            .collect::<Vec<_>>();
+        let name_width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+        let magnitudes = effects
+            .iter()
+            .map(|eff| locale.format_integer(eff.get_magnitude()))
+            .collect::<Vec<_>>();
+        let durations = effects
+            .iter()
+            .map(|eff| locale.format_integer(eff.get_duration()))
+            .collect::<Vec<_>>();
+        let values = effects
+            .iter()
+            .map(|eff| locale.format_integer(eff.get_gold_value()))
+            .collect::<Vec<_>>();
+        let magnitude_width = max_width(&magnitudes);
+        let duration_width = max_width(&durations);
+        let value_width = max_width(&values);
+
+        effects
+            .iter()
+            .enumerate()
+            .zip(names)
+            .zip(magnitudes.iter().zip(&durations).zip(&values))
+            .map(|(((index, eff), name), ((magnitude, duration), value))| {
+                let mut line = format!(
+                    "  {}  mag {:>mw$}  dur {:>dw$}s  {:>vw$} gold",
+                    self.paint_by_hostility(
+                        eff.magic_effect.is_hostile,
+                        &format!("{:<nw$}", name, nw = name_width)
+                    ),
+                    magnitude,
+                    duration,
+                    value,
+                    mw = magnitude_width,
+                    dw = duration_width,
+                    vw = value_width,
+                );
+                if self.show_effect_table {
+                    line += &format!("  {}", self.paint(ANSI_DIM, &eff.get_description(locale)));
+                    if let Some(contributions) = contributions {
+                        line += &format!(
+                            "\n    {}",
+                            self.paint(
+                                ANSI_DIM,
+                                &format_contributions(&contributions[index], locale)
+                            )
+                        );
+                    }
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Formats each potion as a single line with its value, name, effects and ingredients
+    fn format_oneline(&self, potions: &[AnnotatedPotion]) -> String {
+        let names = potions
+            .iter()
+            .map(|annotated| annotated.potion.get_potion_name(annotated.game_data))
+            .collect::<Vec<_>>();
+        let name_width = names.iter().map(|name| name.len()).max().unwrap_or(0);
+        let values = potions
+            .iter()
+            .map(|annotated| {
+                let locale = annotated.game_data.get_number_locale();
+                locale.format_integer(annotated.potion.gold_value)
+            })
+            .collect::<Vec<_>>();
+        let value_width = max_width(&values);
+
+        potions
+            .iter()
+            .zip(names)
+            .zip(&values)
+            .map(|((annotated, name), value)| {
+                let potion = annotated.potion;
+                let is_poison = potion.get_potion_type() == PotionType::Poison;
+                let mut line = format!(
+                    "{:>vw$} gold  {}  {}  ({})",
+                    value,
+                    self.paint_by_hostility(is_poison, &format!("{:<nw$}", name, nw = name_width)),
+                    potion
+                        .effects
+                        .iter()
+                        .map(|eff| self.paint_by_hostility(
+                            eff.magic_effect.is_hostile,
+                            &get_magic_effect_name_or_fallback(eff.magic_effect)
+                        ))
+                        .join(", "),
+                    potion
+                        .ingredients
+                        .iter()
+                        .map(|ig| get_ingredient_name_or_fallback(ig))
+                        .join(" + "),
+                    vw = value_width,
+                );
+                if !annotated.notes.is_empty() {
+                    line += &self.paint(ANSI_DIM, &format!("  [{}]", annotated.notes.join("; ")));
+                }
+                if self.show_effect_table {
+                    for effect_line in self.format_effects(
+                        &potion.effects,
+                        annotated.contributions.as_deref(),
+                        annotated.game_data.get_number_locale(),
+                    ) {
+                        line += &format!("\n{}", effect_line);
+                    }
+                }
+                line
+            })
+            .join("\n")
+    }
+}
+
+/// Formats the versions of an effect that the ingredients contribute, e.g. `from Creep Cluster
+/// instead of Blue Mountain Flower (mag 16, dur 0s)`, with the numbers formatted with the locale
+fn format_contributions(contributions: &[EffectContribution], locale: NumberLocale) -> String {
+    let (used, unused): (Vec<_>, Vec<_>) = contributions
+        .iter()
+        .partition(|contribution| contribution.is_used);
+    let mut text = format!(
+        "from {}",
+        used.iter()
+            .map(|contribution| get_ingredient_name_or_fallback(contribution.ingredient))
+            .join(", ")
+    );
+    if !unused.is_empty() {
+        text += &format!(
+            " instead of {}",
+            unused
+                .iter()
+                .map(|contribution| format!(
+                    "{} (mag {}, dur {}s)",
+                    get_ingredient_name_or_fallback(contribution.ingredient),
+                    locale.format_integer(contribution.magnitude),
+                    locale.format_integer(contribution.duration)
+                ))
+                .join(", ")
+        );
+    }
+    text
+}
+
+/// Returns the width of the widest of the formatted numbers, in characters since thousands
+/// separators may take up more than one byte
+fn max_width(numbers: &[String]) -> usize {
+    numbers.iter().map(|n| n.chars().count()).max().unwrap_or(0)
+}
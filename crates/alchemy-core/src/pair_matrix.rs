@@ -0,0 +1,223 @@
+use std::{io::Write, str::FromStr};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, GameData},
+    plugin_parser::{form_id::FormIdContainer, ingredient::Ingredient},
+    potions_export::escape_csv_field,
+    potions_list::get_pair_values,
+};
+
+/// Identifies a binary pair matrix file
+const MATRIX_MAGIC: &[u8; 4] = b"SAPM";
+
+/// Bump this whenever the binary format changes
+const MATRIX_VERSION: u32 = 1;
+
+/// The value in the binary format of a pair of ingredients that doesn't make a potion
+const NO_POTION: u32 = u32::MAX;
+
+/// The file format to export the ingredient pair matrix to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MatrixFormat {
+    /// A header row and a header column with the ingredients' names. The cells of pairs that
+    /// don't make a potion are empty.
+    #[default]
+    Csv,
+    /// A compact little-endian format: the magic bytes `SAPM`, the format version and the number
+    /// of ingredients N as u32s, each ingredient's name as a u16 byte length followed by UTF-8,
+    /// then N×N u32 values row by row, with `u32::MAX` for pairs that don't make a potion
+    Binary,
+}
+
+impl FromStr for MatrixFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(MatrixFormat::Csv),
+            "binary" => Ok(MatrixFormat::Binary),
+            other => Err(format!("unknown format {}, expected csv or binary", other)),
+        }
+    }
+}
+
+/// Returns the ingredients in the order of the matrix's rows and columns: by name, then by form ID
+fn get_matrix_ingredients(game_data: &GameData) -> Vec<&Ingredient> {
+    game_data
+        .get_ingredients()
+        .values()
+        .sorted_by(|a, b| {
+            get_ingredient_name_or_fallback(a)
+                .cmp(&get_ingredient_name_or_fallback(b))
+                .then(a.get_global_form_id().cmp(&b.get_global_form_id()))
+        })
+        .collect()
+}
+
+/// Writes the gold value of the potion that each pair of ingredients makes as an N×N matrix, which
+/// is symmetric and has no potions on its diagonal
+pub(crate) fn write_pair_matrix<W: Write>(
+    game_data: &GameData,
+    power_factor: f32,
+    format: MatrixFormat,
+    mut writer: W,
+) -> Result<(), anyhow::Error> {
+    let ingredients = get_matrix_ingredients(game_data);
+    let values = get_pair_values(game_data, &ingredients, power_factor);
+    let names = ingredients
+        .iter()
+        .map(|ingredient| get_ingredient_name_or_fallback(ingredient))
+        .collect::<Vec<_>>();
+
+    match format {
+        MatrixFormat::Csv => {
+            writeln!(
+                writer,
+                "ingredient,{}",
+                names.iter().map(|name| escape_csv_field(name)).join(",")
+            )?;
+            for (name, row) in names.iter().zip(values.chunks(ingredients.len().max(1))) {
+                writeln!(
+                    writer,
+                    "{},{}",
+                    escape_csv_field(name),
+                    row.iter()
+                        .map(|value| value.map(|value| value.to_string()).unwrap_or_default())
+                        .join(",")
+                )?;
+            }
+        }
+        MatrixFormat::Binary => {
+            writer.write_all(MATRIX_MAGIC)?;
+            writer.write_all(&MATRIX_VERSION.to_le_bytes())?;
+            writer.write_all(&(ingredients.len() as u32).to_le_bytes())?;
+            for name in &names {
+                let len = u16::try_from(name.len())
+                    .map_err(|_| anyhow!("Ingredient name {} is too long", name))?;
+                writer.write_all(&len.to_le_bytes())?;
+                writer.write_all(name.as_bytes())?;
+            }
+            for value in &values {
+                writer.write_all(&value.unwrap_or(NO_POTION).to_le_bytes())?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{plugin_parser::form_id::GlobalFormId, potion::Potion};
+
+    /// The example game data, with Frost Lily left with only Resist Fire so that some pairs share
+    /// no effect
+    fn game_data() -> GameData {
+        let mut game_data = GameData::example();
+        let frost_lily = GlobalFormId::new(0, 0x800);
+        let resist_fire = GlobalFormId::new(0, 0x904);
+        game_data.retain_ingredient_effects(|ingredient, effect| {
+            ingredient.get_global_form_id() != frost_lily
+                || effect.get_global_form_id() == resist_fire
+        });
+        game_data
+    }
+
+    fn read_u32(bytes: &mut &[u8]) -> u32 {
+        let (value, rest) = bytes.split_at(4);
+        *bytes = rest;
+        u32::from_le_bytes(value.try_into().unwrap())
+    }
+
+    /// Reads back the names and values of a matrix in the binary format
+    fn read_binary_matrix(mut bytes: &[u8]) -> (Vec<String>, Vec<Option<u32>>) {
+        assert_eq!(&bytes[..4], MATRIX_MAGIC);
+        bytes = &bytes[4..];
+        assert_eq!(read_u32(&mut bytes), MATRIX_VERSION);
+        let len = read_u32(&mut bytes) as usize;
+
+        let names = (0..len)
+            .map(|_| {
+                let name_len = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+                let name = std::str::from_utf8(&bytes[2..2 + name_len])
+                    .unwrap()
+                    .to_string();
+                bytes = &bytes[2 + name_len..];
+                name
+            })
+            .collect();
+        let values = (0..len * len)
+            .map(|_| Some(read_u32(&mut bytes)).filter(|&value| value != NO_POTION))
+            .collect();
+        assert!(bytes.is_empty());
+
+        (names, values)
+    }
+
+    #[test]
+    fn binary_matrix_can_be_read_back() {
+        let game_data = game_data();
+        let mut bytes = Vec::new();
+        write_pair_matrix(&game_data, 1.0, MatrixFormat::Binary, &mut bytes).unwrap();
+        let (names, values) = read_binary_matrix(&bytes);
+
+        assert_eq!(
+            names,
+            [
+                "Dusk Moth Wing",
+                "Ember Root",
+                "Frost Lily",
+                "Marsh Cap",
+                "Salt Crystal",
+                "Silver Thistle"
+            ]
+        );
+        let ingredients = get_matrix_ingredients(&game_data);
+        for (i, a) in ingredients.iter().enumerate() {
+            assert_eq!(values[i * names.len() + i], None, "{}", names[i]);
+            for (j, b) in ingredients.iter().enumerate().filter(|&(j, _)| j != i) {
+                let value = values[i * names.len() + j];
+                assert_eq!(value, values[j * names.len() + i]);
+                let expected = Potion::from_ingredients(&[*a, *b], &game_data, 1.0)
+                    .ok()
+                    .map(|potion| potion.gold_value);
+                assert_eq!(value, expected, "{} and {}", names[i], names[j]);
+            }
+        }
+        // Frost Lily and Marsh Cap share no effect
+        assert_eq!(values[2 * names.len() + 3], None);
+        assert!(values[2 * names.len() + 1].is_some());
+    }
+
+    #[test]
+    fn csv_matrix_matches_the_binary_one() {
+        let game_data = game_data();
+        let mut bytes = Vec::new();
+        write_pair_matrix(&game_data, 1.0, MatrixFormat::Binary, &mut bytes).unwrap();
+        let (names, values) = read_binary_matrix(&bytes);
+        let mut csv = Vec::new();
+        write_pair_matrix(&game_data, 1.0, MatrixFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("ingredient,{}", names.join(","))
+        );
+        for ((line, name), row) in lines.zip(&names).zip(values.chunks(names.len())) {
+            let expected_cells = row
+                .iter()
+                .map(|value| value.map(|value| value.to_string()).unwrap_or_default());
+            assert_eq!(
+                line,
+                format!("{},{}", name, expected_cells.collect::<Vec<_>>().join(","))
+            );
+        }
+        assert_eq!(csv.lines().count(), names.len() + 1);
+    }
+}
@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use ahash::AHashMap;
+
+use crate::{
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId},
+    potion::{AlchemySkill, Potion},
+    save_parser::{KnownEffects, SaveData},
+};
+
+/// The factor by which value densities are scaled before they are rounded to integer scores
+const VALUE_DENSITY_SCALE: f32 = 100.0;
+
+/// A number of an ingredient to keep in the inventory instead of brewing with it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IngredientReserve {
+    /// The name or editor ID of the ingredient, as accepted by `find_ingredient_by_name`
+    pub ingredient: String,
+    pub count: u32,
+}
+
+impl FromStr for IngredientReserve {
+    type Err = String;
+
+    /// Parse a value like `Giant's Toe=3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ingredient, count) = s.rsplit_once('=').ok_or_else(|| {
+            format!(
+                "invalid reserve {}, expected an ingredient and a count, e.g. \"Giant's Toe=3\"",
+                s
+            )
+        })?;
+        let ingredient = ingredient.trim();
+        if ingredient.is_empty() {
+            return Err(format!("reserve {} has no ingredient", s));
+        }
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid count in reserve {}: {}", s, err))?;
+
+        Ok(Self {
+            ingredient: ingredient.to_string(),
+            count,
+        })
+    }
+}
+
+/// Limits on which ingredients in the inventory may be used, and how many of them
+#[derive(Clone, Debug, Default)]
+pub struct InventoryLimits {
+    /// Ingredients of which the player has fewer than this are treated as if they weren't in the
+    /// inventory
+    pub min_count: u32,
+    /// How many of some ingredients to keep in the inventory
+    pub reserves: Vec<IngredientReserve>,
+}
+
+impl InventoryLimits {
+    /// Returns whether the limits leave the inventory as it is
+    pub fn is_empty(&self) -> bool {
+        self.min_count <= 1 && self.reserves.iter().all(|reserve| reserve.count == 0)
+    }
+}
+
+/// What we know about the player, from their save and the options they passed. Stored in the
+/// `GameData` so that everything computing potions for the player can use it.
+#[derive(Debug, Default)]
+pub struct PlayerContext {
+    /// The number of each ingredient in the player's inventory
+    pub inventory: AHashMap<GlobalFormId, u32>,
+    /// The player's alchemy skill, including the bonuses from perks
+    pub alchemy_skill: AlchemySkill,
+    /// The effects of each ingredient that the player has discovered
+    pub known_effects: KnownEffects,
+}
+
+impl PlayerContext {
+    /// Creates a context for a player whose save wasn't read
+    pub fn new(alchemy_skill: AlchemySkill) -> Self {
+        Self {
+            alchemy_skill,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a context from the data read from the player's save
+    pub fn from_save_data(save_data: SaveData, alchemy_skill: AlchemySkill) -> Self {
+        Self {
+            inventory: save_data
+                .inventory
+                .iter()
+                .map(|entry| (entry.form_id, entry.count))
+                .collect(),
+            alchemy_skill,
+            known_effects: save_data.known_effects,
+        }
+    }
+
+    /// Treats ingredients of which the player has fewer than `min_count` as absent, and then takes
+    /// the reserved number of each ingredient out of the inventory
+    pub fn limit_inventory(&mut self, min_count: u32, reserves: &AHashMap<GlobalFormId, u32>) {
+        for (ingredient_id, count) in self.inventory.iter_mut() {
+            if *count < min_count {
+                *count = 0;
+            }
+            *count = count.saturating_sub(reserves.get(ingredient_id).copied().unwrap_or(0));
+        }
+        self.inventory.retain(|_, count| *count > 0);
+    }
+
+    /// Returns how many of the ingredient the player has
+    pub fn get_inventory_count(&self, ingredient_id: &GlobalFormId) -> u32 {
+        self.inventory.get(ingredient_id).copied().unwrap_or(0)
+    }
+
+    /// Returns the potion's gold value per ingredient consumed, where each ingredient costs one
+    /// over the number of it in the inventory. Using up an ingredient the player has only one of
+    /// is expensive, while using one of many is cheap. Potions with ingredients that aren't in the
+    /// inventory score 0, since they can't be brewed.
+    pub fn get_value_density(&self, potion: &Potion) -> u32 {
+        let mut cost = 0.0;
+        for ingredient in &potion.ingredients {
+            match self.get_inventory_count(&ingredient.get_global_form_id()) {
+                0 => return 0,
+                count => cost += 1.0 / count as f32,
+            }
+        }
+        // Scores are integers, so scale the density up to keep the ranking of close densities
+        (potion.gold_value as f32 * VALUE_DENSITY_SCALE / cost) as u32
+    }
+
+    /// Returns whether the player has discovered the effect of the ingredient
+    pub fn knows_effect(&self, ingredient_id: &GlobalFormId, effect_id: &GlobalFormId) -> bool {
+        self.known_effects
+            .get(ingredient_id)
+            .map_or(false, |known| known.contains(effect_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn min_count_is_applied_before_reserves() {
+        let id = |id| GlobalFormId::new(0, id);
+        let mut player_context = PlayerContext {
+            inventory: [(1, 2), (2, 4), (3, 3), (4, 10), (5, 1), (6, 7)]
+                .into_iter()
+                .map(|(ingredient, count)| (id(ingredient), count))
+                .collect(),
+            ..Default::default()
+        };
+        let reserves = [(1, 1), (2, 2), (3, 3), (4, 20), (7, 5)]
+            .into_iter()
+            .map(|(ingredient, count)| (id(ingredient), count))
+            .collect();
+
+        player_context.limit_inventory(3, &reserves);
+        // 1 and 5 have fewer than the minimum, and 3 and 4 are all reserved. 2 keeps the 2 that
+        // aren't reserved although that is below the minimum, since the minimum applies to what
+        // the player has.
+        assert_eq!(
+            player_context
+                .inventory
+                .iter()
+                .map(|(ingredient, count)| (ingredient.local_id(), *count))
+                .sorted()
+                .collect::<Vec<_>>(),
+            [(2, 2), (6, 7)]
+        );
+        assert_eq!(player_context.get_inventory_count(&id(1)), 0);
+    }
+
+    #[test]
+    fn reserves_are_parsed_from_name_and_count() {
+        assert_eq!(
+            "Giant's Toe = 3".parse::<IngredientReserve>(),
+            Ok(IngredientReserve {
+                ingredient: "Giant's Toe".to_string(),
+                count: 3,
+            })
+        );
+        assert!("Giant's Toe".parse::<IngredientReserve>().is_err());
+        assert!("=3".parse::<IngredientReserve>().is_err());
+        assert!("Giant's Toe=-1".parse::<IngredientReserve>().is_err());
+    }
+}
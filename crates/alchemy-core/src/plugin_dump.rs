@@ -0,0 +1,268 @@
+use std::{fmt, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+
+use crate::{
+    load_order::LoadOrder,
+    plugin_parser::{
+        self, form_id::FormIdContainer, ingredient::Ingredient, magic_effect::MagicEffect,
+        PluginInfo, StringsFileType,
+    },
+};
+
+/// A single plugin parsed on its own: its header, the strings files it uses and its ingredient and
+/// magic effect records. Displaying it lists all of them.
+pub struct PluginDump {
+    plugin_name: String,
+    plugin_info: PluginInfo,
+    /// The number of strings in each strings file, or `None` for the files that weren't found.
+    /// Empty if the plugin is not localized.
+    strings_counts: Vec<(StringsFileType, Option<usize>)>,
+    load_order: LoadOrder,
+    magic_effects: Vec<MagicEffect>,
+    ingredients: Vec<Ingredient>,
+    num_skipped_records: usize,
+}
+
+/// Parses a single plugin on its own and returns its header, the strings files it uses and its
+/// ingredient and magic effect records, e.g. to find out why a mod's ingredients are missing.
+/// The plugin's masters don't need to be present: form IDs that refer to them are shown with the
+/// master's name. The strings files of a localized plugin are looked up next to the plugin.
+pub fn dump_plugin<P>(plugin_path: P) -> Result<PluginDump, anyhow::Error>
+where
+    P: AsRef<Path>,
+{
+    let plugin_path = plugin_path.as_ref();
+    let plugin_name = plugin_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("{} is not a plugin file", plugin_path.display()))?;
+    let plugin_dir = plugin_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let input = fs::read(plugin_path)
+        .with_context(|| format!("Failed to read plugin {}", plugin_path.display()))?;
+
+    let plugin_info = plugin_parser::read_plugin_info(&input, plugin_name)
+        .with_context(|| format!("Failed to read the header of plugin {}", plugin_name))?;
+
+    let strings_tables = match plugin_info.is_localized {
+        true => plugin_parser::load_strings_tables(&[plugin_name], plugin_dir),
+        false => Default::default(),
+    };
+    let strings_table = strings_tables.get(&plugin_name.to_lowercase());
+    let strings_counts = match plugin_info.is_localized {
+        true => StringsFileType::ALL
+            .into_iter()
+            .map(|file_type| {
+                (
+                    file_type,
+                    strings_table.and_then(|table| table.count(file_type)),
+                )
+            })
+            .collect(),
+        false => Vec::new(),
+    };
+
+    // The masters come first in the load order, so that form IDs referring to them are shown with
+    // their names
+    let load_order = LoadOrder::new(
+        plugin_info
+            .masters
+            .iter()
+            .cloned()
+            .chain([plugin_name.to_string()])
+            .collect(),
+    );
+    let parsed_plugin =
+        plugin_parser::parse_plugin(&input, plugin_name, strings_table, &load_order)
+            .with_context(|| format!("Failed to parse plugin {}", plugin_name))?;
+
+    Ok(PluginDump {
+        plugin_name: plugin_name.to_string(),
+        plugin_info,
+        strings_counts,
+        load_order,
+        magic_effects: parsed_plugin
+            .magic_effects
+            .into_iter()
+            .sorted_by_key(|mgef| mgef.get_global_form_id())
+            .collect(),
+        ingredients: parsed_plugin
+            .ingredients
+            .into_iter()
+            .sorted_by_key(|ig| ig.get_global_form_id())
+            .collect(),
+        num_skipped_records: parsed_plugin.num_skipped_records,
+    })
+}
+
+impl fmt::Display for PluginDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_plugin_info(f)?;
+        if self.plugin_info.is_localized {
+            self.fmt_strings_counts(f)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Magic effects ({}):", self.magic_effects.len())?;
+        for magic_effect in &self.magic_effects {
+            self.fmt_magic_effect(f, magic_effect)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Ingredients ({}):", self.ingredients.len())?;
+        for ingredient in &self.ingredients {
+            self.fmt_ingredient(f, ingredient)?;
+        }
+
+        if self.num_skipped_records > 0 {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "Skipped {} deleted or ignored records",
+                self.num_skipped_records
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PluginDump {
+    fn fmt_plugin_info(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plugin_info = &self.plugin_info;
+        writeln!(f, "Plugin: {}", self.plugin_name)?;
+        writeln!(f, "Version: {}", plugin_info.version)?;
+        writeln!(
+            f,
+            "Records and groups: {}",
+            plugin_info.record_and_group_count
+        )?;
+        let flags = [
+            (plugin_info.is_localized, "localized"),
+            (plugin_info.is_light, "light"),
+        ]
+        .iter()
+        .filter(|(is_set, _)| *is_set)
+        .map(|(_, flag)| *flag)
+        .join(", ");
+        writeln!(
+            f,
+            "Flags: {}",
+            if flags.is_empty() { "none" } else { &flags }
+        )?;
+        match plugin_info.masters.is_empty() {
+            true => writeln!(f, "Masters: none"),
+            false => {
+                writeln!(f, "Masters:")?;
+                for (index, master) in plugin_info.masters.iter().enumerate() {
+                    writeln!(f, "  {:02X} {}", index, master)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn fmt_strings_counts(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Strings files:")?;
+        for (file_type, count) in &self.strings_counts {
+            match count {
+                Some(count) => writeln!(f, "  .{}: {} strings", file_type.get_extension(), count)?,
+                None => writeln!(
+                    f,
+                    "  .{}: not found, its strings will be empty",
+                    file_type.get_extension()
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fmt_magic_effect(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        magic_effect: &MagicEffect,
+    ) -> fmt::Result {
+        writeln!(
+            f,
+            "  {} {} {}",
+            magic_effect
+                .get_global_form_id()
+                .to_display_with(&self.load_order),
+            magic_effect.editor_id,
+            format_name(magic_effect.name.as_deref())
+        )?;
+        writeln!(
+            f,
+            "    {}, base cost {}, flags {:#010X}, archetype {:?}, actor value {}",
+            if magic_effect.is_hostile {
+                "hostile"
+            } else {
+                "beneficial"
+            },
+            magic_effect.base_cost,
+            magic_effect.flags,
+            magic_effect.archetype,
+            magic_effect.actor_value
+        )
+    }
+
+    fn fmt_ingredient(&self, f: &mut fmt::Formatter<'_>, ingredient: &Ingredient) -> fmt::Result {
+        writeln!(
+            f,
+            "  {} {} {}",
+            ingredient
+                .get_global_form_id()
+                .to_display_with(&self.load_order),
+            ingredient.editor_id,
+            format_name(ingredient.name.as_deref())
+        )?;
+        writeln!(
+            f,
+            "    weight {}, value {}",
+            ingredient
+                .weight
+                .map_or_else(|| String::from("?"), |weight| weight.to_string()),
+            ingredient
+                .value
+                .map_or_else(|| String::from("?"), |value| value.to_string())
+        )?;
+
+        let effects = ingredient
+            .get_effects_in_record_order()
+            .unwrap_or_else(|| ingredient.effects.iter().collect());
+        for effect in effects {
+            let form_id = effect.get_global_form_id();
+            // Effects defined by a master can't be named without parsing the master
+            let name = self
+                .magic_effects
+                .iter()
+                .find(|mgef| mgef.get_global_form_id() == form_id)
+                .map_or_else(
+                    || String::from("(defined elsewhere)"),
+                    |mgef| format_name(mgef.name.as_deref()),
+                );
+            writeln!(
+                f,
+                "    {} {}: magnitude {}, duration {}",
+                form_id.to_display_with(&self.load_order),
+                name,
+                effect.magnitude,
+                effect.duration
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_name(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("{:?}", name),
+        None => String::from("<no name>"),
+    }
+}
@@ -0,0 +1,143 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::load_order::LoadOrder;
+
+#[derive(
+    Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, DeserializeFromStr, SerializeDisplay,
+)]
+pub struct GlobalFormId {
+    pub load_order_index: u16,
+    pub id: u32,
+}
+
+impl GlobalFormId {
+    pub fn new(load_order_index: u16, id: u32) -> Self {
+        GlobalFormId {
+            load_order_index,
+            id,
+        }
+    }
+
+    /// Returns the index of the plugin that the record belongs to in the load order
+    pub fn load_order_index(&self) -> u16 {
+        self.load_order_index
+    }
+
+    /// Returns the ID of the record within its plugin, i.e. the form ID without the load order
+    /// byte
+    pub fn local_id(&self) -> u32 {
+        self.id
+    }
+
+    /// Formats the form ID with the name of its plugin, e.g. `Skyrim.esm|03AD66`, so that users can
+    /// find the record in xEdit. `load_order` must be the load order that the load order index
+    /// refers to. Falls back to the plain format if the plugin is not in the load order.
+    pub fn to_display_with(&self, load_order: &LoadOrder) -> String {
+        match load_order.get(self.load_order_index) {
+            Some(plugin_name) => format!("{}|{:06X}", plugin_name, self.id),
+            None => self.to_string(),
+        }
+    }
+
+    /// Sets the load order index to the specified value
+    pub fn set_load_order_index(&mut self, load_order_index: u16) {
+        self.load_order_index = load_order_index;
+    }
+}
+
+impl Display for GlobalFormId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}:{:06x}", self.load_order_index, self.id)
+    }
+}
+
+impl FromStr for GlobalFormId {
+    type Err = String;
+
+    /// Parse a value like `0004:3F0001`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+
+        let load_order_index = {
+            let part = parts
+                .next()
+                .ok_or_else(|| "Missing first value".to_string())?;
+            part.parse::<u16>().map_err(|err| err.to_string())?
+        };
+
+        let id = {
+            let part = parts
+                .next()
+                .ok_or_else(|| "Missing second value".to_string())?;
+            u32::from_str_radix(part, 16).map_err(|err| err.to_string())?
+        };
+
+        Ok(Self {
+            load_order_index,
+            id,
+        })
+    }
+}
+
+/// Refers to a record by the plugin that defines it and its ID within that plugin, which unlike a
+/// `GlobalFormId` doesn't change when the load order changes
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, DeserializeFromStr, SerializeDisplay,
+)]
+pub(crate) struct StableFormId {
+    /// The lowercase name of the plugin
+    pub(crate) plugin: String,
+    pub(crate) local_id: u32,
+}
+
+impl StableFormId {
+    /// Returns the stable form ID of the record with the form ID, or None if its plugin is not in
+    /// the load order
+    pub(crate) fn from_global(
+        global_form_id: &GlobalFormId,
+        load_order: &LoadOrder,
+    ) -> Option<Self> {
+        Some(Self {
+            plugin: load_order
+                .get(global_form_id.load_order_index)?
+                .to_lowercase(),
+            local_id: global_form_id.id,
+        })
+    }
+
+    /// Returns the form ID of the record in the load order, or None if its plugin is not in it
+    pub(crate) fn to_global(&self, load_order: &LoadOrder) -> Option<GlobalFormId> {
+        load_order
+            .find_index(&self.plugin)
+            .map(|index| GlobalFormId::new(index, self.local_id))
+    }
+}
+
+impl Display for StableFormId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}|{:06X}", self.plugin, self.local_id)
+    }
+}
+
+impl FromStr for StableFormId {
+    type Err = String;
+
+    /// Parse a value like `Skyrim.esm|03AD66`, as printed by `GlobalFormId::to_display_with`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (plugin, local_id) = s
+            .rsplit_once('|')
+            .ok_or_else(|| format!("expected a value like Skyrim.esm|03AD66, got {}", s))?;
+        let local_id = u32::from_str_radix(local_id.trim(), 16).map_err(|err| err.to_string())?;
+
+        Ok(Self {
+            plugin: plugin.trim().to_lowercase(),
+            local_id,
+        })
+    }
+}
+
+pub trait FormIdContainer {
+    fn get_global_form_id(&self) -> GlobalFormId;
+}
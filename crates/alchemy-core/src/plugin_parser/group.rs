@@ -40,32 +40,44 @@ pub struct Group {
 }
 
 impl Group {
-    pub fn parse(
-        input: &[u8],
+    /// Parses the records of a group whose header and records data were already split off by
+    /// `split_group`
+    pub fn parse_records_data(
+        header: GroupHeader,
+        group_records_data: &[u8],
         skip_group_records: fn(RecordType) -> bool,
     ) -> IResult<&[u8], Group> {
-        group(input, skip_group_records)
+        let (remaining_input, group_records) = if !skip_group_records(header.label) {
+            parse_group_records(group_records_data, skip_group_records)?
+        } else {
+            (&group_records_data[group_records_data.len()..], Vec::new())
+        };
+
+        Ok((
+            remaining_input,
+            Group {
+                header,
+                group_records,
+            },
+        ))
     }
 }
 
-fn group(input: &[u8], skip_group_records: fn(RecordType) -> bool) -> IResult<&[u8], Group> {
+/// Reads the header of the group at the start of the input and splits off the group's records
+/// data using the group size, without looking at the records. Returns the input after the group.
+pub fn split_group(input: &[u8]) -> IResult<&[u8], (GroupHeader, &[u8])> {
     let (remaining_input, header) = group_header(input)?;
     let (remaining_input, group_records_data) =
         take(header.size_of_group_records)(remaining_input)?;
 
-    let group_records: Vec<GroupRecord> = if !skip_group_records(header.label) {
-        parse_group_records(group_records_data, skip_group_records)?.1
-    } else {
-        Vec::new()
-    };
-
-    Ok((
-        remaining_input,
-        Group {
-            header,
-            group_records,
-        },
-    ))
+    Ok((remaining_input, (header, group_records_data)))
+}
+
+fn group(input: &[u8], skip_group_records: fn(RecordType) -> bool) -> IResult<&[u8], Group> {
+    let (remaining_input, (header, group_records_data)) = split_group(input)?;
+    let (_, group) = Group::parse_records_data(header, group_records_data, skip_group_records)?;
+
+    Ok((remaining_input, group))
 }
 
 fn parse_group_records(
@@ -0,0 +1,316 @@
+use ahash::AHashSet;
+use esplugin::record::Record;
+
+use super::utils::{le_slice_to_u32, parse_zstring};
+
+/// The record type of a plugin's header record
+const HEADER_RECORD_TYPE: &[u8] = b"TES4";
+
+/// Length of the HEDR subrecord: version (f32), number of records and groups (u32) and next
+/// object ID (u32). See https://en.uesp.net/wiki/Skyrim_Mod:Mod_File_Format/TES4
+const HEDR_LENGTH: usize = 12;
+
+/// Offset of the number of records and groups in the HEDR subrecord
+const COUNT_OFFSET: usize = 4;
+
+/// Range of plugin versions that Skyrim SE plugins use. Oldrim plugins use 0.94, SE plugins 1.7
+/// and plugins saved by newer versions of the Creation Kit 1.71. The bounds allow for rounding,
+/// since e.g. 1.7 isn't exactly representable.
+const SUPPORTED_VERSIONS: std::ops::RangeInclusive<f32> = 0.939..=1.711;
+
+/// Offset of the form version in a record header, after the record type, data size, flags, form ID
+/// and version control info. Oblivion's record headers end before it.
+const FORM_VERSION_OFFSET: usize = 20;
+
+/// Length of a record header in Skyrim and later games
+const RECORD_HEADER_LENGTH: usize = 24;
+
+/// Length of a subrecord header: the subrecord type and data size
+const SUBRECORD_HEADER_LENGTH: usize = 6;
+
+/// The newest form version that Skyrim SE reads. Oldrim plugins use 43 and Fallout 4 plugins 131.
+const MAX_FORM_VERSION: u16 = 44;
+
+/// The form ID's load order byte can only refer to 255 masters besides the plugin itself
+const MAX_MASTERS: usize = 255;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginHeaderError {
+    #[error("file does not start with a TES4 header record (found {0:?})")]
+    InvalidMagic(String),
+    #[error("header record has no HEDR subrecord")]
+    MissingHedr,
+    #[error("HEDR subrecord is {0} bytes long, expected {}", HEDR_LENGTH)]
+    MalformedHedr(usize),
+    #[error("{0} doesn't look like a Skyrim SE plugin: {1}")]
+    NotSkyrimSePlugin(String, String),
+    #[error("master list contains a name that is empty or not null terminated")]
+    MalformedMasterName,
+    #[error("master {0} is listed more than once")]
+    DuplicateMaster(String),
+    #[error("plugin lists itself as a master")]
+    SelfMaster,
+    #[error("plugin has {0} masters, more than the maximum of {}", MAX_MASTERS)]
+    TooManyMasters(usize),
+}
+
+/// The validated contents of a plugin's header record
+pub struct PluginHeader {
+    pub version: f32,
+    /// The number of records and groups in the plugin according to the header
+    pub record_and_group_count: u32,
+    pub masters: Vec<String>,
+}
+
+/// Returns an error if the input doesn't start with a plugin header record, so that files which
+/// aren't plugins are reported as such rather than as a confusing parse error
+pub fn check_magic(input: &[u8]) -> Result<(), PluginHeaderError> {
+    match input.get(..HEADER_RECORD_TYPE.len()) {
+        Some(magic) if magic == HEADER_RECORD_TYPE => Ok(()),
+        magic => Err(PluginHeaderError::InvalidMagic(
+            String::from_utf8_lossy(magic.unwrap_or(input)).to_string(),
+        )),
+    }
+}
+
+/// Returns an error naming the plugin if its header looks like it comes from another game or a
+/// console, e.g. a big-endian Xbox 360 plugin. Only the raw bytes of the header are read, so such
+/// plugins fail fast instead of with an error from deep within the record parser.
+pub fn check_format(input: &[u8], plugin_name: &str) -> Result<(), PluginHeaderError> {
+    if let Some(reason) = find_foreign_format(input) {
+        return Err(PluginHeaderError::NotSkyrimSePlugin(
+            plugin_name.to_string(),
+            reason,
+        ));
+    }
+    check_magic(input)?;
+
+    // The HEDR subrecord comes first, so its version can be checked without parsing the record
+    let hedr = input.get(RECORD_HEADER_LENGTH..).unwrap_or_default();
+    if hedr.starts_with(b"HEDR") {
+        if let Some(version) = hedr.get(SUBRECORD_HEADER_LENGTH..SUBRECORD_HEADER_LENGTH + 4) {
+            let version = f32::from_le_bytes(
+                version
+                    .try_into()
+                    .expect("slice should be the size of an f32"),
+            );
+            check_version(version, plugin_name)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns why the plugin's header doesn't look like that of a Skyrim SE plugin, if it doesn't.
+/// Headers that are too short to tell are left to the record parser to report.
+fn find_foreign_format(input: &[u8]) -> Option<String> {
+    match input.get(..HEADER_RECORD_TYPE.len())? {
+        b"4SET" => {
+            return Some("its header is big-endian, like that of a console plugin".to_string())
+        }
+        b"TES3" => return Some("it has a Morrowind header record".to_string()),
+        magic if magic == HEADER_RECORD_TYPE => {}
+        // Not a plugin of any game, which `check_magic` reports
+        _ => return None,
+    }
+
+    let header = input.get(..RECORD_HEADER_LENGTH)?;
+    let max_data_size = input.len() - RECORD_HEADER_LENGTH;
+
+    // The header record is small, since it only holds the HEDR subrecord, the description and the
+    // masters. A data size that only fits in the file when read as big-endian means that the
+    // plugin was written for a big-endian console.
+    let data_size = &header[4..8];
+    let big_endian_data_size = u32::from_be_bytes(
+        data_size
+            .try_into()
+            .expect("slice should be the size of a u32"),
+    );
+    if le_slice_to_u32(data_size)? as usize > max_data_size
+        && big_endian_data_size as usize <= max_data_size
+    {
+        return Some("its header is big-endian, like that of a console plugin".to_string());
+    }
+
+    if &header[FORM_VERSION_OFFSET..] == b"HEDR" {
+        return Some(
+            "its record headers have no form version, like those of Oblivion plugins".to_string(),
+        );
+    }
+    let form_version = u16::from_le_bytes(
+        header[FORM_VERSION_OFFSET..FORM_VERSION_OFFSET + 2]
+            .try_into()
+            .expect("slice should be the size of a u16"),
+    );
+    if form_version > MAX_FORM_VERSION {
+        return Some(format!(
+            "its records use form version {}, newer than Skyrim SE's {}, e.g. because it is a \
+             Fallout 4 plugin",
+            form_version, MAX_FORM_VERSION
+        ));
+    }
+
+    None
+}
+
+/// Returns an error naming the plugin if the version in its HEDR subrecord isn't one of Skyrim's
+fn check_version(version: f32, plugin_name: &str) -> Result<(), PluginHeaderError> {
+    match SUPPORTED_VERSIONS.contains(&version) {
+        true => Ok(()),
+        false => Err(PluginHeaderError::NotSkyrimSePlugin(
+            plugin_name.to_string(),
+            format!(
+                "its header version is {}, Skyrim plugins use 0.94, 1.7 or 1.71",
+                version
+            ),
+        )),
+    }
+}
+
+/// Validates the HEDR subrecord and master list of the plugin's header record
+pub fn validate_header(
+    header_record: &Record,
+    plugin_name: &str,
+) -> Result<PluginHeader, PluginHeaderError> {
+    let hedr = header_record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"HEDR")
+        .ok_or(PluginHeaderError::MissingHedr)?;
+    if hedr.data().len() != HEDR_LENGTH {
+        return Err(PluginHeaderError::MalformedHedr(hedr.data().len()));
+    }
+
+    let version = f32::from_le_bytes(
+        hedr.data()[..COUNT_OFFSET]
+            .try_into()
+            .expect("slice should be the size of an f32"),
+    );
+    check_version(version, plugin_name)?;
+    let record_and_group_count = le_slice_to_u32(&hedr.data()[COUNT_OFFSET..])
+        .ok_or(PluginHeaderError::MalformedHedr(hedr.data().len()))?;
+
+    let masters = header_record
+        .subrecords()
+        .iter()
+        .filter(|s| s.subrecord_type() == b"MAST")
+        .map(|s| {
+            let data = s.data();
+            match data.first() != Some(&b'\0') && data.contains(&b'\0') {
+                true => Ok(parse_zstring(data)),
+                false => Err(PluginHeaderError::MalformedMasterName),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if masters.len() > MAX_MASTERS {
+        return Err(PluginHeaderError::TooManyMasters(masters.len()));
+    }
+    let mut seen_masters = AHashSet::new();
+    for master in &masters {
+        if master.eq_ignore_ascii_case(plugin_name) {
+            return Err(PluginHeaderError::SelfMaster);
+        }
+        if !seen_masters.insert(master.to_lowercase()) {
+            return Err(PluginHeaderError::DuplicateMaster(master.clone()));
+        }
+    }
+
+    Ok(PluginHeader {
+        version,
+        record_and_group_count,
+        masters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::{header_bytes, hedr_data};
+
+    /// Returns the plugin's header with the form version in its record header replaced
+    fn with_form_version(mut header: Vec<u8>, form_version: u16) -> Vec<u8> {
+        header[FORM_VERSION_OFFSET..FORM_VERSION_OFFSET + 2]
+            .copy_from_slice(&form_version.to_le_bytes());
+        header
+    }
+
+    fn assert_rejected(input: &[u8], expected_reason: &str) {
+        match check_format(input, "Test.esp") {
+            Err(PluginHeaderError::NotSkyrimSePlugin(plugin_name, reason)) => {
+                assert_eq!(plugin_name, "Test.esp");
+                assert!(
+                    reason.contains(expected_reason),
+                    "unexpected reason: {}",
+                    reason
+                );
+            }
+            other => panic!("expected the plugin to be rejected, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn skyrim_plugins_are_accepted() {
+        // Oldrim
+        check_format(&with_form_version(header_bytes(0.94, 0), 43), "Test.esp").unwrap();
+        // SE, and SE plugins saved by newer versions of the Creation Kit
+        check_format(&header_bytes(1.7, 0), "Test.esp").unwrap();
+        check_format(&header_bytes(1.71, 0), "Test.esp").unwrap();
+    }
+
+    #[test]
+    fn big_endian_plugins_are_rejected() {
+        let mut header = header_bytes(1.7, 0);
+        header[..4].copy_from_slice(b"4SET");
+        assert_rejected(&header, "big-endian");
+
+        // Only the data size gives away a big-endian header that starts with TES4
+        let mut header = header_bytes(1.7, 0);
+        let data_size = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        header[4..8].copy_from_slice(&data_size.to_be_bytes());
+        assert_rejected(&header, "big-endian");
+    }
+
+    #[test]
+    fn morrowind_plugins_are_rejected() {
+        let mut header = header_bytes(1.3, 0);
+        header[..4].copy_from_slice(b"TES3");
+        assert_rejected(&header, "Morrowind");
+    }
+
+    #[test]
+    fn oblivion_plugins_are_rejected() {
+        // Oblivion's record headers are 20 bytes long, so the HEDR subrecord follows right away
+        let hedr = hedr_data(1.0);
+        let mut header = b"TES4".to_vec();
+        header.extend((hedr.len() as u32 + 6).to_le_bytes());
+        header.extend([0; 12]);
+        header.extend(b"HEDR");
+        header.extend((hedr.len() as u16).to_le_bytes());
+        header.extend(hedr);
+        assert_rejected(&header, "Oblivion");
+    }
+
+    #[test]
+    fn fallout_4_plugins_are_rejected() {
+        assert_rejected(
+            &with_form_version(header_bytes(1.0, 0), 131),
+            "form version 131",
+        );
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        assert_rejected(&header_bytes(1.0, 0), "header version is 1");
+    }
+
+    #[test]
+    fn files_that_are_not_plugins_are_left_to_the_magic_check() {
+        assert!(matches!(
+            check_format(b"PK\x03\x04 not a plugin", "Test.esp"),
+            Err(PluginHeaderError::InvalidMagic(_))
+        ));
+        // Too short to tell, which the record parser reports
+        check_format(b"TES4", "Test.esp").unwrap();
+    }
+}
@@ -0,0 +1,439 @@
+use anyhow::anyhow;
+use arrayvec::ArrayVec;
+use nom::error::ErrorKind;
+use serde::{Deserialize, Serialize};
+
+use std::hash::Hash;
+
+use std::iter::Peekable;
+use std::num::NonZeroU32;
+
+use nom::number::complete::{le_f32, le_u32};
+use nom::sequence::{separated_pair, tuple};
+
+// use crate::error::Error;
+use esplugin::record::Record;
+
+use crate::plugin_parser::utils::{parse_zstring, read_le_u32};
+
+use super::form_id::{FormIdContainer, GlobalFormId};
+use super::strings_table::StringsFileType;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ingredient {
+    pub global_form_id: GlobalFormId,
+    pub editor_id: String,
+    pub name: Option<String>,
+    pub effects: ArrayVec<IngredientEffect, 4>,
+    /// The weight of a single ingredient. `None` for game data exported by older versions.
+    #[serde(default)]
+    pub weight: Option<f32>,
+    /// The base gold value of a single ingredient. `None` for game data exported by older versions.
+    #[serde(default)]
+    pub value: Option<u32>,
+    /// Whether the ingredient is flagged as a food item, i.e. something to cook or eat rather than
+    /// brew with. `None` for game data exported by older versions.
+    #[serde(default)]
+    pub is_food: Option<bool>,
+    /// The name qualified with where the ingredient comes from, e.g. "Frost Salts [CACO]", if
+    /// another ingredient has the same name. Set by the game data, not serialized.
+    #[serde(skip)]
+    pub qualified_name: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct IngredientEffect {
+    pub global_form_id: GlobalFormId,
+    pub duration: u32,
+    pub magnitude: f32,
+    /// The position of the effect in the ingredient record. Effects are sorted by form ID after
+    /// parsing, but the game refers to them by this index (e.g. for known effects). `None` for
+    /// game data exported by older versions.
+    #[serde(default)]
+    pub record_index: Option<u8>,
+}
+
+/// The flag in an ingredient's ENIT subrecord that marks it as a food item
+const FOOD_ITEM_FLAG: u32 = 0x2;
+
+/// The number of ranks of the Experimenter perk, each of which makes eating an ingredient reveal
+/// one more of its effects
+pub const EXPERIMENTER_RANKS: u8 = 3;
+
+impl Ingredient {
+    /// Returns the effects in the order they are listed in the ingredient record, which is the
+    /// order in which eating the ingredient reveals them. `None` for game data exported by older
+    /// versions, which doesn't have the order.
+    pub fn get_effects_in_record_order(&self) -> Option<Vec<&IngredientEffect>> {
+        let mut effects = self.effects.iter().collect::<Vec<_>>();
+        if effects.iter().any(|eff| eff.record_index.is_none()) {
+            return None;
+        }
+        effects.sort_by_key(|eff| eff.record_index);
+        Some(effects)
+    }
+
+    /// Returns the effects that eating the ingredient reveals: the first effect in the record,
+    /// plus one more for each rank of the Experimenter perk. `None` for game data exported by
+    /// older versions, which doesn't have the order.
+    pub fn get_effects_revealed_by_eating(
+        &self,
+        experimenter_rank: u8,
+    ) -> Option<Vec<&IngredientEffect>> {
+        let mut effects = self.get_effects_in_record_order()?;
+        // The last rank reveals all four effects
+        effects.truncate(1 + usize::from(experimenter_rank.min(EXPERIMENTER_RANKS)));
+        Some(effects)
+    }
+
+    /// Parses the ingredient record of the plugin with the given name. Invalid effect magnitudes
+    /// are clamped and logged, see `sanitize_magnitude`. Subrecords that are too short fail the
+    /// record rather than the plugin.
+    pub fn parse<FnGlobalizeFormId, FnParseLstring>(
+        record: &Record,
+        plugin_name: &str,
+        globalize_form_id: FnGlobalizeFormId,
+        parse_lstring: FnParseLstring,
+    ) -> Result<Ingredient, anyhow::Error>
+    where
+        FnGlobalizeFormId: Fn(NonZeroU32) -> Result<GlobalFormId, anyhow::Error>,
+        FnParseLstring: Fn(&[u8], StringsFileType) -> Result<String, anyhow::Error>,
+    {
+        ingredient(record, plugin_name, globalize_form_id, parse_lstring)
+    }
+
+    /// Returns whether the ingredient shares any effects with another ingredient (and thus can be combined)
+    pub fn shares_effects_with(&self, other: &Ingredient) -> bool {
+        self.effects_shared_with(other).peek().is_some()
+    }
+
+    /// Returns an iterator of effects shared between this and another ingredient
+    pub fn effects_shared_with<'a>(
+        &'a self,
+        other: &'a Ingredient,
+    ) -> Peekable<impl Iterator<Item = &IngredientEffect> + '_> {
+        // Note: effects vecs are sorted and limited to 4 elements, so this shouldn't be too slow
+        self.effects
+            .iter()
+            .filter(|self_effect| {
+                other
+                    .effects
+                    .iter()
+                    .any(|other_effect| other_effect.global_form_id == self_effect.global_form_id)
+            })
+            .peekable()
+    }
+}
+
+impl FormIdContainer for Ingredient {
+    fn get_global_form_id(&self) -> crate::plugin_parser::form_id::GlobalFormId {
+        self.global_form_id
+    }
+}
+
+impl Hash for Ingredient {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.get_global_form_id().hash(state);
+    }
+}
+
+impl PartialEq for Ingredient {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_global_form_id() == other.get_global_form_id()
+    }
+}
+
+impl Eq for Ingredient {}
+
+impl FormIdContainer for IngredientEffect {
+    fn get_global_form_id(&self) -> super::form_id::GlobalFormId {
+        self.global_form_id
+    }
+}
+
+/// Returns the magnitude of an effect of the ingredient, or 0 if it's negative, infinite or NaN.
+/// Broken mods sometimes ship such magnitudes, which would otherwise propagate into nonsense
+/// potion values.
+fn sanitize_magnitude(
+    magnitude: f32,
+    ingredient_editor_id: &str,
+    effect_form_id: GlobalFormId,
+    plugin_name: &str,
+) -> f32 {
+    if magnitude.is_finite() && magnitude >= 0.0 {
+        return magnitude;
+    }
+
+    log::warn!(
+        "Ingredient {} in plugin {} has an invalid magnitude of {} for effect {}, using 0 instead",
+        ingredient_editor_id,
+        plugin_name,
+        magnitude,
+        effect_form_id
+    );
+    0.0
+}
+
+fn ingredient<FnGlobalizeFormId, FnParseLstring>(
+    record: &Record,
+    plugin_name: &str,
+    globalize_form_id: FnGlobalizeFormId,
+    parse_lstring: FnParseLstring,
+) -> Result<Ingredient, anyhow::Error>
+where
+    FnGlobalizeFormId: Fn(NonZeroU32) -> Result<GlobalFormId, anyhow::Error>,
+    FnParseLstring: Fn(&[u8], StringsFileType) -> Result<String, anyhow::Error>,
+{
+    assert!(&record.header_type() == b"INGR");
+
+    let form_id = record
+        .header()
+        .form_id()
+        .ok_or_else(|| anyhow!("Ingredient record has no form ID: {:#?}", record))?;
+
+    let global_form_id = globalize_form_id(form_id)?;
+
+    let editor_id = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"EDID")
+        .map(|s| parse_zstring(s.data()))
+        .ok_or_else(|| anyhow!("Ingredient record is missing editor ID: {}", global_form_id))?;
+
+    let full_name = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"FULL")
+        .map(|s| parse_lstring(s.data(), StringsFileType::Strings))
+        .transpose()
+        .map_err(|err| {
+            anyhow!(
+                "Error parsing name of ingredient record {}: {}",
+                global_form_id,
+                err
+            )
+        })?;
+
+    // DATA contains the value (u32) followed by the weight (f32)
+    let (value, weight) = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"DATA")
+        .map(|s| {
+            tuple((le_u32, le_f32))(s.data())
+                .map(|(_, value_and_weight)| value_and_weight)
+                .map_err(|err: nom::Err<(_, ErrorKind)>| {
+                    anyhow!(
+                        "Error parsing data of ingredient record {}: {}",
+                        global_form_id,
+                        err.to_string()
+                    )
+                })
+        })
+        .transpose()?
+        .unzip();
+
+    // ENIT contains the ingredient value (u32) followed by the flags (u32)
+    let is_food = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"ENIT")
+        .map(|s| {
+            tuple((le_u32, le_u32))(s.data())
+                .map(|(_, (_, flags))| flags & FOOD_ITEM_FLAG != 0)
+                .map_err(|err: nom::Err<(_, ErrorKind)>| {
+                    anyhow!(
+                        "Error parsing flags of ingredient record {}: {}",
+                        global_form_id,
+                        err.to_string()
+                    )
+                })
+        })
+        .transpose()?;
+
+    // TODO: cap to 4
+    let mut effects = ArrayVec::<_, 4>::new();
+    let mut current_effect_id = None;
+    for sr in record
+        .subrecords()
+        .iter()
+        // ENIT is a required field that appears just before the effects we care about
+        .skip_while(|sr| sr.subrecord_type() != b"ENIT")
+        .skip(1)
+    {
+        match sr.subrecord_type() {
+            b"EFID" => {
+                let efid = read_le_u32(sr.data(), "effect ID").map_err(|err| {
+                    anyhow!(
+                        "Error parsing effects of ingredient record {}: {}",
+                        global_form_id,
+                        err
+                    )
+                })?;
+                current_effect_id = Some(efid);
+            }
+            b"EFIT" => {
+                if let Some(efid) = current_effect_id {
+                    let (magnitude, duration) = separated_pair(le_f32, le_u32, le_u32)(sr.data())
+                        .map_err(|err: nom::Err<(_, ErrorKind)>| {
+                            anyhow!(
+                                "Error parsing effects of ingredient record {}: {}",
+                                global_form_id,
+                                err.to_string()
+                            )
+                        })?
+                        .1;
+
+                    let efid = NonZeroU32::new(efid).ok_or_else(|| {
+                        anyhow!(
+                            "Error parsing effects of ingredient record {}: EFID is zero",
+                            global_form_id
+                        )
+                    })?;
+                    let global_form_id = globalize_form_id(efid)?;
+                    let magnitude =
+                        sanitize_magnitude(magnitude, &editor_id, global_form_id, plugin_name);
+                    effects.try_push(IngredientEffect {
+                        global_form_id,
+                        duration,
+                        magnitude,
+                        record_index: Some(effects.len() as u8),
+                    })?;
+                } else {
+                    Err(anyhow!(
+                        "Error parsing effects of ingredient record {}: EFIT appeared before EFID",
+                        global_form_id
+                    ))?
+                }
+                current_effect_id = None;
+            }
+            _ => (),
+        }
+    }
+
+    // Sort to make later usage more optimized. The record order is kept in the record indexes.
+    effects.sort_by_key(|eff| eff.get_global_form_id());
+
+    Ok(Ingredient {
+        global_form_id,
+        editor_id,
+        name: full_name,
+        effects,
+        weight,
+        value,
+        is_food,
+        qualified_name: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::{
+        globalize_form_id, ingredient_bytes, parse_record, EFFECT_FORM_ID,
+    };
+    use crate::plugin_parser::utils::parse_lstring;
+
+    const FORM_ID: u32 = 0x800;
+
+    fn parse_with(replacements: &[(&[u8; 4], &[u8])]) -> Result<Ingredient, anyhow::Error> {
+        let record = parse_record(&ingredient_bytes(FORM_ID, replacements));
+        Ingredient::parse(&record, "Test.esp", globalize_form_id, |data, file_type| {
+            parse_lstring(data, true, None, file_type)
+        })
+    }
+
+    fn assert_fails_naming_record(replacements: &[(&[u8; 4], &[u8])], expected_error: &str) {
+        let err = parse_with(replacements)
+            .expect_err("record should fail to parse")
+            .to_string();
+        assert!(
+            err.contains(expected_error)
+                && err.contains(&GlobalFormId::new(0, FORM_ID).to_string()),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn valid_record_parses() {
+        let ingredient = parse_with(&[]).unwrap();
+        assert_eq!(ingredient.global_form_id, GlobalFormId::new(0, FORM_ID));
+        assert_eq!(ingredient.editor_id, "TestIngredient");
+        assert_eq!(ingredient.value, Some(100));
+        assert_eq!(ingredient.is_food, Some(false));
+        assert_eq!(
+            ingredient.effects.as_slice(),
+            [IngredientEffect {
+                global_form_id: GlobalFormId::new(0, EFFECT_FORM_ID),
+                duration: 10,
+                magnitude: 2.0,
+                record_index: Some(0),
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_full_fails_the_record() {
+        assert_fails_naming_record(&[(b"FULL", b"")], "Error parsing name of ingredient record");
+    }
+
+    #[test]
+    fn empty_efid_fails_the_record() {
+        assert_fails_naming_record(
+            &[(b"EFID", b"")],
+            "Error parsing effects of ingredient record",
+        );
+        // Shorter than a u32
+        assert_fails_naming_record(&[(b"EFID", &[0x09, 0x00])], "effect ID");
+    }
+
+    #[test]
+    fn zero_efid_fails_the_record() {
+        assert_fails_naming_record(&[(b"EFID", &0u32.to_le_bytes())], "EFID is zero");
+    }
+
+    #[test]
+    fn short_efit_fails_the_record() {
+        assert_fails_naming_record(
+            &[(b"EFIT", &2.0f32.to_le_bytes())],
+            "Error parsing effects of ingredient record",
+        );
+    }
+
+    #[test]
+    fn invalid_magnitudes_are_clamped_to_zero() {
+        let effect_form_id = GlobalFormId::new(0, EFFECT_FORM_ID);
+        for magnitude in [0.0, 2.5, 1e6] {
+            assert_eq!(
+                sanitize_magnitude(magnitude, "TestIngredient", effect_form_id, "Test.esp"),
+                magnitude
+            );
+        }
+        for magnitude in [-2.5, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(
+                sanitize_magnitude(magnitude, "TestIngredient", effect_form_id, "Test.esp"),
+                0.0,
+                "magnitude {}",
+                magnitude
+            );
+        }
+
+        // Magnitude, area and duration
+        let efit = [
+            (-2.5f32).to_le_bytes(),
+            0u32.to_le_bytes(),
+            10u32.to_le_bytes(),
+        ]
+        .concat();
+        let ingredient = parse_with(&[(b"EFIT", &efit)]).unwrap();
+        assert_eq!(ingredient.effects[0].magnitude, 0.0);
+        assert_eq!(ingredient.effects[0].duration, 10);
+    }
+
+    #[test]
+    fn unterminated_editor_id_ends_with_the_subrecord() {
+        let ingredient = parse_with(&[(b"EDID", b"TestIngredient")]).unwrap();
+        assert_eq!(ingredient.editor_id, "TestIngredient");
+    }
+}
@@ -0,0 +1,401 @@
+use anyhow::anyhow;
+use nom::error::ErrorKind;
+use serde::{Deserialize, Serialize};
+
+use std::num::NonZeroU32;
+
+use nom::number::complete::{le_f32, le_u32};
+
+// use crate::error::Error;
+use esplugin::record::Record;
+
+use crate::plugin_parser::utils::{le_slice_to_u32, parse_zstring};
+
+use super::form_id::{FormIdContainer, GlobalFormId};
+use super::strings_table::StringsFileType;
+
+/// The actor value for health. See https://en.uesp.net/wiki/Skyrim_Mod:Actor_Value_Indices
+pub const ACTOR_VALUE_HEALTH: i32 = 24;
+/// The actor value for magicka
+pub const ACTOR_VALUE_MAGICKA: i32 = 25;
+/// The actor value for stamina
+pub const ACTOR_VALUE_STAMINA: i32 = 26;
+/// The actor value for the rate at which health regenerates
+pub const ACTOR_VALUE_HEAL_RATE: i32 = 27;
+/// The actor value for the rate at which magicka regenerates
+pub const ACTOR_VALUE_MAGICKA_RATE: i32 = 28;
+/// The actor value for the rate at which stamina regenerates
+pub const ACTOR_VALUE_STAMINA_RATE: i32 = 29;
+
+/// The type of a magic effect, which determines what it does.
+/// See https://en.uesp.net/wiki/Skyrim_Mod:Mod_File_Format/MGEF
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum EffectArchetype {
+    ValueModifier,
+    Script,
+    Dispel,
+    CureDisease,
+    Absorb,
+    DualValueModifier,
+    Calm,
+    Demoralize,
+    Frenzy,
+    Disarm,
+    CommandSummoned,
+    Invisibility,
+    Light,
+    Lock,
+    Open,
+    BoundWeapon,
+    SummonCreature,
+    DetectLife,
+    Telekinesis,
+    Paralysis,
+    Reanimate,
+    SoulTrap,
+    TurnUndead,
+    Guide,
+    WerewolfFeed,
+    CureParalysis,
+    CureAddiction,
+    CurePoison,
+    Concussion,
+    ValueAndParts,
+    AccumulateMagnitude,
+    Stagger,
+    PeakValueModifier,
+    Cloak,
+    Werewolf,
+    SlowTime,
+    Rally,
+    EnhanceWeapon,
+    SpawnHazard,
+    Etherealize,
+    Banish,
+    Disguise,
+    GrabActor,
+    VampireLord,
+    Other(u32),
+}
+
+impl Default for EffectArchetype {
+    fn default() -> Self {
+        EffectArchetype::Other(u32::MAX)
+    }
+}
+
+impl From<u32> for EffectArchetype {
+    fn from(value: u32) -> Self {
+        use EffectArchetype::*;
+        match value {
+            0 => ValueModifier,
+            1 => Script,
+            2 => Dispel,
+            3 => CureDisease,
+            4 => Absorb,
+            5 => DualValueModifier,
+            6 => Calm,
+            7 => Demoralize,
+            8 => Frenzy,
+            9 => Disarm,
+            10 => CommandSummoned,
+            11 => Invisibility,
+            12 => Light,
+            15 => Lock,
+            16 => Open,
+            17 => BoundWeapon,
+            18 => SummonCreature,
+            19 => DetectLife,
+            20 => Telekinesis,
+            21 => Paralysis,
+            22 => Reanimate,
+            23 => SoulTrap,
+            24 => TurnUndead,
+            25 => Guide,
+            26 => WerewolfFeed,
+            27 => CureParalysis,
+            28 => CureAddiction,
+            29 => CurePoison,
+            30 => Concussion,
+            31 => ValueAndParts,
+            32 => AccumulateMagnitude,
+            33 => Stagger,
+            34 => PeakValueModifier,
+            35 => Cloak,
+            36 => Werewolf,
+            37 => SlowTime,
+            38 => Rally,
+            39 => EnhanceWeapon,
+            40 => SpawnHazard,
+            41 => Etherealize,
+            42 => Banish,
+            44 => Disguise,
+            45 => GrabActor,
+            46 => VampireLord,
+            other => Other(other),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MagicEffect {
+    pub global_form_id: GlobalFormId,
+    pub editor_id: String,
+    pub name: Option<String>,
+    pub description: String,
+    pub flags: u32,
+    pub is_hostile: bool,
+    pub base_cost: f32,
+    #[serde(default)]
+    pub archetype: EffectArchetype,
+    /// The actor value (e.g. health) that the effect modifies, or -1 if none
+    #[serde(default = "no_actor_value")]
+    pub actor_value: i32,
+}
+
+fn no_actor_value() -> i32 {
+    -1
+}
+
+impl MagicEffect {
+    pub fn parse<FnGlobalizeFormId, FnParseLstring>(
+        record: &Record,
+        globalize_form_id: FnGlobalizeFormId,
+        parse_lstring: FnParseLstring,
+    ) -> Result<MagicEffect, anyhow::Error>
+    where
+        FnGlobalizeFormId: Fn(NonZeroU32) -> Result<GlobalFormId, anyhow::Error>,
+        FnParseLstring: Fn(&[u8], StringsFileType) -> Result<String, anyhow::Error>,
+    {
+        magic_effect(record, globalize_form_id, parse_lstring)
+    }
+}
+
+impl MagicEffect {
+    /// Returns whether the effect restores health (as opposed to e.g. fortifying it)
+    pub fn restores_health(&self) -> bool {
+        self.restores_actor_value(ACTOR_VALUE_HEALTH)
+    }
+
+    /// Returns whether the effect restores health, magicka or stamina
+    pub fn restores_vitals(&self) -> bool {
+        [ACTOR_VALUE_HEALTH, ACTOR_VALUE_MAGICKA, ACTOR_VALUE_STAMINA]
+            .into_iter()
+            .any(|actor_value| self.restores_actor_value(actor_value))
+    }
+
+    fn restores_actor_value(&self, actor_value: i32) -> bool {
+        // "Recover" flag, which restores the actor value when the effect ends
+        let is_recover = self.flags & 0x00000002 != 0;
+
+        !self.is_hostile
+            && !is_recover
+            && self.actor_value == actor_value
+            && matches!(
+                self.archetype,
+                EffectArchetype::ValueModifier | EffectArchetype::PeakValueModifier
+            )
+    }
+}
+
+impl FormIdContainer for MagicEffect {
+    fn get_global_form_id(&self) -> super::form_id::GlobalFormId {
+        self.global_form_id
+    }
+}
+
+// TODO: only parse magic effects which are actually used by ingredients?
+
+fn magic_effect<FnGlobalizeFormId, FnParseLstring>(
+    record: &Record,
+    globalize_form_id: FnGlobalizeFormId,
+    parse_lstring: FnParseLstring,
+) -> Result<MagicEffect, anyhow::Error>
+where
+    FnGlobalizeFormId: Fn(NonZeroU32) -> Result<GlobalFormId, anyhow::Error>,
+    FnParseLstring: Fn(&[u8], StringsFileType) -> Result<String, anyhow::Error>,
+{
+    assert!(&record.header_type() == b"MGEF");
+
+    let form_id = record
+        .header()
+        .form_id()
+        .ok_or_else(|| anyhow!("Magic effect record has no form ID: {:#?}", record))?;
+
+    let global_form_id = globalize_form_id(form_id)?;
+
+    let editor_id = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"EDID")
+        .ok_or_else(|| {
+            anyhow!(
+                "Magic effect record is missing editor ID: {}",
+                global_form_id
+            )
+        })
+        .map(|s| parse_zstring(s.data()))?;
+
+    let full_name = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"FULL")
+        .map(|s| parse_lstring(s.data(), StringsFileType::Strings))
+        .transpose()
+        .map_err(|err| {
+            anyhow!(
+                "Error parsing name of magic effect record {}: {}",
+                global_form_id,
+                err
+            )
+        })?;
+
+    let description = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"DNAM")
+        .or_else(|| {
+            log::warn!(
+                "Magic effect record is missing description: {}",
+                global_form_id
+            );
+            None
+        })
+        .map(|s| parse_lstring(s.data(), StringsFileType::DlStrings))
+        .transpose()
+        .map_err(|err| {
+            anyhow!(
+                "Error parsing description of magic effect record {}: {}",
+                global_form_id,
+                err
+            )
+        })?
+        .unwrap_or_default();
+
+    let data = record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == b"DATA")
+        .ok_or_else(|| anyhow!("Magic effect record is missing data: {}", global_form_id))?
+        .data();
+
+    let (flags, base_cost) = nom::sequence::pair(le_u32, le_f32)(data)
+        .map(|d| d.1)
+        .map_err(|err: nom::Err<(_, ErrorKind)>| {
+            anyhow!(
+                "Error parsing flags and base cost of magic effect record {}: {}",
+                global_form_id,
+                err.to_string()
+            )
+        })?;
+
+    // The archetype is followed by the primary actor value
+    const ARCHETYPE_OFFSET: usize = 64;
+    let archetype_data = data.get(ARCHETYPE_OFFSET..).unwrap_or_default();
+    let (archetype, actor_value) = match (
+        le_slice_to_u32(archetype_data),
+        archetype_data.get(4..).and_then(le_slice_to_u32),
+    ) {
+        (Some(archetype), Some(actor_value)) => {
+            (EffectArchetype::from(archetype), actor_value as i32)
+        }
+        _ => {
+            log::warn!(
+                "Magic effect record is missing archetype: {}",
+                global_form_id
+            );
+            (EffectArchetype::default(), no_actor_value())
+        }
+    };
+
+    let is_hostile = flags & 0x00000001 == 1;
+
+    Ok(MagicEffect {
+        global_form_id,
+        editor_id,
+        name: full_name,
+        base_cost,
+        description,
+        flags,
+        is_hostile,
+        archetype,
+        actor_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::{globalize_form_id, magic_effect_bytes, parse_record};
+    use crate::plugin_parser::utils::parse_lstring;
+
+    const FORM_ID: u32 = 0x900;
+
+    fn parse_with(replacements: &[(&[u8; 4], &[u8])]) -> Result<MagicEffect, anyhow::Error> {
+        let record = parse_record(&magic_effect_bytes(FORM_ID, replacements));
+        MagicEffect::parse(&record, globalize_form_id, |data, file_type| {
+            parse_lstring(data, true, None, file_type)
+        })
+    }
+
+    fn assert_fails_naming_record(replacements: &[(&[u8; 4], &[u8])], expected_error: &str) {
+        let err = parse_with(replacements)
+            .expect_err("record should fail to parse")
+            .to_string();
+        assert!(
+            err.contains(expected_error)
+                && err.contains(&GlobalFormId::new(0, FORM_ID).to_string()),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn valid_record_parses() {
+        let magic_effect = parse_with(&[]).unwrap();
+        assert_eq!(magic_effect.global_form_id, GlobalFormId::new(0, FORM_ID));
+        assert_eq!(magic_effect.editor_id, "TestDamageHealth");
+        assert_eq!(magic_effect.base_cost, 2.5);
+        assert!(magic_effect.is_hostile);
+        assert_eq!(magic_effect.archetype, EffectArchetype::ValueModifier);
+        assert_eq!(magic_effect.actor_value, ACTOR_VALUE_HEALTH);
+    }
+
+    #[test]
+    fn empty_full_fails_the_record() {
+        assert_fails_naming_record(
+            &[(b"FULL", b"")],
+            "Error parsing name of magic effect record",
+        );
+    }
+
+    #[test]
+    fn short_dnam_fails_the_record() {
+        assert_fails_naming_record(
+            &[(b"DNAM", &[0x02, 0x00, 0x00])],
+            "Error parsing description of magic effect record",
+        );
+    }
+
+    #[test]
+    fn short_data_fails_the_record() {
+        assert_fails_naming_record(
+            &[(b"DATA", &1u32.to_le_bytes())],
+            "Error parsing flags and base cost of magic effect record",
+        );
+    }
+
+    #[test]
+    fn data_without_archetype_falls_back_to_none() {
+        let data = [1u32.to_le_bytes(), 2.5f32.to_le_bytes()].concat();
+        let magic_effect = parse_with(&[(b"DATA", &data)]).unwrap();
+        assert_eq!(magic_effect.archetype, EffectArchetype::default());
+        assert_eq!(magic_effect.actor_value, no_actor_value());
+    }
+
+    #[test]
+    fn unterminated_editor_id_ends_with_the_subrecord() {
+        let magic_effect = parse_with(&[(b"EDID", b"TestDamageHealth")]).unwrap();
+        assert_eq!(magic_effect.editor_id, "TestDamageHealth");
+    }
+}
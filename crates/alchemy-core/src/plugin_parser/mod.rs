@@ -0,0 +1,408 @@
+use std::{num::NonZeroU32, time::Instant};
+
+use anyhow::anyhow;
+use esplugin::record::Record;
+use itertools::{Either, Itertools};
+
+use crate::{
+    load_order::LoadOrder,
+    plugin_parser::{
+        form_id::GlobalFormId,
+        ingredient::Ingredient,
+        magic_effect::MagicEffect,
+        utils::{parse_lstring, parse_string},
+    },
+};
+
+pub use self::header::PluginHeaderError;
+pub use self::skill_records::AlchemyXpRecords;
+pub use self::strings_table::{
+    find_strings_source, load_strings_tables, StringsFileType, StringsTable,
+};
+use self::utils::nom_err_to_anyhow_err;
+
+pub(crate) mod form_id;
+mod group;
+mod header;
+pub(crate) mod ingredient;
+pub(crate) mod magic_effect;
+mod skill_records;
+mod strings_table;
+#[cfg(test)]
+pub(crate) mod test_records;
+pub(crate) mod utils;
+
+/// The data we're interested in from a single plugin
+pub struct ParsedPlugin {
+    pub ingredients: Vec<Ingredient>,
+    pub magic_effects: Vec<MagicEffect>,
+    /// The plugin's values of the settings that the Alchemy XP depends on
+    pub alchemy_xp_records: AlchemyXpRecords,
+    /// Whether the plugin is a light master (ESL)
+    pub is_light: bool,
+    /// The number of ingredient and magic effect records that were skipped because they are
+    /// deleted or ignored
+    pub num_skipped_records: usize,
+}
+
+/// The header flag of plugins whose lstrings are IDs of strings in separate strings files
+const LOCALIZED_FLAG: u32 = 0x80;
+
+/// The header flag of light masters (ESL)
+const LIGHT_FLAG: u32 = 0x200;
+
+/// The record flag of records that are deleted, which the game doesn't load
+const DELETED_FLAG: u32 = 0x20;
+
+/// The record flag of records that are ignored, which the game doesn't load either
+const IGNORED_FLAG: u32 = 0x1000;
+
+/// Returns whether the record is deleted or ignored. Such records mustn't be parsed, since they
+/// would shadow the valid versions of the record in earlier plugins.
+fn is_deleted_or_ignored(record: &Record) -> bool {
+    (record.header().flags() & (DELETED_FLAG | IGNORED_FLAG)) != 0
+}
+
+/// What a plugin's header says about the plugin
+pub struct PluginInfo {
+    pub version: f32,
+    /// The number of records and groups in the plugin according to the header
+    pub record_and_group_count: u32,
+    pub masters: Vec<String>,
+    /// Whether the plugin's lstrings are stored in strings files, see `load_strings_tables`
+    pub is_localized: bool,
+    /// Whether the plugin is a light master (ESL)
+    pub is_light: bool,
+}
+
+/// Returns whether the plugin is localized, i.e. needs its strings table to be parsed. Returns
+/// false if the header can't be read, which `parse_plugin` reports.
+pub fn is_localized(input: &[u8]) -> bool {
+    header::check_magic(input).is_ok()
+        && Record::parse(input, esplugin::GameId::SkyrimSE, false)
+            .map_or(false, |(_, header_record)| {
+                (header_record.header().flags() & LOCALIZED_FLAG) != 0
+            })
+}
+
+/// Parses and validates the plugin's header record, returning the input after it
+fn parse_header<'a>(
+    input: &'a [u8],
+    plugin_name: &str,
+) -> Result<(&'a [u8], PluginInfo), anyhow::Error> {
+    header::check_format(input, plugin_name)?;
+    let (remaining_input, header_record) =
+        Record::parse(input, esplugin::GameId::SkyrimSE, false).map_err(nom_err_to_anyhow_err)?;
+
+    log::trace!("Plugin header_record: {:#?}", header_record);
+
+    let header::PluginHeader {
+        version,
+        record_and_group_count,
+        masters,
+    } = header::validate_header(&header_record, plugin_name)?;
+
+    let flags = header_record.header().flags();
+    let plugin_info = PluginInfo {
+        version,
+        record_and_group_count,
+        masters,
+        is_localized: (flags & LOCALIZED_FLAG) != 0,
+        // Plugins with the .esl extension are light masters even without the ESL flag
+        is_light: (flags & LIGHT_FLAG) != 0 || plugin_name.to_lowercase().ends_with(".esl"),
+    };
+
+    Ok((remaining_input, plugin_info))
+}
+
+/// Reads the plugin's header without parsing its records, e.g. to find its masters
+pub fn read_plugin_info(input: &[u8], plugin_name: &str) -> Result<PluginInfo, anyhow::Error> {
+    parse_header(input, plugin_name).map(|(_, plugin_info)| plugin_info)
+}
+
+/// Parses the ingredients and magic effects of the plugin, and the settings that the Alchemy XP
+/// depends on. The strings table must be given if the plugin is localized, see
+/// `load_strings_tables`.
+pub fn parse_plugin<'a>(
+    input: &'a [u8],
+    plugin_name: &str,
+    strings_table: Option<&StringsTable>,
+    load_order: &LoadOrder,
+) -> Result<ParsedPlugin, anyhow::Error> {
+    log::trace!("Parsing plugin {}", plugin_name);
+
+    let (
+        remaining_input,
+        PluginInfo {
+            version,
+            record_and_group_count,
+            masters,
+            is_localized,
+            is_light,
+        },
+    ) = parse_header(input, plugin_name)?;
+
+    log::trace!("Plugin version: {}", version);
+    log::trace!("Plugin masters: {:#?}", masters);
+    log::trace!("Plugin is_localized: {:?}", is_localized);
+    log::trace!("Plugin is_light: {:?}", is_light);
+
+    let globalize_form_id = |form_id: NonZeroU32| -> Result<GlobalFormId, anyhow::Error> {
+        // See https://en.uesp.net/wiki/Skyrim:Form_ID
+        let mod_id = (u32::from(form_id) >> 24) as usize;
+        let num_masters = masters.len();
+
+        #[allow(clippy::comparison_chain)]
+        let mod_name = {
+            if mod_id == num_masters {
+                Ok(String::from(plugin_name))
+            } else if mod_id < num_masters {
+                Ok(masters[mod_id].clone())
+            } else {
+                Err(anyhow!(
+                    "record has invalid master reference in form ID {:x}",
+                    form_id
+                ))
+            }
+        }?;
+
+        // The last six hex digits are the ID of the record itself
+        let id = u32::from(form_id) & 0x00FFFFFF;
+
+        let load_order_index = load_order
+            .find_index(&mod_name)
+            .ok_or_else(|| anyhow!("plugin {} not found in load order!", &mod_name))?;
+
+        Ok(GlobalFormId::new(load_order_index, id))
+    };
+
+    let parse_lstring =
+        |data: &[u8], file_type: StringsFileType| -> Result<String, anyhow::Error> {
+            parse_lstring(data, is_localized, strings_table, file_type)
+        };
+
+    log::trace!("Plugin record_and_group_count: {}", record_and_group_count);
+
+    // We're only interested in ingredients and magic effects, and the game settings and actor
+    // values that the Alchemy XP depends on
+    let skip_group_records = |label| !matches!(&label, b"INGR" | b"MGEF" | b"GMST" | b"AVIF");
+
+    // Top-level groups are skipped using their size, so only the byte ranges of the interesting
+    // groups are parsed
+    let start = Instant::now();
+    let mut interesting_groups = Vec::new();
+    let mut skipped_bytes = 0;
+    let mut input1 = remaining_input;
+    while !input1.is_empty() {
+        let (input2, (header, group_records_data)) =
+            group::split_group(input1).map_err(nom_err_to_anyhow_err)?;
+        if skip_group_records(header.label) {
+            skipped_bytes += group_records_data.len();
+        } else {
+            let (_, group) =
+                group::Group::parse_records_data(header, group_records_data, skip_group_records)
+                    .map_err(nom_err_to_anyhow_err)?;
+            interesting_groups.push(group);
+        }
+        input1 = input2;
+    }
+    log::debug!(
+        "Parsed groups of plugin {}, skipping {} of {} bytes (in {:?})",
+        plugin_name,
+        skipped_bytes,
+        remaining_input.len(),
+        start.elapsed()
+    );
+
+    // Merged plugins can contain multiple top-level groups of the same type, so records are
+    // collected from all of them
+    let ingredient_groups = interesting_groups
+        .iter()
+        .filter(|ig| &ig.header.label == b"INGR")
+        .collect::<Vec<_>>();
+
+    // TODO: if all records failed to parse, that's probably a problem
+
+    let mut num_skipped_records = 0;
+
+    let ingredients = {
+        let (ingredients, errors): (Vec<_>, Vec<_>) = ingredient_groups
+            .iter()
+            .flat_map(|ig| ig.group_records.iter())
+            .filter_map(|rec| {
+                match rec {
+                    group::GroupRecord::Group(_) => {
+                        // AFAICT ingredient groups don't have subgroups
+                        log::warn!("Found unexpected subgroup in INGR group, ignoring");
+                        None
+                    }
+                    group::GroupRecord::Record(rec) => {
+                        if &rec.header_type() != b"INGR" {
+                            // Unexpected non-ingredient record
+                            log::warn!("Found unexpected non-INGR record in INGR group, ignoring");
+                            None
+                        } else if is_deleted_or_ignored(rec) {
+                            log::debug!(
+                                "Skipping deleted or ignored INGR record {:08X} in plugin {}",
+                                rec.header().form_id().map_or(0, u32::from),
+                                plugin_name
+                            );
+                            num_skipped_records += 1;
+                            None
+                        } else {
+                            Some(rec)
+                        }
+                    }
+                }
+            })
+            .map(|rec| Ingredient::parse(rec, plugin_name, globalize_form_id, parse_lstring))
+            .partition_map(|r| match r {
+                Ok(v) => Either::Left(v),
+                Err(v) => Either::Right(v),
+            });
+
+        if !errors.is_empty() {
+            log::error!(
+                "Skipped {} ingredient records of plugin {} that failed to parse:\n{}",
+                errors.len(),
+                plugin_name,
+                errors.iter().map(|err| format!("{:#}", err)).join("\n")
+            );
+        }
+
+        ingredients
+    };
+
+    let magic_effects_groups = interesting_groups
+        .iter()
+        .filter(|ig| &ig.header.label == b"MGEF")
+        .collect::<Vec<_>>();
+
+    let magic_effects = {
+        let (magic_effects, errors): (Vec<_>, Vec<_>) = magic_effects_groups
+            .iter()
+            .flat_map(|ig| ig.group_records.iter())
+            .filter_map(|rec| {
+                match rec {
+                    group::GroupRecord::Group(_) => {
+                        // AFAICT magic effect groups don't have subgroups
+                        log::warn!("Found unexpected subgroup in MGEF group, ignoring");
+                        None
+                    }
+                    group::GroupRecord::Record(rec) => {
+                        if &rec.header_type() != b"MGEF" {
+                            // Unexpected non-magic effect record
+                            log::warn!("Found unexpected non-MGEF record in MGEF group, ignoring");
+                            None
+                        } else if is_deleted_or_ignored(rec) {
+                            log::debug!(
+                                "Skipping deleted or ignored MGEF record {:08X} in plugin {}",
+                                rec.header().form_id().map_or(0, u32::from),
+                                plugin_name
+                            );
+                            num_skipped_records += 1;
+                            None
+                        } else {
+                            Some(rec)
+                        }
+                    }
+                }
+            })
+            .map(|rec| MagicEffect::parse(rec, globalize_form_id, parse_lstring))
+            .partition_map(|r| match r {
+                Ok(v) => Either::Left(v),
+                Err(v) => Either::Right(v),
+            });
+
+        if !errors.is_empty() {
+            log::error!(
+                "Skipped {} magic effect records of plugin {} that failed to parse:\n{}",
+                errors.len(),
+                plugin_name,
+                errors.iter().map(|err| format!("{:#}", err)).join("\n")
+            );
+        }
+
+        magic_effects
+    };
+
+    if ingredient_groups.len() > 1 || magic_effects_groups.len() > 1 {
+        log::debug!(
+            "Plugin {} has {} INGR groups and {} MGEF groups",
+            plugin_name,
+            ingredient_groups.len(),
+            magic_effects_groups.len()
+        );
+    }
+
+    let alchemy_xp_records = skill_records::parse_alchemy_xp_records(
+        interesting_groups
+            .iter()
+            .filter(|ig| matches!(&ig.header.label, b"GMST" | b"AVIF"))
+            .flat_map(|ig| ig.group_records.iter())
+            .filter_map(|rec| match rec {
+                group::GroupRecord::Record(rec) if !is_deleted_or_ignored(rec) => Some(rec),
+                _ => None,
+            }),
+    );
+
+    Ok(ParsedPlugin {
+        ingredients,
+        magic_effects,
+        alchemy_xp_records,
+        is_light,
+        num_skipped_records,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::{
+        group_bytes, header_bytes, ingredient_bytes, magic_effect_bytes,
+    };
+
+    #[test]
+    fn truncated_subrecords_fail_only_their_record() {
+        let plugin = [
+            header_bytes(1.7, LOCALIZED_FLAG),
+            group_bytes(
+                b"INGR",
+                &[
+                    ingredient_bytes(0x800, &[]),
+                    ingredient_bytes(0x801, &[(b"FULL", b"")]),
+                    ingredient_bytes(0x802, &[(b"EFID", b"")]),
+                    ingredient_bytes(0x803, &[(b"EFID", &0u32.to_le_bytes())]),
+                    ingredient_bytes(0x804, &[(b"EDID", b"Unterminated")]),
+                ],
+            ),
+            group_bytes(
+                b"MGEF",
+                &[
+                    magic_effect_bytes(0x900, &[(b"DNAM", &[0x02])]),
+                    magic_effect_bytes(0x901, &[]),
+                ],
+            ),
+        ]
+        .concat();
+        let load_order = LoadOrder::new(vec!["Test.esp".to_string()]);
+
+        let parsed_plugin = parse_plugin(&plugin, "Test.esp", None, &load_order).unwrap();
+        assert_eq!(
+            parsed_plugin
+                .ingredients
+                .iter()
+                .map(|ingredient| (ingredient.global_form_id.local_id(), &*ingredient.editor_id))
+                .collect::<Vec<_>>(),
+            [(0x800, "TestIngredient"), (0x804, "Unterminated")]
+        );
+        assert_eq!(
+            parsed_plugin
+                .magic_effects
+                .iter()
+                .map(|magic_effect| magic_effect.global_form_id.local_id())
+                .collect::<Vec<_>>(),
+            [0x901]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use esplugin::record::Record;
+
+use crate::{plugin_parser::utils::parse_zstring, skill_xp::AlchemyXpSettings};
+
+/// The editor ID of the game setting for the exponent of the skill XP curve
+const SKILL_USE_CURVE_EDITOR_ID: &str = "fSkillUseCurve";
+
+/// The editor ID of the actor value record of the Alchemy skill
+const ALCHEMY_EDITOR_ID: &str = "AVAlchemy";
+
+/// The values from the GMST and AVIF records that the Alchemy XP settings come from. Each is
+/// `None` if the plugin doesn't set it, so that it doesn't override the value of an earlier plugin.
+#[derive(Debug, Default)]
+pub struct AlchemyXpRecords {
+    /// The fSkillUseCurve game setting
+    pub skill_use_curve: Option<f32>,
+    /// The skill use mult, skill use offset, skill improve mult and skill improve offset of the
+    /// Alchemy skill, from the AVSK subrecord of its AVIF record
+    pub alchemy_skill_data: Option<[f32; 4]>,
+}
+
+impl AlchemyXpRecords {
+    /// Overrides the settings with the values that the plugin sets
+    pub fn apply_to(&self, settings: &mut AlchemyXpSettings) {
+        if let Some(skill_use_curve) = self.skill_use_curve {
+            settings.skill_use_curve = skill_use_curve;
+        }
+        if let Some([use_mult, use_offset, improve_mult, improve_offset]) = self.alchemy_skill_data
+        {
+            settings.skill_use_mult = use_mult;
+            settings.skill_use_offset = use_offset;
+            settings.skill_improve_mult = improve_mult;
+            settings.skill_improve_offset = improve_offset;
+        }
+    }
+}
+
+fn find_subrecord<'r>(record: &'r Record, subrecord_type: &[u8; 4]) -> Option<&'r [u8]> {
+    record
+        .subrecords()
+        .iter()
+        .find(|s| s.subrecord_type() == subrecord_type)
+        .map(|s| s.data())
+}
+
+fn read_f32(data: &[u8]) -> Option<f32> {
+    Some(f32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// Finds the values that the Alchemy XP depends on in the plugin's GMST and AVIF records. Records
+/// of other types are ignored.
+pub(super) fn parse_alchemy_xp_records<'r>(
+    records: impl Iterator<Item = &'r Record>,
+) -> AlchemyXpRecords {
+    let mut alchemy_xp_records = AlchemyXpRecords::default();
+    for record in records {
+        let editor_id = match find_subrecord(record, b"EDID") {
+            Some(data) => parse_zstring(data),
+            None => continue,
+        };
+        match (&record.header_type(), editor_id.as_str()) {
+            (b"GMST", SKILL_USE_CURVE_EDITOR_ID) => {
+                alchemy_xp_records.skill_use_curve =
+                    find_subrecord(record, b"DATA").and_then(read_f32);
+            }
+            (b"AVIF", ALCHEMY_EDITOR_ID) => {
+                alchemy_xp_records.alchemy_skill_data =
+                    find_subrecord(record, b"AVSK").and_then(|data| {
+                        Some([
+                            read_f32(data)?,
+                            read_f32(data.get(4..)?)?,
+                            read_f32(data.get(8..)?)?,
+                            read_f32(data.get(12..)?)?,
+                        ])
+                    });
+            }
+            _ => {}
+        }
+    }
+
+    alchemy_xp_records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_parser::test_records::{parse_record, record_bytes};
+
+    #[test]
+    fn plugin_records_override_the_defaults() {
+        let avsk = [1.5f32, 2.0, 3.0, 40.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        let records = [
+            record_bytes(
+                b"GMST",
+                0x801,
+                &[
+                    (b"EDID", b"fSkillUseCurve\0"),
+                    (b"DATA", &2.0f32.to_le_bytes()),
+                ],
+            ),
+            record_bytes(
+                b"AVIF",
+                0x802,
+                &[(b"EDID", b"AVAlchemy\0"), (b"AVSK", &avsk)],
+            ),
+            // Other settings and skills are ignored
+            record_bytes(
+                b"GMST",
+                0x803,
+                &[
+                    (b"EDID", b"fSkillUseMult\0"),
+                    (b"DATA", &9.0f32.to_le_bytes()),
+                ],
+            ),
+            record_bytes(
+                b"AVIF",
+                0x804,
+                &[(b"EDID", b"AVSmithing\0"), (b"AVSK", &[0; 16])],
+            ),
+        ]
+        .map(|bytes| parse_record(&bytes));
+
+        let mut settings = AlchemyXpSettings::default();
+        parse_alchemy_xp_records(records.iter()).apply_to(&mut settings);
+        assert_eq!(
+            settings,
+            AlchemyXpSettings {
+                skill_use_curve: 2.0,
+                skill_use_mult: 1.5,
+                skill_use_offset: 2.0,
+                skill_improve_mult: 3.0,
+                skill_improve_offset: 40.0,
+            }
+        );
+    }
+
+    #[test]
+    fn settings_that_plugins_leave_out_are_kept() {
+        let records = [
+            record_bytes(b"GMST", 0x801, &[(b"EDID", b"fSkillUseCurve\0")]),
+            // Too short to hold all four values
+            record_bytes(
+                b"AVIF",
+                0x802,
+                &[(b"EDID", b"AVAlchemy\0"), (b"AVSK", &[0; 12])],
+            ),
+        ]
+        .map(|bytes| parse_record(&bytes));
+
+        let mut settings = AlchemyXpSettings::default();
+        parse_alchemy_xp_records(records.iter()).apply_to(&mut settings);
+        assert_eq!(settings, AlchemyXpSettings::default());
+    }
+}
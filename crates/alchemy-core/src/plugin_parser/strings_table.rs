@@ -0,0 +1,399 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    mem,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
+
+use ahash::AHashMap;
+use bsa::Reader;
+use nom::{error::ErrorKind, number::complete::le_u32};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::plugin_parser::utils::parse_zstring;
+
+use super::utils::{le_slice_to_u32, nom_err_to_anyhow_err};
+
+fn strip_ext_from_plugin_name(plugin_name: &str) -> String {
+    let plugin_name_without_ext = PathBuf::from_str(plugin_name)
+        .expect("plugin_name should be valid path")
+        .file_stem()
+        .expect("plugin_name should not be empty")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    plugin_name_without_ext
+}
+
+fn get_bsa_name(plugin_name: &str) -> String {
+    let plugin_name_without_ext = strip_ext_from_plugin_name(plugin_name);
+    match plugin_name_without_ext.to_lowercase().as_str() {
+        "dawnguard" | "dragonborn" | "hearthfires" | "skyrim" | "update" => {
+            String::from("Skyrim - Interface.bsa")
+        }
+        _ => plugin_name_without_ext + ".bsa",
+    }
+}
+
+/// The type of strings file that an lstring is stored in, which depends on the field.
+/// See https://en.uesp.net/wiki/Skyrim_Mod:String_Table_File_Format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringsFileType {
+    /// Names, e.g. FULL fields
+    Strings,
+    /// Descriptions, e.g. DESC and MGEF DNAM fields
+    DlStrings,
+    /// Dialogue, e.g. INFO NAM1 fields
+    IlStrings,
+}
+
+impl StringsFileType {
+    pub const ALL: [StringsFileType; 3] = [
+        StringsFileType::Strings,
+        StringsFileType::DlStrings,
+        StringsFileType::IlStrings,
+    ];
+
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            StringsFileType::Strings => "strings",
+            StringsFileType::DlStrings => "dlstrings",
+            StringsFileType::IlStrings => "ilstrings",
+        }
+    }
+
+    /// In .dlstrings and .ilstrings files, each string is prefixed with its length
+    fn has_length_prefix(&self) -> bool {
+        *self != StringsFileType::Strings
+    }
+}
+
+fn get_strings_path(plugin_name: &str, file_type: StringsFileType) -> String {
+    format!(
+        "strings/{}_english.{}",
+        strip_ext_from_plugin_name(plugin_name).to_lowercase(),
+        file_type.get_extension()
+    )
+}
+
+/// The kind of an archive with the name of a plugin's BSA, as detected from its header. Only
+/// BSAs of the Oblivion and Skyrim versions can be read, but plugins ported from other games can
+/// come with archives in other formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArchiveFormat {
+    /// A BSA of Oblivion (version 103), Skyrim (104) or Skyrim Special Edition (105)
+    Bsa,
+    /// A BSA of Morrowind, which has no magic number
+    MorrowindBsa,
+    /// A BA2 of Fallout 4 or Starfield
+    Ba2,
+    Unknown,
+}
+
+impl ArchiveFormat {
+    fn detect(path: &Path) -> Result<Self, io::Error> {
+        let mut header = [0; 8];
+        match File::open(path)?.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(Self::Unknown),
+            Err(err) => return Err(err),
+        }
+
+        let (magic, version) = header.split_at(4);
+        Ok(match (magic, le_slice_to_u32(version)) {
+            (b"BSA\0", Some(103..=105)) => Self::Bsa,
+            (b"BTDX", _) => Self::Ba2,
+            _ if le_slice_to_u32(magic) == Some(0x100) => Self::MorrowindBsa,
+            _ => Self::Unknown,
+        })
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::Bsa => "a Skyrim BSA",
+            Self::MorrowindBsa => "a Morrowind BSA",
+            Self::Ba2 => "a Fallout 4 BA2",
+            Self::Unknown => "not a BSA",
+        }
+    }
+}
+
+/// Returns where the names of the localized plugin would be loaded from: its loose strings file, or
+/// otherwise its BSA if that is a BSA the game can read. Returns `None` if there is neither, in
+/// which case the plugin's ingredients and effects have no names.
+pub fn find_strings_source(plugin_name: &str, game_plugins_path: &Path) -> Option<PathBuf> {
+    let loose_path =
+        game_plugins_path.join(get_strings_path(plugin_name, StringsFileType::Strings));
+    if loose_path.exists() {
+        return Some(loose_path);
+    }
+
+    let bsa_path = game_plugins_path.join(get_bsa_name(plugin_name));
+    matches!(ArchiveFormat::detect(&bsa_path), Ok(ArchiveFormat::Bsa)).then(|| bsa_path)
+}
+
+/// A strings file to load for a plugin
+struct StringsFileRequest<'p> {
+    plugin_name: &'p str,
+    file_type: StringsFileType,
+    /// The path of the file relative to the game's Data directory, which is also its path in a BSA
+    strings_path: String,
+}
+
+/// The contents of a strings file that was read for a plugin
+type LoadedStringsFile<'p> = (&'p str, StringsFileType, Vec<u8>);
+
+/// Reads the requested strings files from the BSA, which is opened and listed only once. Files
+/// that aren't in the BSA are skipped.
+fn read_from_bsa<'p>(
+    bsa_path: &Path,
+    requests: &[StringsFileRequest<'p>],
+) -> Result<Vec<LoadedStringsFile<'p>>, anyhow::Error> {
+    let mut bsa: bsa::SomeReaderV10X<_> = bsa::open(bsa_path)?;
+    let bsa_dirs_list = bsa.list()?;
+
+    let mut files = Vec::new();
+    for request in requests {
+        let (dir_name_in_bsa, file_name_in_bsa) = match request.strings_path.split_once('/') {
+            Some(names) => names,
+            None => continue,
+        };
+        let file_in_bsa = bsa_dirs_list
+            .iter()
+            .find(|dir| {
+                dir.id.name.as_ref().expect("dir in bsa should have name") == dir_name_in_bsa
+            })
+            .and_then(|dir_in_bsa| {
+                dir_in_bsa.files.iter().find(|file| {
+                    file.id.name.as_ref().expect("file in bsa should have name") == file_name_in_bsa
+                })
+            });
+        if let Some(file_in_bsa) = file_in_bsa {
+            let mut data = Vec::new();
+            bsa.extract(file_in_bsa, &mut data)?;
+            files.push((request.plugin_name, request.file_type, data));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Finds and reads the strings files of all the given plugins, which should be the localized
+/// plugins in the load order. Loose strings files take precedence over those in BSAs. This is done
+/// in one pass before the plugins are parsed, so that each BSA is opened and listed only once even
+/// if it holds the strings of several plugins (as "Skyrim - Interface.bsa" does for the base game),
+/// and so that the BSAs and loose files are read in parallel. Returns the strings tables by
+/// lowercase plugin name, leaving out plugins without any strings files.
+pub fn load_strings_tables(
+    plugin_names: &[&str],
+    game_plugins_path: &Path,
+) -> AHashMap<String, StringsTable> {
+    let start = Instant::now();
+
+    let mut loose_requests = Vec::new();
+    let mut bsa_requests = AHashMap::<PathBuf, Vec<StringsFileRequest>>::new();
+    for &plugin_name in plugin_names {
+        assert!(!plugin_name.contains(|c| c == '/' || c == '\\'));
+        let bsa_path = game_plugins_path.join(get_bsa_name(plugin_name));
+        for file_type in StringsFileType::ALL {
+            let request = StringsFileRequest {
+                plugin_name,
+                file_type,
+                strings_path: get_strings_path(plugin_name, file_type),
+            };
+            // TODO: maybe handle fs errors explicitly instead of coercing to false?
+            if game_plugins_path.join(&request.strings_path).exists() {
+                loose_requests.push(request);
+            } else if bsa_path.exists() {
+                bsa_requests
+                    .entry(bsa_path.clone())
+                    .or_default()
+                    .push(request);
+            }
+        }
+    }
+    let bsa_requests = bsa_requests
+        .into_iter()
+        .filter(|(bsa_path, _)| match ArchiveFormat::detect(bsa_path) {
+            Ok(ArchiveFormat::Bsa) => true,
+            Ok(format) => {
+                log::warn!(
+                    "{} is {}, skipping strings lookup",
+                    bsa_path.display(),
+                    format.describe()
+                );
+                false
+            }
+            Err(err) => {
+                log::error!("failed to read bsa {}: {}", bsa_path.display(), err);
+                false
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let loose_files = loose_requests.par_iter().filter_map(|request| {
+        let path = game_plugins_path.join(&request.strings_path);
+        fs::read(&path)
+            .map_err(|err| log::error!("failed to read {}: {}", path.display(), err))
+            .ok()
+            .map(|data| (request.plugin_name, request.file_type, data))
+    });
+    let bsa_files = bsa_requests
+        .par_iter()
+        .flat_map_iter(|(bsa_path, requests)| {
+            read_from_bsa(bsa_path, requests)
+                .map_err(|err| {
+                    log::error!(
+                        "failed to read strings from bsa {}: {:?}",
+                        bsa_path.display(),
+                        err
+                    )
+                })
+                .unwrap_or_default()
+        });
+    let files = loose_files
+        .chain(bsa_files)
+        .filter_map(|(plugin_name, file_type, data)| {
+            StringsFile::parse(file_type, data)
+                .map_err(|err| {
+                    log::error!(
+                        "failed to load .{} file of plugin {}: {:?}",
+                        file_type.get_extension(),
+                        plugin_name,
+                        err
+                    )
+                })
+                .ok()
+                .map(|file| (plugin_name, file))
+        })
+        .collect::<Vec<_>>();
+
+    let mut strings_tables = AHashMap::<String, StringsTable>::new();
+    for (plugin_name, file) in files {
+        let strings_table = strings_tables
+            .entry(plugin_name.to_lowercase())
+            .or_default();
+        match file.file_type {
+            StringsFileType::Strings => strings_table.strings = Some(file),
+            StringsFileType::DlStrings => strings_table.dlstrings = Some(file),
+            StringsFileType::IlStrings => strings_table.ilstrings = Some(file),
+        }
+    }
+    for plugin_name in plugin_names {
+        match strings_tables.get(&plugin_name.to_lowercase()) {
+            Some(strings_table) => {
+                for file_type in StringsFileType::ALL {
+                    if strings_table.get_file(file_type).is_none() {
+                        log::debug!(
+                            "No .{} file found for plugin {}",
+                            file_type.get_extension(),
+                            plugin_name
+                        );
+                    }
+                }
+            }
+            None => log::warn!(
+                "No strings files found for localized plugin {}, its names will be empty",
+                plugin_name
+            ),
+        }
+    }
+
+    log::debug!(
+        "Loaded the strings tables of {} plugins from loose files and {} BSAs (in {:?})",
+        strings_tables.len(),
+        bsa_requests.len(),
+        start.elapsed()
+    );
+
+    strings_tables
+}
+
+/// The strings of a localized plugin, which are spread across three files by type
+#[derive(Default)]
+pub struct StringsTable {
+    strings: Option<StringsFile>,
+    dlstrings: Option<StringsFile>,
+    ilstrings: Option<StringsFile>,
+}
+
+impl StringsTable {
+    fn get_file(&self, file_type: StringsFileType) -> Option<&StringsFile> {
+        match file_type {
+            StringsFileType::Strings => self.strings.as_ref(),
+            StringsFileType::DlStrings => self.dlstrings.as_ref(),
+            StringsFileType::IlStrings => self.ilstrings.as_ref(),
+        }
+    }
+
+    /// Gets the string with the given ID from the strings file of the given type
+    pub fn get(&self, id: u32, file_type: StringsFileType) -> Option<String> {
+        self.get_file(file_type)?.get(id)
+    }
+
+    /// Returns the number of strings in the strings file of the given type, or `None` if the file
+    /// wasn't found
+    pub fn count(&self, file_type: StringsFileType) -> Option<usize> {
+        self.get_file(file_type).map(|file| file.directory.len())
+    }
+}
+
+/// A single .strings, .dlstrings or .ilstrings file
+struct StringsFile {
+    file_type: StringsFileType,
+    /// The string data, without the header and directory
+    data: Vec<u8>,
+    /// The IDs and offsets of the strings, sorted by ID
+    directory: Vec<(u32, u32)>,
+}
+
+impl StringsFile {
+    fn parse(file_type: StringsFileType, mut data: Vec<u8>) -> Result<Self, anyhow::Error> {
+        let (remaining_input, (num_strings, strings_size)) =
+            nom::sequence::pair(le_u32, le_u32)(data.as_slice())
+                .map_err(|err: nom::Err<(_, ErrorKind)>| nom_err_to_anyhow_err(err))?;
+
+        let mut directory: Vec<(u32, u32)> = nom::multi::count(
+            nom::sequence::pair(le_u32, le_u32),
+            num_strings as usize,
+        )(remaining_input)
+        .map_err(|err: nom::Err<(_, ErrorKind)>| nom_err_to_anyhow_err(err))?
+        .1;
+
+        // Sort by ID for binary search
+        directory.sort_by_key(|e| e.0);
+
+        // Skip two u32s, plus two u32s for every directory entry
+        let data_start_offset = mem::size_of::<u32>() * 2 * (1 + num_strings as usize);
+
+        data.drain(..data_start_offset);
+        assert_eq!(data.len(), strings_size as usize);
+
+        Ok(Self {
+            file_type,
+            data,
+            directory,
+        })
+    }
+
+    fn get(&self, id: u32) -> Option<String> {
+        let offset = self.directory[self.directory.binary_search_by_key(&id, |e| e.0).ok()?].1;
+
+        // TODO: maybe just use string slice syntax instead of nom take here
+        // TODO: error handling
+        let (string_data, _) =
+            nom::bytes::complete::take::<_, _, nom::error::Error<_>>(offset)(self.data.as_slice())
+                .unwrap();
+
+        // Skip the length prefix, the string is null terminated regardless
+        let string_data = match self.file_type.has_length_prefix() {
+            true => string_data.get(mem::size_of::<u32>()..)?,
+            false => string_data,
+        };
+
+        Some(parse_zstring(string_data))
+    }
+}
@@ -0,0 +1,150 @@
+//! Builders of plugin records and headers for the parser's tests
+
+use std::num::NonZeroU32;
+
+use esplugin::{record::Record, GameId};
+
+use super::{form_id::GlobalFormId, magic_effect::ACTOR_VALUE_HEALTH};
+
+/// The form version of records saved by the Skyrim SE Creation Kit
+pub const FORM_VERSION: u16 = 44;
+
+/// The form ID of the effect of the ingredients built by `ingredient_bytes`
+pub const EFFECT_FORM_ID: u32 = 0x900;
+
+/// Returns the bytes of a record of the given type followed by the subrecords
+pub fn record_bytes(
+    record_type: &[u8; 4],
+    form_id: u32,
+    subrecords: &[(&[u8; 4], &[u8])],
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (subrecord_type, subrecord_data) in subrecords {
+        data.extend(*subrecord_type);
+        data.extend(
+            u16::try_from(subrecord_data.len())
+                .expect("test subrecord should be shorter than 64 KiB")
+                .to_le_bytes(),
+        );
+        data.extend(*subrecord_data);
+    }
+
+    let mut bytes = record_type.to_vec();
+    bytes.extend((data.len() as u32).to_le_bytes());
+    // Flags
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(form_id.to_le_bytes());
+    // Version control info
+    bytes.extend([0; 4]);
+    bytes.extend(FORM_VERSION.to_le_bytes());
+    // Unknown
+    bytes.extend([0; 2]);
+    bytes.extend(data);
+    bytes
+}
+
+/// Returns the subrecords with those of the same type in `replacements` replaced
+fn replace_subrecords<'a, const N: usize>(
+    subrecords: [(&'a [u8; 4], &'a [u8]); N],
+    replacements: &[(&'a [u8; 4], &'a [u8])],
+) -> [(&'a [u8; 4], &'a [u8]); N] {
+    subrecords.map(|(subrecord_type, subrecord_data)| {
+        replacements
+            .iter()
+            .find(|(replaced_type, _)| *replaced_type == subrecord_type)
+            .copied()
+            .unwrap_or((subrecord_type, subrecord_data))
+    })
+}
+
+/// Returns the bytes of an ingredient record of a localized plugin with one effect, with the
+/// subrecords of the same type in `replacements` replaced
+pub fn ingredient_bytes(form_id: u32, replacements: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    let data = [100u32.to_le_bytes(), 0.5f32.to_le_bytes()].concat();
+    let enit = [100u32.to_le_bytes(), 0u32.to_le_bytes()].concat();
+    // Magnitude, area and duration
+    let efit = [
+        2.0f32.to_le_bytes(),
+        0u32.to_le_bytes(),
+        10u32.to_le_bytes(),
+    ]
+    .concat();
+    let subrecords: [(&[u8; 4], &[u8]); 6] = [
+        (b"EDID", b"TestIngredient\0"),
+        (b"FULL", &1u32.to_le_bytes()),
+        (b"DATA", &data),
+        (b"ENIT", &enit),
+        (b"EFID", &EFFECT_FORM_ID.to_le_bytes()),
+        (b"EFIT", &efit),
+    ];
+    record_bytes(
+        b"INGR",
+        form_id,
+        &replace_subrecords(subrecords, replacements),
+    )
+}
+
+/// Returns the bytes of the magic effect record of a localized plugin for a hostile Damage Health
+/// effect, with the subrecords of the same type in `replacements` replaced
+pub fn magic_effect_bytes(form_id: u32, replacements: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+    // Flags and base cost, followed by the archetype and actor value at offset 64
+    let mut data = [1u32.to_le_bytes(), 2.5f32.to_le_bytes()].concat();
+    data.resize(64, 0);
+    data.extend(0u32.to_le_bytes());
+    data.extend((ACTOR_VALUE_HEALTH as u32).to_le_bytes());
+    let subrecords: [(&[u8; 4], &[u8]); 4] = [
+        (b"EDID", b"TestDamageHealth\0"),
+        (b"FULL", &1u32.to_le_bytes()),
+        (b"DATA", &data),
+        (b"DNAM", &2u32.to_le_bytes()),
+    ];
+    record_bytes(
+        b"MGEF",
+        form_id,
+        &replace_subrecords(subrecords, replacements),
+    )
+}
+
+/// Returns the bytes of a top-level group of the records
+pub fn group_bytes(label: &[u8; 4], records: &[Vec<u8>]) -> Vec<u8> {
+    let data = records.concat();
+    let mut bytes = b"GRUP".to_vec();
+    // The group size includes the 24 byte group header
+    bytes.extend((data.len() as u32 + 24).to_le_bytes());
+    bytes.extend(label);
+    // Group type (0 for top-level groups), timestamp, version control info and unknown
+    bytes.extend([0; 12]);
+    bytes.extend(data);
+    bytes
+}
+
+/// Returns the HEDR subrecord data of a plugin with the given version
+pub fn hedr_data(version: f32) -> Vec<u8> {
+    let mut data = version.to_le_bytes().to_vec();
+    // Number of records and groups
+    data.extend(1u32.to_le_bytes());
+    // Next object ID
+    data.extend(0x800u32.to_le_bytes());
+    data
+}
+
+/// Returns the header record of a Skyrim SE plugin, or of an Oldrim plugin for version 0.94, with
+/// the given header flags
+pub fn header_bytes(version: f32, flags: u32) -> Vec<u8> {
+    let mut bytes = record_bytes(b"TES4", 0, &[(b"HEDR", &hedr_data(version))]);
+    bytes[8..12].copy_from_slice(&flags.to_le_bytes());
+    bytes
+}
+
+/// Parses a record built by `record_bytes`
+pub fn parse_record(bytes: &[u8]) -> Record {
+    Record::parse(bytes, GameId::SkyrimSE, false)
+        .expect("test record should be valid")
+        .1
+}
+
+/// Globalizes form IDs as if the record's plugin were the first in the load order and had no
+/// masters
+pub fn globalize_form_id(form_id: NonZeroU32) -> Result<GlobalFormId, anyhow::Error> {
+    Ok(GlobalFormId::new(0, u32::from(form_id) & 0x00FFFFFF))
+}
@@ -0,0 +1,112 @@
+use super::strings_table::{StringsFileType, StringsTable};
+use anyhow::anyhow;
+use encoding_rs::WINDOWS_1252;
+
+pub fn parse_string(data: &[u8]) -> String {
+    WINDOWS_1252
+        .decode_without_bom_handling_and_without_replacement(data)
+        .map(|s| Some(s.to_string()))
+        // FIXME: may panic
+        .unwrap()
+        .unwrap()
+}
+
+pub fn parse_zstring(data: &[u8]) -> String {
+    let len = data.len();
+    if len < 2 {
+        String::from("")
+    } else {
+        // zstrings are null terminated strings
+        // See https://en.uesp.net/wiki/Skyrim_Mod:File_Format_Conventions#Data_Types
+
+        // A string that a broken plugin didn't terminate ends with the subrecord
+        let null_index = data.iter().position(|byte| *byte == b'\0').unwrap_or(len);
+        parse_string(&data[..null_index])
+    }
+}
+
+/// Parses an lstring, which is the ID of a string in the strings files if the plugin is localized.
+/// Fails if the data is too short to hold the ID.
+pub fn parse_lstring(
+    data: &[u8],
+    is_localized: bool,
+    strings_table: Option<&StringsTable>,
+    file_type: StringsFileType,
+) -> Result<String, anyhow::Error> {
+    if is_localized {
+        let id = read_le_u32(data, "string ID")?;
+        // Plugins without strings tables were warned about when the tables were loaded
+        return Ok(strings_table
+            .and_then(|strings_table| strings_table.get(id, file_type))
+            .unwrap_or_default());
+    }
+
+    // All lstrings are zstrings when not localized
+    // See https://en.uesp.net/wiki/Skyrim_Mod:File_Format_Conventions#Data_Types
+    Ok(parse_zstring(data))
+}
+
+/// Reads a little-endian u32 from the start of the slice, or returns `None` if it is shorter than
+/// 4 bytes
+pub fn le_slice_to_u32(input: &[u8]) -> Option<u32> {
+    let int_bytes = input.get(..std::mem::size_of::<u32>())?;
+    Some(u32::from_le_bytes(int_bytes.try_into().ok()?))
+}
+
+/// Reads a little-endian u32 from the start of the subrecord data, failing with an error that
+/// names `what` if the data is too short, e.g. because a broken plugin left the subrecord empty
+pub fn read_le_u32(data: &[u8], what: &str) -> Result<u32, anyhow::Error> {
+    le_slice_to_u32(data).ok_or_else(|| {
+        anyhow!(
+            "expected 4 bytes of {}, but the subrecord has {}",
+            what,
+            data.len()
+        )
+    })
+}
+
+pub fn nom_err_to_anyhow_err<E>(err: nom::Err<E>) -> anyhow::Error
+where
+    E: std::fmt::Debug,
+{
+    anyhow::anyhow!(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zstring_ends_at_the_null_or_the_subrecord() {
+        assert_eq!(parse_zstring(b"Frost Salts\0"), "Frost Salts");
+        assert_eq!(parse_zstring(b"Frost Salts\0junk"), "Frost Salts");
+        assert_eq!(parse_zstring(b"Frost Salts"), "Frost Salts");
+        assert_eq!(parse_zstring(b"\0"), "");
+        assert_eq!(parse_zstring(b""), "");
+    }
+
+    #[test]
+    fn short_data_is_an_error_naming_what_was_read() {
+        assert_eq!(read_le_u32(&[1, 0, 0, 0, 9], "effect ID").unwrap(), 1);
+        let err = read_le_u32(&[1, 0], "effect ID").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected 4 bytes of effect ID, but the subrecord has 2"
+        );
+    }
+
+    #[test]
+    fn short_localized_lstring_is_an_error() {
+        assert!(parse_lstring(b"", true, None, StringsFileType::Strings).is_err());
+        assert!(parse_lstring(&[1, 0, 0], true, None, StringsFileType::DlStrings).is_err());
+        // Without a strings table, the string is empty rather than an error
+        assert_eq!(
+            parse_lstring(&[1, 0, 0, 0], true, None, StringsFileType::Strings).unwrap(),
+            ""
+        );
+        assert_eq!(
+            parse_lstring(b"Frost Salts", false, None, StringsFileType::Strings).unwrap(),
+            "Frost Salts"
+        );
+    }
+}
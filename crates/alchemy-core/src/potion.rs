@@ -0,0 +1,1209 @@
+use std::{cmp::max, collections::HashMap, fmt::Display, str::FromStr};
+
+use arrayvec::ArrayVec;
+use itertools::Itertools;
+
+use crate::{
+    crafting_rules::{PoisonRule, PrimaryEffectRule},
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData},
+    locale::NumberLocale,
+    player_context::PlayerContext,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::{Ingredient, IngredientEffect},
+        magic_effect::MagicEffect,
+    },
+};
+use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+use serde_with::DeserializeFromStr;
+
+/// Minimum number of ingredients per potion
+pub const MIN_INGREDIENTS: usize = 2;
+
+/// Maximum number of ingredients per potion in the vanilla game
+pub const DEFAULT_MAX_INGREDIENTS: usize = 3;
+
+/// The weight of a crafted potion or poison in the vanilla game. Mods can change it, so it can be
+/// overridden where it matters, e.g. for brew plans.
+pub const DEFAULT_POTION_WEIGHT: f32 = 0.5;
+
+/// Maximum number of ingredients per potion that we can represent. Some mods allow brewing with
+/// more ingredients than the vanilla game, so the actual maximum is chosen at runtime.
+pub const MAX_SUPPORTED_INGREDIENTS: usize = 4;
+
+/// Maximum number of effects per potion
+pub(crate) const MAX_EFFECTS: usize = 6;
+
+// TODO: read these from the game settings (fAlchemyIngredientInitMult and fAlchemySkillFactor)
+/// Base multiplier applied to the strength of ingredient effects
+const INGREDIENT_MULT: f32 = 4.0;
+
+/// Multiplier applied to the strength of ingredient effects at alchemy skill level 100
+const SKILL_FACTOR: f32 = 1.5;
+
+/// The player's alchemy skill and bonuses, which determine the strength of crafted potions
+#[derive(Clone, Copy, Debug)]
+pub struct AlchemySkill {
+    /// The player's alchemy skill level
+    pub skill_level: u32,
+    /// Total Fortify Alchemy bonus from equipment and potions, in percent
+    pub fortify_alchemy: f32,
+    /// Total bonus from perks such as Alchemist, in percent
+    pub perk_bonus: f32,
+}
+
+impl Default for AlchemySkill {
+    /// Alchemy skill level 100 without any bonuses
+    fn default() -> Self {
+        Self {
+            skill_level: 100,
+            fortify_alchemy: 0.0,
+            perk_bonus: 0.0,
+        }
+    }
+}
+
+impl AlchemySkill {
+    /// Returns the factor by which the magnitude or duration of effects is multiplied (for effects
+    /// that have the "Power affects magnitude/duration" flags)
+    pub fn get_power_factor(&self) -> f32 {
+        // See https://en.uesp.net/wiki/Skyrim:Alchemy#Potion_Strength
+        INGREDIENT_MULT
+            * (1.0 + (SKILL_FACTOR - 1.0) * self.skill_level as f32 / 100.0)
+            * (1.0 + self.fortify_alchemy / 100.0)
+            * (1.0 + self.perk_bonus / 100.0)
+    }
+}
+
+/// Serializes a reference to a record as its form ID, to avoid embedding the whole record
+fn ser_form_id<S, T>(x: &&T, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: FormIdContainer,
+{
+    x.get_global_form_id().serialize(s)
+}
+
+/// Serializes references to ingredients as their form IDs
+fn ser_ingredients_vec<S>(
+    ingredients: &ArrayVec<&Ingredient, MAX_SUPPORTED_INGREDIENTS>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(ingredients.len()))?;
+    for ingredient in ingredients {
+        seq.serialize_element(&ingredient.get_global_form_id())?;
+    }
+    seq.end()
+}
+
+/// This is basically an `IngredientEffect` with some extra data + a ref to its `MagicEffect`
+#[derive(Debug, Serialize)]
+pub struct PotionEffect<'a> {
+    #[serde(serialize_with = "ser_form_id", rename = "magic_effect_form_id")]
+    pub magic_effect: &'a MagicEffect,
+    magnitude: u32,
+    duration: u32,
+    gold_value: u32,
+}
+
+/// An ingredient's version of a potion effect. Every ingredient with the effect contributes its own
+/// magnitude and duration, and the potion gets the most valuable version.
+#[derive(Clone, Debug)]
+pub struct EffectContribution<'a> {
+    pub ingredient: &'a Ingredient,
+    pub magnitude: u32,
+    pub duration: u32,
+    pub gold_value: u32,
+    /// Whether this is the version the potion has
+    pub is_used: bool,
+}
+
+// TODO: use enums for all the various flags
+
+impl<'a> PotionEffect<'a> {
+    pub fn from_ingredient_effect(
+        igef: &'a IngredientEffect,
+        game_data: &'a GameData,
+        power_factor: f32,
+    ) -> Self {
+        let magic_effect = game_data
+            .get_magic_effect(&igef.get_global_form_id())
+            .unwrap();
+        let magnitude =
+            PotionEffect::calc_magnitude(igef.magnitude, magic_effect.flags, power_factor);
+        let duration = PotionEffect::calc_duration(igef.duration, magic_effect.flags, power_factor);
+        let gold_value = PotionEffect::calc_gold_value(magnitude, duration, magic_effect.base_cost);
+
+        PotionEffect {
+            magic_effect,
+            duration,
+            magnitude,
+            gold_value,
+        }
+    }
+
+    /// Returns the actual magnitude, taking into account various factors
+    ///
+    /// Note: this does not currently include every factor so it won't be fully accurate
+    pub fn calc_magnitude(base_magnitude: f32, magic_effect_flags: u32, power_factor: f32) -> u32 {
+        let magnitude = {
+            // "No magnitude" flag
+            if magic_effect_flags & 0x00000400 != 0 {
+                0.0
+            } else {
+                base_magnitude
+            }
+        };
+
+        let magnitude_factor = {
+            // "Power affects magnitude" flag
+            if magic_effect_flags & 0x00200000 != 0 {
+                power_factor
+            } else {
+                1.0
+            }
+        };
+
+        // Negative and NaN magnitudes are already clamped when plugins are parsed, but may still
+        // come from older game data or an absurd power factor
+        let magnitude = magnitude * magnitude_factor;
+        if magnitude.is_nan() || magnitude <= 0.0 {
+            0
+        } else {
+            // Infinite magnitudes saturate to u32::MAX
+            f32::round(magnitude) as u32
+        }
+    }
+
+    /// Returns the actual duration, taking into account various factors
+    ///
+    /// Note: this does not currently include every factor so it won't be fully accurate
+    pub fn calc_duration(base_duration: u32, magic_effect_flags: u32, power_factor: f32) -> u32 {
+        let duration = {
+            // "No duration" flag
+            if magic_effect_flags & 0x00000200 != 0 {
+                0.0
+            } else {
+                base_duration as f32
+            }
+        };
+
+        let duration_factor = {
+            // "Power affects duration" flag
+            if magic_effect_flags & 0x00400000 != 0 {
+                power_factor
+            } else {
+                1.0
+            }
+        };
+
+        let duration = duration * duration_factor;
+        if duration.is_nan() || duration <= 0.0 {
+            0
+        } else {
+            f32::round(duration) as u32
+        }
+    }
+
+    /// Returns the gold value of this effect with its magnitude and duration factored in
+    ///
+    /// Values too large for a `u32`, which only occur with absurd base costs, are clamped to
+    /// `u32::MAX` so that such potions still sort as the most valuable ones.
+    pub fn calc_gold_value(magnitude: u32, duration: u32, magic_effect_base_cost: f32) -> u32 {
+        // See https://en.uesp.net/wiki/Skyrim_Mod:Mod_File_Format/INGR
+        // and https://en.uesp.net/wiki/Skyrim:Alchemy_Effects#Strength_Equations
+        let magnitude_factor = max(magnitude, 1) as f32;
+        let duration_factor: f32 = {
+            (match duration {
+                // A duration of 0 is treated as 10
+                0 => 10.0,
+                _ => duration as f32,
+            }) / 10.0
+        };
+
+        let gold_value = magic_effect_base_cost * (magnitude_factor * duration_factor).powf(1.1);
+        if gold_value.is_nan() || gold_value <= 0.0 {
+            0
+        } else if gold_value >= u32::MAX as f32 {
+            u32::MAX
+        } else {
+            gold_value as u32
+        }
+    }
+
+    pub fn get_magnitude(&self) -> u32 {
+        self.magnitude
+    }
+
+    pub fn get_duration(&self) -> u32 {
+        self.duration
+    }
+
+    pub fn get_gold_value(&self) -> u32 {
+        self.gold_value
+    }
+
+    /// Returns the in-game description of the effect, with its magnitude and duration formatted
+    /// with the locale
+    pub fn get_description(&self, locale: NumberLocale) -> String {
+        self.magic_effect
+            .description
+            .replace("<mag>", &locale.format_integer(self.magnitude))
+            .replace("<dur>", &locale.format_integer(self.duration))
+    }
+}
+
+impl<'a> FormIdContainer for PotionEffect<'a> {
+    fn get_global_form_id(&self) -> crate::plugin_parser::form_id::GlobalFormId {
+        self.magic_effect.get_global_form_id()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Potion<'a> {
+    #[serde(serialize_with = "ser_ingredients_vec", rename = "ingredient_form_ids")]
+    pub ingredients: ArrayVec<&'a Ingredient, MAX_SUPPORTED_INGREDIENTS>,
+    /// Potion's effects sorted by strength descending, except that the primary effect is first
+    pub effects: ArrayVec<PotionEffect<'a>, MAX_EFFECTS>,
+    pub gold_value: u32,
+    /// Determined by the crafting rules when the potion is made
+    #[serde(skip)]
+    potion_type: PotionType,
+}
+
+/// Displays the potion with the name the game gives it and plain numbers, since it doesn't know the
+/// settings of the game data it was made from
+impl<'a> Display for Potion<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let locale = NumberLocale::PLAIN;
+        write!(
+            f,
+            "{}\n{}\nValue: {} gold\nIngredients:\n{}",
+            self.get_game_name(),
+            self.get_potion_description(locale),
+            locale.format_integer(self.gold_value),
+            self.ingredients
+                .iter()
+                .map(|ig| String::from("- ") + &get_ingredient_name_or_fallback(ig))
+                .join("\n")
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PotionCraftError<'a> {
+    #[error("cannot use the same ingredient more than once in a potion: {}", get_ingredient_name_or_fallback(.0))]
+    DuplicateIngredient(&'a Ingredient),
+    // TODO: since this shouldn't happen with valid game data, panic instead?
+    #[error("ingredient has invalid data (duplicate effects): {}", get_ingredient_name_or_fallback(.0))]
+    InvalidIngredient(&'a Ingredient),
+    #[error("must supply at least {0} ingredients")]
+    NotEnoughIngredients(usize),
+    #[error("cannot supply more than {} ingredients", MAX_SUPPORTED_INGREDIENTS)]
+    TooManyIngredients,
+    #[error("none of the ingredients have a shared effect")]
+    NoSharedEffects,
+}
+
+/// Something about a valid potion that the player probably didn't intend, as reported by
+/// `Potion::check`
+#[derive(Debug)]
+pub enum PotionWarning<'a> {
+    /// None of the ingredient's effects are in the potion, so it is used up for nothing
+    WastedIngredient(&'a Ingredient),
+    /// The ingredient shares the effect, but another ingredient's stronger version of it is used
+    OverriddenEffect {
+        ingredient: &'a Ingredient,
+        magic_effect: &'a MagicEffect,
+    },
+    /// The ingredients share the effect, but the potion already has as many effects as it can
+    DroppedEffect(&'a MagicEffect),
+}
+
+impl<'a> Display for PotionWarning<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PotionWarning::WastedIngredient(ingredient) => write!(
+                f,
+                "{} doesn't contribute any effects",
+                get_ingredient_name_or_fallback(ingredient)
+            ),
+            PotionWarning::OverriddenEffect {
+                ingredient,
+                magic_effect,
+            } => write!(
+                f,
+                "{} of {} is overridden by a stronger version from another ingredient",
+                get_magic_effect_name_or_fallback(magic_effect),
+                get_ingredient_name_or_fallback(ingredient)
+            ),
+            PotionWarning::DroppedEffect(magic_effect) => write!(
+                f,
+                "{} is shared but dropped, since the potion has the maximum number of effects",
+                get_magic_effect_name_or_fallback(magic_effect)
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, DeserializeFromStr)]
+pub enum PotionType {
+    Potion,
+    Poison,
+}
+
+impl FromStr for PotionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "potion" => Ok(PotionType::Potion),
+            "poison" => Ok(PotionType::Poison),
+            other => Err(format!(
+                "unknown potion type {}, expected potion or poison",
+                other
+            )),
+        }
+    }
+}
+
+/// What to rank potions (as opposed to poisons) by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, DeserializeFromStr)]
+pub enum PotionRanking {
+    /// Gold value, for selling
+    #[default]
+    Value,
+    /// Amount of health restored
+    Healing,
+    /// Gold value per ingredient consumed, weighing each ingredient by how scarce it is in the
+    /// inventory
+    ValueDensity,
+    /// Lowest total base value of the ingredients, for leveling alchemy with ingredients that are
+    /// worth little
+    CheapestIngredients,
+}
+
+impl FromStr for PotionRanking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "value" => Ok(PotionRanking::Value),
+            "healing" => Ok(PotionRanking::Healing),
+            "value-density" => Ok(PotionRanking::ValueDensity),
+            "cheapest-ingredients" => Ok(PotionRanking::CheapestIngredients),
+            other => Err(format!(
+                "unknown potion ranking {}, expected value, healing, value-density or \
+                 cheapest-ingredients",
+                other
+            )),
+        }
+    }
+}
+
+/// What to rank poisons by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, DeserializeFromStr)]
+pub enum PoisonRanking {
+    /// Gold value, for selling
+    #[default]
+    Value,
+    /// Magnitude and duration of the hostile effects, for applying them to weapons
+    Strength,
+    /// Gold value per ingredient consumed, weighing each ingredient by how scarce it is in the
+    /// inventory
+    ValueDensity,
+    /// Lowest total base value of the ingredients, for leveling alchemy with ingredients that are
+    /// worth little
+    CheapestIngredients,
+}
+
+impl FromStr for PoisonRanking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "value" => Ok(PoisonRanking::Value),
+            "strength" => Ok(PoisonRanking::Strength),
+            "value-density" => Ok(PoisonRanking::ValueDensity),
+            "cheapest-ingredients" => Ok(PoisonRanking::CheapestIngredients),
+            other => Err(format!(
+                "unknown poison ranking {}, expected value, strength, value-density or \
+                 cheapest-ingredients",
+                other
+            )),
+        }
+    }
+}
+
+/// How to rank potions and poisons
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct Ranking {
+    pub potions: PotionRanking,
+    pub poisons: PoisonRanking,
+}
+
+impl Ranking {
+    /// Returns whether both potions and poisons are ranked by gold value
+    pub fn is_by_value(&self) -> bool {
+        self.potions == PotionRanking::Value && self.poisons == PoisonRanking::Value
+    }
+
+    /// Returns whether potions or poisons are ranked by the base value of their ingredients
+    pub fn is_by_ingredients_value(&self) -> bool {
+        self.potions == PotionRanking::CheapestIngredients
+            || self.poisons == PoisonRanking::CheapestIngredients
+    }
+}
+
+impl Display for PotionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PotionType::Poison => write!(f, "Poison"),
+            PotionType::Potion => write!(f, "Potion"),
+        }
+    }
+}
+
+impl<'a> Potion<'a> {
+    fn calc_gold_value(effects: &[PotionEffect]) -> u32 {
+        // See https://en.uesp.net/wiki/Skyrim:Alchemy_Effects#Multiple-Effect_Potions
+        // Saturate rather than wrap, so that overflowing potions don't sort as the least valuable
+        effects
+            .iter()
+            .fold(0u32, |sum, eff| sum.saturating_add(eff.gold_value))
+    }
+
+    /// Constructs a potion from the given ingredients, checking that they make a valid potion
+    /// under the game data's crafting rules.
+    pub fn from_ingredients(
+        ingredients: &[&'a Ingredient],
+        game_data: &'a GameData,
+        power_factor: f32,
+    ) -> Result<Self, PotionCraftError<'a>> {
+        let min_ingredients = max(
+            game_data.get_crafting_rules().min_ingredients,
+            MIN_INGREDIENTS,
+        );
+        if ingredients.len() < min_ingredients {
+            return Err(PotionCraftError::NotEnoughIngredients(min_ingredients));
+        }
+
+        if ingredients.len() > MAX_SUPPORTED_INGREDIENTS {
+            return Err(PotionCraftError::TooManyIngredients);
+        }
+
+        if let Some(dup) = ingredients.iter().duplicates().next() {
+            return Err(PotionCraftError::DuplicateIngredient(dup));
+        }
+
+        if let Some(ing_with_dup_effects) = ingredients.iter().find(|ig| {
+            ig.effects
+                .iter()
+                .duplicates_by(|igef| igef.get_global_form_id())
+                .count()
+                > 0
+        }) {
+            return Err(PotionCraftError::InvalidIngredient(ing_with_dup_effects));
+        }
+
+        let potion = Self::from_ingredients_unchecked(
+            ingredients.iter().copied().collect(),
+            game_data,
+            power_factor,
+        );
+
+        if potion.effects.is_empty() {
+            return Err(PotionCraftError::NoSharedEffects);
+        }
+
+        Ok(potion)
+    }
+
+    /// Constructs a potion from the given ingredients.
+    /// For performance, does not perform any checks on the input. The caller is responsible for
+    /// only passing valid combinations. Input must:
+    ///
+    /// - Contain at least two ingredients (and at most `MAX_SUPPORTED_INGREDIENTS`, but this is
+    ///   enforced at type level)
+    /// - Not contain duplicate ingredients
+    /// - Not contain ingredients with duplicate effects
+    /// - Not contain ingredients that don't share any effects
+    ///
+    /// Input that fails the above criteria may produce a nonsensical Potion. The number of effects,
+    /// the potion's type and its primary effect follow the game data's crafting rules.
+    pub fn from_ingredients_unchecked(
+        ingredients: ArrayVec<&'a Ingredient, MAX_SUPPORTED_INGREDIENTS>,
+        game_data: &'a GameData,
+        power_factor: f32,
+    ) -> Self {
+        let rules = game_data.get_crafting_rules();
+        let ingredients_effects_iter = ingredients.iter().flat_map(|ig| ig.effects.iter());
+
+        // assert_eq!(ingredients_effects.len(), ingredients.len() * 4);
+
+        let ingredients_effects_counts = ingredients_effects_iter
+            .clone()
+            .counts_by(|igef| igef.get_global_form_id());
+
+        // TODO: research how the game breaks ties in potion effect strength
+        // active effects are those that appear in more than one ingredient
+        let mut effects = ingredients_effects_iter
+            .filter(|igef| {
+                *(ingredients_effects_counts
+                    .get(&igef.get_global_form_id())
+                    .unwrap())
+                    > 1
+            })
+            .sorted_by_key(|igef| igef.get_global_form_id())
+            .map(|igef| PotionEffect::from_ingredient_effect(igef, game_data, power_factor))
+            .coalesce(|potef1, potef2| {
+                if potef1.get_global_form_id() == potef2.get_global_form_id() {
+                    // Select most valuable (strongest) version of each effect
+                    Ok({
+                        if potef1.gold_value >= potef2.gold_value {
+                            potef1
+                        } else {
+                            potef2
+                        }
+                    })
+                } else {
+                    Err((potef1, potef2))
+                }
+            })
+            .sorted_by(|potef1, potef2| {
+                // Sort by gold value from largest to smallest
+                potef1.gold_value.cmp(&potef2.gold_value).reverse()
+            })
+            .collect::<Vec<_>>();
+
+        let max_effects = rules.max_effects.min(MAX_EFFECTS);
+        if rules.prefer_effects_of_type && effects.len() > max_effects {
+            // Drop the effects that don't match the type the potion would have with all of its
+            // effects first, so that e.g. a valuable hostile side effect can't push out the
+            // healing effect of a potion. The sorts are stable, so the value order is kept.
+            let is_poison = Self::is_poison(rules.poison, &effects);
+            effects.sort_by_key(|eff| eff.magic_effect.is_hostile != is_poison);
+            effects.truncate(max_effects);
+            effects.sort_by(|potef1, potef2| potef1.gold_value.cmp(&potef2.gold_value).reverse());
+        }
+        let mut effects = effects
+            .into_iter()
+            .take(max_effects)
+            .collect::<ArrayVec<_, MAX_EFFECTS>>();
+
+        let is_poison = Self::is_poison(rules.poison, &effects);
+        if rules.primary_effect == PrimaryEffectRule::MostValuableOfType {
+            // Move the most valuable effect of the potion's type to the front, keeping the order
+            // of the other effects
+            if let Some(index) = effects
+                .iter()
+                .position(|eff| eff.magic_effect.is_hostile == is_poison)
+            {
+                let primary_effect = effects.remove(index);
+                effects.insert(0, primary_effect);
+            }
+        }
+
+        let gold_value = Potion::calc_gold_value(&effects);
+
+        Self {
+            effects,
+            ingredients,
+            gold_value,
+            potion_type: if is_poison {
+                PotionType::Poison
+            } else {
+                PotionType::Potion
+            },
+        }
+    }
+
+    /// Returns whether effects sorted by gold value descending make a poison under the rule
+    fn is_poison(rule: PoisonRule, effects: &[PotionEffect]) -> bool {
+        match rule {
+            // See https://en.uesp.net/wiki/Skyrim:Alchemy_Effects#Multiple-Effect_Potions
+            PoisonRule::PrimaryEffect => effects
+                .first()
+                .map_or(false, |eff| eff.magic_effect.is_hostile),
+            PoisonRule::MajorityHostile => {
+                effects
+                    .iter()
+                    .filter(|eff| eff.magic_effect.is_hostile)
+                    .count()
+                    * 2
+                    > effects.len()
+            }
+        }
+    }
+
+    pub fn get_primary_effect(&self) -> &PotionEffect<'a> {
+        // The effects are sorted by strength descending, except that the crafting rules may have
+        // moved another effect to the front
+        self.effects.first().unwrap()
+    }
+
+    pub fn get_potion_type(&self) -> PotionType {
+        self.potion_type
+    }
+
+    /// Returns the score of the potion according to the ranking for its type. Higher is better.
+    /// Ranking by value density needs the player's inventory, without which potions are ranked by
+    /// value instead. Potions with the same score keep their order by gold value, so e.g. of the
+    /// potions with the cheapest ingredients, the most valuable come first.
+    pub fn get_score(&self, ranking: &Ranking, player_context: Option<&PlayerContext>) -> u32 {
+        let get_value_density = || {
+            player_context.map_or(self.gold_value, |player_context| {
+                player_context.get_value_density(self)
+            })
+        };
+        let get_cheapness = || u32::MAX - self.get_ingredients_value();
+        match self.get_potion_type() {
+            PotionType::Potion => match ranking.potions {
+                PotionRanking::Value => self.gold_value,
+                PotionRanking::Healing => self.get_healing(),
+                PotionRanking::ValueDensity => get_value_density(),
+                PotionRanking::CheapestIngredients => get_cheapness(),
+            },
+            PotionType::Poison => match ranking.poisons {
+                PoisonRanking::Value => self.gold_value,
+                PoisonRanking::Strength => self.get_strength(),
+                PoisonRanking::ValueDensity => get_value_density(),
+                PoisonRanking::CheapestIngredients => get_cheapness(),
+            },
+        }
+    }
+
+    /// Returns the combined strength of the effects that match the potion's type (beneficial
+    /// effects for potions, hostile effects for poisons), i.e. the sum of their magnitude times
+    /// their duration. Effects without a magnitude or duration count as 1 for that factor. Strengths
+    /// too large for a `u32` are clamped to `u32::MAX`.
+    pub fn get_strength(&self) -> u32 {
+        let is_poison = self.get_potion_type() == PotionType::Poison;
+        self.effects
+            .iter()
+            .filter(|eff| eff.magic_effect.is_hostile == is_poison)
+            .map(|eff| max(eff.magnitude, 1).saturating_mul(max(eff.duration, 1)))
+            .fold(0u32, u32::saturating_add)
+    }
+
+    /// Returns the total base gold value of the potion's ingredients. Ingredients without a value,
+    /// from game data exported by older versions, count as worthless.
+    pub fn get_ingredients_value(&self) -> u32 {
+        self.ingredients
+            .iter()
+            .fold(0u32, |sum, ig| sum.saturating_add(ig.value.unwrap_or(0)))
+    }
+
+    /// Returns the total amount of health the potion restores, clamped to `u32::MAX`
+    pub fn get_healing(&self) -> u32 {
+        self.effects
+            .iter()
+            .filter(|eff| eff.magic_effect.restores_health())
+            .map(|eff| eff.magnitude.saturating_mul(max(eff.duration, 1)))
+            .fold(0u32, u32::saturating_add)
+    }
+
+    /// Returns the versions of each of the potion's effects that its ingredients contribute, in the
+    /// order of the effects and then of the ingredients. The potion has the most valuable version
+    /// of each effect, or the first one if several are equally valuable, like in the game. The
+    /// power factor must be the one the potion was made with.
+    pub fn get_effect_contributions(&self, power_factor: f32) -> Vec<Vec<EffectContribution<'a>>> {
+        self.effects
+            .iter()
+            .map(|potef| {
+                let magic_effect = potef.magic_effect;
+                let mut contributions =
+                    self.ingredients
+                        .iter()
+                        .filter_map(|&ingredient| {
+                            let igef = ingredient.effects.iter().find(|igef| {
+                                igef.get_global_form_id() == potef.get_global_form_id()
+                            })?;
+                            let magnitude = PotionEffect::calc_magnitude(
+                                igef.magnitude,
+                                magic_effect.flags,
+                                power_factor,
+                            );
+                            let duration = PotionEffect::calc_duration(
+                                igef.duration,
+                                magic_effect.flags,
+                                power_factor,
+                            );
+                            Some(EffectContribution {
+                                ingredient,
+                                magnitude,
+                                duration,
+                                gold_value: PotionEffect::calc_gold_value(
+                                    magnitude,
+                                    duration,
+                                    magic_effect.base_cost,
+                                ),
+                                is_used: false,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                // `max_by_key` returns the last of equal elements, so reverse to get the first
+                if let Some(used) = contributions
+                    .iter_mut()
+                    .rev()
+                    .max_by_key(|contribution| contribution.gold_value)
+                {
+                    used.is_used = true;
+                }
+                contributions
+            })
+            .collect()
+    }
+
+    /// Checks the potion for ingredients and effects that are used up without adding anything to
+    /// it. The power factor must be the one the potion was made with.
+    pub fn check(&self, game_data: &'a GameData, power_factor: f32) -> Vec<PotionWarning<'a>> {
+        let ingredients_effects_counts = self
+            .ingredients
+            .iter()
+            .flat_map(|ig| ig.effects.iter())
+            .counts_by(|igef| igef.get_global_form_id());
+        let get_potion_effect = |form_id: GlobalFormId| {
+            self.effects
+                .iter()
+                .find(|eff| eff.get_global_form_id() == form_id)
+        };
+
+        let mut warnings = Vec::new();
+        for &ingredient in &self.ingredients {
+            if ingredient
+                .effects
+                .iter()
+                .all(|igef| get_potion_effect(igef.get_global_form_id()).is_none())
+            {
+                warnings.push(PotionWarning::WastedIngredient(ingredient));
+                continue;
+            }
+            for igef in &ingredient.effects {
+                let potef = match get_potion_effect(igef.get_global_form_id()) {
+                    Some(potef) => potef,
+                    None => continue,
+                };
+                if PotionEffect::from_ingredient_effect(igef, game_data, power_factor).gold_value
+                    < potef.gold_value
+                {
+                    warnings.push(PotionWarning::OverriddenEffect {
+                        ingredient,
+                        magic_effect: potef.magic_effect,
+                    });
+                }
+            }
+        }
+        warnings.extend(
+            ingredients_effects_counts
+                .into_iter()
+                .filter(|(form_id, count)| *count > 1 && get_potion_effect(*form_id).is_none())
+                .map(|(form_id, _)| form_id)
+                .sorted()
+                .filter_map(|form_id| game_data.get_magic_effect(&form_id))
+                .map(PotionWarning::DroppedEffect),
+        );
+
+        warnings
+    }
+
+    /// Returns the potion's name, formatted with the potion name template of the game data it was
+    /// made from if one is set
+    pub fn get_potion_name(&self, game_data: &GameData) -> String {
+        match game_data.get_potion_name_template() {
+            Some(template) => template.render(self, game_data.get_number_locale()),
+            None => self.get_game_name(),
+        }
+    }
+
+    /// Returns the name the game gives the potion, e.g. "Potion of Restore Health"
+    fn get_game_name(&self) -> String {
+        let type_string = self.get_potion_type().to_string();
+        let primary_effect_name =
+            get_magic_effect_name_or_fallback(self.get_primary_effect().magic_effect);
+        format!("{} of {}", type_string, primary_effect_name)
+    }
+
+    /// Returns the in-game descriptions of the potion's effects, with their numbers formatted with
+    /// the locale
+    pub fn get_potion_description(&self, locale: NumberLocale) -> String {
+        self.effects
+            .iter()
+            .map(|potef| potef.get_description(locale))
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const NO_DURATION: u32 = 0x00000200;
+    const NO_MAGNITUDE: u32 = 0x00000400;
+    const POWER_AFFECTS_MAGNITUDE: u32 = 0x00200000;
+    const POWER_AFFECTS_DURATION: u32 = 0x00400000;
+
+    /// The power factor at alchemy skill level 100 without any bonuses
+    const POWER_FACTOR: f32 = 6.0;
+
+    #[test]
+    fn power_factor_at_skill_100() {
+        assert_eq!(AlchemySkill::default().get_power_factor(), POWER_FACTOR);
+    }
+
+    /// Restore Health: 5 points, base cost 0.5
+    #[test]
+    fn restore_health_at_skill_100() {
+        let magnitude = PotionEffect::calc_magnitude(5.0, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR);
+        let duration = PotionEffect::calc_duration(0, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR);
+        assert_eq!((magnitude, duration), (30, 0));
+        assert_eq!(PotionEffect::calc_gold_value(magnitude, duration, 0.5), 21);
+    }
+
+    /// Fortify Health: 4 points for 60 seconds, base cost 0.35
+    #[test]
+    fn fortify_health_at_skill_100() {
+        let magnitude = PotionEffect::calc_magnitude(4.0, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR);
+        let duration = PotionEffect::calc_duration(60, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR);
+        assert_eq!((magnitude, duration), (24, 60));
+        assert_eq!(PotionEffect::calc_gold_value(magnitude, duration, 0.35), 82);
+    }
+
+    /// Invisibility: 4 seconds without a magnitude, base cost 100
+    #[test]
+    fn invisibility_at_skill_100() {
+        let flags = NO_MAGNITUDE | POWER_AFFECTS_DURATION;
+        let magnitude = PotionEffect::calc_magnitude(1.0, flags, POWER_FACTOR);
+        let duration = PotionEffect::calc_duration(4, flags, POWER_FACTOR);
+        assert_eq!((magnitude, duration), (0, 24));
+        assert_eq!(
+            PotionEffect::calc_gold_value(magnitude, duration, 100.0),
+            261
+        );
+    }
+
+    /// Giant's Toe and Wheat from Skyrim.esm, which share Fortify Health and Damage Stamina Regen
+    const GIANTS_TOE_AND_WHEAT: &str = r#"{
+        "load_order": ["Skyrim.esm"],
+        "plugins": [{ "name": "Skyrim.esm" }],
+        "ingredients": [
+            {
+                "global_form_id": "0000:03ad73",
+                "editor_id": "GiantToe",
+                "name": "Giant's Toe",
+                "effects": [
+                    { "global_form_id": "0000:03a2c6", "duration": 0, "magnitude": 1.0 },
+                    { "global_form_id": "0000:03eaf3", "duration": 60, "magnitude": 4.0 },
+                    { "global_form_id": "0000:03eb01", "duration": 300, "magnitude": 4.0 },
+                    { "global_form_id": "0000:073f2b", "duration": 5, "magnitude": 100.0 }
+                ],
+                "weight": 1.0
+            },
+            {
+                "global_form_id": "0000:04b0ba",
+                "editor_id": "Wheat01",
+                "name": "Wheat",
+                "effects": [
+                    { "global_form_id": "0000:03eb15", "duration": 0, "magnitude": 5.0 },
+                    { "global_form_id": "0000:03eaf3", "duration": 60, "magnitude": 4.0 },
+                    { "global_form_id": "0000:073f2b", "duration": 5, "magnitude": 100.0 },
+                    { "global_form_id": "0000:03a2b6", "duration": 10, "magnitude": 1.0 }
+                ],
+                "weight": 0.1
+            }
+        ],
+        "magic_effects": [
+            {
+                "global_form_id": "0000:03a2c6",
+                "editor_id": "AlchDamageStamina",
+                "name": "Damage Stamina",
+                "description": "Drain the target's Stamina by <mag> points.",
+                "flags": 2097664,
+                "is_hostile": true,
+                "base_cost": 1.8
+            },
+            {
+                "global_form_id": "0000:03eaf3",
+                "editor_id": "AlchFortifyHealth",
+                "name": "Fortify Health",
+                "description": "Health is increased by <mag> points for <dur> seconds.",
+                "flags": 2097152,
+                "is_hostile": false,
+                "base_cost": 0.35
+            },
+            {
+                "global_form_id": "0000:03eb01",
+                "editor_id": "AlchFortifyCarryWeight",
+                "name": "Fortify Carry Weight",
+                "description": "Carrying capacity is increased by <mag> for <dur> seconds.",
+                "flags": 2097152,
+                "is_hostile": false,
+                "base_cost": 0.15
+            },
+            {
+                "global_form_id": "0000:03eb15",
+                "editor_id": "AlchRestoreHealth",
+                "name": "Restore Health",
+                "description": "Restore <mag> points of Health.",
+                "flags": 2097664,
+                "is_hostile": false,
+                "base_cost": 0.5
+            },
+            {
+                "global_form_id": "0000:03a2b6",
+                "editor_id": "AlchDamageMagickaDuration",
+                "name": "Lingering Damage Magicka",
+                "description": "Drain the target's Magicka by <mag> points per second for <dur> seconds.",
+                "flags": 2097152,
+                "is_hostile": true,
+                "base_cost": 9.8
+            },
+            {
+                "global_form_id": "0000:073f2b",
+                "editor_id": "AlchDamageStaminaRegen",
+                "name": "Damage Stamina Regen",
+                "description": "Decrease the target's Stamina regeneration by <mag>% for <dur> seconds.",
+                "flags": 4194304,
+                "is_hostile": true,
+                "base_cost": 0.3
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn giants_toe_and_wheat_at_skill_100() {
+        let game_data: GameData = serde_json::from_str(GIANTS_TOE_AND_WHEAT).unwrap();
+        let giants_toe = game_data
+            .get_ingredient(&GlobalFormId::new(0, 0x03ad73))
+            .unwrap();
+        let wheat = game_data
+            .get_ingredient(&GlobalFormId::new(0, 0x04b0ba))
+            .unwrap();
+
+        let potion =
+            Potion::from_ingredients(&[giants_toe, wheat], &game_data, POWER_FACTOR).unwrap();
+
+        // Damage Stamina Regen is worth more than Fortify Health, so this makes a poison
+        assert_eq!(potion.get_potion_type(), PotionType::Poison);
+        assert_eq!(
+            potion.get_potion_name(&game_data),
+            "Poison of Damage Stamina Regen"
+        );
+        let effects = potion
+            .effects
+            .iter()
+            .map(|eff| {
+                (
+                    eff.magic_effect.editor_id.as_str(),
+                    eff.get_magnitude(),
+                    eff.get_duration(),
+                    eff.get_gold_value(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effects,
+            [
+                ("AlchDamageStaminaRegen", 100, 30, 159),
+                ("AlchFortifyHealth", 24, 60, 82)
+            ]
+        );
+        assert_eq!(potion.gold_value, 241);
+    }
+
+    #[test]
+    fn invalid_magnitudes_make_no_magnitude() {
+        for magnitude in [-3.0, f32::NAN, f32::NEG_INFINITY] {
+            assert_eq!(
+                PotionEffect::calc_magnitude(magnitude, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR),
+                0,
+                "magnitude {}",
+                magnitude
+            );
+        }
+        // A NaN power factor is no better than a NaN magnitude
+        assert_eq!(
+            PotionEffect::calc_magnitude(3.0, POWER_AFFECTS_MAGNITUDE, f32::NAN),
+            0
+        );
+        assert_eq!(
+            PotionEffect::calc_magnitude(f32::INFINITY, POWER_AFFECTS_MAGNITUDE, POWER_FACTOR),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn no_duration_flag() {
+        assert_eq!(
+            PotionEffect::calc_duration(60, NO_DURATION | POWER_AFFECTS_DURATION, POWER_FACTOR),
+            0
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn magnitude_grows_with_base_magnitude_and_power(
+            a in 0.0f32..1000.0,
+            b in 0.0f32..1000.0,
+            power_a in 0.0f32..20.0,
+            power_b in 0.0f32..20.0
+        ) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            let (low_power, high_power) = if power_a <= power_b {
+                (power_a, power_b)
+            } else {
+                (power_b, power_a)
+            };
+            prop_assert!(
+                PotionEffect::calc_magnitude(low, POWER_AFFECTS_MAGNITUDE, low_power)
+                    <= PotionEffect::calc_magnitude(high, POWER_AFFECTS_MAGNITUDE, high_power)
+            );
+        }
+
+        #[test]
+        fn duration_grows_with_base_duration_and_power(
+            a in 0u32..10_000,
+            b in 0u32..10_000,
+            power_a in 0.0f32..20.0,
+            power_b in 0.0f32..20.0
+        ) {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            let (low_power, high_power) = if power_a <= power_b {
+                (power_a, power_b)
+            } else {
+                (power_b, power_a)
+            };
+            prop_assert!(
+                PotionEffect::calc_duration(low, POWER_AFFECTS_DURATION, low_power)
+                    <= PotionEffect::calc_duration(high, POWER_AFFECTS_DURATION, high_power)
+            );
+        }
+
+        #[test]
+        fn gold_value_grows_with_magnitude_duration_and_base_cost(
+            magnitudes in (0u32..10_000, 0u32..10_000),
+            durations in (1u32..10_000, 1u32..10_000),
+            base_costs in (0.0f32..1000.0, 0.0f32..1000.0)
+        ) {
+            let sorted = |(a, b)| if a <= b { (a, b) } else { (b, a) };
+            let (low_magnitude, high_magnitude) = sorted(magnitudes);
+            let (low_duration, high_duration) = sorted(durations);
+            let (low_cost, high_cost) = if base_costs.0 <= base_costs.1 {
+                base_costs
+            } else {
+                (base_costs.1, base_costs.0)
+            };
+            prop_assert!(
+                PotionEffect::calc_gold_value(low_magnitude, low_duration, low_cost)
+                    <= PotionEffect::calc_gold_value(high_magnitude, high_duration, high_cost)
+            );
+        }
+
+        #[test]
+        fn duration_0_counts_as_10(magnitude in 0u32..10_000, base_cost in 0.0f32..1000.0) {
+            prop_assert_eq!(
+                PotionEffect::calc_gold_value(magnitude, 0, base_cost),
+                PotionEffect::calc_gold_value(magnitude, 10, base_cost)
+            );
+        }
+
+        #[test]
+        fn magnitude_0_counts_as_1(duration in 0u32..10_000, base_cost in 0.0f32..1000.0) {
+            prop_assert_eq!(
+                PotionEffect::calc_gold_value(0, duration, base_cost),
+                PotionEffect::calc_gold_value(1, duration, base_cost)
+            );
+        }
+
+        #[test]
+        fn no_magnitude_flag(base_magnitude in 0.0f32..1000.0, power_factor in 0.0f32..20.0) {
+            prop_assert_eq!(
+                PotionEffect::calc_magnitude(
+                    base_magnitude,
+                    NO_MAGNITUDE | POWER_AFFECTS_MAGNITUDE,
+                    power_factor
+                ),
+                0
+            );
+        }
+    }
+
+    /// Frost Lily and Ember Root from the example game data share Restore Health, of which Ember
+    /// Root's is stronger, and Resist Fire, of which both are equally valuable: Frost Lily's has
+    /// magnitude 3 for 60 seconds and Ember Root's magnitude 6 for 30 seconds
+    fn game_data_with_unequal_versions() -> GameData {
+        let mut json = serde_json::from_str::<serde_json::Value>(include_str!(
+            "../fixtures/example_game_data.json"
+        ))
+        .unwrap();
+        let ember_root = json["ingredients"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .find(|ingredient| ingredient["name"] == "Ember Root")
+            .unwrap();
+        for effect in ember_root["effects"].as_array_mut().unwrap() {
+            match effect["global_form_id"].as_str().unwrap() {
+                // Restore Health
+                "0000:000900" => effect["magnitude"] = serde_json::json!(8.0),
+                // Resist Fire
+                "0000:000904" => {
+                    effect["magnitude"] = serde_json::json!(6.0);
+                    effect["duration"] = serde_json::json!(30);
+                }
+                _ => {}
+            }
+        }
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn contributions_agree_with_the_potions_effects() {
+        let game_data = game_data_with_unequal_versions();
+        let frost_lily = game_data.find_ingredient_by_name("Frost Lily").unwrap();
+        let ember_root = game_data.find_ingredient_by_name("Ember Root").unwrap();
+        let restore_health = GlobalFormId::new(0, 0x900);
+
+        for ingredients in [[frost_lily, ember_root], [ember_root, frost_lily]] {
+            let potion = Potion::from_ingredients(&ingredients, &game_data, 1.0).unwrap();
+            let contributions = potion.get_effect_contributions(1.0);
+            assert_eq!(contributions.len(), potion.effects.len());
+
+            for (effect, contributions) in potion.effects.iter().zip(&contributions) {
+                assert_eq!(contributions.len(), 2);
+                let used = contributions
+                    .iter()
+                    .filter(|contribution| contribution.is_used)
+                    .collect::<Vec<_>>();
+                assert_eq!(used.len(), 1);
+                assert_eq!(
+                    (used[0].magnitude, used[0].duration, used[0].gold_value),
+                    (
+                        effect.get_magnitude(),
+                        effect.get_duration(),
+                        effect.get_gold_value()
+                    )
+                );
+
+                if effect.get_global_form_id() == restore_health {
+                    // The stronger version wins whatever the order of the ingredients
+                    assert_eq!(used[0].ingredient.name.as_deref(), Some("Ember Root"));
+                } else {
+                    // Equally valuable versions, of which the first ingredient's wins
+                    assert_eq!(contributions[0].gold_value, contributions[1].gold_value);
+                    assert!(contributions[0].is_used);
+                    assert_eq!(
+                        used[0].ingredient.get_global_form_id(),
+                        ingredients[0].get_global_form_id()
+                    );
+                }
+            }
+        }
+    }
+}
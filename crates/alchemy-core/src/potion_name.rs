@@ -0,0 +1,162 @@
+use std::{borrow::Cow, str::FromStr};
+
+use itertools::Itertools;
+use serde_with::DeserializeFromStr;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback},
+    locale::NumberLocale,
+    potion::{Potion, PotionEffect},
+};
+
+const VARIABLES: &str = "type, effect, secondary, effects, effects:N, value or initials";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// Potion or Poison
+    Type,
+    /// The name of the primary effect
+    Effect,
+    /// The name of the second effect, or nothing if the potion has only one
+    Secondary,
+    /// The names of all effects, or of the first N, separated by commas
+    Effects(Option<usize>),
+    /// The gold value
+    Value,
+    /// The initials of each ingredient's name, separated by plus signs
+    Initials,
+}
+
+/// A template for potion names such as `{type}: {effects:2} ({value}g)`. Variables are enclosed
+/// in braces, and literal braces are written as `{{` and `}}`. The game names potions as
+/// `{type} of {effect}`.
+#[derive(Clone, Debug, PartialEq, Eq, DeserializeFromStr)]
+pub struct PotionNameTemplate {
+    segments: Vec<Segment>,
+}
+
+impl PotionNameTemplate {
+    /// Returns the potion's name, with its value formatted with the locale
+    pub fn render(&self, potion: &Potion, locale: NumberLocale) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Type => potion.get_potion_type().to_string(),
+                Segment::Effect => get_effect_name(potion.get_primary_effect()).to_string(),
+                Segment::Secondary => potion
+                    .effects
+                    .get(1)
+                    .map(get_effect_name)
+                    .unwrap_or_default()
+                    .to_string(),
+                Segment::Effects(limit) => potion
+                    .effects
+                    .iter()
+                    .take(limit.unwrap_or(usize::MAX))
+                    .map(get_effect_name)
+                    .join(", "),
+                Segment::Value => locale.format_integer(potion.gold_value),
+                Segment::Initials => potion
+                    .ingredients
+                    .iter()
+                    .map(|ig| {
+                        get_ingredient_name_or_fallback(ig)
+                            .split_whitespace()
+                            .filter_map(|word| word.chars().next())
+                            .flat_map(char::to_uppercase)
+                            .collect::<String>()
+                    })
+                    .join("+"),
+            })
+            .collect()
+    }
+}
+
+fn get_effect_name<'a>(effect: &'a PotionEffect) -> Cow<'a, str> {
+    get_magic_effect_name_or_fallback(effect.magic_effect)
+}
+
+fn parse_variable(variable: &str) -> Result<Segment, String> {
+    match variable.split_once(':') {
+        None => match variable {
+            "type" => Ok(Segment::Type),
+            "effect" | "primary" => Ok(Segment::Effect),
+            "secondary" => Ok(Segment::Secondary),
+            "effects" => Ok(Segment::Effects(None)),
+            "value" => Ok(Segment::Value),
+            "initials" => Ok(Segment::Initials),
+            other => Err(format!(
+                "unknown variable {{{}}} in potion name template, expected {}",
+                other, VARIABLES
+            )),
+        },
+        Some(("effects", limit)) => match limit.parse::<usize>() {
+            Ok(limit) if limit > 0 => Ok(Segment::Effects(Some(limit))),
+            _ => Err(format!(
+                "invalid number of effects {} in potion name template, expected a positive number",
+                limit
+            )),
+        },
+        Some(_) => Err(format!(
+            "unknown variable {{{}}} in potion name template, expected {}",
+            variable, VARIABLES
+        )),
+    }
+}
+
+impl FromStr for PotionNameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut variable = String::new();
+                    let mut is_closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            is_closed = true;
+                            break;
+                        }
+                        variable.push(c);
+                    }
+                    if !is_closed {
+                        return Err(format!(
+                            "unclosed {{{} in potion name template, use {{{{ for a literal brace",
+                            variable
+                        ));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_variable(variable.trim())?);
+                }
+                '}' => {
+                    return Err(
+                        "unmatched } in potion name template, use }} for a literal brace"
+                            .to_string(),
+                    )
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+}
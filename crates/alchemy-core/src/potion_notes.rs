@@ -0,0 +1,177 @@
+use std::{borrow::Cow, fmt::Display};
+
+use itertools::Itertools;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData},
+    potion::{EffectContribution, Potion, PotionType},
+};
+
+/// Provides contextual notes about potions, such as hints about where to sell or craft them
+pub trait PotionNotesProvider: Sync {
+    /// Returns the notes for the potion, if any
+    fn get_notes(&self, potion: &Potion) -> Vec<String>;
+}
+
+/// Notes which kind of merchant a potion is best sold to, based on the category of its primary
+/// effect. Apothecaries buy all potions and poisons, while general goods merchants are better
+/// suited for potions that aren't restoratives or poisons.
+pub struct MerchantNotesProvider;
+
+impl PotionNotesProvider for MerchantNotesProvider {
+    fn get_notes(&self, potion: &Potion) -> Vec<String> {
+        let primary_effect = potion.get_primary_effect().magic_effect;
+
+        let note = match potion.get_potion_type() {
+            PotionType::Poison => "Sell to an apothecary (poison)",
+            PotionType::Potion if primary_effect.restores_vitals() => {
+                "Sell to an apothecary (restorative)"
+            }
+            PotionType::Potion => "Sell to an apothecary or a general goods merchant",
+        };
+
+        vec![note.to_string()]
+    }
+}
+
+/// Returns the notes providers that are used by default
+pub fn get_default_notes_providers() -> Vec<Box<dyn PotionNotesProvider>> {
+    vec![Box::new(MerchantNotesProvider)]
+}
+
+/// A potion along with the notes about it
+pub struct AnnotatedPotion<'p, 'a> {
+    pub potion: &'p Potion<'a>,
+    /// The game data the potion was made from, whose potion name template and number locale the
+    /// potion is output with
+    pub game_data: &'a GameData,
+    pub notes: Vec<String>,
+    /// The versions of each effect that the ingredients contribute, if they were computed with
+    /// `with_contributions`
+    pub contributions: Option<Vec<Vec<EffectContribution<'a>>>>,
+}
+
+impl<'p, 'a> AnnotatedPotion<'p, 'a> {
+    /// Collects the notes about the potion from all of the given providers
+    pub fn new(
+        potion: &'p Potion<'a>,
+        game_data: &'a GameData,
+        providers: &[Box<dyn PotionNotesProvider>],
+    ) -> Self {
+        Self {
+            potion,
+            game_data,
+            notes: providers
+                .iter()
+                .flat_map(|provider| provider.get_notes(potion))
+                .collect(),
+            contributions: None,
+        }
+    }
+
+    /// Adds the versions of each effect that the ingredients contribute, to show which ingredient
+    /// the potion got each effect's magnitude and duration from. The power factor must be the one
+    /// the potion was made with.
+    pub fn with_contributions(mut self, power_factor: f32) -> Self {
+        self.contributions = Some(self.potion.get_effect_contributions(power_factor));
+        self
+    }
+}
+
+impl<'p, 'a> Display for AnnotatedPotion<'p, 'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.potion)?;
+        if !self.notes.is_empty() {
+            write!(
+                f,
+                "\nNotes:\n{}",
+                self.notes
+                    .iter()
+                    .map(|note| format!("- {}", note))
+                    .join("\n")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An effect of an `AnnotatedPotion` as it is serialized
+#[derive(Serialize)]
+struct SerializedEffect<'e> {
+    name: Cow<'e, str>,
+    /// The in-game description of the effect, with its magnitude and duration filled in
+    description: String,
+    magnitude: u32,
+    duration: u32,
+    gold_value: u32,
+    is_hostile: bool,
+    /// The versions of the effect that the ingredients contribute, if they were computed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contributions: Option<Vec<SerializedContribution<'e>>>,
+}
+
+/// An `EffectContribution` as it is serialized
+#[derive(Serialize)]
+struct SerializedContribution<'e> {
+    ingredient: Cow<'e, str>,
+    magnitude: u32,
+    duration: u32,
+    gold_value: u32,
+    is_used: bool,
+}
+
+impl<'p, 'a> Serialize for AnnotatedPotion<'p, 'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let locale = self.game_data.get_number_locale();
+        let mut ap = serializer.serialize_struct("AnnotatedPotion", 6)?;
+        ap.serialize_field("name", &self.potion.get_potion_name(self.game_data))?;
+        ap.serialize_field("description", &self.potion.get_potion_description(locale))?;
+        ap.serialize_field("gold_value", &self.potion.gold_value)?;
+        ap.serialize_field(
+            "ingredients",
+            &self
+                .potion
+                .ingredients
+                .iter()
+                .map(|ig| get_ingredient_name_or_fallback(ig))
+                .collect::<Vec<_>>(),
+        )?;
+        ap.serialize_field(
+            "effects",
+            &self
+                .potion
+                .effects
+                .iter()
+                .enumerate()
+                .map(|(index, eff)| SerializedEffect {
+                    name: get_magic_effect_name_or_fallback(eff.magic_effect),
+                    description: eff.get_description(locale),
+                    magnitude: eff.get_magnitude(),
+                    duration: eff.get_duration(),
+                    gold_value: eff.get_gold_value(),
+                    is_hostile: eff.magic_effect.is_hostile,
+                    contributions: self.contributions.as_ref().map(|contributions| {
+                        contributions[index]
+                            .iter()
+                            .map(|contribution| SerializedContribution {
+                                ingredient: get_ingredient_name_or_fallback(
+                                    contribution.ingredient,
+                                ),
+                                magnitude: contribution.magnitude,
+                                duration: contribution.duration,
+                                gold_value: contribution.gold_value,
+                                is_used: contribution.is_used,
+                            })
+                            .collect()
+                    }),
+                })
+                .collect::<Vec<_>>(),
+        )?;
+        ap.serialize_field("notes", &self.notes)?;
+        ap.end()
+    }
+}
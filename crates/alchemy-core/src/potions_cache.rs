@@ -0,0 +1,594 @@
+use std::{
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::anyhow;
+use arrayvec::ArrayVec;
+use itertools::Itertools;
+use memmap2::Mmap;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    game_data::GameData,
+    load_order_hash::{Fnv1aHasher, LoadOrderHash},
+    plugin_parser::form_id::{FormIdContainer, GlobalFormId},
+    potion::{AlchemySkill, Potion, MAX_SUPPORTED_INGREDIENTS, MIN_INGREDIENTS},
+    potions_list::PotionsPage,
+};
+
+/// Identifies a potions cache file
+const CACHE_MAGIC: &[u8; 4] = b"SAPC";
+
+/// Bump this whenever the cache format or the way potions are computed changes, so that stale
+/// caches are rebuilt
+const CACHE_VERSION: u32 = 5;
+
+/// The size of the header before the number of potions of each group: the magic bytes, the format
+/// version, the key, the load order hash and the number of groups
+const HEADER_SIZE: usize = 4 + 4 + 8 + 8 + 4;
+
+/// The size of an ingredient's form ID in a record: its load order index as a u16 and its ID as a
+/// u32
+const FORM_ID_SIZE: usize = 6;
+
+/// Returns the size of the record of a potion with the number of ingredients: the form IDs of its
+/// ingredients followed by its gold value as a u32
+fn get_record_size(num_ingredients: usize) -> usize {
+    num_ingredients * FORM_ID_SIZE + 4
+}
+
+/// Computes the key that identifies the potions computed from the given game data and options.
+/// If anything that affects the computed potions changes, so does the key: the load order hash
+/// and the load order, and the effects of the ingredients and the base cost, flags and hostility of
+/// the magic effects, which the ingredient overlay and the hostility overrides can change after
+/// export. The key is computed with a fixed hash function, so it is the same with every build.
+pub fn get_cache_key(
+    game_data: &GameData,
+    max_ingredients: usize,
+    alchemy_skill: &AlchemySkill,
+) -> u64 {
+    let mut hasher = Fnv1aHasher::default();
+    hasher.write(&CACHE_VERSION.to_le_bytes());
+    hasher.write(&(max_ingredients as u64).to_le_bytes());
+    hasher.write(&alchemy_skill.get_power_factor().to_bits().to_le_bytes());
+    let crafting_rules = game_data.get_crafting_rules();
+    hasher.write(&(crafting_rules.min_ingredients as u64).to_le_bytes());
+    hasher.write(&(crafting_rules.max_effects as u64).to_le_bytes());
+    hasher.write(&[
+        crafting_rules.primary_effect as u8,
+        crafting_rules.poison as u8,
+        u8::from(crafting_rules.prefer_effects_of_type),
+    ]);
+
+    hasher.write(
+        &game_data
+            .get_load_order_hash()
+            .map_or(0, LoadOrderHash::to_u64)
+            .to_le_bytes(),
+    );
+    for plugin in game_data.get_load_order().iter() {
+        hasher.write(plugin.as_bytes());
+        hasher.write_u8(0);
+    }
+
+    // Hash maps have no stable iteration order, so sort everything by form ID first
+    let write_form_id = |hasher: &mut Fnv1aHasher, form_id: GlobalFormId| {
+        hasher.write(&form_id.load_order_index.to_le_bytes());
+        hasher.write(&form_id.id.to_le_bytes());
+    };
+    for ingredient in game_data
+        .get_ingredients()
+        .values()
+        .sorted_by_key(|ig| ig.get_global_form_id())
+    {
+        write_form_id(&mut hasher, ingredient.get_global_form_id());
+        hasher.write_u8(ingredient.effects.len() as u8);
+        for effect in &ingredient.effects {
+            write_form_id(&mut hasher, effect.get_global_form_id());
+            hasher.write(&effect.magnitude.to_bits().to_le_bytes());
+            hasher.write(&effect.duration.to_le_bytes());
+        }
+    }
+    for magic_effect in game_data
+        .get_magic_effects()
+        .values()
+        .sorted_by_key(|mgef| mgef.get_global_form_id())
+    {
+        write_form_id(&mut hasher, magic_effect.get_global_form_id());
+        hasher.write(&magic_effect.base_cost.to_bits().to_le_bytes());
+        hasher.write(&magic_effect.flags.to_le_bytes());
+        hasher.write_u8(u8::from(magic_effect.is_hostile));
+    }
+
+    hasher.finish()
+}
+
+/// Writes the potions to the cache file at `path`. After the header, which records the load order
+/// hash of the game data, or 0 if it has none, to tell which data a cache file came from, and the
+/// number of potions of each group as u64s, each group's potions are stored as fixed-width records
+/// in the same order. A record holds the form IDs of the potion's ingredients followed by its gold
+/// value; the effects are recomputed when reading the cache. The file is replaced rather than
+/// overwritten, since another process may have it mapped.
+pub fn write(
+    path: &Path,
+    key: u64,
+    load_order_hash: Option<LoadOrderHash>,
+    potions: &[Vec<Potion>],
+) -> io::Result<()> {
+    let start = Instant::now();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("cache.tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+    writer.write_all(CACHE_MAGIC)?;
+    writer.write_all(&CACHE_VERSION.to_le_bytes())?;
+    writer.write_all(&key.to_le_bytes())?;
+    writer.write_all(
+        &load_order_hash
+            .map_or(0, LoadOrderHash::to_u64)
+            .to_le_bytes(),
+    )?;
+    writer.write_all(&(potions.len() as u32).to_le_bytes())?;
+    for potions_n in potions {
+        writer.write_all(&(potions_n.len() as u64).to_le_bytes())?;
+    }
+    for potions_n in potions {
+        for potion in potions_n {
+            for ingredient in &potion.ingredients {
+                let form_id = ingredient.get_global_form_id();
+                writer.write_all(&form_id.load_order_index.to_le_bytes())?;
+                writer.write_all(&form_id.id.to_le_bytes())?;
+            }
+            writer.write_all(&potion.gold_value.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(&temp_path, path)?;
+
+    log::debug!(
+        "Wrote potions cache to {} (in {:?})",
+        path.display(),
+        start.elapsed()
+    );
+
+    Ok(())
+}
+
+/// Reads the potions from the cache file at `path`. Returns `Ok(None)` if there is no cache file
+/// or if it was written for a different key, i.e. for different game data or options.
+pub fn read<'a>(
+    path: &Path,
+    key: u64,
+    game_data: &'a GameData,
+    max_ingredients: usize,
+    power_factor: f32,
+) -> Result<Option<Vec<Vec<Potion<'a>>>>, anyhow::Error> {
+    let start = Instant::now();
+
+    let cache = match MappedPotionsCache::open(path, key, max_ingredients)? {
+        Some(cache) => cache,
+        None => return Ok(None),
+    };
+    let potions = cache.read_all(game_data, power_factor)?;
+
+    log::debug!(
+        "Read {} potions from cache at {} (in {:?})",
+        potions
+            .iter()
+            .map(|potions_n| potions_n.len())
+            .sum::<usize>(),
+        path.display(),
+        start.elapsed()
+    );
+
+    Ok(Some(potions))
+}
+
+/// A potions cache file mapped into memory. The potions are stored as fixed-width records sorted by
+/// gold value, so any potion can be found without reading the ones before it.
+pub(crate) struct MappedPotionsCache {
+    mmap: Mmap,
+    /// The byte offset of the first record and the number of potions of each group. The first
+    /// group holds the potions with `MIN_INGREDIENTS` ingredients.
+    groups: Vec<(usize, usize)>,
+}
+
+impl MappedPotionsCache {
+    /// Maps the cache file at `path` into memory and checks that its size matches its header,
+    /// without reading the potions. Returns `Ok(None)` if there is no cache file or if it was
+    /// written for a different key, i.e. for different game data or options.
+    pub(crate) fn open(
+        path: &Path,
+        key: u64,
+        max_ingredients: usize,
+    ) -> Result<Option<Self>, anyhow::Error> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => Err(err)?,
+        };
+        // Safety: the cache is replaced rather than overwritten when it is written again, so the
+        // mapped file doesn't change
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let data = &mmap[..];
+        if data.len() < HEADER_SIZE || &data[..4] != CACHE_MAGIC {
+            Err(anyhow!("{} is not a potions cache file", path.display()))?
+        }
+        if read_u32_at(data, 4) != CACHE_VERSION || read_u64_at(data, 8) != key {
+            log::debug!("Potions cache at {} is stale", path.display());
+            return Ok(None);
+        }
+        match read_u64_at(data, 16) {
+            0 => log::debug!("Potions cache has no load order hash"),
+            load_order_hash => log::debug!(
+                "Potions cache load order hash: {}",
+                LoadOrderHash::from_u64(load_order_hash)
+            ),
+        }
+
+        let num_groups = read_u32_at(data, 24) as usize;
+        if num_groups != max_ingredients + 1 - MIN_INGREDIENTS {
+            Err(anyhow!("potions cache has an unexpected number of groups"))?
+        }
+        let mut offset = HEADER_SIZE + num_groups * 8;
+        if data.len() < offset {
+            Err(anyhow!("potions cache is truncated"))?
+        }
+        let mut groups = Vec::with_capacity(num_groups);
+        for group_index in 0..num_groups {
+            let num_potions = usize::try_from(read_u64_at(data, HEADER_SIZE + group_index * 8))?;
+            groups.push((offset, num_potions));
+            offset = num_potions
+                .checked_mul(get_record_size(MIN_INGREDIENTS + group_index))
+                .and_then(|size| offset.checked_add(size))
+                .ok_or_else(|| anyhow!("potions cache has an invalid number of potions"))?;
+        }
+        if offset != data.len() {
+            Err(anyhow!("potions cache has an unexpected size"))?
+        }
+
+        Ok(Some(Self { mmap, groups }))
+    }
+
+    fn get_record(&self, group_index: usize, index: usize) -> &[u8] {
+        let (offset, _) = self.groups[group_index];
+        let record_size = get_record_size(MIN_INGREDIENTS + group_index);
+        &self.mmap[offset + index * record_size..][..record_size]
+    }
+
+    fn get_gold_value(&self, group_index: usize, index: usize) -> u32 {
+        let record = self.get_record(group_index, index);
+        read_u32_at(record, record.len() - 4)
+    }
+
+    /// Recomputes the potion of a record from its ingredients, checking that it has the gold value
+    /// of the record
+    fn get_potion<'a>(
+        &self,
+        group_index: usize,
+        index: usize,
+        game_data: &'a GameData,
+        power_factor: f32,
+    ) -> Result<Potion<'a>, anyhow::Error> {
+        let record = self.get_record(group_index, index);
+        let (form_ids, gold_value) = record.split_at(record.len() - 4);
+        let ingredients = form_ids
+            .chunks_exact(FORM_ID_SIZE)
+            .map(|form_id| {
+                let form_id = GlobalFormId::new(read_u16_at(form_id, 0), read_u32_at(form_id, 2));
+                game_data.get_ingredient(&form_id).ok_or_else(|| {
+                    anyhow!(
+                        "potions cache references unknown ingredient {}",
+                        form_id.to_display_with(game_data.get_load_order())
+                    )
+                })
+            })
+            .collect::<Result<ArrayVec<_, MAX_SUPPORTED_INGREDIENTS>, _>>()?;
+
+        let potion = Potion::from_ingredients_unchecked(ingredients, game_data, power_factor);
+        if potion.gold_value != read_u32_at(gold_value, 0) {
+            Err(anyhow!("potions cache contains an incorrect gold value"))?
+        }
+        Ok(potion)
+    }
+
+    /// Recomputes all of the potions, grouped by number of ingredients and sorted by gold value
+    /// descending. This is still much faster than finding the valid combos and sorting them.
+    pub(crate) fn read_all<'a>(
+        &self,
+        game_data: &'a GameData,
+        power_factor: f32,
+    ) -> Result<Vec<Vec<Potion<'a>>>, anyhow::Error> {
+        self.groups
+            .iter()
+            .enumerate()
+            .map(|(group_index, (_, num_potions))| {
+                (0..*num_potions)
+                    .into_par_iter()
+                    .map(|index| self.get_potion(group_index, index, game_data, power_factor))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect()
+    }
+
+    /// Returns the page of potions that match the predicate in order of gold value descending.
+    /// Potions worth less than the page's minimum value are skipped by binary search, and only the
+    /// potions looked at before the page is full are recomputed, so a small page of a cache of
+    /// millions of potions takes milliseconds.
+    pub(crate) fn find_potions_by_value<'a, F>(
+        &self,
+        game_data: &'a GameData,
+        power_factor: f32,
+        page: PotionsPage,
+        predicate: F,
+    ) -> Result<Vec<Potion<'a>>, anyhow::Error>
+    where
+        F: Fn(&Potion) -> bool,
+    {
+        let positions = self
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(group_index, (_, num_potions))| {
+                // Each group is sorted by gold value descending
+                let end = partition_point(*num_potions, |index| {
+                    self.get_gold_value(group_index, index) >= page.min_value
+                });
+                (0..end).map(move |index| (group_index, index))
+            })
+            .kmerge_by(|a, b| self.get_gold_value(a.0, a.1) > self.get_gold_value(b.0, b.1));
+
+        let mut potions = Vec::new();
+        let mut num_skipped = 0;
+        for (group_index, index) in positions {
+            if potions.len() >= page.limit {
+                break;
+            }
+            let potion = self.get_potion(group_index, index, game_data, power_factor)?;
+            if !predicate(&potion) {
+                continue;
+            }
+            if num_skipped < page.offset {
+                num_skipped += 1;
+                continue;
+            }
+            potions.push(potion);
+        }
+
+        Ok(potions)
+    }
+}
+
+/// Returns the index of the first of `len` items for which the predicate is false, given that it
+/// is true for all items before it and false for all items after it
+fn partition_point(len: usize, predicate: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if predicate(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+fn read_u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64_at(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp::Reverse, path::PathBuf};
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const MAX_INGREDIENTS: usize = 3;
+
+    /// Computes the potions of each number of ingredients, sorted by gold value descending, as
+    /// `PotionsList` stores them
+    fn get_potions(game_data: &GameData) -> Vec<Vec<Potion>> {
+        let ingredients = game_data
+            .get_ingredients()
+            .values()
+            .sorted_by_key(|ig| ig.get_global_form_id())
+            .collect::<Vec<_>>();
+        (MIN_INGREDIENTS..=MAX_INGREDIENTS)
+            .map(|num_ingredients| {
+                ingredients
+                    .iter()
+                    .copied()
+                    .combinations(num_ingredients)
+                    .filter_map(|combo| Potion::from_ingredients(&combo, game_data, 1.0).ok())
+                    .sorted_by_key(|potion| Reverse(potion.gold_value))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the form IDs of the ingredients and the gold value of each potion
+    fn describe(potions: &[Vec<Potion>]) -> Vec<Vec<(Vec<GlobalFormId>, u32)>> {
+        potions
+            .iter()
+            .map(|potions_n| {
+                potions_n
+                    .iter()
+                    .map(|potion| {
+                        (
+                            potion
+                                .ingredients
+                                .iter()
+                                .map(|ig| ig.get_global_form_id())
+                                .collect(),
+                            potion.gold_value,
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("alchemy-potions-cache-{}", std::process::id()))
+            .join(format!("{}.cache", name))
+    }
+
+    #[test]
+    fn written_cache_reads_back_the_same_potions() {
+        let game_data = GameData::example();
+        let potions = get_potions(&game_data);
+        let path = get_cache_path("round-trip");
+        write(&path, 42, game_data.get_load_order_hash(), &potions).unwrap();
+
+        let read_potions = read(&path, 42, &game_data, MAX_INGREDIENTS, 1.0)
+            .unwrap()
+            .expect("cache was written with the same key");
+        assert_eq!(describe(&read_potions), describe(&potions));
+
+        // A page by value is the same as the first potions of all groups merged by value
+        let cache = MappedPotionsCache::open(&path, 42, MAX_INGREDIENTS)
+            .unwrap()
+            .unwrap();
+        let page = cache
+            .find_potions_by_value(
+                &game_data,
+                1.0,
+                PotionsPage {
+                    offset: 1,
+                    limit: 3,
+                    min_value: 0,
+                },
+                |_| true,
+            )
+            .unwrap();
+        let expected_values = potions
+            .iter()
+            .flatten()
+            .map(|potion| potion.gold_value)
+            .sorted_by_key(|gold_value| Reverse(*gold_value))
+            .skip(1)
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            page.iter()
+                .map(|potion| potion.gold_value)
+                .collect::<Vec<_>>(),
+            expected_values
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_cache_is_rejected() {
+        let game_data = GameData::example();
+        let potions = get_potions(&game_data);
+        let path = get_cache_path("truncated");
+        write(&path, 42, None, &potions).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        for len in [data.len() - 1, HEADER_SIZE + 8, HEADER_SIZE - 1, 0] {
+            fs::write(&path, &data[..len]).unwrap();
+            assert!(
+                MappedPotionsCache::open(&path, 42, MAX_INGREDIENTS).is_err(),
+                "cache truncated to {} bytes should be rejected",
+                len
+            );
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stale_or_missing_cache_is_ignored() {
+        let game_data = GameData::example();
+        let potions = get_potions(&game_data);
+        let path = get_cache_path("stale");
+        write(&path, 42, None, &potions).unwrap();
+
+        assert!(MappedPotionsCache::open(&path, 43, MAX_INGREDIENTS)
+            .unwrap()
+            .is_none());
+        assert!(read(&path, 43, &game_data, MAX_INGREDIENTS, 1.0)
+            .unwrap()
+            .is_none());
+
+        fs::remove_file(&path).unwrap();
+        assert!(MappedPotionsCache::open(&path, 42, MAX_INGREDIENTS)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn cache_key_changes_with_what_affects_the_potions() {
+        let alchemy_skill = AlchemySkill::default();
+        let game_data = GameData::example();
+        let key = get_cache_key(&game_data, MAX_INGREDIENTS, &alchemy_skill);
+
+        assert_eq!(
+            get_cache_key(&GameData::example(), MAX_INGREDIENTS, &alchemy_skill),
+            key
+        );
+        assert_ne!(get_cache_key(&game_data, 2, &alchemy_skill), key);
+        assert_ne!(
+            get_cache_key(
+                &game_data,
+                MAX_INGREDIENTS,
+                &AlchemySkill {
+                    skill_level: alchemy_skill.skill_level + 1,
+                    ..alchemy_skill
+                }
+            ),
+            key
+        );
+
+        let mut with_hash = GameData::example();
+        with_hash.set_load_order_hash(LoadOrderHash::from_u64(1));
+        assert_ne!(
+            get_cache_key(&with_hash, MAX_INGREDIENTS, &alchemy_skill),
+            key
+        );
+
+        let mut fewer_ingredients = GameData::example();
+        fewer_ingredients.retain_ingredients(|ig| ig.name.as_deref() != Some("Salt Crystal"));
+        assert_ne!(
+            get_cache_key(&fewer_ingredients, MAX_INGREDIENTS, &alchemy_skill),
+            key
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn partition_point_matches_slice_partition_point(
+            mut values in prop::collection::vec(0u32..100, 0..50),
+            threshold in 0u32..100,
+        ) {
+            values.sort_unstable_by_key(|value| Reverse(*value));
+            prop_assert_eq!(
+                partition_point(values.len(), |index| values[index] >= threshold),
+                values.partition_point(|value| *value >= threshold)
+            );
+        }
+    }
+}
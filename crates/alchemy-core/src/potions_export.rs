@@ -0,0 +1,109 @@
+use std::{io::Write, str::FromStr};
+
+use itertools::Itertools;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback, GameData},
+    plugin_parser::form_id::FormIdContainer,
+    potion::{Potion, Ranking},
+    potions_list::PotionsList,
+};
+
+/// The file format to export potions to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// The serialized `PotionsList`, with ingredients and magic effects as form IDs
+    #[default]
+    Json,
+    /// One row per potion, with lists of values separated by semicolons
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!("unknown format {}, expected json or csv", other)),
+        }
+    }
+}
+
+const CSV_HEADER: &[&str] = &[
+    "gold_value",
+    "potion_type",
+    "name",
+    "ingredients",
+    "ingredient_form_ids",
+    "effects",
+    "effect_form_ids",
+    "magnitudes",
+    "durations",
+    "effect_gold_values",
+];
+
+/// Writes all potions in the list to the writer in the given format, in order of gold value
+/// descending
+pub fn write_potions<W: Write>(
+    potions_list: &PotionsList,
+    format: ExportFormat,
+    mut writer: W,
+) -> Result<(), anyhow::Error> {
+    match format {
+        ExportFormat::Json => serde_json::to_writer(&mut writer, potions_list)?,
+        ExportFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER.join(","))?;
+            for potion in potions_list.get_potions(&Ranking::default()) {
+                writeln!(
+                    writer,
+                    "{}",
+                    format_csv_row(potion, potions_list.get_game_data())
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn format_csv_row(potion: &Potion, game_data: &GameData) -> String {
+    let effects = &potion.effects;
+    [
+        potion.gold_value.to_string(),
+        potion.get_potion_type().to_string(),
+        potion.get_potion_name(game_data),
+        potion
+            .ingredients
+            .iter()
+            .map(|ig| get_ingredient_name_or_fallback(ig))
+            .join(";"),
+        potion
+            .ingredients
+            .iter()
+            .map(|ig| ig.get_global_form_id())
+            .join(";"),
+        effects
+            .iter()
+            .map(|eff| get_magic_effect_name_or_fallback(eff.magic_effect))
+            .join(";"),
+        effects.iter().map(|eff| eff.get_global_form_id()).join(";"),
+        effects.iter().map(|eff| eff.get_magnitude()).join(";"),
+        effects.iter().map(|eff| eff.get_duration()).join(";"),
+        effects.iter().map(|eff| eff.get_gold_value()).join(";"),
+    ]
+    .iter()
+    .map(|field| escape_csv_field(field))
+    .join(",")
+}
+
+/// Quotes the field if it contains characters that have a special meaning in CSV
+pub(crate) fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
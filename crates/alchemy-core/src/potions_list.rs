@@ -0,0 +1,1147 @@
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::{cmp::Reverse, collections::HashSet, path::Path, time::Instant};
+
+use ahash::{AHashMap, AHashSet};
+use arrayvec::ArrayVec;
+use itertools::Itertools;
+use permutator::LargeCombinationIterator;
+use rayon::{
+    iter::{
+        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelBridge,
+        ParallelIterator,
+    },
+    slice::ParallelSliceMut,
+};
+
+use crate::{
+    dominance::DominanceIndex,
+    game_data::{GameData, UnknownFormIdError},
+    name_matching::normalize_name,
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::Ingredient,
+    },
+    potion::{
+        AlchemySkill, Potion, PotionType, Ranking, MAX_SUPPORTED_INGREDIENTS, MIN_INGREDIENTS,
+    },
+    potions_cache::{self, MappedPotionsCache},
+};
+
+/// Returns the number of ways to choose `k` of `n` items
+fn count_combinations(n: usize, k: usize) -> u128 {
+    if k > n {
+        return 0;
+    }
+    (0..k as u128).fold(1, |count, i| count * (n as u128 - i) / (i + 1))
+}
+
+/// The position of a potion in `PotionsList::potions`: the index of the Vec of potions with the
+/// same number of ingredients, and the index of the potion in it
+type PotionPosition = (usize, usize);
+
+/// Positions of the potions grouped in ways that are commonly filtered on, so that those queries
+/// don't need to scan all potions. Each Vec is sorted by gold value descending.
+#[derive(Default)]
+struct PotionIndexes {
+    potions: Vec<PotionPosition>,
+    poisons: Vec<PotionPosition>,
+    by_primary_effect: AHashMap<GlobalFormId, Vec<PotionPosition>>,
+}
+
+/// The number of potions that are filtered in parallel at a time. Filtering stops after the chunk
+/// in which enough matches were found, so smaller chunks waste less work on large lists.
+const FILTER_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Returns the first `limit` items that match the predicate, in order. The items are filtered in
+/// parallel one chunk at a time, until enough matches are found.
+fn find_matches<T, F>(items: &[T], limit: usize, predicate: F) -> Vec<&T>
+where
+    T: Sync,
+    F: Fn(&T) -> bool + Sync,
+{
+    let mut matches = Vec::new();
+    for chunk in items.chunks(FILTER_CHUNK_SIZE) {
+        if matches.len() >= limit {
+            break;
+        }
+        matches.extend(
+            chunk
+                .par_iter()
+                .filter(|item| predicate(item))
+                .collect::<Vec<_>>(),
+        );
+    }
+    matches.truncate(limit);
+    matches
+}
+
+/// Which of the matching potions to return, to page through them
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PotionsPage {
+    /// The number of matching potions to skip
+    pub offset: usize,
+    /// The maximum number of potions to return after the skipped ones
+    pub limit: usize,
+    /// Potions worth less gold are never returned
+    pub min_value: u32,
+}
+
+/// Which ingredients potions may contain, resolved from ingredient names to form IDs once so that
+/// filtering potions doesn't need to compare names
+#[derive(Default)]
+pub(crate) struct IngredientFilter {
+    /// If set, all of a potion's ingredients must be in it
+    allowed: Option<AHashSet<GlobalFormId>>,
+    /// None of a potion's ingredients may be in it
+    blocked: AHashSet<GlobalFormId>,
+}
+
+impl IngredientFilter {
+    /// Creates a filter from the names of the ingredients in the whitelist and blacklist. An empty
+    /// whitelist allows all ingredients. Ingredients without a name are never whitelisted. A name
+    /// that several ingredients share matches all of them, while a qualified name such as
+    /// "Frost Salts [CACO]" matches only that one. Names are compared by `normalize_name`.
+    pub(crate) fn new(
+        game_data: &GameData,
+        whitelist: &AHashSet<String>,
+        blacklist: &AHashSet<String>,
+    ) -> Self {
+        let find_ids = |names: &AHashSet<String>| {
+            let names = names
+                .iter()
+                .map(|name| normalize_name(name))
+                .collect::<AHashSet<_>>();
+            game_data
+                .get_ingredients()
+                .values()
+                .filter(|ig| {
+                    [&ig.name, &ig.qualified_name]
+                        .into_iter()
+                        .flatten()
+                        .any(|name| names.contains(&normalize_name(name)))
+                })
+                .map(|ig| ig.get_global_form_id())
+                .collect::<AHashSet<_>>()
+        };
+
+        Self {
+            allowed: (!whitelist.is_empty()).then(|| find_ids(whitelist)),
+            blocked: find_ids(blacklist),
+        }
+    }
+
+    /// Blocks the ingredients in addition to those in the blacklist
+    pub(crate) fn block(&mut self, ingredient_ids: impl IntoIterator<Item = GlobalFormId>) {
+        self.blocked.extend(ingredient_ids);
+    }
+
+    /// Returns whether the filter allows all of the potion's ingredients
+    pub(crate) fn allows(&self, potion: &Potion) -> bool {
+        potion
+            .ingredients
+            .iter()
+            .all(|ig| self.allows_ingredient(ig))
+    }
+
+    /// Returns whether the filter allows the ingredient
+    pub(crate) fn allows_ingredient(&self, ingredient: &Ingredient) -> bool {
+        let form_id = ingredient.get_global_form_id();
+        !self.blocked.contains(&form_id)
+            && self
+                .allowed
+                .as_ref()
+                .map_or(true, |allowed| allowed.contains(&form_id))
+    }
+
+    /// Returns whether the filter allows all ingredients
+    pub(crate) fn is_empty(&self) -> bool {
+        self.allowed.is_none() && self.blocked.is_empty()
+    }
+
+    /// Returns whether the filter only allows the ingredients in a whitelist
+    pub(crate) fn has_whitelist(&self) -> bool {
+        self.allowed.is_some()
+    }
+}
+
+/// Which potions to build, when only some of them are needed
+pub(crate) struct BuildFilter<'f> {
+    /// Combinations with ingredients that this doesn't allow are skipped without building a potion
+    pub ingredients: &'f IngredientFilter,
+    /// Potions are only kept if this returns true for them
+    pub is_match: &'f (dyn Fn(&Potion) -> bool + Sync),
+}
+
+pub struct PotionsList<'a> {
+    game_data: &'a GameData,
+    max_ingredients: usize,
+    alchemy_skill: AlchemySkill,
+    /// Potions grouped by number of ingredients, each sorted by gold value descending. The first
+    /// entry holds the potions with `MIN_INGREDIENTS` ingredients.
+    potions: Vec<Vec<Potion<'a>>>,
+    indexes: PotionIndexes,
+    /// The potions cache, if it was mapped instead of reading the potions from it. Only
+    /// `find_mapped_potions_by_value` can use it.
+    mapped_cache: Option<MappedPotionsCache>,
+}
+
+/// Serializes the potions in order of gold value descending. Ingredients and magic effects are
+/// serialized as form IDs which refer to the game data, to keep the size manageable.
+impl<'a> Serialize for PotionsList<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut pl = serializer.serialize_struct("PotionsList", 3)?;
+        pl.serialize_field("load_order_hash", &self.game_data.get_load_order_hash())?;
+        pl.serialize_field("max_ingredients", &self.max_ingredients)?;
+        pl.serialize_field("potions", &self.get_potions_by_value().collect::<Vec<_>>())?;
+        pl.end()
+    }
+}
+
+impl<'a> PotionsList<'a> {
+    /// Create a new `PotionsList` from the provided ingredients and magic effects.
+    /// Note: the ingredients and magic effects hashmaps should include all those that exist in the
+    /// game. Filtering the `PotionsList` can be done after construction.
+    ///
+    /// `max_ingredients` is the maximum number of ingredients per potion. It must be in the range
+    /// `MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS`.
+    ///
+    /// The potions' strength is determined by the alchemy skill in the game data's player context,
+    /// or the default alchemy skill if there is no player context.
+    pub fn new(game_data: &'a GameData, max_ingredients: usize) -> Self {
+        assert!(
+            (MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS).contains(&max_ingredients),
+            "max_ingredients must be between {} and {}",
+            MIN_INGREDIENTS,
+            MAX_SUPPORTED_INGREDIENTS
+        );
+
+        let alchemy_skill = game_data
+            .get_player_context()
+            .map_or_else(AlchemySkill::default, |player_context| {
+                player_context.alchemy_skill
+            });
+
+        Self {
+            game_data,
+            max_ingredients,
+            alchemy_skill,
+            potions: Vec::new(),
+            indexes: PotionIndexes::default(),
+            mapped_cache: None,
+        }
+    }
+
+    /// Returns the power factor that the potions are made with
+    pub fn get_power_factor(&self) -> f32 {
+        self.alchemy_skill.get_power_factor()
+    }
+
+    /// Returns the game data that the potions are made from
+    pub fn get_game_data(&self) -> &'a GameData {
+        self.game_data
+    }
+
+    /// Computes all possible potions
+    pub fn build_potions(&mut self) {
+        self.build_potions_with(None);
+    }
+
+    /// Computes only the potions that the filter allows. Combinations with ingredients that it
+    /// doesn't allow are never considered, so this is much faster than computing all potions when
+    /// it allows few ingredients.
+    pub(crate) fn build_potions_filtered(&mut self, filter: &BuildFilter) {
+        self.build_potions_with(Some(filter));
+    }
+
+    fn build_potions_with(&mut self, filter: Option<&BuildFilter>) {
+        let power_factor = self.alchemy_skill.get_power_factor();
+        self.potions = (MIN_INGREDIENTS..=self.max_ingredients)
+            .map(|num_ingredients| {
+                PotionsList::build_potions_n(self.game_data, num_ingredients, power_factor, filter)
+            })
+            .collect();
+        self.build_indexes();
+    }
+
+    /// Indexes the potions by type and primary effect. Must be called whenever the potions change.
+    fn build_indexes(&mut self) {
+        let start = Instant::now();
+        let mut indexes = PotionIndexes::default();
+        // Visiting the potions in order of gold value keeps each index sorted
+        for (position, potion) in self.get_positions_by_value() {
+            match potion.get_potion_type() {
+                PotionType::Potion => indexes.potions.push(position),
+                PotionType::Poison => indexes.poisons.push(position),
+            }
+            indexes
+                .by_primary_effect
+                .entry(potion.get_primary_effect().get_global_form_id())
+                .or_default()
+                .push(position);
+        }
+        log::debug!(
+            "Indexed {} potions and {} poisons with {} different primary effects (in {:?})",
+            indexes.potions.len(),
+            indexes.poisons.len(),
+            indexes.by_primary_effect.len(),
+            start.elapsed()
+        );
+        self.indexes = indexes;
+    }
+
+    /// Reads the potions from the cache at `cache_path` if it was written for the same game data
+    /// and options, and otherwise computes all possible potions and writes them to the cache.
+    /// Problems with the cache are logged rather than returned, since the potions can always be
+    /// computed instead.
+    pub fn build_potions_cached(&mut self, cache_path: &Path) {
+        let key =
+            potions_cache::get_cache_key(self.game_data, self.max_ingredients, &self.alchemy_skill);
+
+        match potions_cache::read(
+            cache_path,
+            key,
+            self.game_data,
+            self.max_ingredients,
+            self.alchemy_skill.get_power_factor(),
+        ) {
+            Ok(Some(potions)) => {
+                self.potions = potions;
+                self.build_indexes();
+                return;
+            }
+            Ok(None) => {}
+            Err(err) => log::warn!(
+                "Ignoring invalid potions cache at {}: {}",
+                cache_path.display(),
+                err
+            ),
+        }
+
+        self.build_potions();
+        if let Err(err) = potions_cache::write(
+            cache_path,
+            key,
+            self.game_data.get_load_order_hash(),
+            &self.potions,
+        ) {
+            log::warn!(
+                "Failed to write potions cache to {}: {}",
+                cache_path.display(),
+                err
+            );
+        }
+    }
+
+    /// Maps the potions cache at `cache_path` into memory instead of reading its potions, if it was
+    /// written for the same game data and options, so that pages of potions by gold value can be
+    /// found without recomputing all potions. Returns false if there is no such cache, in which
+    /// case the potions must be built another way. Only `find_mapped_potions_by_value` can use the
+    /// mapped potions, so this is only useful if nothing else is needed.
+    pub(crate) fn map_potions_cached(&mut self, cache_path: &Path) -> bool {
+        let start = Instant::now();
+        let key =
+            potions_cache::get_cache_key(self.game_data, self.max_ingredients, &self.alchemy_skill);
+        let mapped_cache = MappedPotionsCache::open(cache_path, key, self.max_ingredients);
+        match mapped_cache {
+            Ok(Some(mapped_cache)) => {
+                log::debug!(
+                    "Mapped potions cache at {} (in {:?})",
+                    cache_path.display(),
+                    start.elapsed()
+                );
+                self.mapped_cache = Some(mapped_cache);
+                true
+            }
+            Ok(None) => false,
+            Err(err) => {
+                log::warn!(
+                    "Ignoring invalid potions cache at {}: {}",
+                    cache_path.display(),
+                    err
+                );
+                false
+            }
+        }
+    }
+
+    /// Returns the page of potions that match the predicate in order of gold value descending from
+    /// the mapped potions cache, or `None` if the cache wasn't mapped. See `map_potions_cached`.
+    pub(crate) fn find_mapped_potions_by_value<F>(
+        &self,
+        page: PotionsPage,
+        predicate: F,
+    ) -> Option<Result<Vec<Potion<'a>>, anyhow::Error>>
+    where
+        F: Fn(&Potion) -> bool,
+    {
+        self.mapped_cache.as_ref().map(|mapped_cache| {
+            mapped_cache.find_potions_by_value(
+                self.game_data,
+                self.alchemy_skill.get_power_factor(),
+                page,
+                predicate,
+            )
+        })
+    }
+
+    /// Compute the Vec of potions with `num_ingredients` ingredients that the filter allows, if any
+    fn build_potions_n<'g>(
+        game_data: &'g GameData,
+        num_ingredients: usize,
+        power_factor: f32,
+        filter: Option<&BuildFilter>,
+    ) -> Vec<Potion<'g>> {
+        if num_ingredients < game_data.get_crafting_rules().min_ingredients {
+            log::debug!(
+                "Skipping {}-ingredient combos, which the crafting rules don't allow",
+                num_ingredients
+            );
+            return Vec::new();
+        }
+
+        let ingredients = game_data
+            .get_ingredients()
+            .values()
+            .filter(|ig| filter.map_or(true, |filter| filter.ingredients.allows_ingredient(ig)))
+            .sorted_by_key(|ig| &ig.name)
+            .collect::<Vec<_>>();
+        let is_match = |potion: &Potion| filter.map_or(true, |filter| (filter.is_match)(potion));
+
+        let estimated_combos_size = count_combinations(ingredients.len(), num_ingredients)
+            .saturating_mul(
+                (std::mem::size_of::<Vec<&&Ingredient>>()
+                    + num_ingredients * std::mem::size_of::<&&Ingredient>())
+                    as u128,
+            );
+        let mut potions = match game_data.get_memory_budget() {
+            Some(budget) if estimated_combos_size > budget as u128 => {
+                log::debug!(
+                    "Streaming {}-ingredient combos since storing them would take about {} MiB",
+                    num_ingredients,
+                    estimated_combos_size / (1024 * 1024)
+                );
+                PotionsList::build_potions_n_streaming(
+                    game_data,
+                    &ingredients,
+                    num_ingredients,
+                    power_factor,
+                    is_match,
+                )
+            }
+            _ => PotionsList::build_potions_n_stored(
+                game_data,
+                &ingredients,
+                num_ingredients,
+                power_factor,
+                is_match,
+            ),
+        };
+
+        let start = Instant::now();
+        // Sort (unstably) in parallel by gold value descending
+        potions.par_sort_unstable_by(|a, b| a.gold_value.cmp(&b.gold_value).reverse());
+        log::debug!(
+            "Sorted {} Potion instances (in {:?})",
+            potions.len(),
+            start.elapsed()
+        );
+
+        potions
+    }
+
+    /// Computes the potions by temporarily storing all combinations, which is fast but needs a lot
+    /// of memory for larger numbers of ingredients
+    fn build_potions_n_stored<'g>(
+        game_data: &'g GameData,
+        ingredients: &[&'g Ingredient],
+        num_ingredients: usize,
+        power_factor: f32,
+        is_match: impl Fn(&Potion) -> bool + Sync,
+    ) -> Vec<Potion<'g>> {
+        // TODO: see if it might be possible to generate the combinations in parallel somehow
+        // TODO: recheck this note
+        // Note: temporarily storing the combinations and then using par_iter is about twice as
+        // fast as using par_bridge directly on the combinations iterator (at the cost of some ram)
+        let start = Instant::now();
+        let combos: Vec<_> =
+            LargeCombinationIterator::new(ingredients, num_ingredients).collect::<Vec<_>>();
+        log::debug!(
+            "Found {} possible {}-ingredient combos (in {:?})",
+            combos.len(),
+            num_ingredients,
+            start.elapsed()
+        );
+
+        // Checking, building and filtering each combo in one pass avoids collecting the valid
+        // combos, and potions that the filter rejects are dropped right away
+        let start = Instant::now();
+        let potions: Vec<_> = combos
+            .par_iter()
+            .filter_map(|combo| {
+                let ingredients = combo
+                    .iter()
+                    .copied()
+                    .copied()
+                    .collect::<ArrayVec<_, MAX_SUPPORTED_INGREDIENTS>>();
+                is_valid_combo(&ingredients)
+                    .then(|| {
+                        Potion::from_ingredients_unchecked(ingredients, game_data, power_factor)
+                    })
+                    .filter(|potion| is_match(potion))
+            })
+            .collect();
+        log::debug!(
+            "Created {} Potion instances from {} {}-ingredient combos (in {:?})",
+            potions.len(),
+            combos.len(),
+            num_ingredients,
+            start.elapsed()
+        );
+
+        potions
+    }
+
+    /// Computes the potions directly from the combinations iterator, which is slower but only
+    /// keeps the valid potions in memory
+    fn build_potions_n_streaming<'g>(
+        game_data: &'g GameData,
+        ingredients: &[&'g Ingredient],
+        num_ingredients: usize,
+        power_factor: f32,
+        is_match: impl Fn(&Potion) -> bool + Sync,
+    ) -> Vec<Potion<'g>> {
+        let start = Instant::now();
+        let potions: Vec<_> = LargeCombinationIterator::new(ingredients, num_ingredients)
+            .par_bridge()
+            .filter_map(|combo| {
+                let ingredients = combo
+                    .into_iter()
+                    .copied()
+                    .collect::<ArrayVec<_, MAX_SUPPORTED_INGREDIENTS>>();
+                is_valid_combo(&ingredients)
+                    .then(|| {
+                        Potion::from_ingredients_unchecked(ingredients, game_data, power_factor)
+                    })
+                    .filter(|potion| is_match(potion))
+            })
+            .collect();
+        log::debug!(
+            "Created {} Potion instances from streamed {}-ingredient combos (in {:?})",
+            potions.len(),
+            num_ingredients,
+            start.elapsed()
+        );
+
+        potions
+    }
+
+    /// Removes the potions that another potion of the same type beats on every count: it has at
+    /// least the same effects, its ingredients cost at most as much and it is worth more. See
+    /// `DominanceIndex`.
+    pub fn prune_dominated(&mut self) {
+        let start = Instant::now();
+        let index = DominanceIndex::new(self.potions.iter().flatten());
+        let mut num_pruned = 0;
+        for potions in &mut self.potions {
+            let is_dominated = potions
+                .par_iter()
+                .map(|potion| index.is_dominated(potion))
+                .collect::<Vec<_>>();
+            let mut is_dominated = is_dominated.into_iter();
+            let num_potions = potions.len();
+            potions.retain(|_| !is_dominated.next().unwrap());
+            num_pruned += num_potions - potions.len();
+        }
+        log::debug!(
+            "Pruned {} dominated potions, {} remain (in {:?})",
+            num_pruned,
+            self.potions.iter().map(Vec::len).sum::<usize>(),
+            start.elapsed()
+        );
+        self.build_indexes();
+    }
+
+    /// Returns an iterator over all potions in order of the given ranking. The scores of potions
+    /// and poisons are not comparable unless both are ranked by gold value, so in that case the
+    /// best potions and poisons are interleaved.
+    pub fn get_potions(&self, ranking: &Ranking) -> Box<dyn Iterator<Item = &Potion> + '_> {
+        if ranking.is_by_value() {
+            return Box::new(self.get_potions_by_value());
+        }
+
+        Box::new(
+            self.rank_by_score(&self.indexes.potions, ranking)
+                .into_iter()
+                .interleave(self.rank_by_score(&self.indexes.poisons, ranking)),
+        )
+    }
+
+    /// Returns an iterator over the potions of the given type in order of the given ranking. This
+    /// uses an index, so it doesn't need to go through the potions of the other type.
+    pub fn get_potions_of_type(
+        &self,
+        potion_type: PotionType,
+        ranking: &Ranking,
+    ) -> Box<dyn Iterator<Item = &Potion> + '_> {
+        let positions = match potion_type {
+            PotionType::Potion => &self.indexes.potions,
+            PotionType::Poison => &self.indexes.poisons,
+        };
+        if ranking.is_by_value() {
+            return Box::new(
+                positions
+                    .iter()
+                    .map(move |position| self.get_potion_at(position)),
+            );
+        }
+        Box::new(self.rank_by_score(positions, ranking).into_iter())
+    }
+
+    /// Returns an iterator over the potions whose primary effect is one of the given magic effects,
+    /// in order of the given ranking. This uses an index, so it doesn't need to go through the
+    /// potions with other primary effects.
+    pub fn get_potions_with_primary_effects(
+        &self,
+        effect_ids: &AHashSet<GlobalFormId>,
+        ranking: &Ranking,
+    ) -> Box<dyn Iterator<Item = &Potion> + '_> {
+        let by_value = effect_ids
+            .iter()
+            .filter_map(|effect_id| self.indexes.by_primary_effect.get(effect_id))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(move |positions| {
+                positions
+                    .iter()
+                    .map(move |position| self.get_potion_at(position))
+            })
+            .kmerge_by(|a, b| a.gold_value > b.gold_value);
+        if ranking.is_by_value() {
+            return Box::new(by_value);
+        }
+
+        // The primary effect determines the potion type, but the effects may be of both types
+        let (mut potions, mut poisons): (Vec<_>, Vec<_>) =
+            by_value.partition(|p| p.get_potion_type() == PotionType::Potion);
+        // Sort by score descending. The sort is stable, so ties remain sorted by gold value.
+        let player_context = self.game_data.get_player_context();
+        potions.sort_by_cached_key(|p| Reverse(p.get_score(ranking, player_context)));
+        poisons.sort_by_cached_key(|p| Reverse(p.get_score(ranking, player_context)));
+
+        Box::new(potions.into_iter().interleave(poisons.into_iter()))
+    }
+
+    /// Returns the page of potions that match the predicate in order of gold value descending,
+    /// optionally only of the given type. Unlike filtering the iterator returned by `get_potions`,
+    /// this filters the potions in parallel, skips the potions worth less than the page's minimum
+    /// value without looking at them, and stops once enough matches are found.
+    pub(crate) fn find_potions_by_value<F>(
+        &self,
+        potion_type: Option<PotionType>,
+        page: PotionsPage,
+        predicate: F,
+    ) -> Vec<&Potion>
+    where
+        F: Fn(&Potion) -> bool + Sync,
+    {
+        let limit = page.offset.saturating_add(page.limit);
+        let positions = match potion_type {
+            Some(PotionType::Potion) => &self.indexes.potions,
+            Some(PotionType::Poison) => &self.indexes.poisons,
+            // Each group is sorted by gold value, so the best matches overall are among the best
+            // matches of each group
+            None => {
+                return self
+                    .potions
+                    .iter()
+                    .map(|potions| {
+                        let end = potions.partition_point(|p| p.gold_value >= page.min_value);
+                        find_matches(&potions[..end], limit, &predicate)
+                    })
+                    .kmerge_by(|a, b| a.gold_value > b.gold_value)
+                    .skip(page.offset)
+                    .take(page.limit)
+                    .collect()
+            }
+        };
+        let end = positions
+            .partition_point(|position| self.get_potion_at(position).gold_value >= page.min_value);
+        find_matches(&positions[..end], limit, |position| {
+            predicate(self.get_potion_at(position))
+        })
+        .into_iter()
+        .skip(page.offset)
+        .map(|position| self.get_potion_at(position))
+        .collect()
+    }
+
+    /// Returns the potions at the positions, which must be sorted by gold value, sorted by score
+    /// descending. The sort is stable, so ties remain sorted by gold value.
+    fn rank_by_score(&self, positions: &[PotionPosition], ranking: &Ranking) -> Vec<&Potion> {
+        let mut potions = positions
+            .iter()
+            .map(|position| self.get_potion_at(position))
+            .collect::<Vec<_>>();
+        let player_context = self.game_data.get_player_context();
+        potions.sort_by_cached_key(|p| Reverse(p.get_score(ranking, player_context)));
+        potions
+    }
+
+    fn get_potion_at(&self, (group_index, index): &PotionPosition) -> &Potion {
+        &self.potions[*group_index][*index]
+    }
+
+    fn get_potions_by_value(&self) -> impl Iterator<Item = &Potion> + '_ {
+        self.get_positions_by_value().map(|(_, potion)| potion)
+    }
+
+    fn get_positions_by_value(&self) -> impl Iterator<Item = (PotionPosition, &Potion)> + '_ {
+        // Return an iterator over the potions vecs merged in order of gold value descending
+        self.potions
+            .iter()
+            .enumerate()
+            .map(|(group_index, potions)| {
+                potions
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, potion)| ((group_index, index), potion))
+            })
+            .kmerge_by(|(_, a), (_, b)| a.gold_value > b.gold_value)
+    }
+}
+
+/// Suggests the most valuable potions containing at least one of the given ingredients, without
+/// computing all possible potions. Only the combinations found by `grow_combos` are considered,
+/// which covers every valid potion containing them.
+///
+/// The potions' strength is determined by the alchemy skill, and `max_ingredients` is the maximum
+/// number of ingredients per potion. It must be in the range
+/// `MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS`.
+pub fn suggest_for_ingredients<'a>(
+    game_data: &'a GameData,
+    ingredient_ids: &[GlobalFormId],
+    alchemy_skill: &AlchemySkill,
+    max_ingredients: usize,
+    limit: usize,
+) -> Result<Vec<Potion<'a>>, UnknownFormIdError> {
+    assert!(
+        (MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS).contains(&max_ingredients),
+        "max_ingredients must be between {} and {}",
+        MIN_INGREDIENTS,
+        MAX_SUPPORTED_INGREDIENTS
+    );
+
+    let start = Instant::now();
+    let power_factor = alchemy_skill.get_power_factor();
+    let min_ingredients = game_data.get_crafting_rules().min_ingredients;
+
+    let mut seen_combos = AHashSet::<ArrayVec<GlobalFormId, MAX_SUPPORTED_INGREDIENTS>>::new();
+    let mut combos = Vec::<ArrayVec<&Ingredient, MAX_SUPPORTED_INGREDIENTS>>::new();
+    for form_id in ingredient_ids {
+        let ingredient = game_data
+            .get_ingredient(form_id)
+            .ok_or(UnknownFormIdError { form_id: *form_id })?;
+        combos.extend(grow_combos(
+            game_data,
+            ingredient,
+            max_ingredients,
+            |_| true,
+            &mut seen_combos,
+        ));
+    }
+
+    let mut potions = combos
+        .into_par_iter()
+        .filter(|combo| combo.len() >= min_ingredients && is_valid_combo(combo))
+        .map(|combo| Potion::from_ingredients_unchecked(combo, game_data, power_factor))
+        .collect::<Vec<_>>();
+    potions.par_sort_unstable_by(|a, b| a.gold_value.cmp(&b.gold_value).reverse());
+    potions.truncate(limit);
+
+    log::debug!(
+        "Suggested {} potions for {} ingredients (in {:?})",
+        potions.len(),
+        ingredient_ids.len(),
+        start.elapsed()
+    );
+
+    Ok(potions)
+}
+
+/// Finds the most valuable potion containing the ingredient, combined only with partner
+/// ingredients for which `is_partner` returns true. Like `suggest_for_ingredients`, this only
+/// considers the combinations grown from the ingredient instead of computing all potions.
+pub fn find_best_potion_with<'a, F>(
+    game_data: &'a GameData,
+    ingredient: &'a Ingredient,
+    max_ingredients: usize,
+    power_factor: f32,
+    is_partner: F,
+) -> Option<Potion<'a>>
+where
+    F: Fn(&Ingredient) -> bool,
+{
+    let start = Instant::now();
+    let min_ingredients = game_data.get_crafting_rules().min_ingredients;
+
+    let combos = grow_combos(
+        game_data,
+        ingredient,
+        max_ingredients,
+        is_partner,
+        &mut AHashSet::new(),
+    );
+    let num_combos = combos.len();
+    let potion = combos
+        .into_par_iter()
+        .filter(|combo| combo.len() >= min_ingredients && is_valid_combo(combo))
+        .map(|combo| Potion::from_ingredients_unchecked(combo, game_data, power_factor))
+        .max_by_key(|potion| potion.gold_value);
+
+    log::debug!(
+        "Found the best potion with {} among {} combinations (in {:?})",
+        ingredient.get_global_form_id(),
+        num_combos,
+        start.elapsed()
+    );
+
+    potion
+}
+
+/// Returns the combinations of up to `max_ingredients` ingredients that can be grown from the
+/// ingredient by repeatedly adding a partner ingredient that shares an effect with the
+/// combination, plus the combinations of a pair with the ingredient and another pair of partner
+/// ingredients. Together they include every valid potion with the ingredient: in a valid potion
+/// each ingredient shares an effect with another one, so up to three ingredients are always
+/// connected, and four ingredients are either connected or two pairs that share nothing with each
+/// other. Combinations already in `seen_combos` are skipped, and the returned ones are added to it.
+fn grow_combos<'a, F>(
+    game_data: &'a GameData,
+    ingredient: &'a Ingredient,
+    max_ingredients: usize,
+    is_partner: F,
+    seen_combos: &mut AHashSet<ArrayVec<GlobalFormId, MAX_SUPPORTED_INGREDIENTS>>,
+) -> Vec<ArrayVec<&'a Ingredient, MAX_SUPPORTED_INGREDIENTS>>
+where
+    F: Fn(&Ingredient) -> bool,
+{
+    let mut combos = Vec::new();
+    let mut frontier = vec![ArrayVec::from_iter([ingredient])];
+    for _ in MIN_INGREDIENTS..=max_ingredients {
+        let mut next_frontier = Vec::new();
+        for combo in &frontier {
+            let neighbors = combo
+                .iter()
+                .flat_map(|ig| game_data.get_neighbors(ig))
+                .filter(|neighbor| !combo.contains(neighbor) && is_partner(neighbor))
+                .collect::<AHashSet<_>>();
+            for neighbor in neighbors {
+                let mut grown_combo = combo.clone();
+                grown_combo.push(neighbor);
+                if seen_combos.insert(get_combo_key(&grown_combo)) {
+                    next_frontier.push(grown_combo);
+                }
+            }
+        }
+        combos.extend(next_frontier.iter().cloned());
+        frontier = next_frontier;
+    }
+
+    // Two pairs that don't share effects with each other can't be reached by growing the
+    // combination, so combine each pair with the ingredient with every other pair of partners
+    if max_ingredients >= 2 * MIN_INGREDIENTS {
+        let own_pairs = combos
+            .iter()
+            .filter(|combo| combo.len() == MIN_INGREDIENTS)
+            .cloned()
+            .collect::<Vec<_>>();
+        let other_pairs = game_data
+            .get_ingredients()
+            .values()
+            .filter(|ig| *ig != ingredient && is_partner(ig))
+            .flat_map(|a| {
+                game_data
+                    .get_neighbors(a)
+                    .into_iter()
+                    .filter(move |b| a.get_global_form_id() < b.get_global_form_id())
+                    .map(move |b| [a, b])
+            })
+            .filter(|[_, b]| *b != ingredient && is_partner(b))
+            .collect::<Vec<_>>();
+        for own_pair in &own_pairs {
+            for other_pair in &other_pairs {
+                if other_pair.iter().any(|ig| own_pair.contains(ig)) {
+                    continue;
+                }
+                let mut combo = own_pair.clone();
+                combo.extend(other_pair.iter().copied());
+                if seen_combos.insert(get_combo_key(&combo)) {
+                    combos.push(combo);
+                }
+            }
+        }
+    }
+
+    combos
+}
+
+/// Returns the form IDs of the ingredients in a combination, sorted so that the same ingredients
+/// in any order have the same key
+fn get_combo_key(combo: &[&Ingredient]) -> ArrayVec<GlobalFormId, MAX_SUPPORTED_INGREDIENTS> {
+    combo
+        .iter()
+        .map(|ig| ig.get_global_form_id())
+        .sorted()
+        .collect()
+}
+
+/// Returns the gold value of the potion that each pair of the ingredients makes, row by row, so
+/// that the value for ingredients `i` and `j` is at `i * ingredients.len() + j`. Pairs that don't
+/// make a potion, and each ingredient paired with itself, are `None`. The crafting rules must allow
+/// potions of two ingredients.
+pub(crate) fn get_pair_values(
+    game_data: &GameData,
+    ingredients: &[&Ingredient],
+    power_factor: f32,
+) -> Vec<Option<u32>> {
+    ingredients
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(i, a)| {
+            ingredients.iter().enumerate().map(move |(j, b)| {
+                let pair = [*a, *b];
+                if i == j || !is_valid_combo(&pair) {
+                    return None;
+                }
+                let potion = Potion::from_ingredients_unchecked(
+                    pair.into_iter().collect(),
+                    game_data,
+                    power_factor,
+                );
+                Some(potion.gold_value)
+            })
+        })
+        .collect()
+}
+
+/// Maximum number of distinct effects of the ingredients in a potion
+const MAX_COMBO_EFFECTS: usize = MAX_SUPPORTED_INGREDIENTS * 4;
+
+/// Returns whether the combination of ingredients makes a potion in which every ingredient is
+/// actually useful, i.e. contributes an effect that the other ingredients don't already provide
+/// on their own.
+fn is_valid_combo(ingredients: &[&Ingredient]) -> bool {
+    if ingredients.len() == MIN_INGREDIENTS {
+        // Ensure the two ingredients share an effect
+        return ingredients[0].shares_effects_with(ingredients[1]);
+    }
+
+    // Count how many of the ingredients have each effect
+    let mut effect_counts = ArrayVec::<(GlobalFormId, u8), MAX_COMBO_EFFECTS>::new();
+    for igef in ingredients.iter().flat_map(|ig| ig.effects.iter()) {
+        let form_id = igef.get_global_form_id();
+        match effect_counts.iter_mut().find(|(id, _)| *id == form_id) {
+            Some((_, count)) => *count += 1,
+            None => effect_counts.push((form_id, 1)),
+        }
+    }
+
+    // An effect that exactly two of the ingredients have is only in the potion because of each of
+    // them. Every ingredient needs such an effect, otherwise the potion has the same effects
+    // without it and the ingredient is used for no reason and goes to waste, e.g. a third
+    // ingredient that only adds another source of an effect the other two already share.
+    ingredients.iter().all(|ig| {
+        ig.effects.iter().any(|igef| {
+            let form_id = igef.get_global_form_id();
+            effect_counts
+                .iter()
+                .any(|(id, count)| *id == form_id && *count == 2)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ingredient_with_effect_ids;
+    use proptest::prelude::*;
+
+    /// Returns an ingredient with the given form ID and effects, identified by their form IDs
+    fn ingredient(id: u32, effect_ids: &[u32]) -> Ingredient {
+        let effect_ids = effect_ids
+            .iter()
+            .map(|&effect_id| GlobalFormId::new(0, effect_id))
+            .collect::<Vec<_>>();
+        ingredient_with_effect_ids(GlobalFormId::new(0, id), &effect_ids)
+    }
+
+    const X: u32 = 0x100;
+    const Y: u32 = 0x200;
+    const Z: u32 = 0x300;
+
+    #[test]
+    fn pair_must_share_an_effect() {
+        let a = ingredient(1, &[X, 0x900]);
+        let b = ingredient(2, &[X, 0x901]);
+        let c = ingredient(3, &[Y, 0x902]);
+        assert!(is_valid_combo(&[&a, &b]));
+        assert!(!is_valid_combo(&[&a, &c]));
+    }
+
+    #[test]
+    fn every_ingredient_must_contribute_a_unique_effect() {
+        // a-b share X, a-c share Y
+        let a = ingredient(1, &[X, Y]);
+        let b = ingredient(2, &[X]);
+        let c = ingredient(3, &[Y]);
+        assert!(is_valid_combo(&[&a, &b, &c]));
+
+        // All three share X, so any one of them could be left out
+        let d = ingredient(4, &[X]);
+        assert!(!is_valid_combo(&[&a, &b, &d]));
+
+        // a-c and a-d both share Y, so c and d are redundant with each other
+        let d = ingredient(4, &[Y]);
+        assert!(!is_valid_combo(&[&a, &b, &c, &d]));
+
+        // a-d share Z instead, so each ingredient is needed
+        let a = ingredient(1, &[X, Y, Z]);
+        let d = ingredient(4, &[Z]);
+        assert!(is_valid_combo(&[&a, &b, &c, &d]));
+    }
+
+    #[test]
+    fn ingredient_that_only_repeats_shared_effects_is_wasted() {
+        // b only has X, which a and c already share through c's second effect
+        let a = ingredient(1, &[X, Y]);
+        let b = ingredient(2, &[X]);
+        let c = ingredient(3, &[X, Y]);
+        assert!(!is_valid_combo(&[&a, &b, &c]));
+    }
+
+    #[test]
+    fn disjoint_pairs_are_valid() {
+        // a-b share X, c-d share Y, and neither pair shares anything with the other
+        let a = ingredient(1, &[X]);
+        let b = ingredient(2, &[X]);
+        let c = ingredient(3, &[Y]);
+        let d = ingredient(4, &[Y]);
+        assert!(is_valid_combo(&[&a, &b, &c, &d]));
+    }
+
+    #[test]
+    fn grow_combos_includes_disjoint_pairs() {
+        // a-b share X, c-d share Y, and neither pair shares anything with the other
+        let game_data = GameData::from_vecs(
+            vec![String::from("Test.esm")],
+            Vec::new(),
+            vec![
+                ingredient(1, &[X]),
+                ingredient(2, &[X]),
+                ingredient(3, &[Y]),
+                ingredient(4, &[Y]),
+            ],
+            Vec::new(),
+            Vec::new(),
+        );
+        let a = game_data.get_ingredient(&GlobalFormId::new(0, 1)).unwrap();
+
+        let combos = grow_combos(&game_data, a, 4, |_| true, &mut AHashSet::new());
+        let keys = combos
+            .iter()
+            .map(|combo| get_combo_key(combo))
+            .collect::<Vec<_>>();
+        let all_four = (1..=4).map(|id| GlobalFormId::new(0, id)).collect();
+        assert!(keys.contains(&all_four));
+        // Growing from a alone only reaches b
+        let pair = [1, 2]
+            .map(|id| GlobalFormId::new(0, id))
+            .into_iter()
+            .collect();
+        assert!(keys.contains(&pair));
+        assert_eq!(keys.len(), 2);
+
+        // Without room for four ingredients, only the pair is possible
+        let combos = grow_combos(&game_data, a, 3, |_| true, &mut AHashSet::new());
+        assert_eq!(combos.len(), 1);
+    }
+
+    /// Returns the ingredients and gold value of each potion in the list, sorted so that lists can
+    /// be compared regardless of the order of equally valuable potions
+    fn describe_potions(potions_list: &PotionsList) -> Vec<(Vec<GlobalFormId>, u32)> {
+        potions_list
+            .potions
+            .iter()
+            .flatten()
+            .map(|potion| {
+                (
+                    potion
+                        .ingredients
+                        .iter()
+                        .map(|ig| ig.get_global_form_id())
+                        .collect(),
+                    potion.gold_value,
+                )
+            })
+            .sorted()
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn build_potions_filtered_matches_filtering_all_potions(
+            allowed_mask in prop::option::of(0u8..64),
+            blocked_mask in 0u8..64,
+            min_value in 0u32..200,
+            max_ingredients in MIN_INGREDIENTS..=MAX_SUPPORTED_INGREDIENTS,
+            is_streaming in any::<bool>(),
+        ) {
+            let all_game_data = GameData::example();
+            let mut all_potions = PotionsList::new(&all_game_data, max_ingredients);
+            all_potions.build_potions();
+
+            let ingredient_ids = all_game_data
+                .get_ingredients()
+                .keys()
+                .copied()
+                .sorted()
+                .collect::<Vec<_>>();
+            let select = |mask: u8| {
+                ingredient_ids
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| mask & (1 << index) != 0)
+                    .map(|(_, form_id)| *form_id)
+                    .collect::<AHashSet<_>>()
+            };
+            let ingredient_filter = IngredientFilter {
+                allowed: allowed_mask.map(select),
+                blocked: select(blocked_mask),
+            };
+            let is_match = |potion: &Potion| potion.gold_value >= min_value;
+            all_potions.potions = std::mem::take(&mut all_potions.potions)
+                .into_iter()
+                .map(|potions| {
+                    potions
+                        .into_iter()
+                        .filter(|potion| ingredient_filter.allows(potion) && is_match(potion))
+                        .collect()
+                })
+                .collect();
+
+            // A budget of 0 bytes makes every number of ingredients stream its combos
+            let mut game_data = GameData::example();
+            game_data.set_memory_budget(is_streaming.then_some(0));
+            let mut filtered_potions = PotionsList::new(&game_data, max_ingredients);
+            filtered_potions.build_potions_filtered(&BuildFilter {
+                ingredients: &ingredient_filter,
+                is_match: &is_match,
+            });
+
+            prop_assert_eq!(describe_potions(&filtered_potions), describe_potions(&all_potions));
+        }
+    }
+}
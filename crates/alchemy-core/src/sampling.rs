@@ -0,0 +1,58 @@
+/// A small, fast pseudorandom number generator. Its output only depends on the seed, so samples
+/// are reproducible across runs, platforms and versions.
+/// See https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in the range `0..bound`. The slight bias of the modulo doesn't matter for
+    /// our purposes.
+    fn next_below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Picks a reproducible random sample of at most `sample_size` items, spread evenly across the
+/// items by dividing them into equally sized strata and picking one item from each. The sample
+/// keeps the order of the items, so if they're ranked, so is the sample.
+pub fn sample_stratified<T>(items: Vec<T>, sample_size: usize, seed: u64) -> Vec<T> {
+    let len = items.len();
+    let sample_size = sample_size.min(len);
+    if sample_size == 0 {
+        return Vec::new();
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    let mut picked_indexes = (0..sample_size).map(|stratum| {
+        let start = stratum * len / sample_size;
+        let end = (stratum + 1) * len / sample_size;
+        start + rng.next_below(end - start)
+    });
+
+    let mut next_index = picked_indexes.next();
+    items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            if Some(index) == next_index {
+                next_index = picked_indexes.next();
+                Some(item)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use nom::{
+    bytes::complete::{tag, take},
+    multi::count,
+    number::complete::{le_i32, le_u32},
+    sequence::tuple,
+    IResult,
+};
+
+use crate::{game_data::GameData, plugin_parser::utils::nom_err_to_anyhow_err};
+
+use super::inventory::InventoryCandidate;
+
+/// The signature at the start of every SKSE co-save
+const COSAVE_SIGNATURE: &[u8] = b"SKSE";
+
+/// A chunk of data stored in the co-save by an SKSE plugin
+pub struct CoSaveChunk<'a> {
+    pub chunk_type: u32,
+    pub version: u32,
+    pub data: &'a [u8],
+}
+
+/// The chunks stored in the co-save by one SKSE plugin
+pub struct CoSavePlugin<'a> {
+    /// The plugin's unique ID, usually four ASCII characters
+    pub signature: u32,
+    pub chunks: Vec<CoSaveChunk<'a>>,
+}
+
+/// Returns the path of the SKSE co-save belonging to the save file, which has the same name with
+/// the .skse extension
+pub fn get_cosave_path(save_path: &Path) -> std::path::PathBuf {
+    save_path.with_extension("skse")
+}
+
+/// Parses the chunks of an SKSE co-save. See `Serialization.cpp` in the SKSE source for the
+/// format.
+pub fn parse_cosave(input: &[u8]) -> Result<Vec<CoSavePlugin>, anyhow::Error> {
+    let (_, plugins) = cosave(input)
+        .map_err(nom_err_to_anyhow_err)
+        .context("failed to parse SKSE co-save")?;
+    Ok(plugins)
+}
+
+fn cosave(input: &[u8]) -> IResult<&[u8], Vec<CoSavePlugin>> {
+    // Signature, format version, SKSE version, runtime version and number of plugins
+    let (input, (_, _, _, _, num_plugins)) =
+        tuple((tag(COSAVE_SIGNATURE), le_u32, le_u32, le_u32, le_u32))(input)?;
+    count(cosave_plugin, num_plugins as usize)(input)
+}
+
+fn cosave_plugin(input: &[u8]) -> IResult<&[u8], CoSavePlugin> {
+    // Signature, number of chunks and total length of the chunks
+    let (input, (signature, num_chunks, _)) = tuple((le_u32, le_u32, le_u32))(input)?;
+    let (input, chunks) = count(cosave_chunk, num_chunks as usize)(input)?;
+    Ok((input, CoSavePlugin { signature, chunks }))
+}
+
+fn cosave_chunk(input: &[u8]) -> IResult<&[u8], CoSaveChunk> {
+    let (input, (chunk_type, version, length)) = tuple((le_u32, le_u32, le_u32))(input)?;
+    let (input, data) = take(length)(input)?;
+    Ok((
+        input,
+        CoSaveChunk {
+            chunk_type,
+            version,
+            data,
+        },
+    ))
+}
+
+/// Scans the chunks of the co-save for inventory entries. SKSE plugins store form IDs as full
+/// runtime form IDs, so this looks for the form IDs of known ingredients followed by a plausible
+/// item count. The candidates of each chunk must be validated like those found in the save.
+pub fn scan_inventory_candidates(
+    plugins: &[CoSavePlugin],
+    game_data: &GameData,
+) -> Vec<Vec<InventoryCandidate>> {
+    plugins
+        .iter()
+        .flat_map(|plugin| plugin.chunks.iter().map(move |chunk| (plugin, chunk)))
+        .map(|(plugin, chunk)| {
+            let mut candidates = Vec::new();
+            let mut remaining_data = chunk.data;
+            while !remaining_data.is_empty() {
+                let offset = chunk.data.len() - remaining_data.len();
+                match cosave_inventory_item(remaining_data, offset, game_data) {
+                    Ok((remaining_input, candidate)) => {
+                        candidates.push(candidate);
+                        remaining_data = remaining_input;
+                    }
+                    Err(_) => remaining_data = &remaining_data[1..],
+                }
+            }
+            if !candidates.is_empty() {
+                log::debug!(
+                    "Found {} inventory candidates in co-save chunk {} version {} of SKSE plugin {}",
+                    candidates.len(),
+                    format_signature(chunk.chunk_type),
+                    chunk.version,
+                    format_signature(plugin.signature)
+                );
+            }
+            candidates
+        })
+        .filter(|candidates| !candidates.is_empty())
+        .collect()
+}
+
+/// Formats a signature such as a plugin's unique ID, which is usually four ASCII characters
+fn format_signature(signature: u32) -> String {
+    String::from_utf8_lossy(&signature.to_be_bytes()).to_string()
+}
+
+fn cosave_inventory_item<'a>(
+    input: &'a [u8],
+    offset: usize,
+    game_data: &GameData,
+) -> Result<(&'a [u8], InventoryCandidate), anyhow::Error> {
+    let (remaining_input, (form_id, item_count)) = tuple((le_u32, le_i32))(input)
+        .map_err(|err: nom::Err<(_, nom::error::ErrorKind)>| nom_err_to_anyhow_err(err))?;
+
+    let form_id = game_data
+        .get_global_form_id_from_runtime(form_id)
+        .filter(|form_id| game_data.has_ingredient(form_id))
+        .ok_or_else(|| anyhow!("form ID is not a known ingredient"))?;
+
+    if !(1..=5000).contains(&item_count) {
+        return Err(anyhow!("item count is improbable"));
+    }
+
+    Ok((
+        remaining_input,
+        InventoryCandidate {
+            offset,
+            form_id,
+            count: item_count,
+            in_form_id_array: false,
+        },
+    ))
+}
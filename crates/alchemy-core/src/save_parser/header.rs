@@ -0,0 +1,167 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context};
+use nom::{
+    bytes::complete::tag,
+    combinator::{cond, map},
+    multi::length_data,
+    number::complete::{le_f32, le_u16, le_u32, le_u64},
+    sequence::tuple,
+    IResult,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::plugin_parser::utils::nom_err_to_anyhow_err;
+
+/// The magic bytes at the start of every save file
+const SAVE_MAGIC: &[u8] = b"TESV_SAVEGAME";
+
+/// Save files of Skyrim (Legendary Edition)
+const VERSION_LEGENDARY_EDITION: u32 = 9;
+/// Save files of Skyrim Special Edition and Anniversary Edition. The header of these versions ends
+/// with the compression type of the rest of the file.
+const VERSION_SPECIAL_EDITION: u32 = 12;
+
+/// The information in the header of a save file, which is shown in the in-game load menu. See
+/// https://en.uesp.net/wiki/Skyrim_Mod:Save_File_Format#Header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveMetadata {
+    pub path: PathBuf,
+    pub last_modified: SystemTime,
+    pub version: u32,
+    pub save_number: u32,
+    pub character_name: String,
+    pub character_level: u32,
+    /// The name of the location the character was in, e.g. "Whiterun"
+    pub location: String,
+    /// The in-game time played, formatted by the game, e.g. "007.12.34" (days.hours.minutes)
+    pub playtime: String,
+    pub race_editor_id: String,
+    pub screenshot_width: u32,
+    pub screenshot_height: u32,
+}
+
+impl SaveMetadata {
+    /// Returns whether the save belongs to the character with the given name, ignoring case
+    pub fn is_character(&self, character_name: &str) -> bool {
+        self.character_name.to_lowercase() == character_name.to_lowercase()
+    }
+}
+
+/// The fields of the header, without the file system information
+struct SaveHeader {
+    version: u32,
+    save_number: u32,
+    character_name: String,
+    character_level: u32,
+    location: String,
+    playtime: String,
+    race_editor_id: String,
+    screenshot_width: u32,
+    screenshot_height: u32,
+}
+
+/// Reads the metadata of a save file. Only the header at the start of the file is read, so this
+/// is much faster than parsing the whole save.
+pub fn read_save_metadata(path: &Path) -> Result<SaveMetadata, anyhow::Error> {
+    let file =
+        File::open(path).with_context(|| format!("failed to open save file {}", path.display()))?;
+    let last_modified = file
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .with_context(|| "failed to read save file modification time")?;
+
+    let mut reader = BufReader::new(file);
+    let mut prefix = [0u8; SAVE_MAGIC.len() + std::mem::size_of::<u32>()];
+    reader
+        .read_exact(&mut prefix)
+        .with_context(|| format!("save file {} is too short", path.display()))?;
+    let (_, header_size) = save_prefix(&prefix)
+        .map_err(nom_err_to_anyhow_err)
+        .with_context(|| format!("{} is not a Skyrim save file", path.display()))?;
+
+    let mut header_data = vec![0u8; header_size as usize];
+    reader
+        .read_exact(&mut header_data)
+        .with_context(|| format!("save file {} has a truncated header", path.display()))?;
+    let header = parse_save_header(&header_data)
+        .with_context(|| format!("failed to parse header of save file {}", path.display()))?;
+
+    Ok(SaveMetadata {
+        path: path.to_path_buf(),
+        last_modified,
+        version: header.version,
+        save_number: header.save_number,
+        character_name: header.character_name,
+        character_level: header.character_level,
+        location: header.location,
+        playtime: header.playtime,
+        race_editor_id: header.race_editor_id,
+        screenshot_width: header.screenshot_width,
+        screenshot_height: header.screenshot_height,
+    })
+}
+
+/// Parses the magic bytes and returns the size of the header that follows them
+fn save_prefix(input: &[u8]) -> IResult<&[u8], u32> {
+    let (input, _) = tag(SAVE_MAGIC)(input)?;
+    le_u32(input)
+}
+
+fn parse_save_header(input: &[u8]) -> Result<SaveHeader, anyhow::Error> {
+    let (_, version) = le_u32(input)
+        .map_err(|err: nom::Err<(_, nom::error::ErrorKind)>| nom_err_to_anyhow_err(err))?;
+    if version < VERSION_LEGENDARY_EDITION {
+        return Err(anyhow!("unsupported save file version {}", version));
+    }
+    if version > VERSION_SPECIAL_EDITION {
+        // The fields we read have been the same across versions so far, so try anyway
+        log::warn!(
+            "Save file version {} is newer than the latest known version {}, its header may be read incorrectly",
+            version,
+            VERSION_SPECIAL_EDITION
+        );
+    }
+
+    let (_, header) = save_header(input).map_err(nom_err_to_anyhow_err)?;
+    Ok(header)
+}
+
+fn save_header(input: &[u8]) -> IResult<&[u8], SaveHeader> {
+    let (input, (version, save_number, character_name, character_level)) =
+        tuple((le_u32, le_u32, wstring, le_u32))(input)?;
+    let (input, (location, playtime, race_editor_id)) = tuple((wstring, wstring, wstring))(input)?;
+    // Sex, current experience, experience needed to level up and the time the save was made
+    let (input, _) = tuple((le_u16, le_f32, le_f32, le_u64))(input)?;
+    let (input, (screenshot_width, screenshot_height)) = tuple((le_u32, le_u32))(input)?;
+    let (input, _compression_type) = cond(version >= VERSION_SPECIAL_EDITION, le_u16)(input)?;
+
+    Ok((
+        input,
+        SaveHeader {
+            version,
+            save_number,
+            character_name,
+            character_level,
+            location,
+            playtime,
+            race_editor_id,
+            screenshot_width,
+            screenshot_height,
+        },
+    ))
+}
+
+/// Parses a string prefixed with its length as a u16. The game uses the Windows-1252 encoding,
+/// which is only decoded correctly for ASCII characters.
+fn wstring(input: &[u8]) -> IResult<&[u8], String> {
+    map(length_data(le_u16), |data: &[u8]| {
+        String::from_utf8_lossy(data).to_string()
+    })(input)
+}
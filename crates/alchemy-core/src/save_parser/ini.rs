@@ -0,0 +1,133 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use encoding_rs::WINDOWS_1252;
+
+/// The INI files in the game's My Games directory that can move the saves directory, in the order
+/// the game reads them, so that settings in later files take precedence
+const INI_FILE_NAMES: &[&str] = &["Skyrim.ini", "SkyrimCustom.ini"];
+
+/// The section and name of the setting for the saves directory, relative to the My Games directory
+const SAVE_PATH_SECTION: &str = "General";
+const SAVE_PATH_KEY: &str = "SLocalSavePath";
+
+/// Returns the saves directory set by the game's INI files in `my_games_path`, along with the INI
+/// file that set it, or None if none of them set it. Mod Organizer's profile-specific INI files
+/// are found too when running under Mod Organizer, which maps them into the My Games directory.
+pub(super) fn find_ini_saves_path(my_games_path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let mut saves_path = None;
+    for ini_file_name in INI_FILE_NAMES {
+        let ini_path = my_games_path.join(ini_file_name);
+        let contents = match fs::read(&ini_path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::warn!("Failed to read {}: {}", ini_path.display(), err);
+                }
+                continue;
+            }
+        };
+        // The game writes its INI files in the system's code page, which is usually Windows-1252
+        let contents = match String::from_utf8(contents) {
+            Ok(contents) => contents,
+            Err(err) => WINDOWS_1252
+                .decode_without_bom_handling(err.as_bytes())
+                .0
+                .into_owned(),
+        };
+
+        if let Some(value) = find_ini_setting(&contents, SAVE_PATH_SECTION, SAVE_PATH_KEY) {
+            // Paths in the INI files use backslashes, which are only separators on Windows, while
+            // Windows also accepts forward slashes. Absolute paths replace the My Games directory.
+            let path = PathBuf::from(value.replace('\\', "/"));
+            saves_path = Some((my_games_path.join(path), ini_path));
+        }
+    }
+    saves_path
+}
+
+/// Returns the non-empty value of the setting in the section of the INI file, ignoring case. If
+/// the setting occurs several times, the last value is returned, like the game does.
+fn find_ini_setting<'a>(contents: &'a str, section: &str, key: &str) -> Option<&'a str> {
+    let mut in_section = false;
+    let mut value = None;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name.trim().eq_ignore_ascii_case(section);
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((name, setting_value)) if name.trim().eq_ignore_ascii_case(key) => {
+                value = Some(setting_value.trim()).filter(|value| !value.is_empty());
+            }
+            _ => {}
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_ini_setting_ignores_case() {
+        let contents = "[general]\nslocalsavepath=Saves\\Custom\\\n";
+        assert_eq!(
+            find_ini_setting(contents, "General", "SLocalSavePath"),
+            Some("Saves\\Custom\\")
+        );
+    }
+
+    #[test]
+    fn find_ini_setting_only_matches_the_section() {
+        let contents = "[Display]\nSLocalSavePath=Display\n\n\
+                        [ General ]\n  SLocalSavePath = General  \n\
+                        [Launcher]\nSLocalSavePath=Launcher\n";
+        assert_eq!(
+            find_ini_setting(contents, "General", "SLocalSavePath"),
+            Some("General")
+        );
+        assert_eq!(
+            find_ini_setting(contents, "Archive", "SLocalSavePath"),
+            None
+        );
+    }
+
+    #[test]
+    fn find_ini_setting_skips_comments() {
+        let contents = "[General]\n;SLocalSavePath=Semicolon\n# SLocalSavePath=Hash\n";
+        assert_eq!(
+            find_ini_setting(contents, "General", "SLocalSavePath"),
+            None
+        );
+    }
+
+    #[test]
+    fn find_ini_setting_returns_the_last_value() {
+        let contents = "[General]\nSLocalSavePath=First\nSLocalSavePath=Last\n";
+        assert_eq!(
+            find_ini_setting(contents, "General", "SLocalSavePath"),
+            Some("Last")
+        );
+
+        // An empty value unsets the setting
+        let contents = "[General]\nSLocalSavePath=First\nSLocalSavePath=\n";
+        assert_eq!(
+            find_ini_setting(contents, "General", "SLocalSavePath"),
+            None
+        );
+    }
+}
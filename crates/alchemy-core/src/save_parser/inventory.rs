@@ -0,0 +1,102 @@
+use ahash::AHashMap;
+use itertools::Itertools;
+
+use crate::plugin_parser::form_id::GlobalFormId;
+
+/// Candidates further apart than this many bytes are not considered part of the same cluster.
+/// Inventory entries are close together, but each can be followed by some extra data.
+const MAX_ENTRY_GAP: usize = 256;
+
+/// Item counts above this are possible but unusual for ingredients
+const USUAL_MAX_COUNT: i32 = 500;
+
+/// Entries with a lower confidence than this are discarded
+pub const MIN_CONFIDENCE: f32 = 0.5;
+
+/// An inventory entry found by scanning the player's change form data for known ingredients. The
+/// scan can find false positives where random bytes happen to match an ingredient.
+pub struct InventoryCandidate {
+    /// The position in the scanned data where the entry was found
+    pub offset: usize,
+    pub form_id: GlobalFormId,
+    pub count: i32,
+    /// Whether the entry's ref ID is an index into the save's form ID array, which is how the save
+    /// refers to forms from plugins
+    pub in_form_id_array: bool,
+}
+
+/// An ingredient in the player's inventory
+#[derive(Clone, Copy, Debug)]
+pub struct InventoryEntry {
+    pub form_id: GlobalFormId,
+    pub count: u32,
+    /// How likely it is that the entry is real rather than a false positive, from 0 to 1
+    pub confidence: f32,
+}
+
+/// Scores the candidates by how likely they are to be real inventory entries and returns at most
+/// one entry per ingredient. Real entries are clustered together and refer to the ingredient
+/// through the form ID array, so candidates outside the largest cluster or with a plain ref ID are
+/// likely false positives.
+pub fn validate_candidates(candidates: &[InventoryCandidate]) -> Vec<InventoryEntry> {
+    let mut clusters = Vec::<Vec<&InventoryCandidate>>::new();
+    for candidate in candidates
+        .iter()
+        .sorted_by_key(|candidate| candidate.offset)
+    {
+        let starts_new_cluster = clusters
+            .last()
+            .and_then(|cluster| cluster.last())
+            .map_or(true, |previous| {
+                candidate.offset - previous.offset > MAX_ENTRY_GAP
+            });
+        if starts_new_cluster {
+            clusters.push(Vec::new());
+        }
+        clusters.last_mut().unwrap().push(candidate);
+    }
+    let largest_cluster_index = clusters
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, cluster)| cluster.len())
+        .map(|(index, _)| index);
+
+    let mut entries = AHashMap::<GlobalFormId, InventoryEntry>::new();
+    for (cluster_index, cluster) in clusters.iter().enumerate() {
+        for candidate in cluster {
+            let mut confidence: f32 = 0.25;
+            if candidate.in_form_id_array {
+                confidence += 0.25;
+            }
+            if Some(cluster_index) == largest_cluster_index {
+                confidence += 0.5;
+            }
+            if candidate.count > USUAL_MAX_COUNT {
+                confidence -= 0.25;
+            }
+
+            let entry = InventoryEntry {
+                form_id: candidate.form_id,
+                count: candidate.count as u32,
+                confidence: confidence.clamp(0.0, 1.0),
+            };
+            // The same ingredient (probably) won't appear multiple times, so keep the most
+            // confident entry, preferring the lowest count
+            entries
+                .entry(entry.form_id)
+                .and_modify(|existing| {
+                    if (entry.confidence, std::cmp::Reverse(entry.count))
+                        > (existing.confidence, std::cmp::Reverse(existing.count))
+                    {
+                        *existing = entry;
+                    }
+                })
+                .or_insert(entry);
+        }
+    }
+
+    entries
+        .into_values()
+        .sorted_by_key(|entry| entry.form_id)
+        .collect()
+}
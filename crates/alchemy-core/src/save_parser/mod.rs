@@ -0,0 +1,782 @@
+use ahash::{AHashMap, AHashSet};
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use log_err::{LogErrOption, LogErrResult};
+use nom::IResult;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use skyrim_savegame::{read_vsval_to_u32, ChangeForm, FormIdType, RefId, SaveFile, VSVal};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::game_data::{get_ingredient_name_or_fallback, GameData};
+use crate::plugin_parser::form_id::{FormIdContainer, GlobalFormId};
+use crate::plugin_parser::utils::nom_err_to_anyhow_err;
+
+use self::header::read_save_metadata;
+use self::ini::find_ini_saves_path;
+use self::inventory::{validate_candidates, InventoryCandidate, MIN_CONFIDENCE};
+use self::save_index::read_save_metadata_indexed;
+
+mod cosave;
+mod header;
+mod ini;
+mod inventory;
+mod save_index;
+
+pub use self::header::SaveMetadata;
+
+/// The name of the saves directory in the My Games directory, unless the INI files change it
+const DEFAULT_SAVES_DIR_NAME: &str = "Saves";
+
+/// Where the save files are, and where the headers read from them are cached
+#[derive(Clone, Debug, Default)]
+pub struct SavesLocation {
+    /// The saves directory, or `None` for the one the game uses
+    pub saves_path: Option<PathBuf>,
+    /// The game's directory in the user's documents, e.g. `My Games/Skyrim Special Edition`, which
+    /// contains its INI files and by default its saves. Needed to find the saves directory the
+    /// game uses.
+    pub my_games_path: Option<PathBuf>,
+    /// The file that the headers of the save files are cached in, or `None` to read the headers of
+    /// all save files every time
+    pub index_path: Option<PathBuf>,
+}
+
+/// Returns the given saves directory, or the one set in the game's INI files, or the default one
+/// in the My Games directory
+pub(crate) fn get_saves_path(saves_location: &SavesLocation) -> Result<PathBuf, anyhow::Error> {
+    if let Some(saves_path) = &saves_location.saves_path {
+        return Ok(saves_path.clone());
+    }
+
+    let my_games_path = saves_location.my_games_path.as_deref().ok_or_else(|| {
+        anyhow!("Cannot find the game's My Games directory, pass the saves directory instead")
+    })?;
+    match find_ini_saves_path(my_games_path) {
+        Some((saves_path, ini_path)) => {
+            log::info!(
+                "Using saves directory {} set in {}",
+                saves_path.display(),
+                ini_path.display()
+            );
+            Ok(saves_path)
+        }
+        None => Ok(my_games_path.join(DEFAULT_SAVES_DIR_NAME)),
+    }
+}
+
+/// Returns the paths of the save files in the directory along with their modification times, most
+/// recently modified first
+pub(crate) fn find_save_files(
+    saves_path: &Path,
+) -> Result<Vec<(PathBuf, SystemTime)>, anyhow::Error> {
+    let mut saves: Vec<(PathBuf, SystemTime)> = vec![];
+    for entry in fs::read_dir(saves_path).with_context(|| "failed to read saves directory")? {
+        let entry = entry.with_context(|| "failed to read saves directory entry")?;
+        let path = entry.path();
+        match path.extension() {
+            Some(ext) if ext != "ess" => continue,
+            None => continue,
+            _ => (),
+        };
+        let metadata = entry
+            .metadata()
+            .with_context(|| "failed to read save file metadata")?;
+        let modified = metadata
+            .modified()
+            .with_context(|| "failed to read save file modification time")?;
+        saves.push((path, modified));
+    }
+
+    // Sort by last modified time descending
+    saves.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+
+    log::debug!(
+        "Found {} save files in directory {}",
+        saves.len(),
+        saves_path.display()
+    );
+
+    Ok(saves)
+}
+
+/// Returns the metadata of the save files in the directory, most recently modified first. Save
+/// files whose header can't be read are skipped with a warning. The headers are cached in the
+/// save index, so only new or changed saves are read.
+pub fn list_saves(saves_location: &SavesLocation) -> Result<Vec<SaveMetadata>, anyhow::Error> {
+    let saves_path = get_saves_path(saves_location)?;
+    let saves = find_save_files(&saves_path)?;
+    Ok(read_save_metadata_indexed(
+        &saves_path,
+        saves_location.index_path.as_deref(),
+        &saves,
+    ))
+}
+
+/// Returns the path and modification time of the most recently modified save file of any
+/// character, or `None` if the directory has no saves. Used to notice when a new save is made.
+pub(crate) fn find_latest_save(
+    saves_location: &SavesLocation,
+) -> Result<Option<(PathBuf, SystemTime)>, anyhow::Error> {
+    Ok(find_save_files(&get_saves_path(saves_location)?)?
+        .into_iter()
+        .next())
+}
+
+/// Returns the path and contents of the most recently modified save file. If a character name is
+/// given, only saves of that character are considered.
+fn get_latest_save_data(
+    saves_location: &SavesLocation,
+    character_name: Option<&str>,
+) -> Result<(PathBuf, Vec<u8>), anyhow::Error> {
+    let saves_path = get_saves_path(saves_location)?;
+    let saves_path_display = saves_path.display();
+
+    let saves = find_save_files(&saves_path)?;
+    let latest_save = match character_name {
+        // Only the headers need to be read to find the character's saves
+        Some(character_name) => {
+            read_save_metadata_indexed(&saves_path, saves_location.index_path.as_deref(), &saves)
+                .into_iter()
+                .find(|metadata| metadata.is_character(character_name))
+                .map(|metadata| (metadata.path, metadata.last_modified))
+        }
+        None => saves.into_iter().next(),
+    };
+
+    let (latest_save_path, modified) = latest_save.ok_or_else(|| match character_name {
+        Some(character_name) => anyhow!(
+            "no save file of character {} found in directory {}",
+            character_name,
+            saves_path_display
+        ),
+        None => anyhow!("no save file found in directory {}", saves_path_display),
+    })?;
+    log::debug!(
+        "Latest save: {} (last modified {})",
+        latest_save_path.display(),
+        modified
+            .elapsed()
+            .map(|dur| format!(
+                "{} ago",
+                // Probably suboptimal way to round Duration to seconds, then format it
+                humantime::format_duration(Duration::from_secs(dur.as_secs()))
+            ))
+            .unwrap_or_else(|_| "<in the future> 🤯".to_string())
+    );
+
+    let save_data = fs::read(&latest_save_path).with_context(|| "failed to read save file")?;
+
+    Ok((latest_save_path, save_data))
+}
+
+pub use self::inventory::InventoryEntry;
+pub type Inventory = Vec<InventoryEntry>;
+
+/// Maps ingredient form IDs to the form IDs of the ingredient's effects the player has discovered
+pub type KnownEffects = AHashMap<GlobalFormId, AHashSet<GlobalFormId>>;
+
+/// The data read from a save file
+pub struct SaveData {
+    pub inventory: Inventory,
+    pub known_effects: KnownEffects,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error(
+    "the plugins in the save don't match the game data (missing from save: [{}], missing from game data: [{}]). Re-export the game data if your load order changed",
+    .missing_from_save.join(", "),
+    .missing_from_game_data.join(", ")
+)]
+pub struct PluginsMismatchError {
+    /// Plugins that were active when the game data was exported, but are not used by the save
+    pub missing_from_save: Vec<String>,
+    /// Plugins used by the save that were not active when the game data was exported
+    pub missing_from_game_data: Vec<String>,
+}
+
+/// Compares the plugins used by the save file to the plugins that were active when the game data
+/// was exported.
+fn check_plugins(save_file: &SaveFile, game_data: &GameData) -> Result<(), PluginsMismatchError> {
+    let save_plugins = save_file
+        .plugin_info
+        .iter()
+        .chain(save_file.light_plugin_info.iter())
+        .collect::<Vec<_>>();
+    let save_plugins_lowercase = save_plugins
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<HashSet<_>>();
+    let game_data_plugins_lowercase = game_data
+        .get_plugins()
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<HashSet<_>>();
+
+    let missing_from_save = game_data
+        .get_plugins()
+        .iter()
+        .filter(|name| !save_plugins_lowercase.contains(&name.to_lowercase()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let missing_from_game_data = save_plugins
+        .iter()
+        .filter(|name| !game_data_plugins_lowercase.contains(&name.to_lowercase()))
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+
+    if missing_from_save.is_empty() && missing_from_game_data.is_empty() {
+        return Ok(());
+    }
+
+    Err(PluginsMismatchError {
+        missing_from_save,
+        missing_from_game_data,
+    })
+}
+
+pub fn read_saves(
+    saves_location: &SavesLocation,
+    game_data: &GameData,
+    character_name: Option<&str>,
+    strict_load_order: bool,
+    use_cosave: bool,
+) -> Result<SaveData, anyhow::Error> {
+    let (save_path, save_data) = get_latest_save_data(saves_location, character_name)?;
+    read_save_data(
+        &save_path,
+        save_data,
+        game_data,
+        strict_load_order,
+        use_cosave,
+    )
+}
+
+/// Reads the `max_saves` most recent saves of the character with the given name, or of the
+/// character of the most recent save if no name is given. The saves are parsed in parallel and
+/// returned with their metadata, oldest first by save number.
+pub fn read_character_saves(
+    saves_location: &SavesLocation,
+    game_data: &GameData,
+    character_name: Option<&str>,
+    max_saves: usize,
+    strict_load_order: bool,
+    use_cosave: bool,
+) -> Result<Vec<(SaveMetadata, SaveData)>, anyhow::Error> {
+    let saves_path = get_saves_path(saves_location)?;
+    let saves = find_save_files(&saves_path)?;
+    // Most recently modified first, like the save files
+    let saves_metadata =
+        read_save_metadata_indexed(&saves_path, saves_location.index_path.as_deref(), &saves);
+    let character_name = match character_name {
+        Some(character_name) => character_name.to_string(),
+        None => saves_metadata
+            .first()
+            .ok_or_else(|| anyhow!("no save file found in directory {}", saves_path.display()))?
+            .character_name
+            .clone(),
+    };
+
+    let mut character_saves = saves_metadata
+        .into_iter()
+        .filter(|metadata| metadata.is_character(&character_name))
+        .take(max_saves)
+        .collect::<Vec<_>>();
+    if character_saves.is_empty() {
+        Err(anyhow!(
+            "no save file of character {} found in directory {}",
+            character_name,
+            saves_path.display()
+        ))?
+    }
+    character_saves.sort_by_key(|metadata| metadata.save_number);
+    log::info!(
+        "Reading {} saves of {}",
+        character_saves.len(),
+        character_name
+    );
+
+    character_saves
+        .into_par_iter()
+        .map(|metadata| {
+            let save_data = fs::read(&metadata.path)
+                .with_context(|| format!("failed to read save file {}", metadata.path.display()))?;
+            let save_data = read_save_data(
+                &metadata.path,
+                save_data,
+                game_data,
+                strict_load_order,
+                use_cosave,
+            )?;
+            Ok((metadata, save_data))
+        })
+        .collect()
+}
+
+/// Reads the inventory and known effects from the contents of the save file at `save_path`
+fn read_save_data(
+    save_path: &Path,
+    save_data: Vec<u8>,
+    game_data: &GameData,
+    strict_load_order: bool,
+    use_cosave: bool,
+) -> Result<SaveData, anyhow::Error> {
+    // Reading the header first gives a proper error for unsupported or corrupt files, which the
+    // full parser would panic on
+    let metadata = read_save_metadata(save_path)?;
+    log::debug!(
+        "Reading save {} of {} (level {}, version {})",
+        metadata.save_number,
+        metadata.character_name,
+        metadata.character_level,
+        metadata.version
+    );
+    // TODO: this may panic. Catch somehow?
+    let start = Instant::now();
+    let save_file = skyrim_savegame::parse_save_file(save_data);
+    log::debug!("Rudimentarily parsed save file (in {:?})", start.elapsed());
+    log::info!("{:#?}", save_file);
+
+    if game_data.get_plugins().is_empty() {
+        log::warn!("Unable to check the save's plugins against the game data, re-export the game data to enable this check");
+    } else if let Err(err) = check_plugins(&save_file, game_data) {
+        if strict_load_order {
+            return Err(err.into());
+        }
+        log::warn!("{}", err);
+    }
+
+    let start = Instant::now();
+    let known_effects = read_known_effects(&save_file, game_data);
+    log::debug!(
+        "Found known effects for {} ingredients (in {:?})",
+        known_effects.len(),
+        start.elapsed()
+    );
+
+    let start = Instant::now();
+    let player_change_form = save_file
+        .change_forms
+        .iter()
+        .find(|cf| {
+            matches!(
+                get_change_form_data_type(cf),
+                Some(ChangeFormDataType::Actor)
+            ) && ({
+                let form_id = get_real_form_id(&cf.form_id, &save_file).log_unwrap();
+
+                // Is player change form
+                form_id == 0x14
+            })
+        })
+        .log_expect("save game contains no player data");
+    log::debug!("Found player change form (in {:?})", start.elapsed());
+
+    let start = Instant::now();
+    // See https://en.uesp.net/wiki/Skyrim_Mod:ChangeFlags#Initial_type
+    // Note: assumes ACHR change form type
+    let initial_type: u32 = {
+        if matches!(player_change_form.form_id, FormIdType::Created(_)) {
+            5
+            // CHANGE_REFR_PROMOTED or CHANGE_REFR_CELL_CHANGED flags
+        } else if player_change_form.change_flags & 0x02000000 != 0
+            || player_change_form.change_flags & 0x00000008 != 0
+        {
+            6
+            // CHANGE_REFR_HAVOK_MOVE or CHANGE_REFR_MOVE flags
+        } else if player_change_form.change_flags & 0x00000004 != 0
+            || player_change_form.change_flags & 0x00000002 != 0
+        {
+            4
+        } else {
+            0
+        }
+    };
+    let initial_type_size: u32 = match initial_type {
+        0 => 0,
+        1 => 8,
+        2 => 10,
+        3 => 4,
+        4 => 27,
+        5 => 31,
+        6 => 34,
+        other => panic!("unknown initial type {}", other),
+    };
+
+    let (remaining_data, _) = nom::sequence::tuple((
+        nom::combinator::cond(
+            initial_type_size != 0,
+            // Skip initial data
+            nom::bytes::complete::take(initial_type_size),
+        ),
+        nom::combinator::cond(
+            // CHANGE_REFR_HAVOK_MOVE flag
+            player_change_form.change_flags & 0x00000004 != 0,
+            // Skip havok data
+            nom::multi::length_count(read_vsval, nom::number::complete::le_u8),
+        ),
+        // Skip unknown integer + unknown data
+        nom::bytes::complete::take(std::mem::size_of::<u32>() + std::mem::size_of::<u8>() * 4),
+        nom::combinator::cond(
+            // CHANGE_FORM_FLAGS flag
+            player_change_form.change_flags & 0x00000001 != 0,
+            // Skip flag + unknown
+            nom::bytes::complete::take(std::mem::size_of::<u32>() + std::mem::size_of::<u16>()),
+        ),
+        nom::combinator::cond(
+            // CHANGE_REFR_BASEOBJECT flag
+            player_change_form.change_flags & 0x00000080 != 0,
+            // Skip base object ref ID
+            nom::bytes::complete::take(3usize),
+        ),
+        nom::combinator::cond(
+            // CHANGE_REFR_SCALE flag
+            player_change_form.change_flags & 0x00000010 != 0,
+            // Skip scale float
+            nom::number::complete::le_f32,
+        ),
+    ))(player_change_form.data.as_ref())
+    .map_err(nom_err_to_anyhow_err)?;
+    log::debug!(
+        "Skipped irrelevant data in player change form (in {:?})",
+        start.elapsed()
+    );
+
+    // Now comes the extra data (probably), which we don't have enough information on to skip.
+    // Instead, scan the remaining change form data for ref IDs of known ingredients followed by a
+    // plausible item count. Some random bytes will happen to look like an inventory entry, so the
+    // candidates are validated afterwards.
+
+    log::debug!(
+        "Will try to parse inventory items from remaining {} bytes of player data",
+        remaining_data.len()
+    );
+
+    let start = Instant::now();
+
+    let scanned_data = remaining_data;
+    let mut remaining_data = remaining_data;
+    let mut candidates = vec![];
+    while !remaining_data.is_empty() {
+        let offset = scanned_data.len() - remaining_data.len();
+        match partial_inventory_item(remaining_data, &save_file, game_data) {
+            Ok((remaining_input, (form_id, count, in_form_id_array))) => {
+                candidates.push(InventoryCandidate {
+                    offset,
+                    form_id,
+                    count,
+                    in_form_id_array,
+                });
+                // Move cursor by length of successfully consumed data
+                remaining_data = remaining_input;
+            }
+            Err(_) => {
+                // Move cursor one byte, try again next iteration
+                remaining_data = &remaining_data[1..];
+            }
+        }
+    }
+
+    let (mut inventory, discarded): (Vec<_>, Vec<_>) = validate_candidates(&candidates)
+        .into_iter()
+        .partition(|entry| entry.confidence >= MIN_CONFIDENCE);
+
+    if use_cosave {
+        merge_cosave_inventory(save_path, game_data, &mut inventory);
+    }
+
+    log::debug!(
+        "Parsed {} inventory items from {} candidates, discarding {} unlikely items (in {:?})",
+        inventory.len(),
+        candidates.len(),
+        discarded.len(),
+        start.elapsed()
+    );
+    let format_entry = |entry: &InventoryEntry| {
+        format!(
+            "{} ({}): {} (confidence {:.2})",
+            entry.form_id,
+            game_data.get_ingredient(&entry.form_id).map_or(
+                Cow::Borrowed("unknown ingredient"),
+                get_ingredient_name_or_fallback
+            ),
+            entry.count,
+            entry.confidence
+        )
+    };
+    log::debug!(
+        "Inventory:\n{}",
+        inventory.iter().map(format_entry).join("\n")
+    );
+    log::trace!(
+        "Discarded inventory items:\n{}",
+        discarded.iter().map(format_entry).join("\n")
+    );
+
+    Ok(SaveData {
+        inventory,
+        known_effects,
+    })
+}
+
+/// Adds the ingredients found in the SKSE co-save of the save file to the inventory, for mods which
+/// modify the inventory at save time. Entries found in the save itself take precedence. Problems
+/// with the co-save are logged rather than returned, since the save's inventory is still usable.
+fn merge_cosave_inventory(save_path: &Path, game_data: &GameData, inventory: &mut Inventory) {
+    let cosave_path = cosave::get_cosave_path(save_path);
+    let cosave_data = match fs::read(&cosave_path) {
+        Ok(cosave_data) => cosave_data,
+        Err(err) => {
+            log::warn!(
+                "Failed to read SKSE co-save {}: {}",
+                cosave_path.display(),
+                err
+            );
+            return;
+        }
+    };
+    let plugins = match cosave::parse_cosave(&cosave_data) {
+        Ok(plugins) => plugins,
+        Err(err) => {
+            log::warn!("{:#}", err);
+            return;
+        }
+    };
+
+    let known_form_ids = inventory
+        .iter()
+        .map(|entry| entry.form_id)
+        .collect::<AHashSet<_>>();
+    let cosave_entries = cosave::scan_inventory_candidates(&plugins, game_data)
+        .iter()
+        .flat_map(|candidates| validate_candidates(candidates))
+        .filter(|entry| entry.confidence >= MIN_CONFIDENCE)
+        .filter(|entry| !known_form_ids.contains(&entry.form_id))
+        .unique_by(|entry| entry.form_id)
+        .collect::<Vec<_>>();
+
+    log::debug!(
+        "Added {} inventory items from SKSE co-save {}",
+        cosave_entries.len(),
+        cosave_path.display()
+    );
+    inventory.extend(cosave_entries);
+}
+
+/// Reads which effects of each ingredient the player has discovered from the ingredient change
+/// forms in the save. Ingredients that don't have a change form have no known effects.
+fn read_known_effects(save_file: &SaveFile, game_data: &GameData) -> KnownEffects {
+    save_file
+        .change_forms
+        .iter()
+        .filter(|cf| {
+            matches!(
+                get_change_form_data_type(cf),
+                Some(ChangeFormDataType::Ingredient)
+            ) && cf.change_flags & CHANGE_INGREDIENT_USE != 0
+        })
+        .filter_map(|cf| {
+            let form_id = get_real_form_id(&cf.form_id, save_file)
+                .map_err(|err| log::warn!("Failed to read ingredient change form: {}", err))
+                .ok()?;
+            let global_form_id = game_data.get_global_form_id_from_runtime(form_id)?;
+            let ingredient = game_data.get_ingredient(&global_form_id)?;
+            let ingredient_use_flags = parse_ingredient_use_flags(cf)
+                .map_err(|err| {
+                    log::warn!(
+                        "Failed to read known effects of ingredient {}: {}",
+                        global_form_id,
+                        err
+                    )
+                })
+                .ok()?;
+
+            // Each bit in the flags indicates whether the effect at that position in the
+            // ingredient record is known
+            let known_effects = ingredient
+                .effects
+                .iter()
+                .filter(|eff| {
+                    eff.record_index
+                        .map_or(false, |index| ingredient_use_flags & (1 << index) != 0)
+                })
+                .map(|eff| eff.get_global_form_id())
+                .collect();
+
+            Some((global_form_id, known_effects))
+        })
+        .collect()
+}
+
+/// Parses the known effects bitfield from an ingredient change form
+fn parse_ingredient_use_flags(change_form: &ChangeForm) -> Result<u8, anyhow::Error> {
+    // Only the lowest 4 bits of the bitfield are used, so just read the first byte
+    let (_, (_, flags)) = nom::sequence::pair(
+        nom::combinator::cond(
+            // CHANGE_FORM_FLAGS flag
+            change_form.change_flags & 0x00000001 != 0,
+            // Skip flag + unknown
+            nom::bytes::complete::take(std::mem::size_of::<u32>() + std::mem::size_of::<u16>()),
+        ),
+        nom::number::complete::le_u8,
+    )(change_form.data.as_ref())
+    .map_err(|err: nom::Err<(_, nom::error::ErrorKind)>| nom_err_to_anyhow_err(err))?;
+
+    Ok(flags)
+}
+
+/// Parses an inventory entry candidate: the ingredient, its item count and whether its ref ID is an
+/// index into the save's form ID array
+fn partial_inventory_item<'a>(
+    input: &'a [u8],
+    save_file: &SaveFile,
+    game_data: &GameData,
+) -> Result<(&'a [u8], (GlobalFormId, i32, bool)), anyhow::Error> {
+    let (remaining_input, (form_id, in_form_id_array)) = parse_ref_id_to_form_id(input, save_file)?;
+
+    // I don't believe we'll ever see an ingredient with a form ID of exactly 0x00000000
+    if form_id == 0x00000000 {
+        return Err(anyhow!("form ID is 0x00000000"));
+    }
+
+    // Form IDs starting with 0xFF are dynamically allocated, ingredients (probably) don't have this
+    if form_id >> 24 == 0xFF {
+        return Err(anyhow!("form ID starts with 0xFF"));
+    }
+
+    let form_id = match game_data.get_global_form_id_from_runtime(form_id) {
+        Some(form_id) => form_id,
+        // Game data exported by older versions doesn't contain the list of plugins needed to map
+        // runtime form IDs, but records from Skyrim.esm can still be found
+        None if game_data.get_plugins().is_empty() && form_id >> 24 == 0 => {
+            GlobalFormId::new(0, form_id)
+        }
+        None => return Err(anyhow!("form ID doesn't belong to any plugin")),
+    };
+
+    if !game_data.has_ingredient(&form_id) {
+        return Err(anyhow!("form ID is not a known ingredient"));
+    }
+
+    // The item count i32 is followed by a vsval indicating the count of extra data for this item. We don't care about this value, but we can use it to improve parsing accuracy
+    let (remaining_input, item_count) =
+        nom::sequence::terminated(nom::number::complete::le_i32, read_vsval)(remaining_input)
+            .map_err(nom_err_to_anyhow_err)?;
+
+    if item_count < 1 {
+        return Err(anyhow!("item count is less than 1"));
+    }
+
+    if item_count > 5000 {
+        return Err(anyhow!("item count is improbably high"));
+    }
+
+    Ok((remaining_input, (form_id, item_count, in_form_id_array)))
+}
+
+/// Parses a ref ID to a runtime form ID. Also returns whether the ref ID is an index into the
+/// save's form ID array.
+fn parse_ref_id_to_form_id<'a>(
+    input: &'a [u8],
+    save_file: &SaveFile,
+) -> Result<(&'a [u8], (u32, bool)), anyhow::Error> {
+    let (remaining_input, three_bytes) = nom::bytes::complete::take(3usize)(input)
+        .map_err(|err: nom::Err<(_, nom::error::ErrorKind)>| nom_err_to_anyhow_err(err))?;
+    let (byte0, byte1, byte2) = (three_bytes[0], three_bytes[1], three_bytes[2]);
+    let ref_id = RefId {
+        byte0,
+        byte1,
+        byte2,
+    };
+    let raw_form_id = ref_id.get_form_id();
+    let form_id = get_real_form_id(&raw_form_id, save_file)?;
+
+    Ok((
+        remaining_input,
+        (form_id, matches!(raw_form_id, FormIdType::Index(_))),
+    ))
+}
+
+/// See https://en.uesp.net/wiki/Skyrim_Mod:ChangeFlags
+const CHANGE_INGREDIENT_USE: u32 = 0x80000000;
+
+#[derive(Debug)]
+enum ChangeFormDataType {
+    Actor,
+    Ingredient,
+}
+
+/// Returns `Some(ChangeFormDataType)` if it's a data type we care about
+fn get_change_form_data_type(change_form: &ChangeForm) -> Option<ChangeFormDataType> {
+    // Look at lower 6 bits
+    match change_form.data_type & 0x3F {
+        1 => Some(ChangeFormDataType::Actor),
+        16 => Some(ChangeFormDataType::Ingredient),
+        _ => None,
+    }
+}
+
+fn get_real_form_id(raw_form_id: &FormIdType, save_file: &SaveFile) -> Result<u32, anyhow::Error> {
+    match raw_form_id {
+        FormIdType::Index(value) => Ok(*save_file
+            .form_id_array
+            .get(*value as usize)
+            .ok_or_else(|| anyhow!("form ID index not in form ID array: {}", value))?),
+        FormIdType::Default(value) => Ok(*value),
+        FormIdType::Created(value) => Ok(0xFF000000 | *value),
+        FormIdType::Unknown(_) => Err(anyhow!("encountered unknown form ID type")),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CustomError<I> {
+    InvalidVsvalValueType,
+    Nom(I, nom::error::ErrorKind),
+}
+
+impl<I> nom::error::ParseError<I> for CustomError<I> {
+    fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
+        CustomError::Nom(input, kind)
+    }
+
+    // TODO: see if we need to impl this differently, + other methods on ParseError trait
+    fn append(_: I, _: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+/// Reads a vsval to u32
+pub fn read_vsval(input: &[u8]) -> IResult<&[u8], u32, CustomError<&[u8]>> {
+    let (input, first_byte) = nom::number::complete::le_u8(input)?;
+    let val_type_enc = first_byte & 0b00000011;
+    match val_type_enc {
+        0 => Ok((input, ((first_byte & 0b11111100) >> 2) as u32)),
+        1 => {
+            let first_byte = first_byte as u16;
+            let (input, second_byte) = nom::number::complete::le_u8(input)?;
+            Ok((
+                input,
+                // TODO: XOR, correct?
+                (((second_byte as u16) << 8 ^ first_byte) >> 2) as u32,
+            ))
+        }
+        2 => {
+            let first_byte = first_byte as u32;
+            let (input, second_byte) = nom::number::complete::le_u8(input)?;
+            let (input, third_byte) = nom::number::complete::le_u8(input)?;
+            Ok((
+                input,
+                (((third_byte as u32) << 16 ^ (second_byte as u32) << 8 ^ first_byte) >> 2),
+            ))
+        }
+        _ => {
+            // TODO: determine if should be unrecoverable (I'm guessing not)
+            Err(nom::Err::Error(CustomError::InvalidVsvalValueType))
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
+
+use ahash::AHashMap;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use super::header::{read_save_metadata, SaveMetadata};
+
+/// Bump this whenever `SaveMetadata` changes, so that stale indexes are rebuilt
+const SAVE_INDEX_VERSION: u32 = 1;
+
+/// The headers of previously read save files, so they don't have to be read again on every run
+#[derive(Serialize, Deserialize)]
+struct SaveIndex {
+    version: u32,
+    saves: Vec<SaveMetadata>,
+}
+
+fn read_index(path: &Path) -> Result<Option<SaveIndex>, anyhow::Error> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => Err(err)?,
+    };
+    let index: SaveIndex = serde_json::from_reader(BufReader::new(file))?;
+    if index.version != SAVE_INDEX_VERSION {
+        log::debug!("Save index at {} is stale", path.display());
+        return Ok(None);
+    }
+    Ok(Some(index))
+}
+
+fn write_index(path: &Path, index: &SaveIndex) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    serde_json::to_writer(BufWriter::new(File::create(path)?), index)?;
+    Ok(())
+}
+
+/// Returns the metadata of the save files in the directory, in the same order as `saves`. Headers
+/// that are in the index with the same modification time are reused, and the others are read in
+/// parallel and added to the index. Save files whose header can't be read are skipped with a
+/// warning. Problems with the index are logged rather than returned, since the headers can always
+/// be read from the save files. Without an index path, the headers of all save files are read.
+pub(super) fn read_save_metadata_indexed(
+    saves_path: &Path,
+    index_path: Option<&Path>,
+    saves: &[(PathBuf, SystemTime)],
+) -> Vec<SaveMetadata> {
+    let start = Instant::now();
+    let index = index_path
+        .and_then(|index_path| {
+            read_index(index_path)
+                .map_err(|err| {
+                    log::warn!(
+                        "Ignoring invalid save index at {}: {}",
+                        index_path.display(),
+                        err
+                    )
+                })
+                .ok()
+                .flatten()
+        })
+        .unwrap_or(SaveIndex {
+            version: SAVE_INDEX_VERSION,
+            saves: Vec::new(),
+        });
+    let num_indexed = index.saves.len();
+    let mut indexed_saves = index
+        .saves
+        .into_iter()
+        .map(|metadata| (metadata.path.clone(), metadata))
+        .collect::<AHashMap<_, _>>();
+
+    let metadata = saves
+        .par_iter()
+        .filter_map(|(path, last_modified)| match indexed_saves.get(path) {
+            Some(metadata) if metadata.last_modified == *last_modified => {
+                Some((metadata.clone(), false))
+            }
+            _ => read_save_metadata(path)
+                .map_err(|err| log::warn!("{:#}", err))
+                .ok()
+                .map(|metadata| (metadata, true)),
+        })
+        .collect::<Vec<_>>();
+    let num_read = metadata.iter().filter(|(_, is_read)| *is_read).count();
+
+    // Saves in other directories stay in the index, but deleted or changed saves in this one are
+    // replaced
+    indexed_saves.retain(|path, _| path.parent() != Some(saves_path));
+    let num_stale = num_indexed - indexed_saves.len() - (metadata.len() - num_read);
+    let metadata = metadata
+        .into_iter()
+        .map(|(metadata, _)| metadata)
+        .collect::<Vec<_>>();
+
+    if let Some(index_path) = index_path.filter(|_| num_read > 0 || num_stale > 0) {
+        let index = SaveIndex {
+            version: SAVE_INDEX_VERSION,
+            saves: indexed_saves
+                .into_values()
+                .chain(metadata.iter().cloned())
+                .collect(),
+        };
+        if let Err(err) = write_index(index_path, &index) {
+            log::warn!(
+                "Failed to write save index to {}: {}",
+                index_path.display(),
+                err
+            );
+        }
+    }
+
+    log::debug!(
+        "Read the headers of {} of {} save files, the others were indexed (in {:?})",
+        num_read,
+        metadata.len(),
+        start.elapsed()
+    );
+
+    metadata
+}
@@ -0,0 +1,571 @@
+#[cfg(unix)]
+use std::os::unix::{
+    fs::PermissionsExt,
+    net::{UnixListener, UnixStream},
+};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context};
+use serde_json::json;
+
+use crate::{
+    check_max_ingredients,
+    game_data::GameData,
+    import_game_data,
+    player_context::PlayerContext,
+    plugin_parser::form_id::FormIdContainer,
+    potion::{AlchemySkill, Potion, PotionType, Ranking},
+    potion_notes::{get_default_notes_providers, AnnotatedPotion, PotionNotesProvider},
+    potions_list::PotionsList,
+    save_parser::{find_latest_save, read_saves, SavesLocation},
+    ImportOptions,
+};
+
+/// The default address that `serve` listens on
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:7525";
+
+/// The prefix of addresses that are the path of a Unix socket rather than a TCP address
+const UNIX_SOCKET_PREFIX: &str = "unix:";
+
+/// The number of potions returned by the `best` command if no limit is given
+const DEFAULT_BEST_LIMIT: usize = 5;
+
+/// The maximum length of a request line in bytes. Longer requests are answered with an error and
+/// the connection is closed, so that a client can't make the server buffer unlimited input.
+const MAX_REQUEST_LENGTH: usize = 1024;
+
+/// The maximum number of clients connected at the same time, each of which is served by its own
+/// thread. Further clients are answered with an error and disconnected.
+const MAX_CONNECTIONS: usize = 16;
+
+/// Options for `serve`
+pub struct ServeOptions {
+    /// The address to listen on: a loopback TCP address such as `127.0.0.1:7525`, since the
+    /// protocol has no authentication, or on Unix `unix:` followed by the path of a socket, which
+    /// only the current user can connect to
+    pub address: String,
+    /// The maximum number of ingredients per potion
+    pub max_ingredients: usize,
+    /// The player's alchemy skill, which determines the strength of the potions
+    pub alchemy_skill: AlchemySkill,
+    /// If specified, the latest save of the character with this name is read instead of the latest
+    /// save of any character
+    pub character: Option<String>,
+    /// Whether to fail instead of warn when the save's plugins don't match the game data
+    pub strict_load_order: bool,
+    /// Whether to also read the inventory from the save's SKSE co-save
+    pub use_cosave: bool,
+    /// How often to check the saves directory for a new save
+    pub poll_interval: Duration,
+    /// Where to cache the computed potions between runs, or `None` to always compute them
+    pub cache_path: Option<PathBuf>,
+}
+
+/// What the server knows from the latest save it read
+#[derive(Default)]
+struct LoadedSave<'a> {
+    /// The path and modification time of the newest save when it was last read
+    newest_save: Option<(PathBuf, SystemTime)>,
+    /// The potions that can be crafted with the ingredients in the inventory, by gold value
+    /// descending
+    craftable_potions: Vec<&'a Potion<'a>>,
+    num_inventory_ingredients: usize,
+}
+
+struct Server<'a> {
+    game_data: &'a GameData,
+    potions_list: &'a PotionsList<'a>,
+    saves_location: &'a SavesLocation,
+    options: &'a ServeOptions,
+    loaded_save: RwLock<LoadedSave<'a>>,
+    notes_providers: Vec<Box<dyn PotionNotesProvider>>,
+}
+
+impl<'a> Server<'a> {
+    /// Reads the latest save and updates the craftable potions
+    fn reload(&self) -> Result<(), anyhow::Error> {
+        let newest_save = find_latest_save(self.saves_location)?;
+        // The save parser panics on some malformed saves, which would otherwise stop the server
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            read_saves(
+                self.saves_location,
+                self.game_data,
+                self.options.character.as_deref(),
+                self.options.strict_load_order,
+                self.options.use_cosave,
+            )
+        }))
+        .unwrap_or_else(|_| Err(anyhow!("the save parser panicked")));
+        let save_data = match result {
+            Ok(save_data) => save_data,
+            Err(err) => {
+                // The save may still be being written, in which case its modification time
+                // changes again once it's done, so it isn't retried until then
+                self.loaded_save.write().unwrap().newest_save = newest_save;
+                return Err(err);
+            }
+        };
+        let player_context = PlayerContext::from_save_data(save_data, self.options.alchemy_skill);
+
+        let craftable_potions = self
+            .potions_list
+            .get_potions(&Ranking::default())
+            .filter(|p| {
+                p.ingredients
+                    .iter()
+                    .all(|ig| player_context.get_inventory_count(&ig.get_global_form_id()) > 0)
+            })
+            .collect::<Vec<_>>();
+        log::info!(
+            "Read the latest save, {} potions can be crafted with the inventory",
+            craftable_potions.len()
+        );
+
+        *self.loaded_save.write().unwrap() = LoadedSave {
+            newest_save,
+            craftable_potions,
+            num_inventory_ingredients: player_context
+                .inventory
+                .keys()
+                .filter(|form_id| self.game_data.has_ingredient(form_id))
+                .count(),
+        };
+
+        Ok(())
+    }
+
+    /// Reloads whenever a newer save appears in the saves directory. Never returns.
+    fn watch_saves(&self) {
+        loop {
+            thread::sleep(self.options.poll_interval);
+            let newest_save = match find_latest_save(self.saves_location) {
+                Ok(newest_save) => newest_save,
+                Err(err) => {
+                    log::warn!("Failed to check for new saves: {}", err);
+                    continue;
+                }
+            };
+            if newest_save == self.loaded_save.read().unwrap().newest_save {
+                continue;
+            }
+            log::debug!("Found a new save, reloading");
+            if let Err(err) = self.reload() {
+                log::warn!("Failed to read the new save: {:#}", err);
+            }
+        }
+    }
+
+    fn get_status(&self) -> serde_json::Value {
+        let loaded_save = self.loaded_save.read().unwrap();
+        json!({
+            "save": loaded_save
+                .newest_save
+                .as_ref()
+                .map(|(path, _)| path.display().to_string()),
+            "load_order_hash": self.game_data.get_load_order_hash(),
+            "ingredients_in_inventory": loaded_save.num_inventory_ingredients,
+            "craftable_potions": loaded_save.craftable_potions.len(),
+        })
+    }
+
+    /// Answers a single request line. See `serve` for the commands.
+    fn respond(&self, line: &str) -> Result<serde_json::Value, String> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("best") => {
+                let mut limit = DEFAULT_BEST_LIMIT;
+                let mut potion_type = None;
+                for word in words {
+                    match word.parse::<usize>() {
+                        Ok(n) => limit = n,
+                        Err(_) => potion_type = Some(word.parse::<PotionType>()?),
+                    }
+                }
+
+                let loaded_save = self.loaded_save.read().unwrap();
+                let potions = loaded_save
+                    .craftable_potions
+                    .iter()
+                    .filter(|p| {
+                        potion_type.map_or(true, |potion_type| p.get_potion_type() == potion_type)
+                    })
+                    .take(limit)
+                    .map(|p| AnnotatedPotion::new(p, self.game_data, &self.notes_providers))
+                    .collect::<Vec<_>>();
+                Ok(json!({ "potions": potions }))
+            }
+            Some("status") => Ok(self.get_status()),
+            Some("reload") => {
+                self.reload().map_err(|err| format!("{:#}", err))?;
+                Ok(self.get_status())
+            }
+            Some("ping") => Ok(json!({ "pong": true })),
+            Some(command) => Err(format!(
+                "unknown command {}, expected best, status, reload or ping",
+                command
+            )),
+            None => Err("empty request".to_string()),
+        }
+    }
+
+    /// Answers the requests of a client, one per line, until it disconnects or sends a request
+    /// longer than `MAX_REQUEST_LENGTH`
+    fn handle_connection<R, W>(&self, reader: R, mut writer: W) -> Result<(), anyhow::Error>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let len = (&mut reader)
+                .take(MAX_REQUEST_LENGTH as u64 + 1)
+                .read_line(&mut line)?;
+            if len == 0 {
+                return Ok(());
+            }
+            if len > MAX_REQUEST_LENGTH && !line.ends_with('\n') {
+                writeln!(
+                    writer,
+                    "{}",
+                    json!({ "error": format!("request longer than {} bytes", MAX_REQUEST_LENGTH) })
+                )?;
+                writer.flush()?;
+                Err(anyhow!("request longer than {} bytes", MAX_REQUEST_LENGTH))?
+            }
+
+            let response = self
+                .respond(line.trim_end())
+                .unwrap_or_else(|err| json!({ "error": err }));
+            writeln!(writer, "{}", response)?;
+            writer.flush()?;
+        }
+    }
+
+    /// Serves each connection on its own thread, at most `MAX_CONNECTIONS` at a time. Never
+    /// returns.
+    fn accept_connections<C, I>(&self, incoming: I)
+    where
+        C: Connection,
+        I: Iterator<Item = io::Result<C>>,
+    {
+        let num_connections = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            scope.spawn(|| self.watch_saves());
+            for connection in incoming {
+                let mut connection = match connection {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        log::warn!("Failed to accept a connection: {}", err);
+                        continue;
+                    }
+                };
+                if num_connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    num_connections.fetch_sub(1, Ordering::SeqCst);
+                    log::warn!(
+                        "Refused a connection, {} clients are connected already",
+                        MAX_CONNECTIONS
+                    );
+                    let _ = writeln!(connection, "{}", json!({ "error": "too many connections" }));
+                    continue;
+                }
+
+                let num_connections = &num_connections;
+                scope.spawn(move || {
+                    let result = connection
+                        .try_clone()
+                        .map_err(anyhow::Error::from)
+                        .and_then(|writer| self.handle_connection(connection, writer));
+                    if let Err(err) = result {
+                        log::debug!("Connection closed: {}", err);
+                    }
+                    num_connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+    }
+}
+
+/// A client's connection, which is read from and written to from the same thread
+trait Connection: Read + Write + Send + Sized {
+    fn try_clone(&self) -> io::Result<Self>;
+}
+
+impl Connection for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+}
+
+/// Listens on the Unix socket at `path`, which only the current user may connect to
+#[cfg(unix)]
+fn listen_unix(server: &Server, path: &Path) -> Result<(), anyhow::Error> {
+    let listener = UnixListener::bind(path).with_context(|| {
+        format!(
+            "Failed to listen on {}, remove it if no server is running",
+            path.display()
+        )
+    })?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("Listening on {}", path.display());
+    server.accept_connections(listener.incoming());
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_server: &Server, path: &Path) -> Result<(), anyhow::Error> {
+    Err(anyhow!(
+        "Cannot listen on {}, Unix sockets are not supported on this platform",
+        path.display()
+    ))
+}
+
+/// Listens on the TCP address, which must be a loopback address since the protocol has no
+/// authentication
+fn listen_tcp(server: &Server, address: &str) -> Result<(), anyhow::Error> {
+    let socket_addresses = address
+        .to_socket_addrs()
+        .with_context(|| format!("Invalid address {}", address))?
+        .collect::<Vec<_>>();
+    if let Some(socket_address) = socket_addresses
+        .iter()
+        .find(|socket_address| !socket_address.ip().is_loopback())
+    {
+        Err(anyhow!(
+            "Refusing to listen on {}, which is not a loopback address, since anyone who can \
+             connect to it could use the server",
+            socket_address
+        ))?
+    }
+
+    let listener = TcpListener::bind(&socket_addresses[..])
+        .with_context(|| format!("Failed to listen on {}", address))?;
+    log::info!("Listening on {}", listener.local_addr()?);
+    server.accept_connections(listener.incoming());
+
+    Ok(())
+}
+
+/// Keeps the potions computed and answers queries about the potions that can be crafted with the
+/// latest save's inventory, for overlays and other tools that need answers quickly. The saves
+/// directory is watched for new saves. Clients connect over loopback TCP or a Unix socket and send
+/// one request per line, and each request is answered with a line of JSON:
+///
+/// - `best [N] [potion|poison]`: the N (default 5) most valuable craftable potions
+/// - `status`: the save that was read and the number of craftable potions
+/// - `reload`: reads the latest save now
+/// - `ping`: checks that the server is running
+///
+/// Errors are answered with `{"error": "..."}`. Runs until the process is stopped.
+pub fn serve<PImport>(
+    import_path: PImport,
+    import_options: &ImportOptions,
+    saves_location: &SavesLocation,
+    options: &ServeOptions,
+) -> Result<(), anyhow::Error>
+where
+    PImport: AsRef<Path>,
+{
+    check_max_ingredients(options.max_ingredients)?;
+
+    let mut game_data = import_game_data(import_path, import_options)?;
+    game_data.set_player_context(PlayerContext::new(options.alchemy_skill));
+    let mut potions_list = PotionsList::new(&game_data, options.max_ingredients);
+    match &options.cache_path {
+        Some(cache_path) => potions_list.build_potions_cached(cache_path),
+        None => potions_list.build_potions(),
+    }
+
+    let server = Server {
+        game_data: &game_data,
+        potions_list: &potions_list,
+        saves_location,
+        options,
+        loaded_save: RwLock::new(LoadedSave::default()),
+        notes_providers: get_default_notes_providers(),
+    };
+    server.reload()?;
+
+    match options.address.strip_prefix(UNIX_SOCKET_PREFIX) {
+        Some(path) => listen_unix(&server, Path::new(path)),
+        None => listen_tcp(&server, &options.address),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Calls `test` with a server whose inventory has every ingredient of the example game data
+    fn with_server(test: impl FnOnce(&Server)) {
+        let mut game_data = GameData::example();
+        game_data.set_player_context(PlayerContext::new(AlchemySkill::default()));
+        let mut potions_list = PotionsList::new(&game_data, 3);
+        potions_list.build_potions();
+        let options = ServeOptions {
+            address: DEFAULT_SERVER_ADDRESS.to_string(),
+            max_ingredients: 3,
+            alchemy_skill: AlchemySkill::default(),
+            character: None,
+            strict_load_order: false,
+            use_cosave: false,
+            poll_interval: Duration::from_secs(1),
+            cache_path: None,
+        };
+        let server = Server {
+            game_data: &game_data,
+            potions_list: &potions_list,
+            saves_location: &SavesLocation::default(),
+            options: &options,
+            loaded_save: RwLock::new(LoadedSave {
+                newest_save: None,
+                craftable_potions: potions_list.get_potions(&Ranking::default()).collect(),
+                num_inventory_ingredients: game_data.get_ingredients().len(),
+            }),
+            notes_providers: get_default_notes_providers(),
+        };
+        test(&server);
+    }
+
+    fn get_potion_names(response: &serde_json::Value) -> Vec<String> {
+        response["potions"]
+            .as_array()
+            .expect("potions should be an array")
+            .iter()
+            .map(|potion| potion["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn respond_answers_each_command() {
+        with_server(|server| {
+            assert_eq!(server.respond("ping"), Ok(json!({ "pong": true })));
+
+            let num_potions = server.loaded_save.read().unwrap().craftable_potions.len();
+            let status = server.respond("status").unwrap();
+            assert_eq!(status["craftable_potions"], json!(num_potions));
+            assert_eq!(status["save"], serde_json::Value::Null);
+
+            let best = server.respond("best").unwrap();
+            assert_eq!(
+                get_potion_names(&best).len(),
+                num_potions.min(DEFAULT_BEST_LIMIT)
+            );
+            let best = server.respond("  best 2  ").unwrap();
+            assert_eq!(get_potion_names(&best).len(), num_potions.min(2));
+
+            let poison_names = server
+                .loaded_save
+                .read()
+                .unwrap()
+                .craftable_potions
+                .iter()
+                .filter(|p| p.get_potion_type() == PotionType::Poison)
+                .map(|p| p.get_potion_name(server.game_data))
+                .collect::<Vec<_>>();
+            assert!(
+                !poison_names.is_empty(),
+                "the example game data should make poisons"
+            );
+            assert_eq!(
+                get_potion_names(&server.respond("best 100 poison").unwrap()),
+                poison_names
+            );
+        });
+    }
+
+    #[test]
+    fn respond_rejects_invalid_requests() {
+        with_server(|server| {
+            assert_eq!(server.respond(""), Err("empty request".to_string()));
+            assert!(server
+                .respond("brew")
+                .unwrap_err()
+                .starts_with("unknown command brew"));
+            assert!(server.respond("best elixir").is_err());
+        });
+    }
+
+    #[test]
+    fn handle_connection_answers_each_line() {
+        with_server(|server| {
+            let mut output = Vec::new();
+            server
+                .handle_connection(&b"ping\r\nbrew\n\nping"[..], &mut output)
+                .unwrap();
+
+            let responses = String::from_utf8(output).unwrap();
+            let responses = responses
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(responses.len(), 4);
+            assert_eq!(responses[0], json!({ "pong": true }));
+            assert!(responses[1]["error"].is_string());
+            assert_eq!(responses[2], json!({ "error": "empty request" }));
+            assert_eq!(responses[3], json!({ "pong": true }));
+        });
+    }
+
+    #[test]
+    fn handle_connection_closes_on_a_long_request() {
+        with_server(|server| {
+            let mut input = b"ping\n".to_vec();
+            input.extend(std::iter::repeat(b'x').take(MAX_REQUEST_LENGTH * 4));
+            input.extend(b"\nping\n");
+            let mut output = Vec::new();
+            assert!(server.handle_connection(&input[..], &mut output).is_err());
+
+            let responses = String::from_utf8(output).unwrap();
+            let responses = responses.lines().collect::<Vec<_>>();
+            assert_eq!(
+                responses.len(),
+                2,
+                "nothing after the long request is answered"
+            );
+            assert!(responses[1].contains("request longer than"));
+
+            // A request of exactly the maximum length is still answered
+            let mut input = vec![b' '; MAX_REQUEST_LENGTH - 4];
+            input.extend(b"ping\n");
+            let mut output = Vec::new();
+            server.handle_connection(&input[..], &mut output).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), "{\"pong\":true}\n");
+        });
+    }
+
+    #[test]
+    fn non_loopback_addresses_are_refused() {
+        with_server(|server| {
+            for address in ["0.0.0.0:0", "[::]:0", "192.0.2.1:7525"] {
+                let err = listen_tcp(server, address).unwrap_err();
+                assert!(
+                    err.to_string().contains("not a loopback address"),
+                    "{}: {}",
+                    address,
+                    err
+                );
+            }
+        });
+    }
+}
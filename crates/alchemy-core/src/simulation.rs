@@ -0,0 +1,148 @@
+use crate::{
+    game_data::get_magic_effect_name_or_fallback,
+    potion::{AlchemySkill, Potion, PotionType},
+};
+
+/// Everything about the player and the game that affects a crafted potion, besides the ingredients
+/// and the game data's crafting rules
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CraftingContext {
+    /// The player's alchemy skill level, Fortify Alchemy bonus and perk bonus
+    pub alchemy_skill: AlchemySkill,
+}
+
+/// An effect of a crafted potion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraftedEffect {
+    pub editor_id: String,
+    pub name: Option<String>,
+    pub magnitude: u32,
+    /// The duration in seconds, 0 for effects without a duration
+    pub duration: u32,
+    pub value: u32,
+}
+
+/// The potion the game produces from a set of ingredients, as returned by
+/// `GameData::simulate_craft`. Unlike a `Potion`, it owns its data and compares by value, so it
+/// can be used in assertions.
+///
+/// The result only depends on the game data, the crafting rules and the `CraftingContext`: there
+/// is no randomness in crafting, and settings such as the number locale and the potion name
+/// template are not applied. Magnitudes and durations are computed with `f32` multiplication and
+/// rounding, which give the same result on every platform Rust supports. Values additionally
+/// raise to the power of 1.1 with `f32::powf`, whose last bit may differ between platforms' math
+/// libraries. Since values are truncated to whole gold, this only changes a value if the exact
+/// result lies within a rounding error of a whole number.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraftResult {
+    /// The name the game gives the potion, e.g. "Potion of Restore Health"
+    pub name: String,
+    pub potion_type: PotionType,
+    /// The effects in the order the game lists them, the primary effect first
+    pub effects: Vec<CraftedEffect>,
+    pub value: u32,
+}
+
+impl CraftResult {
+    pub(crate) fn from_potion(potion: &Potion) -> Self {
+        let effects = potion
+            .effects
+            .iter()
+            .map(|effect| CraftedEffect {
+                editor_id: effect.magic_effect.editor_id.clone(),
+                name: effect.magic_effect.name.clone(),
+                magnitude: effect.get_magnitude(),
+                duration: effect.get_duration(),
+                value: effect.get_gold_value(),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            // The potion name template is not applied, so this is always the game's name
+            name: format!(
+                "{} of {}",
+                potion.get_potion_type(),
+                get_magic_effect_name_or_fallback(potion.get_primary_effect().magic_effect)
+            ),
+            potion_type: potion.get_potion_type(),
+            effects,
+            value: potion.gold_value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_data::GameData,
+        load_order::LoadOrderEntry,
+        plugin_parser::{form_id::GlobalFormId, ingredient::Ingredient, magic_effect::MagicEffect},
+        test_utils::{self, ingredient_effect},
+    };
+
+    use super::*;
+
+    fn ingredient(id: u32, editor_id: &str, effects: &[(u32, u32, f32)]) -> Ingredient {
+        let effects = effects.iter().map(|&(effect_id, duration, magnitude)| {
+            ingredient_effect(GlobalFormId::new(0, effect_id), magnitude, duration)
+        });
+        Ingredient {
+            editor_id: editor_id.to_owned(),
+            ..test_utils::ingredient(GlobalFormId::new(0, id), effects)
+        }
+    }
+
+    fn magic_effect(id: u32, name: &str, base_cost: f32) -> MagicEffect {
+        MagicEffect {
+            name: Some(name.to_owned()),
+            // Power affects magnitude and duration
+            flags: 0x600000,
+            base_cost,
+            ..test_utils::magic_effect(GlobalFormId::new(0, id))
+        }
+    }
+
+    /// Builds the game data anew, so that its hash maps are seeded differently each time
+    fn game_data() -> GameData {
+        GameData::from_vecs(
+            vec![String::from("Test.esm")],
+            vec![LoadOrderEntry {
+                name: String::from("Test.esm"),
+                is_light: false,
+            }],
+            vec![
+                ingredient(0x800, "FrostLily", &[(0x900, 0, 5.0), (0x901, 60, 3.0)]),
+                ingredient(0x801, "EmberRoot", &[(0x900, 0, 4.0), (0x901, 60, 3.0)]),
+                ingredient(0x802, "AshBloom", &[(0x901, 60, 2.0), (0x902, 30, 1.0)]),
+            ],
+            vec![
+                magic_effect(0x900, "Restore Health", 0.5),
+                magic_effect(0x901, "Resist Fire", 0.5),
+                magic_effect(0x902, "Fortify Smithing", 0.75),
+            ],
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn craft_result_is_deterministic() {
+        let context = CraftingContext {
+            alchemy_skill: AlchemySkill {
+                skill_level: 57,
+                fortify_alchemy: 12.5,
+                perk_bonus: 40.0,
+            },
+        };
+        let editor_ids = ["FrostLily", "EmberRoot", "AshBloom"];
+
+        let first = game_data().simulate_craft(&editor_ids, &context).unwrap();
+        let second = game_data().simulate_craft(&editor_ids, &context).unwrap();
+        assert_eq!(first, second);
+
+        // The order the ingredients are given in doesn't matter either
+        let reversed = ["AshBloom", "EmberRoot", "FrostLily"];
+        let third = game_data().simulate_craft(&reversed, &context).unwrap();
+        assert_eq!(first, third);
+        assert_eq!(first.effects.len(), 3);
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+/// The highest level a skill can reach
+pub const MAX_SKILL_LEVEL: u32 = 100;
+
+/// The settings that determine how much Alchemy XP crafting a potion gives and how much XP each
+/// level of the skill takes. They come from the fSkillUseCurve game setting and the skill data of
+/// the Alchemy skill's actor value record, which mods can change.
+/// See https://en.uesp.net/wiki/Skyrim:Leveling#Skill_XP
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlchemyXpSettings {
+    /// The exponent of the skill level in the XP that the next level takes
+    pub skill_use_curve: f32,
+    /// The XP gained per gold that a crafted potion is worth
+    pub skill_use_mult: f32,
+    /// The XP gained per crafted potion on top of that
+    pub skill_use_offset: f32,
+    /// The factor of the XP that the next level takes
+    pub skill_improve_mult: f32,
+    /// The XP that each level takes on top of that
+    pub skill_improve_offset: f32,
+}
+
+impl Default for AlchemyXpSettings {
+    /// The settings of the base game
+    fn default() -> Self {
+        Self {
+            skill_use_curve: 1.95,
+            skill_use_mult: 0.75,
+            skill_use_offset: 0.0,
+            skill_improve_mult: 1.6,
+            skill_improve_offset: 65.0,
+        }
+    }
+}
+
+impl AlchemyXpSettings {
+    /// Returns the XP gained by crafting a potion worth `gold_value`
+    pub fn get_xp_for_potion(&self, gold_value: u32) -> f32 {
+        self.skill_use_mult * gold_value as f32 + self.skill_use_offset
+    }
+
+    /// Returns the XP it takes to go from `level` to the next level
+    pub fn get_xp_to_next_level(&self, level: u32) -> f32 {
+        self.skill_improve_mult * (level as f32).powf(self.skill_use_curve)
+            + self.skill_improve_offset
+    }
+}
+
+/// The level of a skill and the XP gained towards the next level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkillProgress {
+    pub level: u32,
+    pub xp: f32,
+}
+
+impl SkillProgress {
+    /// Starts at the beginning of the level, since the XP gained towards the next level isn't known
+    pub fn new(level: u32) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+            xp: 0.0,
+        }
+    }
+
+    /// Adds the XP, leveling up as many times as it is enough for, up to `MAX_SKILL_LEVEL`
+    pub fn add_xp(&mut self, xp: f32, settings: &AlchemyXpSettings) {
+        if self.level >= MAX_SKILL_LEVEL {
+            return;
+        }
+        self.xp += xp.max(0.0);
+        loop {
+            let xp_to_next_level = settings.get_xp_to_next_level(self.level);
+            // A level that takes no XP would be gained forever
+            if self.xp < xp_to_next_level || xp_to_next_level <= 0.0 {
+                break;
+            }
+            self.xp -= xp_to_next_level;
+            self.level += 1;
+            if self.level >= MAX_SKILL_LEVEL {
+                self.xp = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Returns the share of the XP that the next level takes that was gained, from 0 to 1
+    pub fn get_fraction(&self, settings: &AlchemyXpSettings) -> f32 {
+        if self.level >= MAX_SKILL_LEVEL {
+            return 0.0;
+        }
+        (self.xp / settings.get_xp_to_next_level(self.level)).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings under which each level takes as much XP as the level itself, and each potion
+    /// gives as much XP as its gold value
+    const LINEAR_SETTINGS: AlchemyXpSettings = AlchemyXpSettings {
+        skill_use_curve: 1.0,
+        skill_use_mult: 1.0,
+        skill_use_offset: 0.0,
+        skill_improve_mult: 1.0,
+        skill_improve_offset: 0.0,
+    };
+
+    #[test]
+    fn one_step_can_gain_several_levels() {
+        let mut progress = SkillProgress::new(10);
+        progress.add_xp(5.0, &LINEAR_SETTINGS);
+        assert_eq!(progress, SkillProgress { level: 10, xp: 5.0 });
+
+        // The rest of level 10, all of 11 and 12 and some of 13
+        progress.add_xp(5.0 + 11.0 + 12.0 + 3.0, &LINEAR_SETTINGS);
+        assert_eq!(progress, SkillProgress { level: 13, xp: 3.0 });
+        assert_eq!(progress.get_fraction(&LINEAR_SETTINGS), 3.0 / 13.0);
+
+        // Negative XP is ignored
+        progress.add_xp(-100.0, &LINEAR_SETTINGS);
+        assert_eq!(progress, SkillProgress { level: 13, xp: 3.0 });
+    }
+
+    #[test]
+    fn leveling_stops_at_100() {
+        assert_eq!(SkillProgress::new(150), SkillProgress::new(MAX_SKILL_LEVEL));
+
+        let mut progress = SkillProgress::new(98);
+        progress.add_xp(1_000_000.0, &AlchemyXpSettings::default());
+        assert_eq!(
+            progress,
+            SkillProgress {
+                level: MAX_SKILL_LEVEL,
+                xp: 0.0
+            }
+        );
+        progress.add_xp(1_000_000.0, &AlchemyXpSettings::default());
+        assert_eq!(progress.level, MAX_SKILL_LEVEL);
+        assert_eq!(progress.get_fraction(&AlchemyXpSettings::default()), 0.0);
+    }
+
+    #[test]
+    fn levels_that_take_no_xp_are_not_gained() {
+        let settings = AlchemyXpSettings {
+            skill_improve_mult: 0.0,
+            ..LINEAR_SETTINGS
+        };
+        let mut progress = SkillProgress::new(15);
+        progress.add_xp(10.0, &settings);
+        assert_eq!(
+            progress,
+            SkillProgress {
+                level: 15,
+                xp: 10.0
+            }
+        );
+    }
+
+    #[test]
+    fn default_settings_match_the_game() {
+        let settings = AlchemyXpSettings::default();
+        // 1.6 * 15^1.95 + 65
+        assert!((settings.get_xp_to_next_level(15) - 379.41).abs() < 0.01);
+        assert_eq!(settings.get_xp_for_potion(100), 75.0);
+    }
+}
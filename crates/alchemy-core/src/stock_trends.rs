@@ -0,0 +1,193 @@
+use ahash::{AHashMap, AHashSet};
+
+use crate::plugin_parser::form_id::GlobalFormId;
+
+/// The ingredient counts in the inventory of one save
+pub(crate) struct StockSnapshot {
+    /// The in-game time played when the save was made, in hours, if it could be read
+    pub(crate) playtime_hours: Option<f32>,
+    pub(crate) inventory: AHashMap<GlobalFormId, u32>,
+}
+
+/// How the count of one ingredient changed over a series of saves
+pub(crate) struct StockTrend {
+    pub(crate) ingredient_id: GlobalFormId,
+    /// The count in each save, oldest first. Saves without the ingredient count as 0.
+    pub(crate) counts: Vec<u32>,
+    /// The total of the increases between consecutive saves, i.e. how many were gathered or bought
+    pub(crate) gained: u64,
+    /// The total of the decreases between consecutive saves, i.e. how many were used or sold
+    pub(crate) used: u64,
+    /// The net change per in-game day played, if the playtime of the first and last save is known
+    /// and differs
+    pub(crate) change_per_day: Option<f32>,
+}
+
+impl StockTrend {
+    /// Returns the difference between the count in the last and the first save
+    pub(crate) fn get_net_change(&self) -> i64 {
+        let first = self.counts.first().copied().unwrap_or(0);
+        let last = self.counts.last().copied().unwrap_or(0);
+        i64::from(last) - i64::from(first)
+    }
+}
+
+/// Parses the playtime in a save's header, formatted by the game as days.hours.minutes, e.g.
+/// `007.12.34`, into hours
+pub(crate) fn parse_playtime_hours(playtime: &str) -> Option<f32> {
+    let mut parts = playtime.trim().split('.').map(str::parse::<u32>);
+    let (days, hours, minutes) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(days as f32 * 24.0 + hours as f32 + minutes as f32 / 60.0)
+}
+
+/// Computes the trend of every ingredient that is in the inventory of at least one of the
+/// snapshots, which must be ordered oldest first. The trends are sorted by net change descending,
+/// so the ingredients that the player accumulates come first and those they consume come last.
+pub(crate) fn compute_stock_trends(snapshots: &[StockSnapshot]) -> Vec<StockTrend> {
+    let hours_played = match (snapshots.first(), snapshots.last()) {
+        (Some(first), Some(last)) => last
+            .playtime_hours
+            .zip(first.playtime_hours)
+            .map(|(last, first)| last - first)
+            .filter(|hours| *hours > 0.0),
+        _ => None,
+    };
+
+    let ingredient_ids = snapshots
+        .iter()
+        .flat_map(|snapshot| snapshot.inventory.keys().copied())
+        .collect::<AHashSet<_>>();
+
+    let mut trends = ingredient_ids
+        .into_iter()
+        .map(|ingredient_id| {
+            let counts = snapshots
+                .iter()
+                .map(|snapshot| snapshot.inventory.get(&ingredient_id).copied().unwrap_or(0))
+                .collect::<Vec<_>>();
+            let (mut gained, mut used) = (0, 0);
+            for window in counts.windows(2) {
+                match window[1] >= window[0] {
+                    true => gained += u64::from(window[1] - window[0]),
+                    false => used += u64::from(window[0] - window[1]),
+                }
+            }
+            let mut trend = StockTrend {
+                ingredient_id,
+                counts,
+                gained,
+                used,
+                change_per_day: None,
+            };
+            trend.change_per_day =
+                hours_played.map(|hours| trend.get_net_change() as f32 * 24.0 / hours);
+            trend
+        })
+        .collect::<Vec<_>>();
+    // Ties are broken by form ID, so that the report is the same on every run
+    trends.sort_by(|a, b| {
+        b.get_net_change()
+            .cmp(&a.get_net_change())
+            .then(a.ingredient_id.cmp(&b.ingredient_id))
+    });
+    trends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(playtime_hours: Option<f32>, inventory: &[(u32, u32)]) -> StockSnapshot {
+        StockSnapshot {
+            playtime_hours,
+            inventory: inventory
+                .iter()
+                .map(|&(id, count)| (GlobalFormId::new(0, id), count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn missing_ingredients_count_as_zero() {
+        let trends = compute_stock_trends(&[
+            snapshot(None, &[(1, 5)]),
+            snapshot(None, &[(2, 3)]),
+            snapshot(None, &[(1, 2)]),
+        ]);
+        let counts = trends
+            .iter()
+            .map(|trend| (trend.ingredient_id.id, trend.counts.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [(2, vec![0, 3, 0]), (1, vec![5, 0, 2])]);
+    }
+
+    #[test]
+    fn gains_and_uses_are_summed_separately() {
+        let trends = compute_stock_trends(&[
+            snapshot(None, &[(1, 10)]),
+            snapshot(None, &[(1, 4)]),
+            snapshot(None, &[(1, 9)]),
+            snapshot(None, &[(1, 9)]),
+            snapshot(None, &[(1, 7)]),
+        ]);
+        assert_eq!(trends.len(), 1);
+        assert_eq!(trends[0].gained, 5);
+        assert_eq!(trends[0].used, 8);
+        assert_eq!(trends[0].get_net_change(), -3);
+    }
+
+    #[test]
+    fn trends_are_sorted_by_net_change() {
+        let trends = compute_stock_trends(&[
+            snapshot(None, &[(1, 5), (2, 5), (3, 5), (4, 5)]),
+            snapshot(None, &[(1, 0), (2, 9), (3, 5), (4, 9)]),
+        ]);
+        let ids = trends
+            .iter()
+            .map(|trend| trend.ingredient_id.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn change_per_day_needs_increased_playtime() {
+        let trends = compute_stock_trends(&[
+            snapshot(Some(12.0), &[(1, 10)]),
+            snapshot(Some(60.0), &[(1, 4)]),
+        ]);
+        assert_eq!(trends[0].change_per_day, Some(-3.0));
+
+        let trends = compute_stock_trends(&[
+            snapshot(Some(60.0), &[(1, 10)]),
+            snapshot(Some(60.0), &[(1, 4)]),
+        ]);
+        assert_eq!(trends[0].change_per_day, None);
+
+        let trends = compute_stock_trends(&[
+            snapshot(Some(60.0), &[(1, 10)]),
+            snapshot(Some(12.0), &[(1, 4)]),
+        ]);
+        assert_eq!(trends[0].change_per_day, None);
+
+        let trends =
+            compute_stock_trends(&[snapshot(None, &[(1, 10)]), snapshot(Some(60.0), &[(1, 4)])]);
+        assert_eq!(trends[0].change_per_day, None);
+    }
+
+    #[test]
+    fn parse_playtime_hours_reads_days_hours_and_minutes() {
+        assert_eq!(parse_playtime_hours("007.12.30"), Some(180.5));
+        assert_eq!(parse_playtime_hours(" 000.00.00 "), Some(0.0));
+        assert_eq!(parse_playtime_hours("007.12"), None);
+        assert_eq!(parse_playtime_hours("007.12.30.00"), None);
+        assert_eq!(parse_playtime_hours("007.xx.30"), None);
+        assert_eq!(parse_playtime_hours(""), None);
+    }
+}
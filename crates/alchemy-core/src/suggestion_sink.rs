@@ -0,0 +1,357 @@
+use std::{io::Write, sync::mpsc::Sender};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::{
+    game_data::{get_ingredient_name_or_fallback, get_magic_effect_name_or_fallback},
+    load_order_hash::LoadOrderHash,
+    output::{OutputStyle, PotionFormatter, QueryResults},
+    plugin_parser::{
+        form_id::{FormIdContainer, GlobalFormId},
+        ingredient::Ingredient,
+    },
+    potion::PotionType,
+    potion_notes::AnnotatedPotion,
+};
+
+/// The potions suggested for one query, as they are delivered to a `SuggestionSink`
+pub struct QuerySuggestions<'q, 'p, 'a> {
+    /// The name of the query, if it has one
+    pub name: Option<&'q str>,
+    /// The load order hash of the game data the potions came from
+    pub load_order_hash: Option<LoadOrderHash>,
+    pub results: QueryResults<'p, 'a>,
+    /// The different ingredients that the potions need, if the query batches its potions by their
+    /// ingredients
+    pub needed_ingredients: Option<Vec<&'a Ingredient>>,
+}
+
+/// Receives the potions suggested for each query as soon as they are found, so that the potions of
+/// one query can be output while the next query runs
+pub trait SuggestionSink {
+    /// Receives the suggestions for a query. Returning an error stops the remaining queries.
+    fn send(&mut self, suggestions: QuerySuggestions) -> Result<(), anyhow::Error>;
+}
+
+/// Formats the suggestions and writes them to the writer, e.g. stdout
+pub struct WriterSink<'f, W: Write> {
+    formatter: &'f PotionFormatter,
+    writer: W,
+}
+
+impl<'f, W: Write> WriterSink<'f, W> {
+    pub fn new(formatter: &'f PotionFormatter, writer: W) -> Self {
+        Self { formatter, writer }
+    }
+}
+
+impl<'f, W: Write> SuggestionSink for WriterSink<'f, W> {
+    fn send(&mut self, suggestions: QuerySuggestions) -> Result<(), anyhow::Error> {
+        if !suggestions.results.is_empty() {
+            writeln!(
+                self.writer,
+                "{}\n",
+                self.formatter.format_query_results(
+                    suggestions.name,
+                    suggestions.load_order_hash,
+                    &suggestions.results
+                )
+            )?;
+        }
+        // The ingredients are already part of each potion in the JSON output
+        match suggestions.needed_ingredients {
+            Some(ingredients)
+                if !ingredients.is_empty() && self.formatter.style != OutputStyle::Json =>
+            {
+                writeln!(
+                    self.writer,
+                    "These potions need {} different ingredients: {}\n",
+                    ingredients.len(),
+                    ingredients
+                        .iter()
+                        .map(|ig| get_ingredient_name_or_fallback(ig))
+                        .join(", ")
+                )?;
+            }
+            _ => {}
+        }
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// A suggested potion that doesn't borrow from the game data, so that it can be kept after the
+/// game data is dropped or sent to another thread
+#[derive(Clone, Debug)]
+pub struct SuggestedPotion {
+    /// The heading of the group that the potion is in, if the query groups its potions
+    pub group: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub gold_value: u32,
+    pub potion_type: PotionType,
+    /// The names of the ingredients
+    pub ingredients: Vec<String>,
+    pub ingredient_ids: Vec<GlobalFormId>,
+    /// The names of the effects, strongest first
+    pub effects: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+impl SuggestedPotion {
+    fn new(group: Option<&str>, annotated: &AnnotatedPotion) -> Self {
+        let potion = annotated.potion;
+        Self {
+            group: group.map(String::from),
+            name: potion.get_potion_name(annotated.game_data),
+            description: potion.get_potion_description(annotated.game_data.get_number_locale()),
+            gold_value: potion.gold_value,
+            potion_type: potion.get_potion_type(),
+            ingredients: potion
+                .ingredients
+                .iter()
+                .map(|ig| get_ingredient_name_or_fallback(ig).into_owned())
+                .collect(),
+            ingredient_ids: potion
+                .ingredients
+                .iter()
+                .map(|ig| ig.get_global_form_id())
+                .collect(),
+            effects: potion
+                .effects
+                .iter()
+                .map(|eff| get_magic_effect_name_or_fallback(eff.magic_effect).into_owned())
+                .collect(),
+            notes: annotated.notes.clone(),
+        }
+    }
+}
+
+/// The suggestions for one query in a form that doesn't borrow from the game data
+#[derive(Clone, Debug)]
+pub struct SuggestedQuery {
+    pub name: Option<String>,
+    pub load_order_hash: Option<LoadOrderHash>,
+    /// The potions in the order they were suggested. Grouped potions follow each other.
+    pub potions: Vec<SuggestedPotion>,
+    /// The names of the different ingredients that the potions need, if the query batches its
+    /// potions by their ingredients
+    pub needed_ingredients: Option<Vec<String>>,
+}
+
+impl<'q, 'p, 'a> From<QuerySuggestions<'q, 'p, 'a>> for SuggestedQuery {
+    fn from(suggestions: QuerySuggestions<'q, 'p, 'a>) -> Self {
+        let potions = match &suggestions.results {
+            QueryResults::Potions(potions) => potions
+                .iter()
+                .map(|potion| SuggestedPotion::new(None, potion))
+                .collect(),
+            QueryResults::Groups(groups) => groups
+                .iter()
+                .flat_map(|group| {
+                    group
+                        .potions
+                        .iter()
+                        .map(|potion| SuggestedPotion::new(Some(&group.heading), potion))
+                })
+                .collect(),
+        };
+        Self {
+            name: suggestions.name.map(String::from),
+            load_order_hash: suggestions.load_order_hash,
+            potions,
+            needed_ingredients: suggestions.needed_ingredients.map(|ingredients| {
+                ingredients
+                    .iter()
+                    .map(|ig| get_ingredient_name_or_fallback(ig).into_owned())
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Collects the suggestions of all queries
+impl SuggestionSink for Vec<SuggestedQuery> {
+    fn send(&mut self, suggestions: QuerySuggestions) -> Result<(), anyhow::Error> {
+        self.push(suggestions.into());
+        Ok(())
+    }
+}
+
+/// Sends the suggestions of each query to the receiver, e.g. on another thread. The remaining
+/// queries are stopped if the receiver is dropped.
+impl SuggestionSink for Sender<SuggestedQuery> {
+    fn send(&mut self, suggestions: QuerySuggestions) -> Result<(), anyhow::Error> {
+        Sender::send(self, suggestions.into())
+            .map_err(|_| anyhow!("The receiver of the suggestions was dropped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+    use crate::{game_data::GameData, output::AnnotatedPotionGroup, potion::Potion};
+
+    /// Frost Lily with Ember Root, and Marsh Cap with Silver Thistle
+    fn example_potions(game_data: &GameData) -> Vec<Potion> {
+        [[0x800, 0x801], [0x802, 0x803]]
+            .into_iter()
+            .map(|ids| {
+                let ingredients =
+                    ids.map(|id| game_data.get_ingredient(&GlobalFormId::new(0, id)).unwrap());
+                Potion::from_ingredients(&ingredients, game_data, 1.0).unwrap()
+            })
+            .collect()
+    }
+
+    /// The suggestions for a query with the potions, in a group if `heading` is given, batched by
+    /// their ingredients
+    fn get_suggestions<'q, 'p, 'a>(
+        name: &'q str,
+        heading: Option<&str>,
+        potions: &'p [Potion<'a>],
+        game_data: &'a GameData,
+    ) -> QuerySuggestions<'q, 'p, 'a> {
+        let annotated = potions
+            .iter()
+            .map(|potion| AnnotatedPotion::new(potion, game_data, &[]))
+            .collect();
+        QuerySuggestions {
+            name: Some(name),
+            load_order_hash: game_data.get_load_order_hash(),
+            results: match heading {
+                Some(heading) => QueryResults::Groups(vec![AnnotatedPotionGroup {
+                    heading: heading.to_string(),
+                    potions: annotated,
+                }]),
+                None => QueryResults::Potions(annotated),
+            },
+            needed_ingredients: Some(
+                potions
+                    .iter()
+                    .flat_map(|potion| potion.ingredients.iter().copied())
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn writer_sink_writes_the_formatted_results() {
+        let game_data = GameData::example();
+        let potions = example_potions(&game_data);
+
+        for style in [OutputStyle::Block, OutputStyle::Oneline, OutputStyle::Json] {
+            for heading in [None, Some("Restore health")] {
+                let formatter = PotionFormatter::new(style, false);
+                let mut output = Vec::new();
+                WriterSink::new(&formatter, &mut output)
+                    .send(get_suggestions("query", heading, &potions, &game_data))
+                    .unwrap();
+
+                // What suggest-potions printed before the sinks
+                let suggestions = get_suggestions("query", heading, &potions, &game_data);
+                let mut expected = format!(
+                    "{}\n\n",
+                    formatter.format_query_results(
+                        suggestions.name,
+                        suggestions.load_order_hash,
+                        &suggestions.results
+                    )
+                );
+                if style != OutputStyle::Json {
+                    expected.push_str(
+                        "These potions need 4 different ingredients: Frost Lily, Ember Root, \
+                         Marsh Cap, Silver Thistle\n\n",
+                    );
+                }
+                assert_eq!(String::from_utf8(output).unwrap(), expected, "{:?}", style);
+            }
+        }
+
+        // Nothing is written for a query without potions
+        let formatter = PotionFormatter::new(OutputStyle::Block, false);
+        let mut output = Vec::new();
+        WriterSink::new(&formatter, &mut output)
+            .send(get_suggestions("query", None, &[], &game_data))
+            .unwrap();
+        assert!(output.is_empty());
+    }
+
+    fn assert_suggested_queries(queries: &[SuggestedQuery]) {
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].name.as_deref(), Some("first"));
+        assert_eq!(queries[1].name.as_deref(), Some("second"));
+        for (query, group) in queries.iter().zip([None, Some("Restore health")]) {
+            assert_eq!(query.potions.len(), 2);
+            assert!(query
+                .potions
+                .iter()
+                .all(|potion| potion.group.as_deref() == group));
+            assert_eq!(query.potions[0].ingredients, ["Frost Lily", "Ember Root"]);
+            assert_eq!(
+                query.potions[1].ingredients,
+                ["Marsh Cap", "Silver Thistle"]
+            );
+            assert_eq!(
+                query.needed_ingredients.as_deref(),
+                Some(
+                    &["Frost Lily", "Ember Root", "Marsh Cap", "Silver Thistle"].map(String::from)
+                        [..]
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn vec_sink_receives_every_query() {
+        let game_data = GameData::example();
+        let potions = example_potions(&game_data);
+
+        let mut queries = Vec::<SuggestedQuery>::new();
+        queries
+            .send(get_suggestions("first", None, &potions, &game_data))
+            .unwrap();
+        queries
+            .send(get_suggestions(
+                "second",
+                Some("Restore health"),
+                &potions,
+                &game_data,
+            ))
+            .unwrap();
+        assert_suggested_queries(&queries);
+        assert_eq!(queries[0].potions[0].gold_value, potions[0].gold_value);
+    }
+
+    #[test]
+    fn channel_sink_receives_every_query() {
+        let game_data = GameData::example();
+        let potions = example_potions(&game_data);
+
+        let (mut sender, receiver) = channel::<SuggestedQuery>();
+        SuggestionSink::send(
+            &mut sender,
+            get_suggestions("first", None, &potions, &game_data),
+        )
+        .unwrap();
+        SuggestionSink::send(
+            &mut sender,
+            get_suggestions("second", Some("Restore health"), &potions, &game_data),
+        )
+        .unwrap();
+        let queries = receiver.try_iter().collect::<Vec<_>>();
+        assert_suggested_queries(&queries);
+
+        // The remaining queries are stopped once the receiver is gone
+        drop(receiver);
+        assert!(SuggestionSink::send(
+            &mut sender,
+            get_suggestions("third", None, &potions, &game_data),
+        )
+        .is_err());
+    }
+}
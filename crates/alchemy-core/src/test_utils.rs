@@ -0,0 +1,69 @@
+//! Builders of ingredients and magic effects for tests that need other records than those of
+//! `GameData::example()`
+
+use crate::plugin_parser::{
+    form_id::GlobalFormId,
+    ingredient::{Ingredient, IngredientEffect},
+    magic_effect::{EffectArchetype, MagicEffect},
+};
+
+/// Returns an ingredient effect with the given magnitude and duration
+pub fn ingredient_effect(
+    global_form_id: GlobalFormId,
+    magnitude: f32,
+    duration: u32,
+) -> IngredientEffect {
+    IngredientEffect {
+        global_form_id,
+        duration,
+        magnitude,
+        record_index: None,
+    }
+}
+
+/// Returns a nameless ingredient with the given effects and no weight, value or food flag. Its
+/// editor ID is made from its form ID, e.g. `Ingredient0000:000800`.
+pub fn ingredient(
+    global_form_id: GlobalFormId,
+    effects: impl IntoIterator<Item = IngredientEffect>,
+) -> Ingredient {
+    Ingredient {
+        global_form_id,
+        editor_id: format!("Ingredient{}", global_form_id),
+        name: None,
+        effects: effects.into_iter().collect(),
+        weight: None,
+        value: None,
+        is_food: None,
+        qualified_name: None,
+    }
+}
+
+/// Returns a nameless ingredient with the given effects, all of magnitude 1 and without a duration
+pub fn ingredient_with_effect_ids(
+    global_form_id: GlobalFormId,
+    effect_ids: &[GlobalFormId],
+) -> Ingredient {
+    ingredient(
+        global_form_id,
+        effect_ids
+            .iter()
+            .map(|&effect_id| ingredient_effect(effect_id, 1.0, 0)),
+    )
+}
+
+/// Returns a nameless, non-hostile magic effect with a base cost of 1 that modifies no actor
+/// value. Its editor ID is made from its form ID, e.g. `Effect0000:000900`.
+pub fn magic_effect(global_form_id: GlobalFormId) -> MagicEffect {
+    MagicEffect {
+        global_form_id,
+        editor_id: format!("Effect{}", global_form_id),
+        name: None,
+        description: String::new(),
+        flags: 0,
+        is_hostile: false,
+        base_cost: 1.0,
+        archetype: EffectArchetype::ValueModifier,
+        actor_value: -1,
+    }
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+
+/// Writes the vanilla game data bundled with the `vanilla-data` feature to the path, e.g. in the
+/// cache directory, unless it is there already. Fails if the program was built without it.
+#[cfg(feature = "vanilla-data")]
+pub fn unpack_vanilla_game_data(path: &Path) -> Result<(), anyhow::Error> {
+    use anyhow::Context;
+    use std::fs;
+
+    use crate::game_data::VANILLA_GAME_DATA;
+
+    // The data is written to a file so that it can be read like any other game data file. A file
+    // left by a build with other vanilla data is replaced.
+    if fs::read(path).ok().as_deref() != Some(VANILLA_GAME_DATA) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, VANILLA_GAME_DATA)
+            .with_context(|| format!("Failed to unpack the vanilla data to {}", path.display()))?;
+        log::debug!("Unpacked the vanilla data to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Writes the vanilla game data bundled with the `vanilla-data` feature to the path, e.g. in the
+/// cache directory, unless it is there already. Fails if the program was built without it.
+#[cfg(not(feature = "vanilla-data"))]
+pub fn unpack_vanilla_game_data(_path: &Path) -> Result<(), anyhow::Error> {
+    Err(anyhow!(
+        "This build does not include the vanilla data. Write it with export-game-data --vanilla \
+         on a machine with the game, then build with --features vanilla-data and \
+         SKYRIM_ALCHEMY_VANILLA_DATA set to the path of the file."
+    ))
+}